@@ -0,0 +1,96 @@
+use std::{fs, io, path::Path};
+
+use log::{error, info};
+
+use crate::tui::utils::{civil_from_days, unix_now};
+
+pub(crate) const BACKUP_DIR: &str = "backups";
+pub(crate) const DEFAULT_BACKUP_COUNT: usize = 5;
+
+const BACKUP_PREFIX: &str = "notes-";
+const BACKUP_SUFFIX: &str = ".db";
+
+/// Copies the database into `backup_dir` and prunes all but the newest `keep` copies.
+/// Failures are logged, a missing backup never stops the app from starting.
+pub(crate) fn backup_db(db_path: &Path, backup_dir: &Path, keep: usize) {
+    if keep == 0 || !db_path.exists() {
+        return;
+    }
+
+    match copy_backup(db_path, backup_dir, keep) {
+        Ok(pruned) => info!("backup_db::backed up, pruned {} old backups", pruned),
+        Err(e) => error!("backup_db::failed: {:?}", e),
+    }
+}
+
+fn copy_backup(db_path: &Path, backup_dir: &Path, keep: usize) -> io::Result<usize> {
+    fs::create_dir_all(backup_dir)?;
+    fs::copy(db_path, backup_dir.join(backup_name(unix_now())))?;
+
+    let names = fs::read_dir(backup_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<String>>();
+
+    let stale = stale_backups(names, keep);
+    for name in stale.iter() {
+        fs::remove_file(backup_dir.join(name))?;
+    }
+    Ok(stale.len())
+}
+
+/// notes-YYYYMMDD-HHMMSS.db, so names sort oldest first
+fn backup_name(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!(
+        "{}{:04}{:02}{:02}-{:02}{:02}{:02}{}",
+        BACKUP_PREFIX,
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60,
+        BACKUP_SUFFIX
+    )
+}
+
+/// Backups past the newest `keep`, other files in the directory are left alone
+fn stale_backups(names: Vec<String>, keep: usize) -> Vec<String> {
+    let mut backups = names
+        .into_iter()
+        .filter(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX))
+        .collect::<Vec<String>>();
+    backups.sort_by(|a, b| b.cmp(a));
+    backups.split_off(keep.min(backups.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_name() {
+        assert_eq!(backup_name(19_783 * 86_400 + 3_723), "notes-20240301-010203.db");
+    }
+
+    #[test]
+    fn test_stale_backups_keeps_newest() {
+        let names = [
+            "notes-20240301-010203.db",
+            "notes-20240115-120000.db",
+            "readme.txt",
+            "notes-20240302-000000.db",
+            "notes-20231231-235959.db",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<String>>();
+
+        let mut stale = stale_backups(names.clone(), 2);
+        stale.sort();
+        assert_eq!(stale, ["notes-20231231-235959.db", "notes-20240115-120000.db"]);
+        assert!(stale_backups(names, 10).is_empty());
+    }
+}
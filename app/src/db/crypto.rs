@@ -0,0 +1,188 @@
+use std::fmt;
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use color_eyre::eyre::{eyre, Result};
+use log::{info, warn};
+use sqlx::SqlitePool;
+
+use crate::tui::utils::prompt_hidden;
+
+use super::errors::WrongPassphraseError;
+
+use super::db_mac::DbMac;
+
+/// Bodies written by `NoteCipher::seal` start with this, anything else is plaintext
+const SEALED_PREFIX: &str = "tuipaz-enc1:";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Sealed once per database, opening it again checks the passphrase
+const CHECK_TEXT: &str = "tuipaz";
+const PASSPHRASE_ATTEMPTS: usize = 3;
+
+/// Encrypts note bodies with a key derived from the passphrase and the database's salt.
+/// Titles always stay plaintext, there is no setting for them: the database keeps them
+/// unique, sorts and searches the note list by them, links resolve through them and file
+/// vaults name their files after them, none of which works on titles sealed under a
+/// fresh nonce.
+#[derive(Clone)]
+pub(crate) struct NoteCipher {
+    cipher: ChaCha20Poly1305,
+    /// Kept to derive the key for another vault, which has its own salt
+    passphrase: String,
+}
+
+impl fmt::Debug for NoteCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NoteCipher")
+    }
+}
+
+impl NoteCipher {
+    pub(crate) fn new(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| eyre!("Failed to derive encryption key: {}", e))?;
+
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            passphrase: passphrase.to_owned(),
+        })
+    }
+
+    /// The cipher for `db`, setting up its salt the first time it is encrypted
+    pub(crate) async fn unlock(db: &SqlitePool, passphrase: &str) -> Result<Self> {
+        match DbMac::load_encryption(db).await? {
+            Some((salt, check_value)) => {
+                let cipher = Self::new(passphrase, &salt)?;
+                match cipher.open(&check_value)? == CHECK_TEXT {
+                    true => Ok(cipher),
+                    false => Err(WrongPassphraseError.into()),
+                }
+            }
+            None => {
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                let cipher = Self::new(passphrase, &salt)?;
+                DbMac::save_encryption(db, &salt, &cipher.seal(CHECK_TEXT)?).await?;
+                info!("unlock::encryption set up");
+                Ok(cipher)
+            }
+        }
+    }
+
+    pub(crate) async fn for_db(&self, db: &SqlitePool) -> Result<Self> {
+        Self::unlock(db, &self.passphrase).await
+    }
+
+    /// `body` under a fresh nonce, stored with the nonce in front of the ciphertext
+    pub(crate) fn seal(&self, body: &str) -> Result<String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = self
+            .cipher
+            .encrypt(&nonce, body.as_bytes())
+            .map_err(|_| eyre!("Failed to encrypt note"))?;
+
+        Ok(format!("{}{}{}", SEALED_PREFIX, to_hex(&nonce), to_hex(&sealed)))
+    }
+
+    /// Plaintext bodies, saved before encryption was turned on, pass through
+    pub(crate) fn open(&self, stored: &str) -> Result<String> {
+        let Some(hex) = stored.strip_prefix(SEALED_PREFIX) else {
+            return Ok(stored.to_owned());
+        };
+        let bytes = from_hex(hex)
+            .filter(|bytes| bytes.len() >= NONCE_LEN)
+            .ok_or_else(|| eyre!("Encrypted note is corrupt"))?;
+        let (nonce, sealed) = bytes.split_at(NONCE_LEN);
+
+        let body = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| WrongPassphraseError)?;
+        String::from_utf8(body).map_err(|_| eyre!("Encrypted note is corrupt"))
+    }
+}
+
+/// Asks for the passphrase on the terminal, before the TUI starts, until it opens `db`
+pub(crate) async fn prompt_unlock(db: &SqlitePool) -> Result<NoteCipher> {
+    for _ in 0..PASSPHRASE_ATTEMPTS {
+        let passphrase = prompt_hidden("Passphrase: ")?;
+        match NoteCipher::unlock(db, &passphrase).await {
+            Ok(cipher) => return Ok(cipher),
+            Err(e) if e.is::<WrongPassphraseError>() => {
+                warn!("prompt_unlock::wrong passphrase");
+                println!("{}", e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(WrongPassphraseError.into())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&text[idx..idx + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db::create_test_db;
+
+    const SALT: &[u8] = b"tuipaz test salt";
+
+    #[test]
+    fn test_seal_round_trips() {
+        let cipher = NoteCipher::new("correct horse", SALT).unwrap();
+        let body = "# Secret\nlines with ünïcode";
+
+        let sealed = cipher.seal(body).unwrap();
+        assert!(sealed.starts_with(SEALED_PREFIX));
+        assert!(!sealed.contains("Secret"));
+        assert_ne!(sealed, cipher.seal(body).unwrap());
+
+        assert_eq!(cipher.open(&sealed).unwrap(), body);
+        assert_eq!(cipher.open("plain old note").unwrap(), "plain old note");
+    }
+
+    #[test]
+    fn test_wrong_key_fails_to_open() {
+        let sealed = NoteCipher::new("correct horse", SALT)
+            .unwrap()
+            .seal("secret")
+            .unwrap();
+        let wrong = NoteCipher::new("battery staple", SALT).unwrap();
+
+        let err = wrong.open(&sealed).unwrap_err();
+        assert!(err.is::<WrongPassphraseError>());
+        assert!(wrong.open(&format!("{}abc", SEALED_PREFIX)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unlock_checks_the_passphrase() {
+        let db = create_test_db().await;
+
+        let first = NoteCipher::unlock(&db, "correct horse").await.unwrap();
+        let sealed = first.seal("secret").unwrap();
+
+        let again = NoteCipher::unlock(&db, "correct horse").await.unwrap();
+        assert_eq!(again.open(&sealed).unwrap(), "secret");
+
+        let err = NoteCipher::unlock(&db, "battery staple").await.unwrap_err();
+        assert!(err.is::<WrongPassphraseError>());
+    }
+}
@@ -3,6 +3,8 @@ use log::info;
 use serde::{Deserialize, Serialize};
 use sqlx::{QueryBuilder, SqlitePool};
 
+use super::errors::DuplicateTitleError;
+
 #[derive(
     sqlx::
     FromRow,
@@ -39,6 +41,9 @@ pub(crate) struct DbNoteLink {
     pub(crate) start_col: i64,
     pub(crate) end_col: i64,
     pub(crate) linked_note_id: i64,
+    /// Heading the link jumps to, None for the top of the note
+    #[serde(default)]
+    pub(crate) anchor: Option<String>,
 }
 
 #[derive(
@@ -56,6 +61,28 @@ pub(crate) struct Note {
     pub(crate) has_links: bool,
 }
 
+/// Folder of a note that isn't at the root
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NoteFolder {
+    pub(crate) id: i64,
+    pub(crate) folder: String,
+}
+
+/// One tag on a note, a note with several tags has a row for each
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NoteTag {
+    pub(crate) id: i64,
+    pub(crate) tag: String,
+}
+
+/// A note and how many other notes link to it
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LinkCount {
+    pub(crate) id: i64,
+    pub(crate) title: String,
+    pub(crate) count: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct NotePatch {
     pub(crate) title: Option<String>,
@@ -74,7 +101,8 @@ impl DbMac {
         has_links: bool,
     ) -> Result<i64> {
         let result = sqlx::query!(
-            "INSERT INTO notes (title, body, has_links) VALUES (?,?,?) RETURNING id",
+            "INSERT INTO notes (title, body, has_links, updated_at)
+            VALUES (?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER)) RETURNING id",
             title,
             body,
             has_links
@@ -84,6 +112,9 @@ impl DbMac {
 
         match result {
             Ok(row) => Ok(row.id),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                Err(DuplicateTitleError(title.to_owned()).into())
+            }
             Err(e) => Err(eyre!("Failed to save note: {:?}", e)),
         }
     }
@@ -97,7 +128,9 @@ impl DbMac {
         id: i64,
     ) -> Result<i64> {
         let result = sqlx::query!(
-            "UPDATE notes SET title=?, body=?, has_links=? WHERE id=? RETURNING id",
+            "UPDATE notes
+            SET title=?, body=?, has_links=?, updated_at=CAST(strftime('%s', 'now') AS INTEGER)
+            WHERE id=? RETURNING id",
             title,
             body,
             has_links,
@@ -111,6 +144,9 @@ impl DbMac {
                 let id = row.id.expect("Updated note should be in database");
                 Ok(id)
             }
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                Err(DuplicateTitleError(title.to_owned()).into())
+            }
             Err(e) => Err(eyre!("Failed to save note: {:?}", e)),
         }
     }
@@ -137,7 +173,8 @@ impl DbMac {
         let result = sqlx::query_as!(
             DbNoteLink,
             "SELECT
-                parent_note_id, textarea_id, textarea_row, start_col, end_col, linked_note_id 
+                parent_note_id, textarea_id, textarea_row, start_col, end_col,
+                linked_note_id, anchor
             FROM 
                 links 
             WHERE 
@@ -153,6 +190,48 @@ impl DbMac {
         }
     }
 
+    pub(crate) async fn load_backlinks(
+        db: &SqlitePool,
+        linked_note_id: i64,
+    ) -> Result<Vec<DbNoteLink>> {
+        let result = sqlx::query_as!(
+            DbNoteLink,
+            "SELECT
+                parent_note_id, textarea_id, textarea_row, start_col, end_col,
+                linked_note_id, anchor
+            FROM 
+                links 
+            WHERE 
+                linked_note_id=?",
+            linked_note_id
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(links) => Ok(links),
+            Err(e) => Err(eyre!("Failed to load backlinks: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn load_all_links(db: &SqlitePool) -> Result<Vec<DbNoteLink>> {
+        let result = sqlx::query_as!(
+            DbNoteLink,
+            "SELECT
+                parent_note_id, textarea_id, textarea_row, start_col, end_col,
+                linked_note_id, anchor
+            FROM 
+                links"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(links) => Ok(links),
+            Err(e) => Err(eyre!("Failed to load links: {:?}", e)),
+        }
+    }
+
     pub(crate) async fn load_note_identifiers(db: &SqlitePool) -> Result<Vec<NoteIdentifier>> {
         let result = sqlx::query_as!(NoteIdentifier, "SELECT id, title FROM notes")
             .fetch_all(db)
@@ -164,7 +243,172 @@ impl DbMac {
         }
     }
 
+    pub(crate) async fn load_note_identifiers_page(
+        db: &SqlitePool,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<NoteIdentifier>> {
+        let result = sqlx::query_as!(
+            NoteIdentifier,
+            "SELECT id, title FROM notes ORDER BY id LIMIT ? OFFSET ?",
+            limit,
+            offset
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(notes) => Ok(notes),
+            Err(e) => Err(eyre!("Failed to load note identifiers page: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn note_exists(db: &SqlitePool, id: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            "SELECT EXISTS(SELECT 1 FROM notes WHERE id=?) AS found",
+            id
+        )
+        .fetch_one(db)
+        .await;
+
+        match result {
+            Ok(row) => Ok(row.found == 1),
+            Err(e) => Err(eyre!("Failed to check note exists: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn count_notes(db: &SqlitePool) -> Result<i64> {
+        let result = sqlx::query!("SELECT COUNT(*) AS count FROM notes")
+            .fetch_one(db)
+            .await;
+
+        match result {
+            Ok(row) => Ok(row.count as i64),
+            Err(e) => Err(eyre!("Failed to count notes: {:?}", e)),
+        }
+    }
+
+    /// The note saved last, ties go to the newest note
+    pub(crate) async fn load_most_recent_note_id(db: &SqlitePool) -> Result<Option<i64>> {
+        let result = sqlx::query!("SELECT id FROM notes ORDER BY updated_at DESC, id DESC LIMIT 1")
+            .fetch_optional(db)
+            .await;
+
+        match result {
+            Ok(row) => Ok(row.map(|row| row.id)),
+            Err(e) => Err(eyre!("Failed to load most recent note: {:?}", e)),
+        }
+    }
+
+    /// Every note body, stored as is, for the vault statistics
+    pub(crate) async fn load_note_bodies(db: &SqlitePool) -> Result<Vec<String>> {
+        let result = sqlx::query!(r#"SELECT COALESCE(body, '') AS "body!: String" FROM notes"#)
+            .fetch_all(db)
+            .await;
+
+        match result {
+            Ok(rows) => Ok(rows.into_iter().map(|row| row.body).collect()),
+            Err(e) => Err(eyre!("Failed to load note bodies: {:?}", e)),
+        }
+    }
+
+    /// Notes linked from the most other notes, a note linking itself doesn't count
+    pub(crate) async fn load_most_linked(db: &SqlitePool, limit: i64) -> Result<Vec<LinkCount>> {
+        let result = sqlx::query_as!(
+            LinkCount,
+            r#"SELECT
+                notes.id AS "id!", notes.title AS "title!",
+                COUNT(DISTINCT links.parent_note_id) AS "count!: i64"
+            FROM
+                links
+                JOIN notes ON notes.id = links.linked_note_id
+                JOIN notes AS parents ON parents.id = links.parent_note_id
+            WHERE
+                links.parent_note_id != links.linked_note_id
+            GROUP BY
+                notes.id
+            ORDER BY
+                3 DESC, notes.title
+            LIMIT ?"#,
+            limit
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(counts) => Ok(counts),
+            Err(e) => Err(eyre!("Failed to load most linked notes: {:?}", e)),
+        }
+    }
+
+    /// Notes with no links in or out, sorted by title
+    pub(crate) async fn load_orphan_notes(db: &SqlitePool) -> Result<Vec<NoteIdentifier>> {
+        let result = sqlx::query_as!(
+            NoteIdentifier,
+            "SELECT id, title FROM notes
+            WHERE NOT EXISTS (
+                SELECT 1 FROM links
+                WHERE links.parent_note_id = notes.id OR links.linked_note_id = notes.id
+            )
+            ORDER BY title"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(notes) => Ok(notes),
+            Err(e) => Err(eyre!("Failed to load orphan notes: {:?}", e)),
+        }
+    }
+
+    /// Undo groups saved with a note, as stored, None if it has none
+    pub(crate) async fn load_undo_history(db: &SqlitePool, note_id: i64) -> Result<Option<String>> {
+        let result = sqlx::query!("SELECT history FROM undo_history WHERE note_id=?", note_id)
+            .fetch_optional(db)
+            .await;
+
+        match result {
+            Ok(row) => Ok(row.map(|row| row.history)),
+            Err(e) => Err(eyre!("Failed to load undo history: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn save_undo_history(
+        db: &SqlitePool,
+        note_id: i64,
+        history: &str,
+    ) -> Result<()> {
+        let result = sqlx::query!(
+            "INSERT INTO undo_history (note_id, history) VALUES (?, ?)
+            ON CONFLICT(note_id) DO UPDATE SET history = excluded.history",
+            note_id,
+            history
+        )
+        .execute(db)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(eyre!("Failed to save undo history: {:?}", e)),
+        }
+    }
+
     pub(crate) async fn delete_note(db: &SqlitePool, note_id: i64) -> Result<()> {
+        let delete_tags_result = sqlx::query!("DELETE FROM note_tags WHERE note_id=?", note_id)
+            .execute(db)
+            .await;
+        if let Err(e) = delete_tags_result {
+            return Err(eyre!("Failed to delete tags of deleted note: {:?}", e));
+        }
+
+        let delete_history_result =
+            sqlx::query!("DELETE FROM undo_history WHERE note_id=?", note_id)
+                .execute(db)
+                .await;
+        if let Err(e) = delete_history_result {
+            return Err(eyre!("Failed to delete undo history of deleted note: {:?}", e));
+        }
+
         let delete_links_result = sqlx::query!("DELETE FROM links WHERE parent_note_id=? OR linked_note_id=?", note_id, note_id)
            .execute(db)
            .await;
@@ -183,9 +427,167 @@ impl DbMac {
         }
     }
 
-    pub(crate) async fn update_links(
-        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, 
+    pub(crate) async fn load_pinned_identifiers(db: &SqlitePool) -> Result<Vec<NoteIdentifier>> {
+        let result = sqlx::query_as!(
+            NoteIdentifier,
+            "SELECT id, title FROM notes WHERE pinned = TRUE ORDER BY id"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(notes) => Ok(notes),
+            Err(e) => Err(eyre!("Failed to load pinned notes: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn set_pinned(db: &SqlitePool, id: i64, pinned: bool) -> Result<()> {
+        let result = sqlx::query!("UPDATE notes SET pinned=? WHERE id=?", pinned, id)
+            .execute(db)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(eyre!("Failed to set pinned: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn load_note_folders(db: &SqlitePool) -> Result<Vec<NoteFolder>> {
+        let result = sqlx::query_as!(
+            NoteFolder,
+            r#"SELECT id, folder AS "folder!" FROM notes WHERE folder IS NOT NULL ORDER BY id"#
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(folders) => Ok(folders),
+            Err(e) => Err(eyre!("Failed to load note folders: {:?}", e)),
+        }
+    }
+
+    /// None moves the note back to the root
+    pub(crate) async fn set_folder(db: &SqlitePool, id: i64, folder: Option<&str>) -> Result<()> {
+        let result = sqlx::query!("UPDATE notes SET folder=? WHERE id=?", folder, id)
+            .execute(db)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(eyre!("Failed to set folder: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn load_note_tags(db: &SqlitePool) -> Result<Vec<NoteTag>> {
+        let result = sqlx::query_as!(
+            NoteTag,
+            "SELECT note_id AS id, tag FROM note_tags ORDER BY note_id, tag"
+        )
+        .fetch_all(db)
+        .await;
+
+        match result {
+            Ok(tags) => Ok(tags),
+            Err(e) => Err(eyre!("Failed to load note tags: {:?}", e)),
+        }
+    }
+
+    /// Adds `tag` to every note in `ids`, or takes it off them when `tagged` is false.
+    /// Either every note changes or none do.
+    pub(crate) async fn tag_notes(
         db: &SqlitePool,
+        ids: &[i64],
+        tag: &str,
+        tagged: bool,
+    ) -> Result<()> {
+        let mut tx = db.begin().await?;
+        for &id in ids {
+            let query = match tagged {
+                true => sqlx::query!(
+                    "INSERT OR IGNORE INTO note_tags (note_id, tag) VALUES (?, ?)",
+                    id,
+                    tag
+                ),
+                false => sqlx::query!("DELETE FROM note_tags WHERE note_id=? AND tag=?", id, tag),
+            };
+
+            if let Err(e) = query.execute(&mut *tx).await {
+                tx.rollback().await?;
+                return Err(eyre!("Failed to tag notes: {:?}", e));
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Salt and sealed check text, None until encryption is first turned on
+    pub(crate) async fn load_encryption(db: &SqlitePool) -> Result<Option<(Vec<u8>, String)>> {
+        let result = sqlx::query!("SELECT salt, check_value FROM encryption WHERE id = 1")
+            .fetch_optional(db)
+            .await;
+
+        match result {
+            Ok(row) => Ok(row.map(|row| (row.salt, row.check_value))),
+            Err(e) => Err(eyre!("Failed to load encryption settings: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn save_encryption(
+        db: &SqlitePool,
+        salt: &[u8],
+        check_value: &str,
+    ) -> Result<()> {
+        let result = sqlx::query!(
+            "INSERT INTO encryption (id, salt, check_value) VALUES (1, ?, ?)",
+            salt,
+            check_value
+        )
+        .execute(db)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(eyre!("Failed to save encryption settings: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn prune_orphan_links(db: &SqlitePool) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM links
+            WHERE
+                parent_note_id NOT IN (SELECT id FROM notes)
+                OR linked_note_id NOT IN (SELECT id FROM notes)"
+        )
+        .execute(db)
+        .await;
+
+        match result {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => Err(eyre!("Failed to prune orphaned links: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn update_note_body(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        body: &str,
+        id: i64,
+    ) -> Result<()> {
+        let result = sqlx::query!(
+            "UPDATE notes SET body=?, updated_at=CAST(strftime('%s', 'now') AS INTEGER) WHERE id=?",
+            body,
+            id
+        )
+        .execute(&mut **tx)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(eyre!("Failed to update note body: {:?}", e)),
+        }
+    }
+
+    pub(crate) async fn update_links(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
         links: Vec<DbNoteLink>,
         parent_note_id: i64,
     ) -> Result<()> {
@@ -201,7 +603,7 @@ impl DbMac {
                 link.linked_note_id, 
                 link.textarea_id,
             )
-            .execute(db)
+            .execute(&mut **tx)
             .await;
 
             if let Err(e) = result {
@@ -213,14 +615,14 @@ impl DbMac {
     }
 
     pub(crate) async fn save_links(
-        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, 
-        db: &SqlitePool,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
         links: Vec<DbNoteLink>,
         parent_note_id: i64,
     ) -> Result<()> {
         let mut query_builder = QueryBuilder::new(
             "INSERT INTO links 
-                (textarea_id, textarea_row, start_col, end_col, parent_note_id, linked_note_id) ",
+                (textarea_id, textarea_row, start_col, end_col,
+                parent_note_id, linked_note_id, anchor) ",
         );
 
         query_builder.push_values(links.into_iter(), |mut b, link| {
@@ -229,12 +631,13 @@ impl DbMac {
                 .push_bind(link.start_col)
                 .push_bind(link.end_col)
                 .push_bind(parent_note_id)
-                .push_bind(link.linked_note_id);
+                .push_bind(link.linked_note_id)
+                .push_bind(link.anchor);
         });
 
         let query = query_builder.build();
 
-        let result = query.execute(db).await;
+        let result = query.execute(&mut **tx).await;
 
         match result {
             Ok(_) => Ok(()),
@@ -243,8 +646,7 @@ impl DbMac {
     }
 
     pub(crate) async fn delete_links(
-        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, 
-        db: &SqlitePool, 
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
         link_identifiers: Vec<(i64, i64)>
     ) -> Result<()> {
         let where_clause = link_identifiers.iter().map(|&(parent_note_id, textarea_id)| {
@@ -254,7 +656,7 @@ impl DbMac {
         let query_str = format!(r#"DELETE FROM links WHERE {}"#, where_clause);
         
         let result = sqlx::query(&query_str)
-            .execute(db)  
+            .execute(&mut **tx)
             .await;
         
         match result {
@@ -263,3 +665,194 @@ impl DbMac {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db::create_test_db;
+
+    fn db_link(parent_note_id: i64, textarea_id: i64, linked_note_id: i64) -> DbNoteLink {
+        DbNoteLink {
+            parent_note_id,
+            textarea_id,
+            textarea_row: 0,
+            start_col: 0,
+            end_col: 4,
+            linked_note_id,
+            anchor: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_link_transaction_rolls_back_saved_links() {
+        let db = create_test_db().await;
+        let parent_id = DbMac::save_note(&db, "Parent", "link", true).await.unwrap();
+        let linked_id = DbMac::save_note(&db, "Linked", "", false).await.unwrap();
+
+        let mut tx = db.begin().await.unwrap();
+        DbMac::save_links(&mut tx, vec![db_link(parent_id, 0, linked_id)], parent_id)
+            .await
+            .expect("links should save inside the transaction");
+
+        // An empty WHERE clause is invalid SQL, forcing the delete step to fail
+        let delete_result = DbMac::delete_links(&mut tx, vec![]).await;
+        assert!(delete_result.is_err());
+        tx.rollback().await.unwrap();
+
+        let links = DbMac::load_note_links(&db, parent_id).await.unwrap();
+        assert!(links.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prune_orphan_links() {
+        let db = create_test_db().await;
+        let parent_id = DbMac::save_note(&db, "Parent", "link link", true).await.unwrap();
+        let linked_id = DbMac::save_note(&db, "Linked", "", false).await.unwrap();
+
+        let mut tx = db.begin().await.unwrap();
+        DbMac::save_links(
+            &mut tx,
+            vec![db_link(parent_id, 0, linked_id), db_link(parent_id, 1, 999)],
+            parent_id,
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        let pruned = DbMac::prune_orphan_links(&db).await.unwrap();
+        assert_eq!(pruned, 1);
+
+        let links = DbMac::load_note_links(&db, parent_id).await.unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].linked_note_id, linked_id);
+    }
+
+    #[tokio::test]
+    async fn test_set_folder_round_trips() {
+        let db = create_test_db().await;
+        let first_id = DbMac::save_note(&db, "First", "", false).await.unwrap();
+        let second_id = DbMac::save_note(&db, "Second", "", false).await.unwrap();
+
+        DbMac::set_folder(&db, first_id, Some("work/meetings")).await.unwrap();
+        DbMac::set_folder(&db, second_id, Some("home")).await.unwrap();
+        DbMac::set_folder(&db, second_id, None).await.unwrap();
+
+        let folders = DbMac::load_note_folders(&db).await.unwrap();
+        assert_eq!(
+            folders,
+            [NoteFolder {
+                id: first_id,
+                folder: "work/meetings".to_owned(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tag_notes_and_delete_drops_tags() {
+        let db = create_test_db().await;
+        let first_id = DbMac::save_note(&db, "First", "", false).await.unwrap();
+        let second_id = DbMac::save_note(&db, "Second", "", false).await.unwrap();
+
+        DbMac::tag_notes(&db, &[first_id, second_id], "work", true).await.unwrap();
+        // Tagging twice is a no-op rather than a duplicate key error
+        DbMac::tag_notes(&db, &[first_id], "work", true).await.unwrap();
+        DbMac::tag_notes(&db, &[second_id], "idea", true).await.unwrap();
+        DbMac::tag_notes(&db, &[first_id], "work", false).await.unwrap();
+        assert_eq!(
+            DbMac::load_note_tags(&db).await.unwrap(),
+            [
+                NoteTag {
+                    id: second_id,
+                    tag: "idea".to_owned(),
+                },
+                NoteTag {
+                    id: second_id,
+                    tag: "work".to_owned(),
+                },
+            ]
+        );
+
+        DbMac::delete_note(&db, second_id).await.unwrap();
+        assert!(DbMac::load_note_tags(&db).await.unwrap().is_empty());
+    }
+
+    /// A and B link C, C links A and itself, D is on its own
+    async fn stats_fixture(db: &SqlitePool) -> [i64; 4] {
+        let mut ids = [0; 4];
+        for (idx, title) in ["A", "B", "C", "D"].into_iter().enumerate() {
+            ids[idx] = DbMac::save_note(db, title, "", false).await.unwrap();
+        }
+        let [a, b, c, _] = ids;
+
+        let mut tx = db.begin().await.unwrap();
+        DbMac::save_links(&mut tx, vec![db_link(a, 0, c), db_link(a, 1, c)], a)
+            .await
+            .unwrap();
+        DbMac::save_links(&mut tx, vec![db_link(b, 0, c)], b).await.unwrap();
+        DbMac::save_links(&mut tx, vec![db_link(c, 0, a), db_link(c, 1, c)], c)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_orphan_notes() {
+        let db = create_test_db().await;
+        let [_, _, _, d] = stats_fixture(&db).await;
+        let e = DbMac::save_note(&db, "E", "", false).await.unwrap();
+
+        let orphans = DbMac::load_orphan_notes(&db).await.unwrap();
+        assert_eq!(
+            orphans.iter().map(|nid| nid.id).collect::<Vec<i64>>(),
+            [d, e]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_most_linked_counts_distinct_linking_notes() {
+        let db = create_test_db().await;
+        let [a, _, c, _] = stats_fixture(&db).await;
+
+        let most_linked = DbMac::load_most_linked(&db, 5).await.unwrap();
+        assert_eq!(
+            most_linked,
+            [
+                LinkCount {
+                    id: c,
+                    title: "C".to_owned(),
+                    count: 2,
+                },
+                LinkCount {
+                    id: a,
+                    title: "A".to_owned(),
+                    count: 1,
+                },
+            ]
+        );
+        assert_eq!(DbMac::load_most_linked(&db, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_load_note_identifiers_page_boundaries() {
+        let db = create_test_db().await;
+        for i in 0..5 {
+            DbMac::save_note(&db, &format!("Note {}", i), "", false)
+                .await
+                .unwrap();
+        }
+
+        let first = DbMac::load_note_identifiers_page(&db, 0, 2).await.unwrap();
+        let last = DbMac::load_note_identifiers_page(&db, 4, 2).await.unwrap();
+        let past_end = DbMac::load_note_identifiers_page(&db, 5, 2).await.unwrap();
+
+        assert_eq!(
+            first.iter().map(|nid| nid.title.as_str()).collect::<Vec<_>>(),
+            vec!["Note 0", "Note 1"]
+        );
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].title, "Note 4");
+        assert!(past_end.is_empty());
+        assert_eq!(DbMac::count_notes(&db).await.unwrap(), 5);
+    }
+}
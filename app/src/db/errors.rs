@@ -0,0 +1,70 @@
+use std::error::Error;
+use std::fmt;
+
+use color_eyre::eyre::{self, eyre};
+
+#[derive(Debug)]
+pub(crate) struct DbError(pub Box<dyn Error + Send + Sync>);
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for DbError {}
+
+impl From<eyre::Report> for DbError {
+    fn from(report: eyre::Report) -> Self {
+        DbError(report.into())
+    }
+}
+
+impl From<sqlx::Error> for DbError {
+    fn from(err: sqlx::Error) -> Self {
+        DbError(err.into())
+    }
+}
+
+pub(crate) fn create_db_error(msg: String) -> DbError {
+    let err = eyre!(msg);
+    DbError::from(err)
+}
+
+#[derive(Debug)]
+pub(crate) struct DuplicateTitleError(pub String);
+impl fmt::Display for DuplicateTitleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "A note titled {:?} already exists", self.0)
+    }
+}
+
+impl Error for DuplicateTitleError {}
+
+/// The database was made by a newer tuipaz, with a schema this one doesn't know
+#[derive(Debug)]
+pub(crate) struct NewerSchemaError {
+    pub(crate) found: i64,
+    pub(crate) supported: i64,
+}
+impl fmt::Display for NewerSchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The database is at schema version {}, this tuipaz only knows up to {}. \
+            Update tuipaz to use it.",
+            self.found, self.supported
+        )
+    }
+}
+
+impl Error for NewerSchemaError {}
+
+#[derive(Debug)]
+pub(crate) struct WrongPassphraseError;
+impl fmt::Display for WrongPassphraseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Wrong passphrase, the notes can't be decrypted")
+    }
+}
+
+impl Error for WrongPassphraseError {}
@@ -0,0 +1,642 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use super::{
+    db_mac::{DbNoteLink, Note, NoteFolder, NoteIdentifier, NoteTag},
+    errors::DuplicateTitleError,
+    store::{BodyRewrite, LinkChanges, NoteStore},
+};
+
+/// Sidecar holding everything about the notes but their bodies
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileEntry {
+    id: i64,
+    title: String,
+    /// Name of the body's `.md` file, relative to the store's directory
+    file: String,
+    has_links: bool,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    folder: Option<String>,
+    #[serde(default)]
+    tags: BTreeSet<String>,
+    /// Sealed like the body when the notes are encrypted
+    #[serde(default)]
+    undo_history: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileIndex {
+    next_id: i64,
+    notes: Vec<FileEntry>,
+    links: Vec<DbNoteLink>,
+}
+
+impl Default for FileIndex {
+    fn default() -> Self {
+        Self {
+            next_id: 1,
+            notes: vec![],
+            links: vec![],
+        }
+    }
+}
+
+impl FileIndex {
+    fn entry(&self, id: i64) -> Result<&FileEntry> {
+        self.notes
+            .iter()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| eyre!("No note with id {}", id))
+    }
+
+    fn entry_mut(&mut self, id: i64) -> Result<&mut FileEntry> {
+        self.notes
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| eyre!("No note with id {}", id))
+    }
+
+    fn check_title(&self, title: &str, id: Option<i64>) -> Result<()> {
+        match self
+            .notes
+            .iter()
+            .any(|entry| entry.title == title && Some(entry.id) != id)
+        {
+            true => Err(DuplicateTitleError(title.to_owned()).into()),
+            false => Ok(()),
+        }
+    }
+
+    /// `<title>.md`, or `<title> (<id>).md` when another note already has that file
+    fn file_name(&self, title: &str, id: i64) -> String {
+        let stem = title
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect::<String>();
+        let file = format!("{}.md", stem);
+
+        match self
+            .notes
+            .iter()
+            .any(|entry| entry.file == file && entry.id != id)
+        {
+            true => format!("{} ({}).md", stem, id),
+            false => file,
+        }
+    }
+
+    fn move_links(&mut self, parent_note_id: i64, moved: Vec<DbNoteLink>) {
+        for link in moved {
+            if let Some(saved) = self.links.iter_mut().find(|saved| {
+                saved.parent_note_id == parent_note_id
+                    && saved.linked_note_id == link.linked_note_id
+                    && saved.textarea_id == link.textarea_id
+            }) {
+                saved.textarea_row = link.textarea_row;
+                saved.start_col = link.start_col;
+                saved.end_col = link.end_col;
+            }
+        }
+    }
+}
+
+/// Notes kept as `.md` files in `dir`, with titles, links, pins, folders, tags and undo
+/// history in `index.json`
+#[derive(Debug, Clone)]
+pub(crate) struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    async fn read_index(&self) -> Result<FileIndex> {
+        match fs::read_to_string(self.dir.join(INDEX_FILE)).await {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| eyre!("Failed to read note index: {:?}", e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(FileIndex::default()),
+            Err(e) => Err(eyre!("Failed to read note index: {:?}", e)),
+        }
+    }
+
+    /// Writes `contents` beside `file`, for `commit_file` to put in its place
+    async fn stage_file(&self, file: &str, contents: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir).await?;
+        fs::write(self.dir.join(format!("{}.tmp", file)), contents).await
+    }
+
+    async fn commit_file(&self, file: &str) -> io::Result<()> {
+        fs::rename(self.dir.join(format!("{}.tmp", file)), self.dir.join(file)).await
+    }
+
+    /// Written beside the old file and renamed over it, so a failed write loses nothing
+    async fn replace_file(&self, file: &str, contents: &str) -> io::Result<()> {
+        self.stage_file(file, contents).await?;
+        self.commit_file(file).await
+    }
+
+    async fn write_index(&self, index: &FileIndex) -> Result<()> {
+        let json = serde_json::to_string_pretty(index)
+            .map_err(|e| eyre!("Failed to write note index: {:?}", e))?;
+        self.replace_file(INDEX_FILE, &json)
+            .await
+            .map_err(|e| eyre!("Failed to write note index: {:?}", e))
+    }
+
+    async fn write_body(&self, file: &str, body: &str) -> Result<()> {
+        self.replace_file(file, body)
+            .await
+            .map_err(|e| eyre!("Failed to save note: {:?}", e))
+    }
+
+    async fn remove_file(&self, file: &str) -> Result<()> {
+        match fs::remove_file(self.dir.join(file)).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(eyre!("Failed to delete note file: {:?}", e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl NoteStore for FileStore {
+    async fn save_note(&self, title: &str, body: &str, has_links: bool) -> Result<i64> {
+        let mut index = self.read_index().await?;
+        index.check_title(title, None)?;
+
+        let id = index.next_id;
+        let file = index.file_name(title, id);
+        self.write_body(&file, body).await?;
+
+        index.next_id += 1;
+        index.notes.push(FileEntry {
+            id,
+            title: title.to_owned(),
+            file,
+            has_links,
+            pinned: false,
+            folder: None,
+            tags: BTreeSet::new(),
+            undo_history: None,
+        });
+        self.write_index(&index).await?;
+        Ok(id)
+    }
+
+    async fn update_note(
+        &self,
+        title: &str,
+        body: &str,
+        has_links: bool,
+        id: i64,
+    ) -> Result<i64> {
+        let mut index = self.read_index().await?;
+        index.check_title(title, Some(id))?;
+
+        let old_file = index.entry(id)?.file.clone();
+        let file = index.file_name(title, id);
+        self.write_body(&file, body).await?;
+
+        let entry = index.entry_mut(id)?;
+        entry.title = title.to_owned();
+        entry.file = file.clone();
+        entry.has_links = has_links;
+        self.write_index(&index).await?;
+
+        // Only once the index points at the new file
+        if file != old_file {
+            self.remove_file(&old_file).await?;
+        }
+        Ok(id)
+    }
+
+    async fn load_note(&self, id: i64) -> Result<Note> {
+        let index = self.read_index().await?;
+        let entry = index.entry(id)?;
+        let body = fs::read_to_string(self.dir.join(&entry.file))
+            .await
+            .map_err(|e| eyre!("Failed to load note: {:?}", e))?;
+
+        Ok(Note {
+            id,
+            title: entry.title.clone(),
+            body: Some(body),
+            has_links: entry.has_links,
+        })
+    }
+
+    async fn load_note_identifiers(&self) -> Result<Vec<NoteIdentifier>> {
+        Ok(self
+            .read_index()
+            .await?
+            .notes
+            .into_iter()
+            .map(|entry| NoteIdentifier {
+                id: entry.id,
+                title: entry.title,
+            })
+            .collect())
+    }
+
+    async fn load_note_identifiers_page(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<NoteIdentifier>> {
+        let mut nids = self.load_note_identifiers().await?;
+        nids.sort_by_key(|nid| nid.id);
+
+        Ok(nids
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn count_notes(&self) -> Result<i64> {
+        Ok(self.read_index().await?.notes.len() as i64)
+    }
+
+    async fn note_exists(&self, id: i64) -> Result<bool> {
+        Ok(self.read_index().await?.entry(id).is_ok())
+    }
+
+    /// Goes by when each body file was last written
+    async fn load_most_recent_note_id(&self) -> Result<Option<i64>> {
+        let mut most_recent = None;
+        for entry in self.read_index().await?.notes {
+            let Ok(meta) = fs::metadata(self.dir.join(&entry.file)).await else {
+                continue;
+            };
+            if let Ok(modified) = meta.modified() {
+                most_recent = most_recent.max(Some((modified, entry.id)));
+            }
+        }
+        Ok(most_recent.map(|(_, id)| id))
+    }
+
+    async fn delete_note(&self, id: i64) -> Result<()> {
+        let mut index = self.read_index().await?;
+        let file = index.entry(id)?.file.clone();
+
+        index.notes.retain(|entry| entry.id != id);
+        index
+            .links
+            .retain(|link| link.parent_note_id != id && link.linked_note_id != id);
+        self.write_index(&index).await?;
+        self.remove_file(&file).await
+    }
+
+    async fn load_note_links(&self, parent_note_id: i64) -> Result<Vec<DbNoteLink>> {
+        let mut links = self.read_index().await?.links;
+        links.retain(|link| link.parent_note_id == parent_note_id);
+        Ok(links)
+    }
+
+    async fn load_backlinks(&self, linked_note_id: i64) -> Result<Vec<DbNoteLink>> {
+        let mut links = self.read_index().await?.links;
+        links.retain(|link| link.linked_note_id == linked_note_id);
+        Ok(links)
+    }
+
+    async fn load_all_links(&self) -> Result<Vec<DbNoteLink>> {
+        Ok(self.read_index().await?.links)
+    }
+
+    async fn sync_links(&self, parent_note_id: i64, changes: LinkChanges) -> Result<()> {
+        let mut index = self.read_index().await?;
+
+        index.move_links(parent_note_id, changes.updated);
+        index.links.retain(|link| {
+            link.parent_note_id != parent_note_id
+                || !changes.deleted.contains(&link.textarea_id)
+        });
+        index.links.extend(changes.saved.into_iter().map(|link| DbNoteLink {
+            parent_note_id,
+            ..link
+        }));
+        self.write_index(&index).await
+    }
+
+    /// Every new body is written out beside its note before any replaces one, so only a
+    /// failed rename can leave part of the rewrites done
+    async fn rewrite_note_bodies(&self, rewrites: Vec<BodyRewrite>) -> Result<()> {
+        let mut index = self.read_index().await?;
+        let mut files = vec![];
+        for rewrite in &rewrites {
+            let file = index.entry(rewrite.id)?.file.clone();
+            self.stage_file(&file, &rewrite.body)
+                .await
+                .map_err(|e| eyre!("Failed to save note: {:?}", e))?;
+            files.push(file);
+        }
+        for file in files {
+            self.commit_file(&file)
+                .await
+                .map_err(|e| eyre!("Failed to save note: {:?}", e))?;
+        }
+
+        for rewrite in rewrites {
+            index.move_links(rewrite.id, rewrite.links);
+        }
+        self.write_index(&index).await
+    }
+
+    async fn load_pinned_identifiers(&self) -> Result<Vec<NoteIdentifier>> {
+        let mut pinned = self
+            .read_index()
+            .await?
+            .notes
+            .into_iter()
+            .filter(|entry| entry.pinned)
+            .map(|entry| NoteIdentifier {
+                id: entry.id,
+                title: entry.title,
+            })
+            .collect::<Vec<NoteIdentifier>>();
+        pinned.sort_by_key(|nid| nid.id);
+        Ok(pinned)
+    }
+
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<()> {
+        let mut index = self.read_index().await?;
+        index.entry_mut(id)?.pinned = pinned;
+        self.write_index(&index).await
+    }
+
+    async fn load_note_folders(&self) -> Result<Vec<NoteFolder>> {
+        let mut folders = self
+            .read_index()
+            .await?
+            .notes
+            .into_iter()
+            .filter_map(|entry| {
+                entry.folder.map(|folder| NoteFolder {
+                    id: entry.id,
+                    folder,
+                })
+            })
+            .collect::<Vec<NoteFolder>>();
+        folders.sort_by_key(|folder| folder.id);
+        Ok(folders)
+    }
+
+    async fn set_folder(&self, id: i64, folder: Option<&str>) -> Result<()> {
+        let mut index = self.read_index().await?;
+        index.entry_mut(id)?.folder = folder.map(str::to_owned);
+        self.write_index(&index).await
+    }
+
+    async fn load_undo_history(&self, note_id: i64) -> Result<Option<String>> {
+        Ok(self.read_index().await?.entry(note_id)?.undo_history.clone())
+    }
+
+    async fn save_undo_history(&self, note_id: i64, history: &str) -> Result<()> {
+        let mut index = self.read_index().await?;
+        index.entry_mut(note_id)?.undo_history = Some(history.to_owned());
+        self.write_index(&index).await
+    }
+
+    async fn load_note_tags(&self) -> Result<Vec<NoteTag>> {
+        let mut notes = self.read_index().await?.notes;
+        notes.sort_by_key(|entry| entry.id);
+
+        Ok(notes
+            .into_iter()
+            .flat_map(|entry| {
+                let id = entry.id;
+                entry.tags.into_iter().map(move |tag| NoteTag { id, tag })
+            })
+            .collect())
+    }
+
+    /// The index is written once, with every note changed
+    async fn tag_notes(&self, ids: &[i64], tag: &str, tagged: bool) -> Result<()> {
+        let mut index = self.read_index().await?;
+        for &id in ids {
+            let tags = &mut index.entry_mut(id)?.tags;
+            match tagged {
+                true => tags.insert(tag.to_owned()),
+                false => tags.remove(tag),
+            };
+        }
+        self.write_index(&index).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_store(name: &str) -> FileStore {
+        let dir = std::env::temp_dir().join(name);
+        std::fs::remove_dir_all(&dir).ok();
+        FileStore::new(dir)
+    }
+
+    fn link(textarea_id: i64, textarea_row: i64, linked_note_id: i64) -> DbNoteLink {
+        DbNoteLink {
+            parent_note_id: 0,
+            textarea_id,
+            textarea_row,
+            start_col: 0,
+            end_col: 4,
+            linked_note_id,
+            anchor: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_store_save_load_list_delete() {
+        let store = test_store("tuipaz_test_file_store_notes");
+
+        let first = store.save_note("Shopping", "- milk", false).await.unwrap();
+        let second = store.save_note("a/b", "slashes", false).await.unwrap();
+        let err = store.save_note("Shopping", "", false).await.unwrap_err();
+        assert!(err.is::<DuplicateTitleError>());
+        assert!(store.dir().join("Shopping.md").exists());
+        assert!(store.dir().join("a_b.md").exists());
+
+        let note = store.load_note(first).await.unwrap();
+        assert_eq!(note.title, "Shopping");
+        assert_eq!(note.body.as_deref(), Some("- milk"));
+
+        store.update_note("Groceries", "- eggs", true, first).await.unwrap();
+        assert!(!store.dir().join("Shopping.md").exists());
+        let note = FileStore::new(store.dir().to_path_buf())
+            .load_note(first)
+            .await
+            .unwrap();
+        assert_eq!(note.title, "Groceries");
+        assert_eq!(note.body.as_deref(), Some("- eggs"));
+        assert!(note.has_links);
+
+        let titles = store
+            .load_note_identifiers()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|nid| nid.title)
+            .collect::<Vec<String>>();
+        assert_eq!(titles, ["Groceries", "a/b"]);
+        let page = store.load_note_identifiers_page(1, 5).await.unwrap();
+        assert_eq!(page[0].id, second);
+        assert_eq!(store.count_notes().await.unwrap(), 2);
+
+        store.delete_note(first).await.unwrap();
+        let exists = store.note_exists(first).await.unwrap();
+        let count = store.count_notes().await.unwrap();
+        let file_left = store.dir().join("Groceries.md").exists();
+        let reused = store.save_note("Groceries", "", false).await.unwrap();
+        std::fs::remove_dir_all(store.dir()).ok();
+
+        assert!(!exists);
+        assert_eq!(count, 1);
+        assert!(!file_left);
+        assert!(reused > second);
+    }
+
+    #[tokio::test]
+    async fn test_file_store_links_round_trip() {
+        let store = test_store("tuipaz_test_file_store_links");
+        let parent = store.save_note("Parent", "[Child] [Other]", true).await.unwrap();
+        let child = store.save_note("Child", "", false).await.unwrap();
+        let other = store.save_note("Other", "", false).await.unwrap();
+
+        let changes = LinkChanges {
+            saved: vec![link(0, 0, child), link(1, 0, other)],
+            ..Default::default()
+        };
+        store.sync_links(parent, changes).await.unwrap();
+
+        let reopened = FileStore::new(store.dir().to_path_buf());
+        let links = reopened.load_note_links(parent).await.unwrap();
+        assert_eq!(links.len(), 2);
+        assert!(links.iter().all(|link| link.parent_note_id == parent));
+        assert_eq!(reopened.load_backlinks(child).await.unwrap().len(), 1);
+
+        let changes = LinkChanges {
+            updated: vec![link(0, 3, child)],
+            deleted: vec![1],
+            ..Default::default()
+        };
+        reopened.sync_links(parent, changes).await.unwrap();
+        let links = reopened.load_note_links(parent).await.unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].textarea_row, 3);
+
+        let rewrite = BodyRewrite {
+            id: parent,
+            body: "\n[Child]".to_owned(),
+            links: vec![link(0, 1, child)],
+        };
+        reopened.rewrite_note_bodies(vec![rewrite]).await.unwrap();
+        let links = reopened.load_note_links(parent).await.unwrap();
+        assert_eq!(links[0].textarea_row, 1);
+        let body = reopened.load_note(parent).await.unwrap().body;
+        assert_eq!(body.as_deref(), Some("\n[Child]"));
+
+        reopened.delete_note(child).await.unwrap();
+        let all_links = reopened.load_all_links().await.unwrap();
+        std::fs::remove_dir_all(store.dir()).ok();
+        assert!(all_links.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_file_store_sync_links_replaces_a_deleted_and_saved_link() {
+        let store = test_store("tuipaz_test_file_store_relink");
+        let parent = store.save_note("Parent", "[Child]", true).await.unwrap();
+        let child = store.save_note("Child", "", false).await.unwrap();
+        let other = store.save_note("Other", "", false).await.unwrap();
+        let changes = LinkChanges {
+            saved: vec![link(0, 0, child)],
+            ..Default::default()
+        };
+        store.sync_links(parent, changes).await.unwrap();
+
+        let changes = LinkChanges {
+            deleted: vec![0],
+            saved: vec![link(0, 0, other)],
+            ..Default::default()
+        };
+        store.sync_links(parent, changes).await.unwrap();
+        let links = store.load_note_links(parent).await.unwrap();
+        std::fs::remove_dir_all(store.dir()).ok();
+
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].linked_note_id, other);
+    }
+
+    #[tokio::test]
+    async fn test_file_store_keeps_pins_folders_and_history() {
+        let store = test_store("tuipaz_test_file_store_note_state");
+        let first = store.save_note("First", "", false).await.unwrap();
+        let second = store.save_note("Second", "", false).await.unwrap();
+
+        store.set_pinned(second, true).await.unwrap();
+        store.set_folder(first, Some("work/plans")).await.unwrap();
+        store.save_undo_history(first, "[]").await.unwrap();
+        store.update_note("First", "edited", false, first).await.unwrap();
+
+        let reopened = FileStore::new(store.dir().to_path_buf());
+        let pinned = reopened.load_pinned_identifiers().await.unwrap();
+        let folders = reopened.load_note_folders().await.unwrap();
+        let history = reopened.load_undo_history(first).await.unwrap();
+        let none = reopened.load_undo_history(second).await.unwrap();
+        reopened.set_folder(first, None).await.unwrap();
+        let moved_back = reopened.load_note_folders().await.unwrap();
+        std::fs::remove_dir_all(store.dir()).ok();
+
+        assert_eq!(pinned.iter().map(|nid| nid.id).collect::<Vec<i64>>(), [second]);
+        assert_eq!(folders.len(), 1);
+        assert_eq!((folders[0].id, folders[0].folder.as_str()), (first, "work/plans"));
+        assert_eq!(history.as_deref(), Some("[]"));
+        assert_eq!(none, None);
+        assert!(moved_back.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_file_store_tags_notes_together() {
+        let store = test_store("tuipaz_test_file_store_tags");
+        let first = store.save_note("First", "", false).await.unwrap();
+        let second = store.save_note("Second", "", false).await.unwrap();
+
+        store.tag_notes(&[second, first], "work", true).await.unwrap();
+        store.tag_notes(&[first], "home", true).await.unwrap();
+        let missing = store.tag_notes(&[second, 99], "late", true).await;
+        store.tag_notes(&[second], "work", false).await.unwrap();
+
+        let tags = FileStore::new(store.dir().to_path_buf())
+            .load_note_tags()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tag| (tag.id, tag.tag))
+            .collect::<Vec<(i64, String)>>();
+        std::fs::remove_dir_all(store.dir()).ok();
+
+        assert!(missing.is_err());
+        assert_eq!(tags, [(first, "home".to_owned()), (first, "work".to_owned())]);
+    }
+}
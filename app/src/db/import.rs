@@ -0,0 +1,140 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Result};
+use log::{error, info};
+use crate::tui::inputs::{clean_title, numbered_title};
+
+use super::{
+    crypto::NoteCipher,
+    db_mac::NoteIdentifier,
+    store::NoteStore,
+};
+
+/// Notes made by an import and the files that couldn't be read or saved
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ImportSummary {
+    pub(crate) imported: Vec<NoteIdentifier>,
+    pub(crate) skipped: usize,
+}
+
+/// The first `# ` heading, or the file name when there is none
+pub(crate) fn import_title(path: &Path, body: &str) -> Option<String> {
+    body.lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .and_then(clean_title)
+        .or_else(|| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(clean_title)
+        })
+}
+
+/// `.md` files under `dir` and its subdirectories, in path order
+fn markdown_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(markdown_files(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Saves every Markdown file under `dir` as a note. Titles already in the store,
+/// or taken earlier in the import, get a number. Only a missing or unreadable `dir`
+/// fails the import, bad files are skipped.
+pub(crate) async fn import_dir(
+    store: &impl NoteStore,
+    dir: &Path,
+    cipher: Option<&NoteCipher>,
+) -> Result<ImportSummary> {
+    let files = markdown_files(dir).map_err(|e| eyre!("Can't read {}: {}", dir.display(), e))?;
+    let mut taken = store
+        .load_note_identifiers()
+        .await?
+        .into_iter()
+        .map(|nid| nid.title)
+        .collect::<HashSet<String>>();
+
+    let mut summary = ImportSummary::default();
+    for path in files {
+        let body = match fs::read_to_string(&path) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("import_dir::can't read {:?}: {}", path, e);
+                summary.skipped += 1;
+                continue;
+            }
+        };
+        let Some(base) = import_title(&path, &body) else {
+            info!("import_dir::no title for {:?}", path);
+            summary.skipped += 1;
+            continue;
+        };
+
+        let title = numbered_title(&base, |title| taken.contains(title));
+        let body = match cipher {
+            Some(cipher) => cipher.seal(&body)?,
+            None => body,
+        };
+        match store.save_note(&title, &body, false).await {
+            Ok(id) => {
+                taken.insert(title.clone());
+                summary.imported.push(NoteIdentifier { id, title });
+            }
+            Err(e) => {
+                error!("import_dir::can't save {:?}: {:?}", path, e);
+                summary.skipped += 1;
+            }
+        }
+    }
+
+    info!(
+        "import_dir::imported {}, skipped {}",
+        summary.imported.len(),
+        summary.skipped
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{db_mac::DbMac, init_db::create_test_db};
+
+    #[tokio::test]
+    async fn test_import_dir_titles_and_collisions() {
+        let dir = std::env::temp_dir().join("tuipaz_test_import");
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("a.md"), "intro\n# Shopping \n- milk").unwrap();
+        fs::write(dir.join("nested").join("Shopping.md"), "no heading").unwrap();
+        fs::write(dir.join("notes.txt"), "not markdown").unwrap();
+
+        let db = create_test_db().await;
+        DbMac::save_note(&db, "Shopping (2)", "", false).await.unwrap();
+        let summary = import_dir(&db, &dir, None).await;
+        fs::remove_dir_all(&dir).ok();
+        let summary = summary.expect("import should succeed");
+
+        let titles = summary
+            .imported
+            .iter()
+            .map(|nid| nid.title.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(titles, ["Shopping", "Shopping (3)"]);
+        assert_eq!(summary.skipped, 0);
+
+        let note = DbMac::load_note(&db, summary.imported[0].id).await.unwrap();
+        assert_eq!(note.body.as_deref(), Some("intro\n# Shopping \n- milk"));
+        assert!(import_dir(&db, Path::new("does/not/exist"), None).await.is_err());
+    }
+}
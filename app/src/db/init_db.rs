@@ -1,43 +1,110 @@
+use std::path::Path;
+
+use log::{info, warn};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
 
-use crate::tui::errors::{create_db_error, DbError};
+use super::{
+    backup::{backup_db, BACKUP_DIR},
+    db_mac::DbMac,
+    errors::{create_db_error, DbError, NewerSchemaError},
+    migrations::{latest_version, run_migrations, schema_version},
+};
+
+pub(crate) const DB_FILE: &str = "notes.db";
 
-pub(crate) async fn create_db() -> Result<SqlitePool, DbError> {
+/// Opens the notes database, backing up the existing file first unless `backup_count` is 0
+pub(crate) async fn create_db(
+    backup_count: usize,
+    allow_newer_schema: bool,
+) -> Result<(SqlitePool, Option<NewerSchemaError>), DbError> {
+    backup_db(Path::new(DB_FILE), Path::new(BACKUP_DIR), backup_count);
+    open_db(Path::new(DB_FILE), allow_newer_schema).await
+}
+
+/// Connects to the database at `path`, creating and migrating it as needed.
+/// A database from a newer tuipaz is refused, or with `allow_newer_schema` opened as it
+/// is and the error handed back as a warning.
+pub(crate) async fn open_db(
+    path: &Path,
+    allow_newer_schema: bool,
+) -> Result<(SqlitePool, Option<NewerSchemaError>), DbError> {
     let opts = SqliteConnectOptions::new()
-        .filename("notes.db")
+        .filename(path)
         .create_if_missing(true);
 
     let conn = SqlitePool::connect_with(opts)
         .await
         .map_err(|e| create_db_error(e.to_string()))?;
 
-    let _notes_table_query = sqlx::query(
-        "CREATE TABLE IF NOT EXISTS notes (
-            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
-            title TEXT NOT NULL UNIQUE,
-            body TEXT,
-            has_links BOOL NOT NULL
-        );",
-    )
-    .execute(&conn)
-    .await?;
-
-    // Create the links table if it doesn't exist
-    let _links_table_query = sqlx::query(
-        "CREATE TABLE IF NOT EXISTS links (
-            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
-            textarea_id INTEGER NOT NULL,
-            textarea_row INTEGER NOT NULL,
-            start_col INTEGER NOT NULL,
-            end_col INTEGER NOT NULL,
-            parent_note_id INTEGER NOT NULL,
-            linked_note_id INTEGER NOT NULL,
-            FOREIGN KEY(parent_note_id) REFERENCES notes(id),
-            FOREIGN KEY(linked_note_id) REFERENCES notes(id)
-        );",
-    )
-    .execute(&conn)
-    .await?;
-
-    Ok(conn)
+    let found = schema_version(&conn).await?;
+    if found > latest_version() {
+        let newer = NewerSchemaError {
+            found,
+            supported: latest_version(),
+        };
+        if !allow_newer_schema {
+            conn.close().await;
+            return Err(DbError(Box::new(newer)));
+        }
+        warn!("open_db::{}", newer);
+        return Ok((conn, Some(newer)));
+    }
+
+    let applied = run_migrations(&conn).await?;
+    info!("create_db::applied migrations: {}", applied);
+
+    // Links can outlive their notes if they were removed outside of tuipaz
+    let pruned = DbMac::prune_orphan_links(&conn).await?;
+    info!("create_db::pruned orphaned links: {}", pruned);
+
+    Ok((conn, None))
+}
+
+/// In-memory database with the app schema, used by tests.
+/// A single connection is required, otherwise each pooled connection
+/// would see its own empty in-memory database.
+#[cfg(test)]
+pub(crate) async fn create_test_db() -> SqlitePool {
+    let conn = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("in-memory database should open");
+
+    run_migrations(&conn)
+        .await
+        .expect("test schema should migrate");
+
+    conn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_newer_schema_is_refused_or_opened_with_a_warning() {
+        let path = std::env::temp_dir().join("tuipaz_test_newer_schema.db");
+        std::fs::remove_file(&path).ok();
+        let (conn, newer) = open_db(&path, false).await.unwrap();
+        assert!(newer.is_none());
+        let future = latest_version() + 1;
+        sqlx::query(&format!("PRAGMA user_version = {};", future))
+            .execute(&conn)
+            .await
+            .unwrap();
+        conn.close().await;
+
+        let err = open_db(&path, false).await.unwrap_err();
+        assert!(err.0.is::<NewerSchemaError>());
+
+        let (conn, newer) = open_db(&path, true).await.unwrap();
+        let newer = newer.expect("the newer schema should be reported");
+        let version = schema_version(&conn).await.unwrap();
+        conn.close().await;
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!((newer.found, newer.supported), (future, latest_version()));
+        assert_eq!(version, future);
+    }
 }
@@ -0,0 +1,189 @@
+use log::info;
+use sqlx::sqlite::SqlitePool;
+
+use super::errors::DbError;
+
+/// Schema changes in order, migration N takes the database to version N.
+/// Released migrations are never edited, changes go in a new one.
+pub(crate) const MIGRATIONS: &[&[&str]] = &[
+    // 1: notes and the links between them
+    &[
+        "CREATE TABLE IF NOT EXISTS notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            title TEXT NOT NULL UNIQUE,
+            body TEXT
+        );",
+        "CREATE TABLE IF NOT EXISTS links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+            textarea_id INTEGER NOT NULL,
+            textarea_row INTEGER NOT NULL,
+            start_col INTEGER NOT NULL,
+            end_col INTEGER NOT NULL,
+            parent_note_id INTEGER NOT NULL,
+            linked_note_id INTEGER NOT NULL,
+            FOREIGN KEY(parent_note_id) REFERENCES notes(id),
+            FOREIGN KEY(linked_note_id) REFERENCES notes(id)
+        );",
+    ],
+    // 2: has_links flag, filled in from the links table
+    &[
+        "ALTER TABLE notes ADD COLUMN has_links BOOL NOT NULL DEFAULT FALSE;",
+        "UPDATE notes SET has_links = EXISTS (
+            SELECT 1 FROM links WHERE links.parent_note_id = notes.id
+        );",
+    ],
+    // 3: notes pinned to the top of the sidebar
+    &["ALTER TABLE notes ADD COLUMN pinned BOOL NOT NULL DEFAULT FALSE;"],
+    // 4: folder path of each note like "work/meetings", NULL at the root
+    &["ALTER TABLE notes ADD COLUMN folder TEXT;"],
+    // 5: salt for the encryption key and a sealed known text to check passphrases with
+    &["CREATE TABLE IF NOT EXISTS encryption (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        salt BLOB NOT NULL,
+        check_value TEXT NOT NULL
+    );"],
+    // 6: recent undo groups of each note, as JSON, so undo outlives the session
+    &["CREATE TABLE IF NOT EXISTS undo_history (
+        note_id INTEGER PRIMARY KEY NOT NULL,
+        history TEXT NOT NULL,
+        FOREIGN KEY(note_id) REFERENCES notes(id)
+    );"],
+    // 7: unix time of each note's last save, 0 for notes saved before it was kept
+    &["ALTER TABLE notes ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;"],
+    // 8: free-form tags, any number per note
+    &["CREATE TABLE IF NOT EXISTS note_tags (
+        note_id INTEGER NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY(note_id, tag),
+        FOREIGN KEY(note_id) REFERENCES notes(id)
+    );"],
+    // 9: heading each link jumps to in its note, NULL for the top
+    &["ALTER TABLE links ADD COLUMN anchor TEXT;"],
+];
+
+/// Databases made before versioning already have this schema
+const UNVERSIONED_SCHEMA_VERSION: i64 = 2;
+
+pub(crate) fn latest_version() -> i64 {
+    MIGRATIONS.len() as i64
+}
+
+pub(crate) async fn schema_version(conn: &SqlitePool) -> Result<i64, DbError> {
+    let version = sqlx::query_scalar::<_, i64>("PRAGMA user_version;")
+        .fetch_one(conn)
+        .await?;
+    Ok(version)
+}
+
+/// Applies every migration past the database's version, each in its own transaction.
+/// Returns how many were applied.
+pub(crate) async fn run_migrations(conn: &SqlitePool) -> Result<usize, DbError> {
+    let mut version = schema_version(conn).await?;
+    if version == 0 && has_unversioned_schema(conn).await? {
+        version = UNVERSIONED_SCHEMA_VERSION;
+        set_version(conn, version).await?;
+        info!("run_migrations::unversioned database at version {}", version);
+    }
+
+    let pending = MIGRATIONS.iter().enumerate().skip(version as usize);
+    let mut applied = 0;
+    for (idx, statements) in pending {
+        let mut tx = conn.begin().await?;
+        for statement in statements.iter() {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        sqlx::query(&format!("PRAGMA user_version = {};", idx + 1))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        info!("run_migrations::migrated to version {}", idx + 1);
+        applied += 1;
+    }
+
+    Ok(applied)
+}
+
+async fn set_version(conn: &SqlitePool, version: i64) -> Result<(), DbError> {
+    sqlx::query(&format!("PRAGMA user_version = {};", version))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// An unversioned notes table that already has the `has_links` column
+async fn has_unversioned_schema(conn: &SqlitePool) -> Result<bool, DbError> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM pragma_table_info('notes') WHERE name = 'has_links';",
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn empty_db() -> SqlitePool {
+        sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("in-memory database should open")
+    }
+
+    #[tokio::test]
+    async fn test_migrate_fresh_db_is_idempotent() {
+        let db = empty_db().await;
+
+        assert_eq!(run_migrations(&db).await.unwrap(), MIGRATIONS.len());
+        assert_eq!(schema_version(&db).await.unwrap(), latest_version());
+        assert_eq!(run_migrations(&db).await.unwrap(), 0);
+        assert_eq!(schema_version(&db).await.unwrap(), latest_version());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_old_schema_db() {
+        let db = empty_db().await;
+        for statement in MIGRATIONS[0] {
+            sqlx::query(statement).execute(&db).await.unwrap();
+        }
+        sqlx::query("INSERT INTO notes (title, body) VALUES ('Parent', 'see Child'), ('Child', '');")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO links (textarea_id, textarea_row, start_col, end_col, parent_note_id, linked_note_id)
+            VALUES (0, 0, 4, 8, 1, 2);",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        assert_eq!(run_migrations(&db).await.unwrap(), MIGRATIONS.len());
+        assert_eq!(run_migrations(&db).await.unwrap(), 0);
+
+        let has_links = sqlx::query_scalar::<_, bool>("SELECT has_links FROM notes ORDER BY id;")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(has_links, [true, false]);
+        assert_eq!(schema_version(&db).await.unwrap(), latest_version());
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_db_is_not_migrated_twice() {
+        let db = empty_db().await;
+        for migration in MIGRATIONS.iter().take(UNVERSIONED_SCHEMA_VERSION as usize) {
+            for statement in migration.iter() {
+                sqlx::query(statement).execute(&db).await.unwrap();
+            }
+        }
+
+        let applied = run_migrations(&db).await.unwrap();
+
+        assert_eq!(applied, MIGRATIONS.len() - UNVERSIONED_SCHEMA_VERSION as usize);
+        assert_eq!(schema_version(&db).await.unwrap(), latest_version());
+    }
+}
@@ -1,2 +1,9 @@
+pub(crate) mod backup;
+pub(crate) mod crypto;
 pub(crate) mod db_mac;
+pub(crate) mod errors;
+pub(crate) mod file_store;
+pub(crate) mod import;
 pub(crate) mod init_db;
+pub(crate) mod migrations;
+pub(crate) mod store;
@@ -0,0 +1,544 @@
+use std::{collections::HashMap, path::Path};
+
+use color_eyre::eyre::{eyre, Result};
+use sqlx::SqlitePool;
+
+use crate::tui::config::Storage;
+
+use super::{
+    crypto::NoteCipher,
+    db_mac::{DbMac, DbNoteLink, LinkCount, Note, NoteFolder, NoteIdentifier, NoteTag},
+    file_store::FileStore,
+};
+
+/// A note's link edits, applied together by `NoteStore::sync_links`. Deletes go before
+/// saves, so a link can be replaced by deleting and saving its textarea id.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LinkChanges {
+    /// Saved links whose position in the text moved
+    pub(crate) updated: Vec<DbNoteLink>,
+    pub(crate) saved: Vec<DbNoteLink>,
+    /// Textarea ids of the links to drop
+    pub(crate) deleted: Vec<i64>,
+}
+
+impl LinkChanges {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.saved.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// A note's new body and where its links now sit, see `NoteStore::rewrite_note_bodies`
+#[derive(Debug, Clone)]
+pub(crate) struct BodyRewrite {
+    pub(crate) id: i64,
+    pub(crate) body: String,
+    pub(crate) links: Vec<DbNoteLink>,
+}
+
+/// Notes and the links between them. Bodies are stored as given, sealing them is
+/// up to the caller.
+pub(crate) trait NoteStore {
+    async fn save_note(&self, title: &str, body: &str, has_links: bool) -> Result<i64>;
+    async fn update_note(&self, title: &str, body: &str, has_links: bool, id: i64)
+        -> Result<i64>;
+    async fn load_note(&self, id: i64) -> Result<Note>;
+    async fn load_note_identifiers(&self) -> Result<Vec<NoteIdentifier>>;
+    async fn load_note_identifiers_page(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<NoteIdentifier>>;
+    async fn count_notes(&self) -> Result<i64>;
+    async fn note_exists(&self, id: i64) -> Result<bool>;
+    /// The note saved last, ties go to the newest note
+    async fn load_most_recent_note_id(&self) -> Result<Option<i64>>;
+    /// Removes the note along with links from and to it
+    async fn delete_note(&self, id: i64) -> Result<()>;
+    async fn load_note_links(&self, parent_note_id: i64) -> Result<Vec<DbNoteLink>>;
+    async fn load_backlinks(&self, linked_note_id: i64) -> Result<Vec<DbNoteLink>>;
+    async fn load_all_links(&self) -> Result<Vec<DbNoteLink>>;
+    /// All of the changes are kept, or none of them when one fails
+    async fn sync_links(&self, parent_note_id: i64, changes: LinkChanges) -> Result<()>;
+    /// Swaps the bodies of several notes, moving their links to where their text now is.
+    /// All of the rewrites are kept, or none of them when one fails.
+    async fn rewrite_note_bodies(&self, rewrites: Vec<BodyRewrite>) -> Result<()>;
+    /// Pinned notes in id order, whatever page of the list they are in
+    async fn load_pinned_identifiers(&self) -> Result<Vec<NoteIdentifier>>;
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<()>;
+    /// Folders of the notes that aren't at the root, in id order
+    async fn load_note_folders(&self) -> Result<Vec<NoteFolder>>;
+    /// None moves the note back to the root
+    async fn set_folder(&self, id: i64, folder: Option<&str>) -> Result<()>;
+    /// A note's undo groups as saved, sealed like its body
+    async fn load_undo_history(&self, note_id: i64) -> Result<Option<String>>;
+    async fn save_undo_history(&self, note_id: i64, history: &str) -> Result<()>;
+    /// Every tag on every note, sorted by note then tag
+    async fn load_note_tags(&self) -> Result<Vec<NoteTag>>;
+    /// Adds `tag` to every note in `ids`, or takes it off them when `tagged` is false.
+    /// Either every note changes or none do.
+    async fn tag_notes(&self, ids: &[i64], tag: &str, tagged: bool) -> Result<()>;
+
+    /// Title and the first `max_lines` lines of a note's body
+    async fn load_note_preview(
+        &self,
+        id: i64,
+        max_lines: usize,
+        cipher: Option<&NoteCipher>,
+    ) -> Result<(String, Vec<String>)> {
+        let note = self.load_note(id).await?;
+        let body = note.body.unwrap_or_default();
+        let body = match cipher {
+            Some(cipher) => cipher.open(&body)?,
+            None => body,
+        };
+        let lines = body
+            .lines()
+            .take(max_lines)
+            .map(str::to_owned)
+            .collect();
+
+        Ok((note.title, lines))
+    }
+
+    /// Every note body, stored as is, for the vault statistics
+    async fn load_note_bodies(&self) -> Result<Vec<String>> {
+        let mut bodies = vec![];
+        for nid in self.load_note_identifiers().await? {
+            bodies.push(self.load_note(nid.id).await?.body.unwrap_or_default());
+        }
+        Ok(bodies)
+    }
+
+    /// Notes linked from the most other notes, a note linking itself doesn't count
+    async fn load_most_linked(&self, limit: i64) -> Result<Vec<LinkCount>> {
+        let titles = self
+            .load_note_identifiers()
+            .await?
+            .into_iter()
+            .map(|nid| (nid.id, nid.title))
+            .collect::<HashMap<i64, String>>();
+
+        let mut links = self
+            .load_all_links()
+            .await?
+            .into_iter()
+            .filter(|link| link.parent_note_id != link.linked_note_id)
+            .filter(|link| titles.contains_key(&link.parent_note_id))
+            .map(|link| (link.linked_note_id, link.parent_note_id))
+            .collect::<Vec<(i64, i64)>>();
+        links.sort();
+        links.dedup();
+
+        let mut counts = HashMap::new();
+        for (linked_id, _) in links {
+            *counts.entry(linked_id).or_insert(0) += 1;
+        }
+        let mut most_linked = counts
+            .into_iter()
+            .filter_map(|(id, count)| {
+                titles.get(&id).map(|title| LinkCount {
+                    id,
+                    title: title.clone(),
+                    count,
+                })
+            })
+            .collect::<Vec<LinkCount>>();
+        most_linked.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.title.cmp(&b.title)));
+        most_linked.truncate(limit.max(0) as usize);
+
+        Ok(most_linked)
+    }
+
+    /// Notes with no links in or out, sorted by title
+    async fn load_orphan_notes(&self) -> Result<Vec<NoteIdentifier>> {
+        let links = self.load_all_links().await?;
+        let mut orphans = self
+            .load_note_identifiers()
+            .await?
+            .into_iter()
+            .filter(|nid| {
+                !links
+                    .iter()
+                    .any(|link| link.parent_note_id == nid.id || link.linked_note_id == nid.id)
+            })
+            .collect::<Vec<NoteIdentifier>>();
+        orphans.sort_by(|a, b| a.title.cmp(&b.title));
+
+        Ok(orphans)
+    }
+}
+
+impl NoteStore for SqlitePool {
+    async fn save_note(&self, title: &str, body: &str, has_links: bool) -> Result<i64> {
+        DbMac::save_note(self, title, body, has_links).await
+    }
+
+    async fn update_note(
+        &self,
+        title: &str,
+        body: &str,
+        has_links: bool,
+        id: i64,
+    ) -> Result<i64> {
+        DbMac::update_note(self, title, body, has_links, id).await
+    }
+
+    async fn load_note(&self, id: i64) -> Result<Note> {
+        DbMac::load_note(self, id).await
+    }
+
+    async fn load_note_identifiers(&self) -> Result<Vec<NoteIdentifier>> {
+        DbMac::load_note_identifiers(self).await
+    }
+
+    async fn load_note_identifiers_page(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<NoteIdentifier>> {
+        DbMac::load_note_identifiers_page(self, offset, limit).await
+    }
+
+    async fn count_notes(&self) -> Result<i64> {
+        DbMac::count_notes(self).await
+    }
+
+    async fn note_exists(&self, id: i64) -> Result<bool> {
+        DbMac::note_exists(self, id).await
+    }
+
+    async fn load_most_recent_note_id(&self) -> Result<Option<i64>> {
+        DbMac::load_most_recent_note_id(self).await
+    }
+
+    async fn load_note_bodies(&self) -> Result<Vec<String>> {
+        DbMac::load_note_bodies(self).await
+    }
+
+    async fn load_most_linked(&self, limit: i64) -> Result<Vec<LinkCount>> {
+        DbMac::load_most_linked(self, limit).await
+    }
+
+    async fn load_orphan_notes(&self) -> Result<Vec<NoteIdentifier>> {
+        DbMac::load_orphan_notes(self).await
+    }
+
+    async fn delete_note(&self, id: i64) -> Result<()> {
+        DbMac::delete_note(self, id).await
+    }
+
+    async fn load_note_links(&self, parent_note_id: i64) -> Result<Vec<DbNoteLink>> {
+        DbMac::load_note_links(self, parent_note_id).await
+    }
+
+    async fn load_backlinks(&self, linked_note_id: i64) -> Result<Vec<DbNoteLink>> {
+        DbMac::load_backlinks(self, linked_note_id).await
+    }
+
+    async fn load_all_links(&self) -> Result<Vec<DbNoteLink>> {
+        DbMac::load_all_links(self).await
+    }
+
+    async fn sync_links(&self, parent_note_id: i64, changes: LinkChanges) -> Result<()> {
+        let mut tx = self.begin().await?;
+        let result = apply_link_changes(&mut tx, parent_note_id, changes).await;
+
+        match result {
+            Ok(_) => {
+                tx.commit().await?;
+                Ok(())
+            }
+            Err(e) => {
+                tx.rollback().await?;
+                Err(eyre!("Transaction error::sync_links: {:?}", e))
+            }
+        }
+    }
+
+    async fn rewrite_note_bodies(&self, rewrites: Vec<BodyRewrite>) -> Result<()> {
+        let mut tx = self.begin().await?;
+        let result = apply_body_rewrites(&mut tx, rewrites).await;
+
+        match result {
+            Ok(_) => {
+                tx.commit().await?;
+                Ok(())
+            }
+            Err(e) => {
+                tx.rollback().await?;
+                Err(eyre!("Transaction error::rewrite_note_bodies: {:?}", e))
+            }
+        }
+    }
+
+    async fn load_pinned_identifiers(&self) -> Result<Vec<NoteIdentifier>> {
+        DbMac::load_pinned_identifiers(self).await
+    }
+
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<()> {
+        DbMac::set_pinned(self, id, pinned).await
+    }
+
+    async fn load_note_folders(&self) -> Result<Vec<NoteFolder>> {
+        DbMac::load_note_folders(self).await
+    }
+
+    async fn set_folder(&self, id: i64, folder: Option<&str>) -> Result<()> {
+        DbMac::set_folder(self, id, folder).await
+    }
+
+    async fn load_undo_history(&self, note_id: i64) -> Result<Option<String>> {
+        DbMac::load_undo_history(self, note_id).await
+    }
+
+    async fn save_undo_history(&self, note_id: i64, history: &str) -> Result<()> {
+        DbMac::save_undo_history(self, note_id, history).await
+    }
+
+    async fn load_note_tags(&self) -> Result<Vec<NoteTag>> {
+        DbMac::load_note_tags(self).await
+    }
+
+    async fn tag_notes(&self, ids: &[i64], tag: &str, tagged: bool) -> Result<()> {
+        DbMac::tag_notes(self, ids, tag, tagged).await
+    }
+}
+
+async fn apply_body_rewrites(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    rewrites: Vec<BodyRewrite>,
+) -> Result<()> {
+    for rewrite in rewrites {
+        DbMac::update_note_body(tx, &rewrite.body, rewrite.id).await?;
+        DbMac::update_links(tx, rewrite.links, rewrite.id).await?;
+    }
+    Ok(())
+}
+
+async fn apply_link_changes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    parent_note_id: i64,
+    changes: LinkChanges,
+) -> Result<()> {
+    if !changes.updated.is_empty() {
+        DbMac::update_links(tx, changes.updated, parent_note_id).await?;
+    }
+    if !changes.deleted.is_empty() {
+        let link_identifiers = changes
+            .deleted
+            .into_iter()
+            .map(|textarea_id| (parent_note_id, textarea_id))
+            .collect();
+        DbMac::delete_links(tx, link_identifiers).await?;
+    }
+    if !changes.saved.is_empty() {
+        DbMac::save_links(tx, changes.saved, parent_note_id).await?;
+    }
+    Ok(())
+}
+
+/// The backend picked by `general.storage`
+#[derive(Debug, Clone)]
+pub(crate) enum Store {
+    Db(SqlitePool),
+    Files(FileStore),
+}
+
+impl Store {
+    /// File storage keeps the notes of the vault at `db_path` in a directory of the
+    /// same name, less the extension
+    pub(crate) fn new(storage: Storage, db: &SqlitePool, db_path: &Path) -> Self {
+        match storage {
+            Storage::Sqlite => Self::Db(db.clone()),
+            Storage::Files => Self::Files(FileStore::new(db_path.with_extension(""))),
+        }
+    }
+}
+
+impl NoteStore for Store {
+    async fn save_note(&self, title: &str, body: &str, has_links: bool) -> Result<i64> {
+        match self {
+            Self::Db(db) => db.save_note(title, body, has_links).await,
+            Self::Files(files) => files.save_note(title, body, has_links).await,
+        }
+    }
+
+    async fn update_note(
+        &self,
+        title: &str,
+        body: &str,
+        has_links: bool,
+        id: i64,
+    ) -> Result<i64> {
+        match self {
+            Self::Db(db) => db.update_note(title, body, has_links, id).await,
+            Self::Files(files) => files.update_note(title, body, has_links, id).await,
+        }
+    }
+
+    async fn load_note(&self, id: i64) -> Result<Note> {
+        match self {
+            Self::Db(db) => db.load_note(id).await,
+            Self::Files(files) => files.load_note(id).await,
+        }
+    }
+
+    async fn load_note_identifiers(&self) -> Result<Vec<NoteIdentifier>> {
+        match self {
+            Self::Db(db) => db.load_note_identifiers().await,
+            Self::Files(files) => files.load_note_identifiers().await,
+        }
+    }
+
+    async fn load_note_identifiers_page(
+        &self,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<NoteIdentifier>> {
+        match self {
+            Self::Db(db) => db.load_note_identifiers_page(offset, limit).await,
+            Self::Files(files) => files.load_note_identifiers_page(offset, limit).await,
+        }
+    }
+
+    async fn count_notes(&self) -> Result<i64> {
+        match self {
+            Self::Db(db) => db.count_notes().await,
+            Self::Files(files) => files.count_notes().await,
+        }
+    }
+
+    async fn note_exists(&self, id: i64) -> Result<bool> {
+        match self {
+            Self::Db(db) => db.note_exists(id).await,
+            Self::Files(files) => files.note_exists(id).await,
+        }
+    }
+
+    async fn load_most_recent_note_id(&self) -> Result<Option<i64>> {
+        match self {
+            Self::Db(db) => db.load_most_recent_note_id().await,
+            Self::Files(files) => files.load_most_recent_note_id().await,
+        }
+    }
+
+    async fn load_note_bodies(&self) -> Result<Vec<String>> {
+        match self {
+            Self::Db(db) => db.load_note_bodies().await,
+            Self::Files(files) => files.load_note_bodies().await,
+        }
+    }
+
+    async fn load_most_linked(&self, limit: i64) -> Result<Vec<LinkCount>> {
+        match self {
+            Self::Db(db) => db.load_most_linked(limit).await,
+            Self::Files(files) => files.load_most_linked(limit).await,
+        }
+    }
+
+    async fn load_orphan_notes(&self) -> Result<Vec<NoteIdentifier>> {
+        match self {
+            Self::Db(db) => db.load_orphan_notes().await,
+            Self::Files(files) => files.load_orphan_notes().await,
+        }
+    }
+
+    async fn delete_note(&self, id: i64) -> Result<()> {
+        match self {
+            Self::Db(db) => db.delete_note(id).await,
+            Self::Files(files) => files.delete_note(id).await,
+        }
+    }
+
+    async fn load_note_links(&self, parent_note_id: i64) -> Result<Vec<DbNoteLink>> {
+        match self {
+            Self::Db(db) => db.load_note_links(parent_note_id).await,
+            Self::Files(files) => files.load_note_links(parent_note_id).await,
+        }
+    }
+
+    async fn load_backlinks(&self, linked_note_id: i64) -> Result<Vec<DbNoteLink>> {
+        match self {
+            Self::Db(db) => db.load_backlinks(linked_note_id).await,
+            Self::Files(files) => files.load_backlinks(linked_note_id).await,
+        }
+    }
+
+    async fn load_all_links(&self) -> Result<Vec<DbNoteLink>> {
+        match self {
+            Self::Db(db) => db.load_all_links().await,
+            Self::Files(files) => files.load_all_links().await,
+        }
+    }
+
+    async fn sync_links(&self, parent_note_id: i64, changes: LinkChanges) -> Result<()> {
+        match self {
+            Self::Db(db) => db.sync_links(parent_note_id, changes).await,
+            Self::Files(files) => files.sync_links(parent_note_id, changes).await,
+        }
+    }
+
+    async fn rewrite_note_bodies(&self, rewrites: Vec<BodyRewrite>) -> Result<()> {
+        match self {
+            Self::Db(db) => db.rewrite_note_bodies(rewrites).await,
+            Self::Files(files) => files.rewrite_note_bodies(rewrites).await,
+        }
+    }
+
+    async fn load_pinned_identifiers(&self) -> Result<Vec<NoteIdentifier>> {
+        match self {
+            Self::Db(db) => db.load_pinned_identifiers().await,
+            Self::Files(files) => files.load_pinned_identifiers().await,
+        }
+    }
+
+    async fn set_pinned(&self, id: i64, pinned: bool) -> Result<()> {
+        match self {
+            Self::Db(db) => db.set_pinned(id, pinned).await,
+            Self::Files(files) => files.set_pinned(id, pinned).await,
+        }
+    }
+
+    async fn load_note_folders(&self) -> Result<Vec<NoteFolder>> {
+        match self {
+            Self::Db(db) => db.load_note_folders().await,
+            Self::Files(files) => files.load_note_folders().await,
+        }
+    }
+
+    async fn set_folder(&self, id: i64, folder: Option<&str>) -> Result<()> {
+        match self {
+            Self::Db(db) => db.set_folder(id, folder).await,
+            Self::Files(files) => files.set_folder(id, folder).await,
+        }
+    }
+
+    async fn load_undo_history(&self, note_id: i64) -> Result<Option<String>> {
+        match self {
+            Self::Db(db) => db.load_undo_history(note_id).await,
+            Self::Files(files) => files.load_undo_history(note_id).await,
+        }
+    }
+
+    async fn save_undo_history(&self, note_id: i64, history: &str) -> Result<()> {
+        match self {
+            Self::Db(db) => db.save_undo_history(note_id, history).await,
+            Self::Files(files) => files.save_undo_history(note_id, history).await,
+        }
+    }
+
+    async fn load_note_tags(&self) -> Result<Vec<NoteTag>> {
+        match self {
+            Self::Db(db) => db.load_note_tags().await,
+            Self::Files(files) => files.load_note_tags().await,
+        }
+    }
+
+    async fn tag_notes(&self, ids: &[i64], tag: &str, tagged: bool) -> Result<()> {
+        match self {
+            Self::Db(db) => db.tag_notes(ids, tag, tagged).await,
+            Self::Files(files) => files.tag_notes(ids, tag, tagged).await,
+        }
+    }
+}
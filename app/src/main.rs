@@ -1,23 +1,62 @@
 mod db;
 mod tui;
 
-use std::path::PathBuf;
+use std::{
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process,
+};
 
 use log::{LevelFilter, info};
 use log4rs::config::{Config as LogConfig, Root, Appender};
 use log4rs::encode::pattern::PatternEncoder;
 use log4rs::append::file::FileAppender;
 use color_eyre::Result;
-use db::{db_mac::DbMac, init_db};
+use db::{
+    crypto,
+    init_db::{self, DB_FILE},
+    store::{NoteStore, Store},
+};
 use dotenv::dotenv;
 use tui::app::{run, App};
-use tui::config::Config;
+use tui::config::{Config, Startup};
+use tui::events::Events;
+use tui::note_list::NOTE_PAGE_SIZE;
+use tui::user_messages::{MessageType, UserMessage};
 
-use crate::tui::config::try_load_config;
+use crate::tui::config::{check_config, try_load_config};
+
+/// `--check-config`, prints what's wrong with `config.toml` and returns the exit code
+fn check_config_file() -> i32 {
+    let content = match fs::read_to_string("config.toml") {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            println!("No config.toml in the current directory, the defaults are used");
+            return 0;
+        }
+        Err(e) => {
+            eprintln!("Failed to read config.toml: {}", e);
+            return 1;
+        }
+    };
+    let problems = check_config(&content);
+    if problems.is_empty() {
+        println!("config.toml is valid");
+        return 0;
+    }
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+    1
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv().ok();
+    if env::args().skip(1).any(|arg| arg == "--check-config") {
+        process::exit(check_config_file());
+    }
     
     // Initialize logging
     let path = PathBuf::from("./logs/application.log");
@@ -37,12 +76,32 @@ async fn main() -> Result<()> {
     let seperator = "-".repeat(40);
     info!("{}NEW SESSION{}\n", seperator, seperator);
     tui::errors::install_hooks()?;
-    let db = init_db::create_db().await?;
+    let config: Config = try_load_config()?;
+    let (db, newer_schema) =
+        init_db::create_db(config.general.backup_count, config.general.allow_newer_schema).await?;
+    let cipher = match config.general.encryption {
+        true => Some(crypto::prompt_unlock(&db).await?),
+        false => None,
+    };
     let mut term = tui::utils::init()?;
-    let note_titles = DbMac::load_note_identifiers(&db).await?;
+    let store = Store::new(config.general.storage, &db, Path::new(DB_FILE));
+    let note_titles = store.load_note_identifiers_page(0, NOTE_PAGE_SIZE).await?;
+    let note_count = store.count_notes().await? as usize;
     let term_size = term.size().expect("Terminal should have a size").width;
-    let config: Config = try_load_config()?;
-    let mut app = App::new(config, db, note_titles, term_size);
+    let mut warnings = config.warnings.clone();
+    let mut app = App::new(config, db, note_titles, note_count, term_size);
+    app.cipher = cipher;
+    if let Some(newer) = newer_schema {
+        warnings.push(format!("{} Opened anyway, saving may fail.", newer));
+    }
+    if !warnings.is_empty() {
+        // The welcome screen is where the warning gets read, whatever `startup` says
+        app.startup = Startup::Welcome;
+        app.user_msg = UserMessage::new(warnings.join("; "), MessageType::Warning);
+    }
+    Events::load_pins(&mut app).await?;
+    Events::load_folders(&mut app).await?;
+    Events::load_tags(&mut app).await?;
     run(&mut app, &mut term).await?;
     tui::utils::restore()?;
     info!("{}END SESSION{}\n", seperator, seperator);
@@ -1,23 +1,47 @@
-use std::collections::HashMap;
+use std::{
+    cmp,
+    collections::{BTreeMap, HashMap},
+    mem,
+    path::Path,
+    time::Instant,
+};
 
 use color_eyre::eyre::{Context, Result};
-use log::info;
+use crossterm::event;
+use log::{error, info};
+use ratatui::layout::Rect;
 use sqlx::{Pool, Sqlite};
 
-use crate::db::db_mac::NoteIdentifier;
+use crate::db::{
+    crypto::NoteCipher,
+    db_mac::NoteIdentifier,
+    init_db::DB_FILE,
+    store::Store,
+};
 use tuipaz_textarea::{Input, Link as TextAreaLink};
 
 use super::{
     buttons::{Button, ButtonAction},
-    config::Config,
+    config::{BorderStyle, Config, ConfirmQuit, Startup, StatuslineSettings, Storage},
     editor::{Editor, EditorTheme},
+    graph::GraphView,
+    link_list::LinkList,
+    link_preview::LinkPreview,
+    link_suggest::LinkSuggestions,
+    lock::IdleLock,
+    picker::Picker,
     events::{Action, Events},
     inputs::{InputAction, UserInput},
+    jump_list::{JumpList, JUMP_LIST_CAP},
     note_list::{NoteList, NoteListAction, NoteListMode, NoteListTheme, SelectionStyle},
-    searchbar::{Searchbar, SearchbarTheme},
+    palette::CommandPalette,
+    searchbar::{Searchbar, SearchbarTheme, Substitute},
+    session::{Session, SESSION_FILE},
+    stats::VaultStats,
     ui::ui,
-    user_messages::UserMessage,
+    user_messages::{MessageType, UserMessage},
     utils::Tui,
+    vaults::DEFAULT_VAULT,
 };
 
 #[derive(PartialEq, Debug, Default, Clone, Copy)]
@@ -52,10 +76,35 @@ pub(crate) enum Screen {
     NewLinkedNote,
     LoadNote,
     DeleteNoteConfirmation,
-    Popup,
+    CloseTabConfirmation,
+    NavigateConfirmation,
+    RenameLinksConfirmation,
+    CreateLinkTargetConfirmation,
+    LinkExistingNote,
+    Graph,
+    Stats,
+    RecentNotes,
+    TemplatePicker,
+    VaultSwitcher,
+    CommandPalette,
+    LinkList,
+    LinkPreview,
+    ReplaceConfirmation,
+    Help,
+    /// Idle lock, nothing but the passphrase prompt is drawn
+    Locked,
     Exiting,
 }
 
+/// Sidebar width limits, in percent of the terminal width
+pub(crate) const DEFAULT_SIDEBAR_SIZE: u16 = 18;
+pub(crate) const MIN_SIDEBAR_SIZE: u16 = 12;
+pub(crate) const MAX_SIDEBAR_SIZE: u16 = 70;
+
+/// Size of an unsaved edit that asks before another note is loaded over it
+pub(crate) const DEFAULT_UNSAVED_PROMPT_LINES: usize = 20;
+pub(crate) const DEFAULT_UNSAVED_PROMPT_CHARS: usize = 800;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum SidebarState {
     Open,
@@ -68,25 +117,99 @@ pub(crate) enum SearchbarState {
     Hidden,
 }
 
+/// Layout from before distraction-free mode, put back when it is turned off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ZenLayout {
+    pub(crate) sidebar_state: SidebarState,
+    pub(crate) sidebar_size: u16,
+    pub(crate) searchbar_state: SearchbarState,
+    pub(crate) active_widget: Option<ActiveWidget>,
+}
+
 #[derive(Debug)]
 pub(crate) struct App<'a> {
     pub(crate) state: AppState,
     pub(crate) keymap: HashMap<Action, Input>,
     pub(crate) db: Pool<Sqlite>,
+    /// Where notes and links go, `db` or a directory of files
+    pub(crate) store: Store,
     pub(crate) current_screen: Screen,
     pub(crate) prev_screen: Screen,
+    /// The note in the active tab
     pub(crate) editor: Editor<'a>,
+    /// Every open tab in tab bar order. The slot at `active_tab` is stale while
+    /// that tab is focused, its live state is in `editor`.
+    pub(crate) tabs: Vec<Editor<'a>>,
+    pub(crate) active_tab: usize,
     pub(crate) note_list: NoteList,
     pub(crate) btns: [Button; 2],
     pub(crate) btn_idx: usize,
     pub(crate) user_input: UserInput<'a>,
     pub(crate) user_msg: UserMessage,
+    /// Short lived message shown in the bottom row rather than a popup
+    pub(crate) status_msg: Option<UserMessage>,
     pub(crate) sidebar_state: SidebarState,
     pub(crate) sidebar_size: u16,
     pub(crate) searchbar: Searchbar<'a>,
     pub(crate) searchbar_state: SearchbarState,
+    /// Set while in distraction-free mode
+    pub(crate) zen_layout: Option<ZenLayout>,
     pub(crate) pending_link: Option<TextAreaLink>,
+    pub(crate) pending_replace: Option<Substitute>,
+    /// Note waiting to be loaded once the unsaved edit prompt is answered
+    pub(crate) pending_navigation: Option<i64>,
+    /// Renamed note and its new title, while asking whether to retitle links to it
+    pub(crate) pending_rename: Option<(i64, String)>,
+    /// Textarea id of a broken link, while asking whether to create its note
+    pub(crate) pending_link_target: Option<usize>,
+    /// Start and end of the selection to move into a new note, while asking its title
+    pub(crate) pending_extract: Option<((usize, usize), (usize, usize))>,
+    pub(crate) unsaved_prompt_lines: usize,
+    pub(crate) unsaved_prompt_chars: usize,
+    pub(crate) confirm_delete: bool,
+    pub(crate) confirm_quit: ConfirmQuit,
+    pub(crate) allow_newer_schema: bool,
+    pub(crate) leader: char,
+    /// Actions by the keys typed after the leader
+    pub(crate) leader_bindings: BTreeMap<String, Action>,
+    /// Keys typed since the leader and when it was pressed
+    pub(crate) pending_leader: Option<(String, Instant)>,
+    pub(crate) idle_lock: Option<IdleLock>,
+    pub(crate) startup: Startup,
+    /// Set when note bodies are encrypted
+    pub(crate) cipher: Option<NoteCipher>,
+    pub(crate) statusline: StatuslineSettings,
+    pub(crate) popup_border: BorderStyle,
     pub(crate) active_widget: Option<ActiveWidget>,
+    pub(crate) help_scroll: u16,
+    pub(crate) graph: Option<GraphView>,
+    pub(crate) stats: Option<VaultStats>,
+    /// Ids of recently loaded notes, most recent first
+    pub(crate) recent_notes: Vec<i64>,
+    pub(crate) recent_notes_cap: usize,
+    pub(crate) recent_switcher: Option<Picker>,
+    pub(crate) templates: BTreeMap<String, String>,
+    pub(crate) template_picker: Option<Picker>,
+    /// Body of the picked template, used by the next new note
+    pub(crate) pending_template: Option<String>,
+    /// Set by the events loop, the main loop owns the terminal needed to act on it
+    pub(crate) external_edit_requested: bool,
+    pub(crate) vaults: BTreeMap<String, String>,
+    /// Name of the vault `db` is connected to
+    pub(crate) active_vault: String,
+    /// Kept to rebuild `store` when switching vaults
+    pub(crate) storage: Storage,
+    pub(crate) vault_switcher: Option<Picker>,
+    pub(crate) command_palette: Option<CommandPalette>,
+    pub(crate) link_list: Option<LinkList>,
+    pub(crate) link_preview: Option<LinkPreview>,
+    /// Open while typing a title after `[[` in insert mode
+    pub(crate) link_suggestions: Option<LinkSuggestions>,
+    /// Text typed after `:` in normal mode, while the command line is open
+    pub(crate) command_line: Option<String>,
+    pub(crate) jump_list: JumpList,
+    pub(crate) editor_area: Rect,
+    pub(crate) note_list_area: Rect,
     pub(crate) max_col: u16,
 }
 
@@ -95,6 +218,7 @@ impl<'a> App<'a> {
         config: Config,
         db: Pool<Sqlite>,
         note_identifiers: Vec<NoteIdentifier>,
+        note_count: usize,
         term_size: u16,
     ) -> Self {
         let load_btn_state = match note_identifiers.len() {
@@ -102,7 +226,7 @@ impl<'a> App<'a> {
             _ => ComponentState::Inactive,
         };
 
-        let max_col = term_size - 4;
+        let max_col = term_size.saturating_sub(4);
 
         let editor_theme = EditorTheme {
             title: config.theme.note_title,
@@ -112,19 +236,25 @@ impl<'a> App<'a> {
             insert_mode: config.theme.modes.insert_mode,
             visual_mode: config.theme.modes.visual_mode,
             visual_line_mode: config.theme.modes.visual_line_mode,
+            search_mode: config.theme.modes.search_mode,
             select: config.theme.highlights.select,
             search: config.theme.highlights.search,
             links: config.theme.highlights.links,
+            hop: config.theme.highlights.hop,
+            cursor: config.theme.highlights.cursor,
+            cursor_line: config.theme.highlights.cursor_line,
             main_heading: config.theme.headings.main_color,
             main_heading_modifiers: config.theme.headings.main_modifiers,
             sub_heading: config.theme.headings.sub_color,
             sub_heading_modifiers: config.theme.headings.sub_modifiers,
+            border: config.borders.editor,
         };
 
         let search_theme = SearchbarTheme {
             text: config.theme.text,
             search_mode: config.theme.modes.search_mode,
             borders: config.theme.borders,
+            border: config.borders.searchbar,
         };
 
         let note_list_theme = NoteListTheme {
@@ -136,30 +266,40 @@ impl<'a> App<'a> {
                 modifier: config.theme.notelist.selection_modifier,
             },
             borders: config.theme.borders,
+            border: config.borders.note_list,
         };
 
-        let note_list = NoteList::new(
+        let mut note_list = NoteList::new(
             note_identifiers,
+            note_count,
             NoteListAction::LoadNote,
             ComponentState::Active,
             note_list_theme,
         );
+        note_list.wrap_selection = config.notelist.wrap_selection;
+
+        let mut editor = Editor::new(
+            " Untitled ".to_owned(),
+            vec!["".to_owned()],
+            HashMap::new(),
+            None,
+            false,
+            max_col,
+            editor_theme,
+        );
+        editor.settings = config.editor.clone();
 
         Self {
             state: AppState::default(),
             keymap: config.keymap,
+            store: Store::new(config.general.storage, &db, Path::new(DB_FILE)),
+            storage: config.general.storage,
             db,
             current_screen: Screen::Welcome,
             prev_screen: Screen::Welcome,
-            editor: Editor::new(
-                " Untitled ".to_owned(),
-                vec!["".to_owned()],
-                HashMap::new(),
-                None,
-                false,
-                max_col,
-                editor_theme,
-            ),
+            editor: editor.clone(),
+            tabs: vec![editor],
+            active_tab: 0,
             note_list,
             btns: [
                 Button::new(
@@ -176,12 +316,56 @@ impl<'a> App<'a> {
             btn_idx: 0,
             user_input: UserInput::new(ComponentState::Active, InputAction::Note),
             user_msg: UserMessage::welcome(),
-            sidebar_state: SidebarState::Hidden(18),
+            status_msg: None,
+            sidebar_state: SidebarState::Hidden(config.general.sidebar_size),
             sidebar_size: 0,
             searchbar: Searchbar::new(false, ComponentState::Inactive, max_col, search_theme),
             searchbar_state: SearchbarState::Hidden,
+            zen_layout: None,
             pending_link: None,
+            pending_replace: None,
+            pending_navigation: None,
+            pending_rename: None,
+            pending_link_target: None,
+            pending_extract: None,
+            unsaved_prompt_lines: config.general.unsaved_prompt_lines,
+            unsaved_prompt_chars: config.general.unsaved_prompt_chars,
+            confirm_delete: config.general.confirm_delete,
+            confirm_quit: config.general.confirm_quit,
+            allow_newer_schema: config.general.allow_newer_schema,
+            leader: config.general.leader,
+            leader_bindings: config.leader_bindings.clone(),
+            pending_leader: None,
+            idle_lock: IdleLock::new(
+                config.general.lock_after_secs,
+                config.general.lock_passphrase_hash.as_deref(),
+            ),
+            cipher: None,
+            startup: config.general.startup,
+            statusline: config.statusline.clone(),
+            popup_border: config.borders.popups,
             active_widget: None,
+            help_scroll: 0,
+            graph: None,
+            stats: None,
+            recent_notes: vec![],
+            recent_notes_cap: config.general.recent_notes_cap,
+            recent_switcher: None,
+            templates: config.templates.clone(),
+            template_picker: None,
+            pending_template: None,
+            external_edit_requested: false,
+            vaults: config.vaults.clone(),
+            active_vault: DEFAULT_VAULT.to_owned(),
+            vault_switcher: None,
+            command_palette: None,
+            link_list: None,
+            link_preview: None,
+            link_suggestions: None,
+            command_line: None,
+            jump_list: JumpList::new(JUMP_LIST_CAP),
+            editor_area: Rect::default(),
+            note_list_area: Rect::default(),
             max_col,
         }
     }
@@ -218,7 +402,50 @@ impl<'a> App<'a> {
         &mut self.btns[self.btn_idx]
     }
 
+    /// A note body as written to the database, encrypted when encryption is on
+    pub(crate) fn seal(&self, body: &str) -> Result<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.seal(body),
+            None => Ok(body.to_owned()),
+        }
+    }
+
+    /// A note body as read from the database, decrypted when encryption is on
+    pub(crate) fn open(&self, stored: String) -> Result<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.open(&stored),
+            None => Ok(stored),
+        }
+    }
+
+    pub(crate) fn show_status(&mut self, msg: String, typ: MessageType) {
+        self.status_msg = Some(UserMessage::transient(msg, typ));
+    }
+
+    /// Clears the status message once it has been up for its duration
+    pub(crate) fn expire_status(&mut self) {
+        if self.status_msg.as_ref().is_some_and(UserMessage::expired) {
+            self.status_msg = None;
+        }
+    }
+
+    /// Swaps the screen for the lock prompt once the idle timeout has run out
+    pub(crate) fn lock_if_idle(&mut self) {
+        let Some(lock) = self.idle_lock.as_mut() else {
+            return;
+        };
+        if lock.is_locked() || !lock.idle_left().is_zero() {
+            return;
+        }
+        info!("lock_if_idle::locking after {:?}", lock.after);
+        lock.restore = Some((self.current_screen, self.prev_screen));
+        lock.failed = false;
+        self.current_screen = Screen::Locked;
+    }
+
     pub(crate) fn switch_to_main(&mut self) {
+        // A template is only picked for the next new note, however the prompt is left
+        self.pending_template = None;
         self.current_screen = Screen::Main;
         self.note_list.set_mode(NoteListMode::Sidebar);
         self.set_active_widget(ActiveWidget::Editor);
@@ -236,22 +463,97 @@ impl<'a> App<'a> {
         self.set_active_widget(ActiveWidget::NoteTitleInput);
     }
 
+    pub(crate) fn switch_tab(&mut self, idx: usize) {
+        if idx == self.active_tab || idx >= self.tabs.len() {
+            return;
+        }
+        mem::swap(&mut self.editor, &mut self.tabs[self.active_tab]);
+        self.active_tab = idx;
+        mem::swap(&mut self.editor, &mut self.tabs[idx]);
+    }
+
+    /// Opens `editor` in a new tab after the active one and focuses it
+    pub(crate) fn open_tab(&mut self, editor: Editor<'a>) {
+        let idx = self.active_tab + 1;
+        self.tabs.insert(idx, editor);
+        mem::swap(&mut self.editor, &mut self.tabs[self.active_tab]);
+        self.active_tab = idx;
+        mem::swap(&mut self.editor, &mut self.tabs[idx]);
+    }
+
+    /// Closes the active tab and focuses its neighbour.
+    /// Returns false if it was the last tab, which is left open.
+    pub(crate) fn close_tab(&mut self) -> bool {
+        if self.tabs.len() == 1 {
+            return false;
+        }
+        self.tabs.remove(self.active_tab);
+        self.active_tab = cmp::min(self.active_tab, self.tabs.len() - 1);
+        mem::swap(&mut self.editor, &mut self.tabs[self.active_tab]);
+        true
+    }
+
+    pub(crate) fn tab_index(&self, note_id: i64) -> Option<usize> {
+        (0..self.tabs.len()).find(|&idx| self.tab(idx).note_id == Some(note_id))
+    }
+
+    pub(crate) fn tab(&self, idx: usize) -> &Editor<'a> {
+        match idx == self.active_tab {
+            true => &self.editor,
+            false => &self.tabs[idx],
+        }
+    }
+
     pub(crate) fn switch_to_prev_screen(&mut self) {
         self.current_screen = self.prev_screen;
     }
 
+    /// Columns left for the editor text once the sidebar takes its share of the width.
+    /// `sidebar_size` is a percentage, `max_col` is the terminal width minus borders.
     pub(crate) fn get_max_col(&self) -> u16 {
-        self.max_col - self.sidebar_size
+        let term_width = (self.max_col + 4) as u32;
+        let editor_width = term_width * (100 - self.sidebar_size as u32) / 100;
+        (editor_width as u16).saturating_sub(4)
+    }
+
+    /// Reflows the editor after the sidebar is resized or toggled
+    pub(crate) fn resize_editor(&mut self) {
+        let max_col = self.get_max_col();
+        self.editor.set_max_col(max_col);
     }
 }
 
 pub(crate) async fn run(app: &mut App<'_>, terminal: &mut Tui) -> Result<()> {
+    let session_path = Path::new(SESSION_FILE);
+    Events::start(app, Session::load(session_path))
+        .await
+        .wrap_err("startup failed")?;
+
     // MAIN PROGRAM LOOP
     while app.state != AppState::Exit {
+        app.expire_status();
         terminal.draw(|frame| ui(app, frame))?;
+        // Waits no longer than the idle lock or the status message allow,
+        // locking if nothing comes
+        let idle_left = app.idle_lock.as_ref().map(IdleLock::idle_left);
+        let status_left = app.status_msg.as_ref().and_then(UserMessage::time_left);
+        if let Some(timeout) = idle_left.into_iter().chain(status_left).min() {
+            if app.current_screen != Screen::Locked && !event::poll(timeout)? {
+                app.lock_if_idle();
+                continue;
+            }
+        }
         let result = Events::handle_events(app).await;
 
         result.wrap_err("handle events failed")?;
+
+        if app.external_edit_requested {
+            Events::edit_externally(app, terminal).wrap_err("external edit failed")?;
+        }
+    }
+
+    if let Err(e) = Session::from_app(app).save(session_path) {
+        error!("Failed to save session: {:?}", e);
     }
 
     Ok(())
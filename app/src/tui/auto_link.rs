@@ -0,0 +1,98 @@
+use super::link_suggest::split_heading;
+
+/// A `[[Title]]` typed into a note, the columns are those of the text between the brackets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WikiLink {
+    pub(crate) row: usize,
+    pub(crate) start_col: usize,
+    /// Inclusive, like the columns of the editor's links
+    pub(crate) end_col: usize,
+    pub(crate) title: String,
+    pub(crate) heading: Option<String>,
+}
+
+/// Every `[[Title]]` and `[[Title#Heading]]` in `lines`, empty titles and brackets inside
+/// the brackets don't count
+pub(crate) fn wiki_links(lines: &[String]) -> Vec<WikiLink> {
+    let mut links = vec![];
+    for (row, line) in lines.iter().enumerate() {
+        let chars = line.chars().collect::<Vec<char>>();
+        let mut col = 0;
+        while col + 1 < chars.len() {
+            if chars[col] != '[' || chars[col + 1] != '[' {
+                col += 1;
+                continue;
+            }
+            let start_col = col + 2;
+            let Some(close) = (start_col..chars.len() - 1)
+                .find(|&idx| chars[idx] == ']' && chars[idx + 1] == ']')
+            else {
+                break;
+            };
+            let inner = chars[start_col..close].iter().collect::<String>();
+            let (title, heading) = split_heading(&inner);
+            if inner.contains(['[', ']']) || title.trim().is_empty() {
+                col += 1;
+                continue;
+            }
+            links.push(WikiLink {
+                row,
+                start_col,
+                end_col: close - 1,
+                title: title.trim().to_owned(),
+                heading: heading.map(str::to_owned),
+            });
+            col = close + 2;
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_wiki_links_found_with_columns() {
+        let links = wiki_links(&lines(&["see [[Foo]] and [[Bar#Next steps]]", "[[ Baz ]]"]));
+
+        assert_eq!(
+            links,
+            [
+                WikiLink {
+                    row: 0,
+                    start_col: 6,
+                    end_col: 8,
+                    title: "Foo".to_owned(),
+                    heading: None,
+                },
+                WikiLink {
+                    row: 0,
+                    start_col: 18,
+                    end_col: 31,
+                    title: "Bar".to_owned(),
+                    heading: Some("Next steps".to_owned()),
+                },
+                WikiLink {
+                    row: 1,
+                    start_col: 2,
+                    end_col: 6,
+                    title: "Baz".to_owned(),
+                    heading: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wiki_links_skip_empty_and_unclosed_brackets() {
+        assert!(wiki_links(&lines(&["[[]]", "[[ ]]", "[[Foo", "[[Foo]", "[Foo]]"])).is_empty());
+
+        let links = wiki_links(&lines(&["[[[Foo]]"]));
+        assert_eq!((links[0].start_col, links[0].title.as_str()), (3, "Foo"));
+    }
+}
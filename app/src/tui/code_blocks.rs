@@ -0,0 +1,180 @@
+use std::{collections::HashMap, ops::Range};
+
+use ratatui::style::{Color, Style};
+
+/// Code without a known language, and every code line when built without syntect
+const CODE_STYLE: Style = Style::new().fg(Color::Cyan);
+const FENCE_STYLE: Style = Style::new().fg(Color::DarkGray);
+
+/// Styled runs of a line as (start col, end col, style)
+pub(crate) type Runs = Vec<(usize, usize, Style)>;
+
+/// A ``` fenced block, the rows from its opening fence up to and including the closing
+/// one. A block left open runs to the end of the note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CodeBlock {
+    /// First word of the fence's info string, empty without one
+    pub(crate) lang: String,
+    pub(crate) rows: Range<usize>,
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+pub(crate) fn code_blocks(lines: &[String]) -> Vec<CodeBlock> {
+    let mut blocks = vec![];
+    let mut open: Option<(usize, String)> = None;
+    for (row, line) in lines.iter().enumerate() {
+        if !is_fence(line) {
+            continue;
+        }
+        match open.take() {
+            Some((start, lang)) => blocks.push(CodeBlock {
+                lang,
+                rows: start..row + 1,
+            }),
+            None => {
+                let info = line.trim_start().trim_start_matches('`');
+                let lang = info.split_whitespace().next().unwrap_or_default();
+                open = Some((row, lang.to_owned()));
+            }
+        }
+    }
+    if let Some((start, lang)) = open {
+        blocks.push(CodeBlock {
+            lang,
+            rows: start..lines.len(),
+        });
+    }
+    blocks
+}
+
+/// Runs for the code rows among `rows`, prose rows are left out. Highlighting starts at
+/// the opening fence of each block on screen and stops at the last row shown, so long
+/// notes only parse the code they draw.
+pub(crate) fn code_styles(lines: &[String], rows: Range<usize>) -> HashMap<usize, Runs> {
+    let mut styles = HashMap::new();
+    for block in code_blocks(lines) {
+        if block.rows.end <= rows.start || block.rows.start >= rows.end {
+            continue;
+        }
+        let fence_runs = |row: usize| vec![(0, lines[row].chars().count(), FENCE_STYLE)];
+        styles.insert(block.rows.start, fence_runs(block.rows.start));
+
+        let body_start = block.rows.start + 1;
+        let body_end = rows.end.min(block.rows.end);
+        if body_start < body_end {
+            let highlighted = highlight(&block.lang, &lines[body_start..body_end]);
+            for (offset, runs) in highlighted.into_iter().enumerate() {
+                styles.insert(body_start + offset, runs);
+            }
+        }
+
+        let last = block.rows.end - 1;
+        if last > block.rows.start && is_fence(&lines[last]) {
+            styles.insert(last, fence_runs(last));
+        }
+    }
+    styles.retain(|row, _| rows.contains(row));
+    styles
+}
+
+/// The style of `col` in a line's runs
+pub(crate) fn style_at(runs: &Runs, col: usize) -> Option<Style> {
+    runs.iter()
+        .find(|(start, end, _)| (*start..*end).contains(&col))
+        .map(|(_, _, style)| *style)
+}
+
+fn plain_runs(line: &str) -> Runs {
+    vec![(0, line.chars().count(), CODE_STYLE)]
+}
+
+#[cfg(feature = "syntax-highlighting")]
+fn highlight(lang: &str, lines: &[String]) -> Vec<Runs> {
+    use std::sync::OnceLock;
+    use syntect::{easy::HighlightLines, highlighting::ThemeSet, parsing::SyntaxSet};
+
+    // Loading the bundled syntaxes and themes is slow, it's only done once
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let theme = &THEMES.get_or_init(ThemeSet::load_defaults).themes["base16-ocean.dark"];
+
+    let Some(syntax) = syntaxes.find_syntax_by_token(lang) else {
+        return lines.iter().map(|line| plain_runs(line)).collect();
+    };
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    lines
+        .iter()
+        .map(|line| {
+            let with_newline = format!("{}\n", line);
+            let Ok(ranges) = highlighter.highlight_line(&with_newline, syntaxes) else {
+                return plain_runs(line);
+            };
+            let mut col = 0;
+            ranges
+                .into_iter()
+                .filter_map(|(style, text)| {
+                    let len = text.trim_end_matches('\n').chars().count();
+                    let fg = style.foreground;
+                    let run = (col, col + len, Style::new().fg(Color::Rgb(fg.r, fg.g, fg.b)));
+                    col += len;
+                    (len > 0).then_some(run)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "syntax-highlighting"))]
+fn highlight(_lang: &str, lines: &[String]) -> Vec<Runs> {
+    lines.iter().map(|line| plain_runs(line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note() -> Vec<String> {
+        ["Some prose", "```rust", "fn main() {}", "```", "more prose"]
+            .map(str::to_owned)
+            .to_vec()
+    }
+
+    #[test]
+    fn test_code_blocks_found_with_language() {
+        let mut lines = note();
+        assert_eq!(
+            code_blocks(&lines),
+            [CodeBlock {
+                lang: "rust".to_owned(),
+                rows: 1..4,
+            }]
+        );
+
+        lines.push("``` python extra".to_owned());
+        lines.push("print()".to_owned());
+        assert_eq!(code_blocks(&lines)[1].lang, "python");
+        assert_eq!(code_blocks(&lines)[1].rows, 5..7);
+    }
+
+    #[test]
+    fn test_only_code_rows_are_styled() {
+        let lines = note();
+        let styles = code_styles(&lines, 0..lines.len());
+
+        let mut rows = styles.keys().copied().collect::<Vec<usize>>();
+        rows.sort();
+        assert_eq!(rows, [1, 2, 3]);
+        let code = &styles[&2];
+        assert_eq!(code.first().map(|run| run.0), Some(0));
+        assert_eq!(code.last().map(|run| run.1), Some("fn main() {}".len()));
+        assert!(style_at(code, 0).is_some_and(|style| style.fg.is_some()));
+
+        // Scrolled past the opening fence the block is still picked up
+        let styles = code_styles(&lines, 2..5);
+        assert!(styles.contains_key(&2) && !styles.contains_key(&1));
+    }
+}
@@ -0,0 +1,130 @@
+/// Editor options `:set` changes for the session, the config holds their defaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SetOption {
+    Number,
+    Wrap,
+    ReadOnly,
+    AutoPairs,
+}
+
+impl SetOption {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "number" | "nu" => Some(Self::Number),
+            "wrap" => Some(Self::Wrap),
+            "readonly" | "ro" => Some(Self::ReadOnly),
+            "autopairs" => Some(Self::AutoPairs),
+            _ => None,
+        }
+    }
+}
+
+/// A line typed after `:` in normal mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// `:set number` turns it on, `:set nonumber` off, `:set number!` toggles (None)
+    Set(SetOption, Option<bool>),
+    /// `:L` or `:L:C`, both counted from 1
+    GoTo { line: usize, col: Option<usize> },
+    /// `:noh`, hides the search highlights and keeps the pattern for `n` and `N`
+    NoHighlight,
+}
+
+/// `L` or `L:C` as numbers, None if either part isn't one
+fn parse_position(word: &str) -> Option<(usize, Option<usize>)> {
+    let (line, col) = match word.split_once(':') {
+        Some((line, col)) => (line, Some(col.parse().ok()?)),
+        None => (word, None),
+    };
+    Some((line.parse().ok()?, col))
+}
+
+/// Parses a command line, the error is a message for the status line
+pub(crate) fn parse_command(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("set" | "se") => {}
+        Some("nohlsearch" | "noh") => {
+            return match words.next() {
+                Some(extra) => Err(format!("Trailing characters: {}", extra)),
+                None => Ok(Command::NoHighlight),
+            };
+        }
+        Some(word) if word.starts_with(|c: char| c.is_ascii_digit()) => {
+            return match (parse_position(word), words.next()) {
+                (Some((line, col)), None) => Ok(Command::GoTo { line, col }),
+                _ => Err(format!("Not a position: {}", line.trim())),
+            };
+        }
+        Some(name) => return Err(format!("Not a command: {}", name)),
+        None => return Err("No command given".to_owned()),
+    }
+    let Some(arg) = words.next() else {
+        return Err("Usage: set [no]option".to_owned());
+    };
+    if let Some(extra) = words.next() {
+        return Err(format!("Trailing characters: {}", extra));
+    }
+
+    let (name, value) = match (arg.strip_suffix('!'), arg.strip_prefix("no")) {
+        (Some(name), _) => (name, None),
+        (None, Some(name)) if SetOption::from_name(name).is_some() => (name, Some(false)),
+        (None, _) => (arg, Some(true)),
+    };
+    match SetOption::from_name(name) {
+        Some(option) => Ok(Command::Set(option, value)),
+        None => Err(format!("Unknown option: {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_set_commands() {
+        assert_eq!(
+            parse_command("set number"),
+            Ok(Command::Set(SetOption::Number, Some(true)))
+        );
+        assert_eq!(
+            parse_command(" set  nonumber "),
+            Ok(Command::Set(SetOption::Number, Some(false)))
+        );
+        assert_eq!(
+            parse_command("se wrap!"),
+            Ok(Command::Set(SetOption::Wrap, None))
+        );
+        assert_eq!(
+            parse_command("set noro"),
+            Ok(Command::Set(SetOption::ReadOnly, Some(false)))
+        );
+        assert!(parse_command("set spell").is_err());
+        assert!(parse_command("set").is_err());
+        assert!(parse_command("set wrap number").is_err());
+        assert!(parse_command("wq").is_err());
+        assert_eq!(parse_command("noh"), Ok(Command::NoHighlight));
+        assert!(parse_command("noh now").is_err());
+    }
+
+    #[test]
+    fn test_parse_go_to_position() {
+        assert_eq!(
+            parse_command("3:5"),
+            Ok(Command::GoTo {
+                line: 3,
+                col: Some(5),
+            })
+        );
+        assert_eq!(
+            parse_command("12"),
+            Ok(Command::GoTo {
+                line: 12,
+                col: None,
+            })
+        );
+        assert!(parse_command("3:").is_err());
+        assert!(parse_command("3:x").is_err());
+        assert!(parse_command("3 5").is_err());
+    }
+}
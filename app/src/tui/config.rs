@@ -1,5 +1,6 @@
 use std::{
-    collections::HashMap,
+    cmp,
+    collections::{BTreeMap, HashMap},
     env,
     error::Error,
     fmt::{self, Display},
@@ -8,14 +9,29 @@ use std::{
 };
 
 use log::info;
-use ratatui::style::{self, Color, Modifier};
+use ratatui::{
+    style::{self, Color, Modifier},
+    symbols::border,
+    widgets::BorderType,
+};
 use serde::{
     de::{MapAccess, Visitor},
     Deserialize, Deserializer,
 };
 use tuipaz_textarea::{Input, Key};
 
-use super::events::Action;
+use crate::db::backup::DEFAULT_BACKUP_COUNT;
+
+use super::{
+    app::{
+        DEFAULT_SIDEBAR_SIZE, DEFAULT_UNSAVED_PROMPT_CHARS, DEFAULT_UNSAVED_PROMPT_LINES,
+        MAX_SIDEBAR_SIZE, MIN_SIDEBAR_SIZE,
+    },
+    events::Action,
+    help::format_input,
+    lock::DEFAULT_LOCK_AFTER_SECS,
+    recent::DEFAULT_RECENT_NOTES_CAP,
+};
 
 pub(crate) fn get_action(action: &str, input: Input) -> Action {
     match action {
@@ -33,10 +49,99 @@ pub(crate) fn get_action(action: &str, input: Input) -> Action {
         "increase_sidebar" => Action::IncreaseSidebar,
         "decrease_sidebar" => Action::DecreaseSidebar,
         "switch_active_widget" => Action::SwitchActiveWidget,
+        "show_help" => Action::ShowHelp(input),
+        "toggle_mark" => Action::ToggleMark(input),
+        "next_tab" => Action::NextTab,
+        "prev_tab" => Action::PrevTab,
+        "close_tab" => Action::CloseTab,
+        "open_in_tab" => Action::OpenInTab,
+        "show_graph" => Action::ShowGraph,
+        "show_recent_notes" => Action::ShowRecentNotes,
+        "jump_back" => Action::JumpBack,
+        "jump_forward" => Action::JumpForward,
+        "duplicate_note" => Action::DuplicateNote,
+        "toggle_read_only" => Action::ToggleReadOnly,
+        "new_note_from_template" => Action::NewNoteFromTemplate,
+        "edit_externally" => Action::EditExternally,
+        "switch_vault" => Action::SwitchVault,
+        "toggle_pin" => Action::TogglePin,
+        "command_palette" => Action::ShowCommandPalette,
+        "link_existing_note" => Action::LinkExistingNote,
+        "quick_capture" => Action::QuickCapture,
+        "link_list" => Action::ShowLinkList,
+        "zen_mode" => Action::ToggleZenMode,
+        "markdown_preview" => Action::TogglePreview,
+        "import_notes" => Action::ImportNotes,
+        "show_stats" => Action::ShowStats,
+        "reveal_note" => Action::RevealNote,
+        "prev_link" => Action::PrevLink,
+        "tag_marked" => Action::TagMarked,
+        "show_position" => Action::ShowPosition,
+        "extract_note" => Action::ExtractNote,
+        "move_note" => Action::MoveNote,
         _ => Action::Null,
     }
 }
 
+pub(crate) fn get_action_name(action: &Action) -> &'static str {
+    match action {
+        Action::ShowExitScreen => "show_exit_screen",
+        Action::Esc => "prev_screen",
+        Action::Tab => "tab",
+        Action::Quit => "quit",
+        Action::SaveNote => "save",
+        Action::LoadNote => "load",
+        Action::DeleteNote => "delete",
+        Action::NewNote => "new_note",
+        Action::NewTitle => "new_title",
+        Action::OpenNoteList => "open_note_list",
+        Action::ToggleSearchbar(_) => "toggle_searchbar",
+        Action::ToggleSidebar => "toggle_sidebar",
+        Action::IncreaseSidebar => "increase_sidebar",
+        Action::DecreaseSidebar => "decrease_sidebar",
+        Action::InsertLink(_) => "insert_link",
+        Action::SwitchActiveWidget => "switch_active_widget",
+        Action::ShowHelp(_) => "show_help",
+        Action::ToggleMark(_) => "toggle_mark",
+        Action::NextTab => "next_tab",
+        Action::PrevTab => "prev_tab",
+        Action::CloseTab => "close_tab",
+        Action::OpenInTab => "open_in_tab",
+        Action::ShowGraph => "show_graph",
+        Action::ShowRecentNotes => "show_recent_notes",
+        Action::JumpBack => "jump_back",
+        Action::JumpForward => "jump_forward",
+        Action::DuplicateNote => "duplicate_note",
+        Action::ToggleReadOnly => "toggle_read_only",
+        Action::NewNoteFromTemplate => "new_note_from_template",
+        Action::EditExternally => "edit_externally",
+        Action::SwitchVault => "switch_vault",
+        Action::TogglePin => "toggle_pin",
+        Action::ShowCommandPalette => "command_palette",
+        Action::LinkExistingNote => "link_existing_note",
+        Action::QuickCapture => "quick_capture",
+        Action::ShowLinkList => "link_list",
+        Action::ToggleZenMode => "zen_mode",
+        Action::TogglePreview => "markdown_preview",
+        Action::ImportNotes => "import_notes",
+        Action::ShowStats => "show_stats",
+        Action::RevealNote => "reveal_note",
+        Action::PrevLink => "prev_link",
+        Action::TagMarked => "tag_marked",
+        Action::ShowPosition => "show_position",
+        Action::ExtractNote => "extract_note",
+        Action::MoveNote => "move_note",
+        Action::Confirm => "confirm",
+        Action::Cancel => "cancel",
+        Action::Activate(_) => "activate",
+        Action::Up(_) => "up",
+        Action::Down(_) => "down",
+        Action::DeleteChar => "delete_char",
+        Action::Edit(_) => "edit",
+        Action::Null => "null",
+    }
+}
+
 fn complete_keymap(keymap: &mut HashMap<Action, Input>) {
     let default_bindings = KeyMap::get_defaults();
 
@@ -128,7 +233,7 @@ impl KeyMap {
             (
                 Action::IncreaseSidebar,
                 Input {
-                    key: Key::Char('.'),
+                    key: Key::Char(','),
                     ctrl: false,
                     alt: true,
                     shift: false,
@@ -137,7 +242,7 @@ impl KeyMap {
             (
                 Action::DecreaseSidebar,
                 Input {
-                    key: Key::Char(','),
+                    key: Key::Char('.'),
                     ctrl: false,
                     alt: true,
                     shift: false,
@@ -184,7 +289,7 @@ impl KeyMap {
                 Input {
                     key: Key::Backspace,
                     ctrl: false,
-                    alt: true,
+                    alt: false,
                     shift: false,
                 },
             ),
@@ -244,6 +349,286 @@ impl KeyMap {
                     shift: false,
                 },
             ),
+            (
+                Action::NextTab,
+                Input {
+                    key: Key::Char(']'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::PrevTab,
+                Input {
+                    key: Key::Char('['),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::CloseTab,
+                Input {
+                    key: Key::Char('w'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::OpenInTab,
+                Input {
+                    key: Key::Enter,
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ShowGraph,
+                Input {
+                    key: Key::Char('g'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ShowRecentNotes,
+                Input {
+                    key: Key::Char('e'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::JumpBack,
+                Input {
+                    key: Key::Char('o'),
+                    ctrl: true,
+                    alt: false,
+                    shift: false,
+                },
+            ),
+            (
+                Action::JumpForward,
+                Input {
+                    key: Key::Char('i'),
+                    ctrl: true,
+                    alt: false,
+                    shift: false,
+                },
+            ),
+            (
+                Action::DuplicateNote,
+                Input {
+                    key: Key::Char('y'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ToggleReadOnly,
+                Input {
+                    key: Key::Char('R'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::NewNoteFromTemplate,
+                Input {
+                    key: Key::Char('N'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::EditExternally,
+                Input {
+                    key: Key::Char('E'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::SwitchVault,
+                Input {
+                    key: Key::Char('V'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::TogglePin,
+                Input {
+                    key: Key::Char('p'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ShowCommandPalette,
+                Input {
+                    key: Key::Char('x'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::LinkExistingNote,
+                Input {
+                    key: Key::Char('k'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::QuickCapture,
+                Input {
+                    key: Key::Char('j'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ShowLinkList,
+                Input {
+                    key: Key::Char('L'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ToggleZenMode,
+                Input {
+                    key: Key::Char('z'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::TogglePreview,
+                Input {
+                    key: Key::Char('m'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ImportNotes,
+                Input {
+                    key: Key::Char('I'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ShowStats,
+                Input {
+                    key: Key::Char('S'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::RevealNote,
+                Input {
+                    key: Key::Char('F'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::PrevLink,
+                Input {
+                    key: Key::Tab,
+                    ctrl: false,
+                    alt: false,
+                    shift: true,
+                },
+            ),
+            (
+                Action::TagMarked,
+                Input {
+                    key: Key::Char('T'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ShowPosition,
+                Input {
+                    key: Key::Char('g'),
+                    ctrl: true,
+                    alt: false,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ExtractNote,
+                Input {
+                    key: Key::Char('X'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::MoveNote,
+                Input {
+                    key: Key::Char('M'),
+                    ctrl: false,
+                    alt: true,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ShowHelp(Input {
+                    key: Key::Char('?'),
+                    ctrl: false,
+                    alt: false,
+                    shift: false,
+                }),
+                Input {
+                    key: Key::Char('?'),
+                    ctrl: false,
+                    alt: false,
+                    shift: false,
+                },
+            ),
+            (
+                Action::ToggleMark(Input {
+                    key: Key::Char(' '),
+                    ctrl: false,
+                    alt: false,
+                    shift: false,
+                }),
+                Input {
+                    key: Key::Char(' '),
+                    ctrl: false,
+                    alt: false,
+                    shift: false,
+                },
+            ),
             (
                 Action::InsertLink(Input {
                     key: Key::Char(']'),
@@ -283,53 +668,7 @@ impl<'de> Deserialize<'de> for KeyMap {
                 let mut key_map = HashMap::new();
 
                 while let Some((action, input)) = access.next_entry::<String, String>()? {
-                    let mut key = Key::Null;
-                    let mut ctrl = false;
-                    let mut alt = false;
-                    let mut shift = false;
-
-                    if input.contains('-') {
-                        let mut parts = input.split('-');
-                        let modifier_part = parts
-                            .next()
-                            .ok_or_else(|| serde::de::Error::custom("Modifier part missing"))?;
-                        let key_part = parts
-                            .next()
-                            .ok_or_else(|| serde::de::Error::custom("Key part missing"))?;
-
-                        key = match key_part.chars().next() {
-                            Some(c) => Key::Char(c),
-                            None => return Err(serde::de::Error::custom("Invalid key")),
-                        };
-
-                        ctrl = modifier_part.contains("ctrl");
-                        alt = modifier_part.contains("alt");
-                        shift = modifier_part.contains("shift");
-                    } else {
-                        match input.len() > 1 {
-                            true => {
-                                key = match input.as_str() {
-                                    "esc" => Key::Esc,
-                                    "tab" => Key::Tab,
-                                    "enter" => Key::Enter,
-                                    _ => Key::Null,
-                                };
-                            }
-                            false => {
-                                key = match input.chars().next() {
-                                    Some(c) => Key::Char(c),
-                                    None => return Err(serde::de::Error::custom("Invalid key")),
-                                };
-                            }
-                        }
-                    }
-
-                    let i = Input {
-                        key,
-                        ctrl,
-                        alt,
-                        shift,
-                    };
+                    let i = parse_input(&input).map_err(serde::de::Error::custom)?;
                     let a = get_action(&action, i);
                     key_map.insert(a, i);
                 }
@@ -340,6 +679,53 @@ impl<'de> Deserialize<'de> for KeyMap {
     }
 }
 
+/// A `[keymap]` value like `alt-s` or `esc`, key names it doesn't know give `Key::Null`
+fn parse_input(input: &str) -> Result<Input, &'static str> {
+    let mut key = Key::Null;
+    let mut ctrl = false;
+    let mut alt = false;
+    let mut shift = false;
+
+    if input.contains('-') {
+        let mut parts = input.split('-');
+        let modifier_part = parts.next().ok_or("Modifier part missing")?;
+        let key_part = parts.next().ok_or("Key part missing")?;
+
+        key = match key_part.chars().next() {
+            Some(c) => Key::Char(c),
+            None => return Err("Invalid key"),
+        };
+
+        ctrl = modifier_part.contains("ctrl");
+        alt = modifier_part.contains("alt");
+        shift = modifier_part.contains("shift");
+    } else {
+        match input.len() > 1 {
+            true => {
+                key = match input {
+                    "esc" => Key::Esc,
+                    "tab" => Key::Tab,
+                    "enter" => Key::Enter,
+                    _ => Key::Null,
+                };
+            }
+            false => {
+                key = match input.chars().next() {
+                    Some(c) => Key::Char(c),
+                    None => return Err("Invalid key"),
+                };
+            }
+        }
+    }
+
+    Ok(Input {
+        key,
+        ctrl,
+        alt,
+        shift,
+    })
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct Colors(pub HashMap<String, String>);
 
@@ -546,6 +932,12 @@ pub(crate) struct HighlightTheme {
     pub(crate) select: String,
     pub(crate) search: String,
     pub(crate) hop: String,
+    /// Unset keeps the reversed text cursor
+    #[serde(default)]
+    pub(crate) cursor: Option<String>,
+    /// Background of the cursor's line, unset means no highlight
+    #[serde(default)]
+    pub(crate) cursor_line: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -608,6 +1000,8 @@ impl Theme {
                 select: Color::Magenta,
                 search: Color::Red,
                 hop: Color::LightRed,
+                cursor: None,
+                cursor_line: None,
             },
             notelist: NoteListTheme {
                 selection_modifier: Modifier::BOLD,
@@ -630,6 +1024,8 @@ pub(crate) struct HighlightColors {
     pub(crate) select: Color,
     pub(crate) search: Color,
     pub(crate) hop: Color,
+    pub(crate) cursor: Option<Color>,
+    pub(crate) cursor_line: Option<Color>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -641,37 +1037,363 @@ pub(crate) struct ModeColors {
     pub(crate) search_mode: Color,
 }
 
+/// Behaviour settings from the `[general]` table, every key is optional
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct GeneralSettings {
+    pub(crate) recent_notes_cap: usize,
+    /// Sidebar width in percent, used until a session has remembered one
+    pub(crate) sidebar_size: u16,
+    /// Loading another note over an unsaved edit this big asks first, 0 never asks
+    pub(crate) unsaved_prompt_lines: usize,
+    pub(crate) unsaved_prompt_chars: usize,
+    /// Database copies kept in `backups/`, 0 turns backups off
+    pub(crate) backup_count: usize,
+    /// Ask before deleting a note, otherwise `Alt-d` deletes straight away
+    pub(crate) confirm_delete: bool,
+    /// When the quit key asks before exiting
+    pub(crate) confirm_quit: ConfirmQuit,
+    /// Starts a `[leader_bindings]` sequence in normal mode
+    pub(crate) leader: char,
+    /// Seconds without input before the screen locks, 0 never locks
+    pub(crate) lock_after_secs: u64,
+    /// Hex SHA-256 of the unlock passphrase, the lock is off without one
+    pub(crate) lock_passphrase_hash: Option<String>,
+    /// Encrypts note bodies, the passphrase is asked for on startup. Titles stay
+    /// plaintext, see `NoteCipher`.
+    pub(crate) encryption: bool,
+    pub(crate) startup: Startup,
+    pub(crate) storage: Storage,
+    /// Opens a database made by a newer tuipaz with a warning, instead of refusing it
+    pub(crate) allow_newer_schema: bool,
+}
+
+impl Default for GeneralSettings {
+    fn default() -> Self {
+        Self {
+            recent_notes_cap: DEFAULT_RECENT_NOTES_CAP,
+            sidebar_size: DEFAULT_SIDEBAR_SIZE,
+            unsaved_prompt_lines: DEFAULT_UNSAVED_PROMPT_LINES,
+            unsaved_prompt_chars: DEFAULT_UNSAVED_PROMPT_CHARS,
+            backup_count: DEFAULT_BACKUP_COUNT,
+            confirm_delete: true,
+            confirm_quit: ConfirmQuit::default(),
+            leader: ' ',
+            lock_after_secs: DEFAULT_LOCK_AFTER_SECS,
+            lock_passphrase_hash: None,
+            encryption: false,
+            startup: Startup::default(),
+            storage: Storage::default(),
+            allow_newer_schema: false,
+        }
+    }
+}
+
+/// Insert mode settings from the `[editor]` table, every key is optional
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct EditorSettings {
+    pub(crate) expand_tab: bool,
+    pub(crate) tab_width: usize,
+    pub(crate) auto_indent: bool,
+    /// Lines kept visible above and below the cursor when scrolling
+    pub(crate) scrolloff: usize,
+    /// Wraps long lines onto extra screen rows instead of scrolling sideways
+    pub(crate) soft_wrap: bool,
+    /// Typing an opening bracket or quote also types its closing counterpart
+    pub(crate) auto_pairs: bool,
+    /// Numbers each line in a gutter left of the text
+    pub(crate) line_numbers: bool,
+    /// Largest count a command takes, typing a bigger one gets this many
+    pub(crate) max_count: u16,
+    /// What saving a note does with the `[[Title]]`s typed into it
+    pub(crate) auto_link: AutoLink,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            expand_tab: true,
+            tab_width: 4,
+            auto_indent: true,
+            scrolloff: 0,
+            soft_wrap: false,
+            auto_pairs: false,
+            line_numbers: false,
+            max_count: 10_000,
+            auto_link: AutoLink::Off,
+        }
+    }
+}
+
+/// Note list behaviour from the `[notelist]` table, every key is optional
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct NoteListSettings {
+    /// Moving past the last note selects the first and the other way round
+    pub(crate) wrap_selection: bool,
+}
+
+impl Default for NoteListSettings {
+    fn default() -> Self {
+        Self {
+            wrap_selection: true,
+        }
+    }
+}
+
+/// Fields shown in the status line from the `[statusline]` table, every key is optional
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct StatuslineSettings {
+    pub(crate) enabled: bool,
+    pub(crate) position: bool,
+    pub(crate) line_count: bool,
+    pub(crate) note_id: bool,
+    pub(crate) link_count: bool,
+}
+
+impl Default for StatuslineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            position: true,
+            line_count: true,
+            note_id: true,
+            link_count: true,
+        }
+    }
+}
+
+impl StatuslineSettings {
+    /// Rows the main screen gives up for the status line
+    pub(crate) fn height(&self) -> u16 {
+        match self.enabled {
+            true => 1,
+            false => 0,
+        }
+    }
+}
+
+/// What the app opens on, the welcome screen is the fallback when there's no note
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Startup {
+    Welcome,
+    /// An untitled note in the editor
+    Blank,
+    /// The note open when the app last closed
+    #[default]
+    LastOpened,
+    /// The note saved most recently
+    MostRecent,
+}
+
+/// Links for `[[Title]]` typed into a note, made when the note is saved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AutoLink {
+    /// Left as plain text
+    #[default]
+    Off,
+    /// Linked to the note with that title, a title no note has is flagged
+    Existing,
+    /// Like `Existing`, but an empty note is made for a title no note has
+    Create,
+}
+
+/// When quitting goes through the exit screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ConfirmQuit {
+    #[default]
+    Always,
+    /// Only while some open note has unsaved changes
+    IfDirty,
+    Never,
+}
+
+/// Where notes are kept, along with their links, pins, folders, tags and undo history.
+/// The encryption salt stays in the vault's database either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Storage {
+    #[default]
+    Sqlite,
+    /// `.md` files in a directory named after the vault's database
+    Files,
+}
+
+/// Line style of a widget's border
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BorderStyle {
+    Plain,
+    #[default]
+    Rounded,
+    Thick,
+    Double,
+}
+
+/// Tee pieces where a border meets a neighbouring widget's
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BorderJoins {
+    pub(crate) top: &'static str,
+    pub(crate) left: &'static str,
+    pub(crate) right: &'static str,
+    pub(crate) bottom: &'static str,
+}
+
+impl BorderStyle {
+    pub(crate) fn border_type(self) -> BorderType {
+        match self {
+            BorderStyle::Plain => BorderType::Plain,
+            BorderStyle::Rounded => BorderType::Rounded,
+            BorderStyle::Thick => BorderType::Thick,
+            BorderStyle::Double => BorderType::Double,
+        }
+    }
+
+    /// Line set for blocks that swap some corners for joins
+    pub(crate) fn set(self) -> border::Set {
+        BorderType::to_border_set(self.border_type())
+    }
+
+    pub(crate) fn joins(self) -> BorderJoins {
+        let (top, left, right, bottom) = match self {
+            BorderStyle::Plain | BorderStyle::Rounded => ("┬", "├", "┤", "┴"),
+            BorderStyle::Thick => ("┳", "┣", "┫", "┻"),
+            BorderStyle::Double => ("╦", "╠", "╣", "╩"),
+        };
+        BorderJoins {
+            top,
+            left,
+            right,
+            bottom,
+        }
+    }
+}
+
+/// Border styles from the `[borders]` table, every key is optional
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct BorderSettings {
+    pub(crate) editor: BorderStyle,
+    pub(crate) note_list: BorderStyle,
+    pub(crate) searchbar: BorderStyle,
+    /// Messages and confirmations
+    pub(crate) popups: BorderStyle,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct TempConfig {
     pub(crate) colors: Colors,
     pub(crate) theme: TempTheme,
     pub(crate) keymap: KeyMap,
+    #[serde(default)]
+    pub(crate) general: GeneralSettings,
+    #[serde(default)]
+    pub(crate) editor: EditorSettings,
+    #[serde(default)]
+    pub(crate) statusline: StatuslineSettings,
+    #[serde(default)]
+    pub(crate) notelist: NoteListSettings,
+    #[serde(default)]
+    pub(crate) borders: BorderSettings,
+    /// Note templates by name from the `[templates]` table
+    #[serde(default)]
+    pub(crate) templates: BTreeMap<String, String>,
+    /// Database paths by vault name from the `[vaults]` table
+    #[serde(default)]
+    pub(crate) vaults: BTreeMap<String, String>,
+    /// Action names by the keys typed after the leader
+    #[serde(default)]
+    pub(crate) leader_bindings: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct Config {
     pub(crate) theme: Theme,
     pub(crate) keymap: HashMap<Action, Input>,
+    pub(crate) general: GeneralSettings,
+    pub(crate) editor: EditorSettings,
+    pub(crate) statusline: StatuslineSettings,
+    pub(crate) notelist: NoteListSettings,
+    pub(crate) borders: BorderSettings,
+    pub(crate) templates: BTreeMap<String, String>,
+    pub(crate) vaults: BTreeMap<String, String>,
+    pub(crate) leader_bindings: BTreeMap<String, Action>,
+    /// Keybinding conflicts to show the user once the TUI is up
+    pub(crate) warnings: Vec<String>,
 }
 
 impl Config {
     fn new(temp_config: TempConfig) -> Result<Self, ConfigError> {
         let mut keymap = temp_config.keymap.0.clone();
+        let mut general = temp_config.general.clone();
+        general.sidebar_size = general.sidebar_size.clamp(MIN_SIDEBAR_SIZE, MAX_SIDEBAR_SIZE);
+        let mut editor = temp_config.editor.clone();
+        editor.tab_width = cmp::max(editor.tab_width, 1);
+        let statusline = temp_config.statusline.clone();
+        let notelist = temp_config.notelist.clone();
+        let borders = temp_config.borders;
+        let templates = temp_config.templates.clone();
+        let vaults = temp_config.vaults.clone();
+        let leader_bindings = leader_actions(&temp_config.leader_bindings);
+        let warnings = binding_conflicts(&keymap);
         let theme = get_theme(temp_config)?;
         complete_keymap(&mut keymap);
 
-        Ok(Self { theme, keymap })
+        Ok(Self {
+            theme,
+            keymap,
+            general,
+            editor,
+            statusline,
+            notelist,
+            borders,
+            templates,
+            vaults,
+            leader_bindings,
+            warnings,
+        })
     }
 
-    fn default() -> Self {
+    pub(crate) fn default() -> Self {
         let theme = Theme::default();
         let mut keymap = HashMap::new();
         complete_keymap(&mut keymap);
 
-        Config { theme, keymap }
+        Config {
+            theme,
+            keymap,
+            general: GeneralSettings::default(),
+            editor: EditorSettings::default(),
+            statusline: StatuslineSettings::default(),
+            notelist: NoteListSettings::default(),
+            borders: BorderSettings::default(),
+            templates: BTreeMap::new(),
+            vaults: BTreeMap::new(),
+            leader_bindings: BTreeMap::new(),
+            warnings: vec![],
+        }
     }
 }
 
+/// Leader sequences with the actions they run, unknown action names are dropped
+fn leader_actions(bindings: &BTreeMap<String, String>) -> BTreeMap<String, Action> {
+    bindings
+        .iter()
+        .filter_map(|(keys, name)| match get_action(name, Input::default()) {
+            Action::Null => {
+                info!("leader_actions::unknown action {:?} for {:?}", name, keys);
+                None
+            }
+            action => Some((keys.clone(), action)),
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 pub(crate) enum ConfigError {
     Io(std::io::Error),
@@ -758,6 +1480,17 @@ fn try_convert_color(
     }
 }
 
+/// Like `try_convert_color` for keys that may be left out, an unknown name counts as unset
+fn try_convert_optional_color(
+    colors: &Colors,
+    name: Option<&String>,
+) -> Result<Option<Color>, ColorConversionError> {
+    match name.and_then(|name| colors.0.get(name)) {
+        Some(hex) => try_convert_color(Some(hex.clone()), Color::default()).map(Some),
+        None => Ok(None),
+    }
+}
+
 macro_rules! convert_color {
     ($config:expr, $theme_path:expr, $default_option:expr) => {{
         let color_option = $config.colors.0.get($theme_path).cloned();
@@ -858,12 +1591,22 @@ fn get_theme(temp_config: TempConfig) -> Result<Theme, ConfigError> {
             .cloned(),
         default_theme.borders,
     )?;
+    let cursor = try_convert_optional_color(
+        &temp_config.colors,
+        temp_config.theme.highlights.cursor.as_ref(),
+    )?;
+    let cursor_line = try_convert_optional_color(
+        &temp_config.colors,
+        temp_config.theme.highlights.cursor_line.as_ref(),
+    )?;
 
     let highlights = HighlightColors {
         links,
         select,
         search,
         hop,
+        cursor,
+        cursor_line,
     };
     let modes = ModeColors {
         normal_mode,
@@ -884,6 +1627,115 @@ fn get_theme(temp_config: TempConfig) -> Result<Theme, ConfigError> {
     ))
 }
 
+/// Problems in `[keymap]`: unknown action names, keys that can't be read and inputs
+/// bound to more than one action. Defaults count for the actions the table leaves out.
+fn keymap_problems(keymap: &toml::Table) -> Vec<String> {
+    let mut problems = vec![];
+    let mut bound = HashMap::new();
+
+    for (name, value) in keymap {
+        let Some(input) = value.as_str() else {
+            problems.push(format!("Keybinding for {} isn't a string: {}", name, value));
+            continue;
+        };
+        if get_action(name, Input::default()) == Action::Null {
+            problems.push(format!("Unknown action in [keymap]: {}", name));
+            continue;
+        }
+        match parse_input(input) {
+            Ok(i) if i.key != Key::Null => {
+                bound.insert(get_action(name, i), i);
+            }
+            _ => problems.push(format!("Unparseable keybinding for {}: \"{}\"", name, input)),
+        }
+    }
+    problems.extend(binding_conflicts(&bound));
+    problems
+}
+
+/// Inputs the config binds to more than one action, a message naming each. Defaults
+/// count for the actions `keymap` leaves out, clashes only among defaults aren't listed.
+/// Unknown actions and keys are left to `keymap_problems`.
+fn binding_conflicts(keymap: &HashMap<Action, Input>) -> Vec<String> {
+    let user = keymap
+        .iter()
+        .filter(|(action, input)| **action != Action::Null && input.key != Key::Null)
+        .map(|(action, input)| (*action, *input))
+        .collect::<Vec<(Action, Input)>>();
+    let mut bound = user.clone();
+    for (action, input) in KeyMap::get_defaults() {
+        if keymap.get(&action).is_none() {
+            bound.push((action, input));
+        }
+    }
+
+    let mut conflicts = user
+        .iter()
+        .filter_map(|(_, input)| {
+            let mut names = bound
+                .iter()
+                .filter(|(_, other)| other == input)
+                .map(|(action, _)| get_action_name(action))
+                .collect::<Vec<&str>>();
+            names.sort();
+            (names.len() > 1).then(|| {
+                format!(
+                    "{} is bound to more than one action: {}",
+                    format_input(input),
+                    names.join(", ")
+                )
+            })
+        })
+        .collect::<Vec<String>>();
+    conflicts.sort();
+    conflicts.dedup();
+    conflicts
+}
+
+/// `[colors]` entries that aren't `#rrggbb` hex codes
+fn color_problems(colors: &Colors) -> Vec<String> {
+    let mut problems = colors
+        .0
+        .iter()
+        .filter(|(_, hex)| hex_to_color(hex).is_err())
+        .map(|(name, hex)| format!("Invalid color {}: \"{}\"", name, hex))
+        .collect::<Vec<String>>();
+    problems.sort();
+    problems
+}
+
+/// Parses config file content along with the problems it still loads with
+fn parse_config(content: &str) -> Result<(TempConfig, Vec<String>), ConfigError> {
+    let mut problems = vec![];
+    let temp_cfg: TempConfig =
+        serde_ignored::deserialize(toml::Deserializer::new(content), |path| {
+            problems.push(format!("Unknown key: {}", path))
+        })?;
+    problems.extend(color_problems(&temp_cfg.colors));
+
+    let table: toml::Table = toml::de::from_str(content)?;
+    if let Some(keymap) = table.get("keymap").and_then(|keymap| keymap.as_table()) {
+        problems.extend(keymap_problems(keymap));
+    }
+    Ok((temp_cfg, problems))
+}
+
+/// Everything wrong with config file content, empty when it's fine. Used by
+/// `--check-config`.
+pub(crate) fn check_config(content: &str) -> Vec<String> {
+    match parse_config(content) {
+        Ok((temp_cfg, mut problems)) => {
+            match Config::new(temp_cfg) {
+                // Every color the theme fails on is an entry `color_problems` listed
+                Err(ConfigError::ColorConversion(_)) | Ok(_) => {}
+                Err(e) => problems.push(e.to_string()),
+            }
+            problems
+        }
+        Err(e) => vec![e.to_string()],
+    }
+}
+
 pub(crate) fn try_load_config() -> Result<Config, ConfigError> {
     let path = env::current_dir().unwrap();
     let config_path = path.join("config.toml");
@@ -892,7 +1744,10 @@ pub(crate) fn try_load_config() -> Result<Config, ConfigError> {
     // Sanity check - metadata means file exists
     if metadata.is_ok() {
         let content = fs::read_to_string(config_path)?;
-        let temp_cfg: TempConfig = toml::de::from_str(&content)?;
+        let (temp_cfg, problems) = parse_config(&content)?;
+        for problem in problems {
+            info!("try_load_config::{}", problem);
+        }
         let cfg = Config::new(temp_cfg);
         info!("cfg: {:?}", cfg);
         cfg
@@ -903,3 +1758,96 @@ pub(crate) fn try_load_config() -> Result<Config, ConfigError> {
         Ok(default_cfg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with(colors: &str, keymap: &str) -> String {
+        format!(
+            r##"
+[colors]
+{}
+
+[theme]
+note_title = "green"
+text = "green"
+borders = "green"
+
+[theme.modes]
+normal_mode = "green"
+insert_mode = "green"
+visual_mode = "green"
+visual_line_mode = "green"
+search_mode = "green"
+
+[theme.highlights]
+links = "green"
+select = "green"
+search = "green"
+hop = "green"
+
+[theme.notelist]
+
+[theme.headings]
+
+[keymap]
+{}
+"##,
+            colors, keymap
+        )
+    }
+
+    #[test]
+    fn test_check_config_passes_a_valid_config() {
+        let content = config_with("green = \"#00ff00\"", "save = \"alt-u\"");
+
+        assert_eq!(check_config(&content), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_check_config_reports_a_duplicate_binding() {
+        let content = config_with("green = \"#00ff00\"", "save = \"alt-u\"\nload = \"alt-u\"");
+
+        assert_eq!(
+            check_config(&content),
+            ["Alt-u is bound to more than one action: load, save"]
+        );
+    }
+
+    #[test]
+    fn test_conflicting_bindings_are_warned_about_at_load_time() {
+        let content = config_with("green = \"#00ff00\"", "save = \"alt-s\"\nload = \"alt-s\"");
+        let temp_cfg: TempConfig = toml::de::from_str(&content).unwrap();
+        let config = Config::new(temp_cfg).unwrap();
+
+        assert_eq!(config.warnings, ["Alt-s is bound to more than one action: load, save"]);
+
+        // Rebinding away from a default's input leaves no conflict behind
+        let content = config_with("green = \"#00ff00\"", "load = \"alt-s\"\nsave = \"alt-u\"");
+        let temp_cfg: TempConfig = toml::de::from_str(&content).unwrap();
+        assert!(Config::new(temp_cfg).unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_check_config_reports_an_invalid_color() {
+        let content = config_with("green = \"#00ff00\"\nred = \"ff0000\"", "");
+
+        assert_eq!(check_config(&content), ["Invalid color red: \"ff0000\""]);
+    }
+
+    #[test]
+    fn test_check_config_reports_unknown_keys_and_bindings() {
+        let content = config_with("green = \"#00ff00\"", "fly = \"alt-y\"\nsave = \"hyper\"")
+            + "\n[general]\nsidebar_width = 30\n";
+
+        assert_eq!(
+            check_config(&content),
+            [
+                "Unknown key: general.sidebar_width",
+                "Unknown action in [keymap]: fly",
+                "Unparseable keybinding for save: \"hyper\"",
+            ]
+        );
+    }
+}
@@ -1,22 +1,50 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use log::{error, info};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use ratatui::{
-    layout::Alignment,
+    buffer::Buffer,
+    layout::{Alignment, Margin, Rect},
     style::{Color, Modifier, Style, Stylize},
     symbols::border,
     text::{Line, Span},
-    widgets::{block::Title, Block, Borders, Padding, Widget},
+    widgets::{
+        block::Title, Block, Borders, Padding, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, StatefulWidget, Widget, Wrap,
+    },
 };
 use tuipaz_textarea::{CursorMove, Input, Key, Link as TextAreaLink, TextArea, TextAreaTheme};
 
 use crate::db::db_mac::DbNoteLink;
 
-use super::app::ComponentState;
+use super::{
+    app::ComponentState,
+    code_blocks::{code_styles, style_at},
+    config::{BorderStyle, EditorSettings, StatuslineSettings},
+    searchbar::{ReplaceScope, Substitute},
+    statusline::StatusLine,
+    hop::{hop_labels, hop_matches},
+    markdown::preview_lines,
+    utils::elide,
+    wrap::{segment_col, segment_position, wrap_segments},
+};
 
 const DELETE_COMMANDS: [char; 7] = ['d', 'w', 'b', 'j', 'k', 'l', 'h'];
-const YANK_COMMANDS: [char; 6] = ['w', 'b', 'j', 'k', 'l', 'h'];
+const YANK_COMMANDS: [char; 7] = ['y', 'w', 'b', 'j', 'k', 'l', 'h'];
 const GOTO_COMMAND: char = 'g';
+/// A pause in typing this long starts a new undo group
+const UNDO_GROUP_IDLE: Duration = Duration::from_secs(1);
+/// Numbered registers `"1` to `"9`
+const DELETE_REGISTERS: usize = 9;
+/// Undo groups kept with a note between sessions
+pub(crate) const UNDO_HISTORY_CAP: usize = 20;
+/// Opening chars `auto_pairs` closes, with their closing counterpart
+const AUTO_PAIRS: [(char, char); 5] =
+    [('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
 
 #[derive(Debug, Clone)]
 pub(crate) struct EditorTheme {
@@ -27,13 +55,20 @@ pub(crate) struct EditorTheme {
     pub(crate) insert_mode: Color,
     pub(crate) visual_mode: Color,
     pub(crate) visual_line_mode: Color,
+    pub(crate) search_mode: Color,
     pub(crate) select: Color,
     pub(crate) search: Color,
     pub(crate) links: Color,
+    pub(crate) hop: Color,
+    /// None keeps the reversed cursor
+    pub(crate) cursor: Option<Color>,
+    /// None turns the current line highlight off
+    pub(crate) cursor_line: Option<Color>,
     pub(crate) main_heading: Color,
     pub(crate) main_heading_modifiers: Vec<Modifier>,
     pub(crate) sub_heading: Color,
     pub(crate) sub_heading_modifiers: Vec<Modifier>,
+    pub(crate) border: BorderStyle,
 }
 
 #[derive(Debug, Clone)]
@@ -46,13 +81,77 @@ pub(crate) struct Editor<'a> {
     pub(crate) mode: EditorMode,
     pub(crate) block_info: String,
     pub(crate) prev_cursor_col: usize,
+    pub(crate) scroll_top: usize,
+    /// Text rows on screen at the last render, a page for PageUp/PageDown
+    pub(crate) viewport_rows: usize,
     pub(crate) num_buf: Vec<u32>,
     pub(crate) cmd_buf: String,
     pub(crate) cmd_state: CommandState,
     pub(crate) sidebar_open: bool,
     pub(crate) searchbar_open: bool,
+    /// Last pattern searched for, kept when its highlight is cleared so `n` and `N` work
+    pub(crate) search_pattern: String,
+    /// Leaves the mode and key hints out of the border, for distraction-free mode
+    pub(crate) hide_hints: bool,
     pub(crate) state: ComponentState,
     pub(crate) theme: EditorTheme,
+    pub(crate) settings: EditorSettings,
+    pub(crate) max_col: u16,
+    /// Title and body as of the last load or save, used for the dirty marker
+    pub(crate) saved_title: String,
+    pub(crate) saved_lines: Vec<String>,
+    pub(crate) undo_groups: Vec<UndoGroup>,
+    pub(crate) redo_groups: Vec<UndoGroup>,
+    /// Text and links as of the start of the current insert session
+    pub(crate) open_group: Option<(Vec<String>, LinkState)>,
+    pub(crate) last_insert: Instant,
+    /// Corner of a visual block opposite the cursor, as (row, char col)
+    pub(crate) block_anchor: (usize, usize),
+    pub(crate) block_insert: Option<BlockInsert>,
+    /// Set by `gd`, the events loop opens a preview of the link under the cursor
+    pub(crate) preview_requested: bool,
+    /// Set by `gf`, the events loop links the word under the cursor to its note
+    pub(crate) link_word_requested: bool,
+    pub(crate) yank_kind: YankKind,
+    /// Lines taken by the last deletes, newest first, put with `"Np`
+    pub(crate) delete_registers: Vec<String>,
+    /// Motions and search still work, edits and saving are refused
+    pub(crate) read_only: bool,
+    /// Label and (row, char col) of each match while a hop waits for its label
+    pub(crate) hop_targets: Vec<(String, (usize, usize))>,
+    pub(crate) hop_typed: String,
+    /// Shows the body as rendered Markdown, keys only scroll it
+    pub(crate) markdown_preview: bool,
+    /// Top row of the preview, the textarea keeps its own scroll underneath
+    pub(crate) preview_scroll: usize,
+}
+
+/// One undo step as seen by the user, which may span many textarea history entries
+#[derive(Debug, Clone)]
+pub(crate) struct UndoGroup {
+    pub(crate) before: Vec<String>,
+    pub(crate) after: Vec<String>,
+    pub(crate) before_links: LinkState,
+    /// Taken when the group is undone, the events loop syncs links after each key so
+    /// the state right after the edit isn't final
+    pub(crate) after_links: Option<LinkState>,
+}
+
+/// An undo group as kept between sessions, with the links as the database has them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SavedUndo {
+    pub(crate) before: Vec<String>,
+    pub(crate) after: Vec<String>,
+    pub(crate) before_links: Vec<DbNoteLink>,
+    pub(crate) after_links: Vec<DbNoteLink>,
+}
+
+/// The textarea's links and the editor's view of them, restored along with the text
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LinkState {
+    pub(crate) ta_links: HashMap<usize, TextAreaLink>,
+    pub(crate) links: HashMap<i64, Link>,
+    pub(crate) deleted_link_ids: Vec<i64>,
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +165,8 @@ pub(crate) struct Link {
     pub(crate) saved: bool,
     pub(crate) updated: bool,
     pub(crate) deleted: bool,
+    /// Heading in the linked note that following the link jumps to
+    pub(crate) anchor: Option<String>,
 }
 
 impl Link {
@@ -80,6 +181,7 @@ impl Link {
             saved: true,
             updated: false,
             deleted: false,
+            anchor: db_link.anchor,
         }
     }
 
@@ -91,6 +193,7 @@ impl Link {
             start_col: self.start_col as i64,
             end_col: self.end_col as i64,
             linked_note_id: self.linked_id,
+            anchor: self.anchor.clone(),
         }
     }
 
@@ -117,11 +220,17 @@ pub(crate) enum CommandState {
     NoCommand,
     Delete,
     Yank,
+    Change,
+    /// `i` or `a` after an operator, waiting for the delimiter of a text object
+    InnerObject,
+    AroundObject,
     GoTo,
     FindForward,
     FindBackward,
     PrimeHop,
     ExecuteHop,
+    /// `"` then a register number, waiting for `p` or `P`
+    Register,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -130,6 +239,91 @@ pub(crate) enum EditorMode {
     Normal,
     Visual,
     VisualLine,
+    VisualBlock,
+}
+
+/// Whether the last yank or delete took whole lines, which `p` and `P` put on lines
+/// of their own, or part of a line, which they put inline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum YankKind {
+    CharWise,
+    LineWise,
+}
+
+/// A block `I` or `A` waiting for insert mode to end, so the text typed on the
+/// top line can be repeated on the rest of the block
+#[derive(Debug, Clone)]
+pub(crate) struct BlockInsert {
+    pub(crate) top: usize,
+    pub(crate) bottom: usize,
+    pub(crate) col: usize,
+    pub(crate) line_len: usize,
+    pub(crate) append: bool,
+}
+
+fn build_body<'a>(
+    theme: &EditorTheme,
+    lines: Vec<String>,
+    ta_links: HashMap<usize, TextAreaLink>,
+    max_col: u16,
+) -> TextArea<'a> {
+    let ta_theme = TextAreaTheme {
+        text: theme.text,
+        select: theme.select,
+        links: theme.links,
+        hop: theme.hop,
+        main_heading: theme.main_heading,
+        main_heading_modifiers: theme.main_heading_modifiers.clone(),
+        sub_heading: theme.sub_heading,
+        sub_heading_modifiers: theme.sub_heading_modifiers.clone(),
+    };
+
+    let mut body = TextArea::new(lines, ta_links, max_col, ta_theme);
+    body.set_cursor_line_style(cursor_line_style(theme));
+    body.set_selection_style(Style::default().bg(theme.select));
+    body.set_search_style(Style::default().bg(theme.search));
+    body.set_max_histories(100);
+    body
+}
+
+/// Links still in the text, as they would be saved
+fn db_links(state: &LinkState) -> Vec<DbNoteLink> {
+    state
+        .links
+        .values()
+        .filter(|link| !link.deleted)
+        .map(|link| link.to_db_link())
+        .collect()
+}
+
+/// Link state for saved `db_links`, relative to the `loaded` links the database has
+/// now. Loaded links missing from `db_links` are deleted, the others are new or moved.
+fn restored_link_state(db_links: Vec<DbNoteLink>, loaded: &HashMap<i64, Link>) -> LinkState {
+    let mut state = LinkState::default();
+    for db_link in db_links {
+        let mut link = Link::from_db_link(db_link);
+        match loaded.get(&link.text_id) {
+            Some(current) => link.updated = current.moved(&link.to_textarea_link()),
+            None => link.saved = false,
+        }
+        state.ta_links.insert(link.text_id as usize, link.to_textarea_link());
+        state.links.insert(link.text_id, link);
+    }
+    for (text_id, current) in loaded {
+        if !state.links.contains_key(text_id) {
+            let mut link = current.clone();
+            link.deleted = true;
+            state.links.insert(*text_id, link);
+            state.deleted_link_ids.push(*text_id);
+        }
+    }
+    state
+}
+
+fn cursor_line_style(theme: &EditorTheme) -> Style {
+    theme
+        .cursor_line
+        .map_or(Style::default(), |color| Style::default().bg(color))
 }
 
 impl<'a> Editor<'a> {
@@ -147,22 +341,11 @@ impl<'a> Editor<'a> {
             .map(|link| (link.text_id as usize, link.to_textarea_link()))
             .collect::<HashMap<usize, TextAreaLink>>();
 
-        let ta_theme = TextAreaTheme {
-            text: theme.text,
-            select: theme.select,
-            links: theme.links,
-            main_heading: theme.main_heading,
-            main_heading_modifiers: theme.main_heading_modifiers.clone(),
-            sub_heading: theme.sub_heading,
-            sub_heading_modifiers: theme.sub_heading_modifiers.clone(),
-        };
-
-        let mut body = TextArea::new(body, ta_links, max_col, ta_theme);
-        body.set_cursor_line_style(Style::default());
-        body.set_selection_style(Style::default().bg(theme.select));
-        body.set_max_histories(100);
+        let body = build_body(&theme, body, ta_links, max_col);
 
         let block_info = " <| NORMAL |>".to_string();
+        let saved_title = title.clone();
+        let saved_lines = body.lines().to_vec();
 
         Self {
             title,
@@ -173,13 +356,36 @@ impl<'a> Editor<'a> {
             mode: EditorMode::Normal,
             block_info,
             prev_cursor_col: 0,
+            scroll_top: 0,
+            viewport_rows: 0,
             num_buf: Vec::with_capacity(6),
             cmd_buf: String::with_capacity(6),
             cmd_state: CommandState::NoCommand,
             sidebar_open,
             searchbar_open: false,
+            search_pattern: String::new(),
+            hide_hints: false,
             state: ComponentState::Active,
             theme,
+            settings: EditorSettings::default(),
+            max_col,
+            saved_title,
+            saved_lines,
+            undo_groups: vec![],
+            redo_groups: vec![],
+            open_group: None,
+            last_insert: Instant::now(),
+            block_anchor: (0, 0),
+            block_insert: None,
+            preview_requested: false,
+            link_word_requested: false,
+            yank_kind: YankKind::CharWise,
+            delete_registers: vec![],
+            read_only: false,
+            hop_targets: vec![],
+            hop_typed: String::new(),
+            markdown_preview: false,
+            preview_scroll: 0,
         }
     }
 
@@ -196,25 +402,215 @@ impl<'a> Editor<'a> {
             .map(|link| (link.text_id as usize, link.to_textarea_link()))
             .collect::<HashMap<usize, TextAreaLink>>();
 
-        let ta_theme = TextAreaTheme {
-            text: self.theme.text,
-            select: self.theme.select,
-            links: self.theme.links,
-            main_heading: self.theme.main_heading,
-            main_heading_modifiers: self.theme.main_heading_modifiers.clone(),
-            sub_heading: self.theme.sub_heading,
-            sub_heading_modifiers: self.theme.sub_heading_modifiers.clone(),
-        };
-
-        let mut body = TextArea::new(body, ta_links, max_col, ta_theme);
-        body.set_cursor_line_style(Style::default());
-        body.set_selection_style(Style::default().bg(self.theme.select));
-        body.set_max_histories(100);
-
         self.title = title;
-        self.body = body;
+        self.body = build_body(&self.theme, body, ta_links, max_col);
         self.links = links;
         self.note_id = note_id;
+        self.max_col = max_col;
+        self.scroll_top = 0;
+        self.read_only = false;
+        self.markdown_preview = false;
+        self.clear_undo_groups();
+        self.mark_saved();
+    }
+
+    pub(crate) fn toggle_read_only(&mut self) {
+        self.read_only = !self.read_only;
+        if self.read_only && self.mode == EditorMode::Insert {
+            self.set_mode(EditorMode::Normal);
+        }
+    }
+
+    /// The preview opens where the editor is scrolled to, closing it leaves the cursor
+    /// and scroll as they were
+    pub(crate) fn toggle_markdown_preview(&mut self) {
+        self.markdown_preview = !self.markdown_preview;
+        if self.markdown_preview {
+            if self.mode != EditorMode::Normal {
+                self.set_mode(EditorMode::Normal);
+            }
+            self.preview_scroll = self.scroll_top;
+        }
+    }
+
+    fn scroll_preview(&mut self, input: Input) {
+        let last_row = self.body.lines().len().saturating_sub(1);
+        let page = self.viewport_rows.max(1);
+        self.preview_scroll = match input {
+            Input {
+                key: Key::Char('j') | Key::Down,
+                ..
+            } => self.preview_scroll + 1,
+            Input {
+                key: Key::Char('k') | Key::Up,
+                ..
+            } => self.preview_scroll.saturating_sub(1),
+            Input {
+                key: Key::PageDown, ..
+            } => self.preview_scroll + page,
+            Input { key: Key::PageUp, .. } => self.preview_scroll.saturating_sub(page),
+            Input {
+                key: Key::Char('g') | Key::Home,
+                ..
+            } => 0,
+            Input {
+                key: Key::Char('G') | Key::End,
+                ..
+            } => last_row,
+            _ => self.preview_scroll,
+        }
+        .min(last_row);
+    }
+
+    /// Whether `input` would change the text, or enter insert mode, from the current mode.
+    /// The second key of a pending command, like the `x` of `fx`, is not an edit.
+    fn is_edit_input(&self, input: &Input) -> bool {
+        if self.mode == EditorMode::Insert {
+            return true;
+        }
+        match input.key {
+            Key::Backspace | Key::Delete | Key::Enter => {
+                self.cmd_state == CommandState::NoCommand
+            }
+            Key::Char(' ') => input.ctrl,
+            Key::Char(c) if matches!(self.mode, EditorMode::Visual | EditorMode::VisualLine) => {
+                self.settings.auto_pairs && closing_pair(c).is_some()
+            }
+            Key::Char(c) if !input.ctrl && !input.alt => {
+                self.cmd_state == CommandState::NoCommand && "iIaAoOcCdDxXpPJ~<>]ur".contains(c)
+            }
+            _ => false,
+        }
+    }
+
+    /// Swaps in text edited outside the app. Links whose text is no longer at the
+    /// same place are dropped, the rest are kept.
+    pub(crate) fn replace_lines(&mut self, lines: Vec<String>) {
+        let cursor = self.body.cursor();
+        let old_lines = self.body.lines().to_vec();
+        let link_text = |lines: &[String], row: usize, start: usize, end: usize| {
+            lines
+                .get(row)
+                .map(|line| line.chars().skip(start).take(end + 1 - start).collect::<String>())
+        };
+
+        let mut ta_links = self.body.links.clone();
+        ta_links.retain(|&text_id, ta_link| {
+            let (row, start, end) = (ta_link.row, ta_link.start_col, ta_link.end_col);
+            let kept = link_text(&old_lines, row, start, end) == link_text(&lines, row, start, end);
+            if !kept {
+                if let Some(link) = self.links.get_mut(&(text_id as i64)) {
+                    link.deleted = true;
+                    self.deleted_link_ids.push(text_id as i64);
+                }
+            }
+            kept
+        });
+
+        self.body = build_body(&self.theme, lines, ta_links, self.max_col);
+        self.body
+            .move_cursor(CursorMove::Jump(cursor.0 as u16, cursor.1 as u16));
+        self.clear_undo_groups();
+    }
+
+    /// Rebuilds the textarea for a new width, keeping the text, links, cursor and yank
+    /// register. The undo history doesn't survive the rebuild, undo falls back on the
+    /// undo groups to get past it.
+    pub(crate) fn set_max_col(&mut self, max_col: u16) {
+        if max_col == self.max_col {
+            return;
+        }
+        let cursor = self.body.cursor();
+        let lines = self.body.lines().to_vec();
+        let ta_links = self.body.links.clone();
+        let yank_text = self.body.yank_text();
+
+        self.body = build_body(&self.theme, lines, ta_links, max_col);
+        self.body.set_yank_text(yank_text);
+        self.body
+            .move_cursor(CursorMove::Jump(cursor.0 as u16, cursor.1 as u16));
+        self.max_col = max_col;
+    }
+
+    pub(crate) fn mark_saved(&mut self) {
+        self.saved_title = self.title.clone();
+        self.saved_lines = self.body.lines().to_vec();
+    }
+
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.title != self.saved_title || self.body.lines() != self.saved_lines.as_slice()
+    }
+
+    pub(crate) fn status_line(&self, settings: &StatuslineSettings) -> StatusLine {
+        StatusLine {
+            cursor: self.body.cursor(),
+            total_lines: self.body.lines().len(),
+            note_id: self.note_id,
+            link_count: self.links.values().filter(|link| !link.deleted).count(),
+            pending_keys: self.pending_keys(),
+            settings: settings.clone(),
+            style: Style::default().fg(self.theme.text),
+        }
+    }
+
+    /// Moves to a line and column counted from 1, clamped onto the text
+    pub(crate) fn go_to(&mut self, line: usize, col: usize) {
+        let lines = self.body.lines();
+        let row = line.saturating_sub(1).min(lines.len().saturating_sub(1));
+        let col = col
+            .saturating_sub(1)
+            .min(lines.get(row).map_or(0, |line| line.chars().count()));
+        self.body
+            .move_cursor(CursorMove::Jump(row as u16, col as u16));
+    }
+
+    /// Moves to the first heading whose text is `anchor`, ignoring case.
+    /// False if the note has no such heading.
+    pub(crate) fn jump_to_heading(&mut self, anchor: &str) -> bool {
+        let row = self.body.lines().iter().position(|line| {
+            heading_level(line)
+                .is_some_and(|level| line[level..].trim().eq_ignore_ascii_case(anchor.trim()))
+        });
+        match row {
+            Some(row) => {
+                self.go_to(row + 1, 1);
+                self.set_prev_cursor_col();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Title, unsaved state and where the cursor is, as `Ctrl-g` reports it
+    pub(crate) fn position_report(&self) -> String {
+        let (row, col) = self.body.cursor();
+        let total = self.body.lines().len();
+        let modified = match self.is_dirty() {
+            true => " [Modified]",
+            false => "",
+        };
+        format!(
+            "\"{}\"{} line {} of {} --{}%-- col {}",
+            self.title.trim(),
+            modified,
+            row + 1,
+            total,
+            (row + 1) * 100 / total,
+            col + 1
+        )
+    }
+
+    /// Rough size of the unsaved edit as (lines, chars), comparing the body row by row
+    pub(crate) fn unsaved_change_size(&self) -> (usize, usize) {
+        let lines = self.body.lines();
+        let rows = std::cmp::max(lines.len(), self.saved_lines.len());
+        (0..rows)
+            .filter_map(|row| {
+                let new = lines.get(row).map_or("", |line| line.as_str());
+                let old = self.saved_lines.get(row).map_or("", |line| line.as_str());
+                (new != old).then(|| std::cmp::max(new.chars().count(), old.chars().count()))
+            })
+            .fold((0, 0), |(lines, chars), changed| (lines + 1, chars + changed))
     }
 
     pub(crate) fn set_state(&mut self, new_state: ComponentState) {
@@ -242,43 +638,803 @@ impl<'a> Editor<'a> {
                 self.body.clear_search();
                 " <| V-LINE |>".to_owned()
             }
+            // The textarea can't select rectangles, the block is tracked and drawn here
+            EditorMode::VisualBlock => {
+                self.body.cancel_selection();
+                self.body.clear_search();
+                self.block_anchor = self.body.cursor();
+                " <| V-BLOCK |>".to_owned()
+            }
         };
         self.mode = mode;
     }
 
-    pub(crate) fn set_title(&mut self, title: String) {
-        self.title = title;
+    /// Scrolls just enough to keep `scrolloff` lines around the cursor, then moves the
+    /// textarea's viewport to match so screen rows can be mapped back to lines.
+    /// Near the ends of the note the viewport stops rather than scrolling past them.
+    pub(crate) fn update_viewport(&mut self, visible_rows: usize) {
+        self.viewport_rows = visible_rows;
+        if visible_rows == 0 {
+            return;
+        }
+        if self.settings.soft_wrap {
+            self.update_wrapped_viewport(visible_rows);
+            return;
+        }
+        let cursor_row = self.body.cursor().0;
+        let total_rows = self.body.lines().len();
+        let scrolloff = self.settings.scrolloff.min((visible_rows - 1) / 2);
+        let prev_top = self.scroll_top;
+
+        if cursor_row < self.scroll_top + scrolloff {
+            self.scroll_top = cursor_row.saturating_sub(scrolloff);
+        } else if cursor_row + scrolloff >= self.scroll_top + visible_rows {
+            self.scroll_top = (cursor_row + scrolloff + 1 - visible_rows)
+                .min(total_rows.saturating_sub(visible_rows));
+        }
+
+        if self.scroll_top != prev_top {
+            let delta = self.scroll_top as i64 - prev_top as i64;
+            self.body.scroll((delta as i16, 0));
+        }
     }
 
-    pub(crate) fn handle_input(&mut self, input: Input) {
-        let num_buf_len = self.num_buf.len() as u32;
-        //info!("editor::handle_input\nmode: {:?}\ncommand state: {:?}\ninput: {:?}", self.mode, self.cmd_state, input);
+    /// Scrolls by whole lines until the cursor's screen row fits. The textarea's own
+    /// viewport isn't used with soft wrap, so it isn't kept in step.
+    fn update_wrapped_viewport(&mut self, visible_rows: usize) {
+        let (row, col) = self.body.cursor();
+        let width = self.wrap_width();
+        let lines = self.body.lines();
+        let cursor_segment = segment_position(&wrap_segments(&lines[row], width), col).0;
+
+        let mut top = std::cmp::min(self.scroll_top, row);
+        while top < row {
+            let rows_above = (top..row)
+                .map(|r| wrap_segments(&lines[r], width).len())
+                .sum::<usize>();
+            if rows_above + cursor_segment < visible_rows {
+                break;
+            }
+            top += 1;
+        }
+        self.scroll_top = top;
+    }
+
+    /// Columns taken by line numbers, the widest number and a space, none without them
+    pub(crate) fn gutter_width(&self) -> u16 {
+        match self.settings.line_numbers && !self.markdown_preview {
+            true => self.body.lines().len().to_string().len() as u16 + 1,
+            false => 0,
+        }
+    }
+
+    /// Screen column of the first text column, past the border, padding and gutter
+    fn text_x(&self, area: Rect) -> u16 {
+        area.x + 2 + self.gutter_width()
+    }
+
+    /// Columns soft wrapped lines are broken at
+    fn wrap_width(&self) -> usize {
+        self.max_col.saturating_sub(self.gutter_width()) as usize
+    }
+
+    /// Screen rows from `scroll_top` down as (row, start col, end col)
+    pub(crate) fn wrapped_rows(&self, visible_rows: usize) -> Vec<(usize, usize, usize)> {
+        let width = self.wrap_width();
+        self.body
+            .lines()
+            .iter()
+            .enumerate()
+            .skip(self.scroll_top)
+            .flat_map(|(row, line)| {
+                wrap_segments(line, width)
+                    .into_iter()
+                    .map(move |(start, end)| (row, start, end))
+            })
+            .take(visible_rows)
+            .collect()
+    }
+
+    /// `j`/`k`, moving by screen row when long lines are soft wrapped
+    fn vertical_motion(&mut self, num_buf_len: u32, down: bool) {
+        if self.settings.soft_wrap {
+            let count = std::cmp::max(self.get_num_from_buf(num_buf_len), 1);
+            self.num_buf.clear();
+            for _ in 0..count {
+                self.move_screen_row(down);
+            }
+            return;
+        }
+
+        match (num_buf_len, down) {
+            (0, true) => self.body.move_cursor(CursorMove::Down),
+            (0, false) => self.body.move_cursor(CursorMove::Up),
+            (_, true) => self.repeat_action(num_buf_len, move |editor| {
+                editor.body.move_cursor(CursorMove::Down);
+            }),
+            (_, false) => self.repeat_action(num_buf_len, move |editor| {
+                editor.body.move_cursor(CursorMove::Up);
+            }),
+        }
+        self.jump_cursor_to_prev_col();
+    }
+
+    /// PageUp/PageDown, a viewport's worth of lines or screen rows with soft wrap
+    fn page_motion(&mut self, down: bool) {
+        let rows = std::cmp::max(self.viewport_rows, 1);
+        if self.settings.soft_wrap {
+            for _ in 0..rows {
+                self.move_screen_row(down);
+            }
+            return;
+        }
+
+        for _ in 0..rows {
+            match down {
+                true => self.body.move_cursor(CursorMove::Down),
+                false => self.body.move_cursor(CursorMove::Up),
+            }
+        }
+        self.jump_cursor_to_prev_col();
+    }
+
+    /// Moves to the same column of the next or previous screen row, which may be
+    /// another part of the same line
+    pub(crate) fn move_screen_row(&mut self, down: bool) {
+        let (row, col) = self.body.cursor();
+        let width = self.wrap_width();
+        let lines = self.body.lines();
+        let segments = wrap_segments(&lines[row], width);
+        let (idx, offset) = segment_position(&segments, col);
+
+        let target = match down {
+            true if idx + 1 < segments.len() => Some((row, segment_col(&segments, idx + 1, offset))),
+            true if row + 1 < lines.len() => {
+                let next = wrap_segments(&lines[row + 1], width);
+                Some((row + 1, segment_col(&next, 0, offset)))
+            }
+            false if idx > 0 => Some((row, segment_col(&segments, idx - 1, offset))),
+            false if row > 0 => {
+                let prev = wrap_segments(&lines[row - 1], width);
+                Some((row - 1, segment_col(&prev, prev.len() - 1, offset)))
+            }
+            _ => None,
+        };
+
+        if let Some((row, col)) = target {
+            self.body
+                .move_cursor(CursorMove::Jump(row as u16, col as u16));
+        }
+    }
+
+    pub(crate) fn cursor_from_click(
+        &self,
+        area: Rect,
+        column: u16,
+        row: u16,
+    ) -> Option<(usize, usize)> {
+        // Border plus padding on each side
+        let text_x = self.text_x(area);
+        let text_y = area.y + 2;
+
+        if column < text_x
+            || row < text_y
+            || column >= area.right().saturating_sub(2)
+            || row >= area.bottom().saturating_sub(2)
+        {
+            return None;
+        }
+
+        if self.settings.soft_wrap {
+            let visible_rows = area.height.saturating_sub(4) as usize;
+            let rows = self.wrapped_rows(visible_rows);
+            let &(line, start, end) = rows
+                .get((row - text_y) as usize)
+                .or_else(|| rows.last())?;
+            let col = std::cmp::min(start + (column - text_x) as usize, end);
+            return Some((line, col));
+        }
+
+        let lines = self.body.lines();
+        let clicked_row = std::cmp::min(
+            self.scroll_top + (row - text_y) as usize,
+            lines.len().saturating_sub(1),
+        );
+        let clicked_col = std::cmp::min(
+            (column - text_x) as usize,
+            lines[clicked_row].chars().count(),
+        );
+
+        Some((clicked_row, clicked_col))
+    }
+
+    /// Screen cell of the text at `pos` drawn in `area`, None when it is scrolled out
+    pub(crate) fn screen_position(&self, area: Rect, pos: (usize, usize)) -> Option<(u16, u16)> {
+        let (row, col) = pos;
+        let visible_rows = area.height.saturating_sub(4) as usize;
+
+        let (screen_row, screen_col) = match self.settings.soft_wrap {
+            true => self
+                .wrapped_rows(visible_rows)
+                .into_iter()
+                .enumerate()
+                .find(|&(_, (line, start, end))| line == row && (start..=end).contains(&col))
+                .map(|(idx, (_, start, _))| (idx, col - start))?,
+            false => (row.checked_sub(self.scroll_top)?, col),
+        };
+        if screen_row >= visible_rows {
+            return None;
+        }
+
+        Some((
+            self.text_x(area) + screen_col as u16,
+            area.y + 2 + screen_row as u16,
+        ))
+    }
+
+    pub(crate) fn handle_click(&mut self, area: Rect, column: u16, row: u16) {
+        if let Some((r, c)) = self.cursor_from_click(area, column, row) {
+            self.body.move_cursor(CursorMove::Jump(r as u16, c as u16));
+            self.set_prev_cursor_col();
+        }
+    }
+
+    /// Highlights the matches of `pattern` and makes it the one `n` and `N` look for.
+    /// False if it isn't a valid regex.
+    pub(crate) fn set_search(&mut self, pattern: &str) -> bool {
+        match self.body.set_search_pattern(pattern) {
+            Ok(_) => {
+                self.search_pattern = pattern.to_owned();
+                true
+            }
+            Err(e) => {
+                error!("Error searching for {:?}: {:?}", pattern, e);
+                false
+            }
+        }
+    }
+
+    /// `:noh`, hides the match highlights until the next `n` or `N`
+    pub(crate) fn clear_search_highlight(&mut self) {
+        self.body.clear_search();
+    }
+
+    /// Puts the last pattern back on the textarea, mode changes and `:noh` take it off
+    fn restore_search(&mut self) {
+        if !self.search_pattern.is_empty() {
+            let pattern = self.search_pattern.clone();
+            self.set_search(&pattern);
+        }
+    }
+
+    /// Start of every non-empty match of `pattern`, as (row, char col) in document order
+    pub(crate) fn find_matches(&self, pattern: &str) -> Vec<(usize, usize)> {
+        if pattern.is_empty() {
+            return vec![];
+        }
+        let Ok(re) = Regex::new(pattern) else {
+            return vec![];
+        };
+
+        self.body
+            .lines()
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                re.find_iter(line)
+                    .filter(|m| !m.as_str().is_empty())
+                    .map(move |m| (row, line[..m.start()].chars().count()))
+                    .collect::<Vec<(usize, usize)>>()
+            })
+            .collect()
+    }
+
+    /// Leading whitespace of the cursor line, or nothing when `auto_indent` is off
+    fn line_indent(&self) -> String {
+        if !self.settings.auto_indent {
+            return String::new();
+        }
+        let row = self.body.cursor().0;
+        self.body.lines()[row]
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect()
+    }
+
+    /// A line left holding only indentation is cleared, so repeated newlines don't
+    /// leave trailing whitespace behind
+    fn insert_newline_with_indent(&mut self) {
+        let (row, col) = self.body.cursor();
+        let blank = self.body.lines()[row].chars().all(|c| c.is_whitespace());
+        let indent = match blank {
+            true => self.line_indent(),
+            false => self.line_indent().chars().take(col).collect(),
+        };
+
+        if blank && self.settings.auto_indent {
+            self.body.move_cursor(CursorMove::End);
+            while self.body.cursor().1 > 0 {
+                self.body.delete_char();
+            }
+        }
+        self.body.insert_newline();
+        self.body.insert_str(indent);
+    }
+
+    /// With `expand_tab` spaces are inserted up to the next tab stop
+    fn insert_tab(&mut self) {
+        match self.settings.expand_tab {
+            true => {
+                let col = self.body.cursor().1;
+                let width = self.settings.tab_width;
+                self.body.insert_str(" ".repeat(width - col % width));
+            }
+            false => {
+                self.body.insert_str("\t");
+            }
+        }
+    }
+
+    /// The closing char to type along with `open`. Quotes straight after a word are
+    /// left alone, they are more likely an apostrophe.
+    fn auto_pair(&self, open: char) -> Option<char> {
+        if !self.settings.auto_pairs {
+            return None;
+        }
+        let close = closing_pair(open)?;
+        let (row, col) = self.body.cursor();
+        let prev = col
+            .checked_sub(1)
+            .and_then(|prev| self.body.lines()[row].chars().nth(prev));
+        match open == close && prev.is_some_and(char::is_alphanumeric) {
+            true => None,
+            false => Some(close),
+        }
+    }
+
+    /// Types the opening char of `input` and its closing one, leaving the cursor between
+    fn insert_pair(&mut self, input: Input) {
+        let Key::Char(open) = input.key else {
+            return;
+        };
+        let Some(close) = self.auto_pair(open) else {
+            return;
+        };
+        self.body.input(input);
+        let (row, col) = self.body.cursor();
+        self.body.insert_str(close.to_string());
+        self.body.move_cursor(CursorMove::Jump(row as u16, col as u16));
+    }
+
+    /// A closing char typed right before the same char moves over it instead
+    fn skips_over(&self, c: char) -> bool {
+        let (row, col) = self.body.cursor();
+        self.settings.auto_pairs
+            && AUTO_PAIRS.iter().any(|&(_, close)| close == c)
+            && self.body.lines()[row].chars().nth(col) == Some(c)
+    }
+
+    /// Start of the visual selection and the position just past its last char.
+    /// VisualLine takes the whole lines.
+    pub(crate) fn selection_bounds(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.body.get_selection_start()?;
+        let cursor = self.body.cursor();
+        let (first, last) = (std::cmp::min(anchor, cursor), std::cmp::max(anchor, cursor));
+        let last_len = self.body.lines()[last.0].chars().count();
         match self.mode {
-            EditorMode::Insert => match input {
-                Input { key: Key::Esc, .. } => {
-                    self.set_mode(EditorMode::Normal);
-                }
-                Input {
-                    key: Key::Up,
-                    shift: false,
-                    ..
-                }
-                | Input {
-                    key: Key::Down,
-                    shift: false,
-                    ..
-                }
-                | Input {
-                    key: Key::Up,
-                    shift: true,
-                    ..
+            EditorMode::VisualLine => Some(((first.0, 0), (last.0, last_len))),
+            _ => Some((first, (last.0, std::cmp::min(last.1 + 1, last_len)))),
+        }
+    }
+
+    /// Text from `start` up to `end`, rows joined by line breaks
+    pub(crate) fn text_between(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let lines = &self.body.lines()[start.0..=end.0];
+        let mut text = vec![];
+        for (offset, line) in lines.iter().enumerate() {
+            let from = if offset == 0 { start.1 } else { 0 };
+            let to = if start.0 + offset == end.0 { end.1 } else { usize::MAX };
+            text.push(line.chars().take(to).skip(from).collect::<String>());
+        }
+        text.join("\n")
+    }
+
+    /// Deletes from `start` up to `end` and leaves the cursor there, the yank
+    /// register keeps what it had
+    pub(crate) fn delete_between(&mut self, start: (usize, usize), end: (usize, usize)) {
+        let yank_text = self.body.yank_text();
+        self.body.cancel_selection();
+        self.body
+            .move_cursor(CursorMove::Jump(start.0 as u16, start.1 as u16));
+        self.body.start_selection();
+        self.body
+            .move_cursor(CursorMove::Jump(end.0 as u16, end.1 as u16));
+        self.body.cut();
+        self.body.set_yank_text(yank_text);
+        self.body
+            .move_cursor(CursorMove::Jump(start.0 as u16, start.1 as u16));
+        self.set_prev_cursor_col();
+    }
+
+    /// Visual mode with `auto_pairs`, puts the pair of `open` around the selection.
+    /// VisualLine wraps the whole lines.
+    fn wrap_selection(&mut self, open: char, close: char) {
+        let Some((first, end)) = self.selection_bounds() else {
+            return;
+        };
+        self.set_mode(EditorMode::Normal);
+
+        // Closing first, so the opening char doesn't shift where it goes
+        self.body
+            .move_cursor(CursorMove::Jump(end.0 as u16, end.1 as u16));
+        self.body.insert_str(close.to_string());
+        self.body
+            .move_cursor(CursorMove::Jump(first.0 as u16, first.1 as u16));
+        self.body.insert_str(open.to_string());
+        self.body
+            .move_cursor(CursorMove::Jump(first.0 as u16, first.1 as u16));
+        self.set_prev_cursor_col();
+    }
+
+    /// True when everything left of the cursor is indentation made of spaces
+    fn in_soft_tab_indent(&self) -> bool {
+        let (row, col) = self.body.cursor();
+        self.settings.expand_tab
+            && col > 0
+            && self.body.lines()[row].chars().take(col).all(|c| c == ' ')
+    }
+
+    /// Deletes back to the previous tab stop
+    fn delete_soft_tab(&mut self) {
+        let col = self.body.cursor().1;
+        let width = self.settings.tab_width;
+        for _ in 0..(col - 1) % width + 1 {
+            self.body.delete_char();
+        }
+    }
+
+    /// Every non-empty match as (row, char col, char len, expanded replacement)
+    fn substitutions(&self, sub: &Substitute) -> Vec<(usize, usize, usize, String)> {
+        let mut subs = vec![];
+        for (row, line) in self.body.lines().iter().enumerate() {
+            for caps in sub.pattern.captures_iter(line) {
+                let m = caps.get(0).expect("capture group 0 is the whole match");
+                if m.as_str().is_empty() {
+                    continue;
                 }
-                | Input {
-                    key: Key::Down,
-                    shift: true,
-                    ..
-                } => {
-                    self.body.input(input);
+                let mut replacement = String::new();
+                caps.expand(&sub.replacement, &mut replacement);
+                subs.push((
+                    row,
+                    line[..m.start()].chars().count(),
+                    m.as_str().chars().count(),
+                    replacement,
+                ));
+            }
+        }
+        subs
+    }
+
+    /// Start of the first match at (or strictly after) `from`, without wrapping
+    pub(crate) fn next_substitution(
+        &self,
+        sub: &Substitute,
+        from: (usize, usize),
+        inclusive: bool,
+    ) -> Option<(usize, usize)> {
+        self.substitutions(sub)
+            .into_iter()
+            .map(|(row, col, _, _)| (row, col))
+            .find(|&pos| pos > from || (inclusive && pos == from))
+    }
+
+    /// Replaces every match for `ReplaceScope::All`, otherwise the next match from the cursor.
+    /// Returns the number of replacements made.
+    pub(crate) fn replace(&mut self, sub: &Substitute) -> usize {
+        let cursor = self.body.cursor();
+        let mut subs = self.substitutions(sub);
+
+        if sub.scope != ReplaceScope::All && !subs.is_empty() {
+            let idx = subs
+                .iter()
+                .position(|&(row, col, _, _)| (row, col) >= cursor)
+                .unwrap_or(0);
+            subs = vec![subs.swap_remove(idx)];
+        }
+
+        // Later matches first, so the positions of earlier ones stay valid
+        for (row, col, len, replacement) in subs.iter().rev() {
+            self.replace_at(*row, *col, *len, replacement);
+        }
+        subs.len()
+    }
+
+    /// Replaces the match starting at the cursor, if there is one
+    pub(crate) fn replace_current(&mut self, sub: &Substitute) -> bool {
+        let cursor = self.body.cursor();
+        match self
+            .substitutions(sub)
+            .into_iter()
+            .find(|&(row, col, _, _)| (row, col) == cursor)
+        {
+            Some((row, col, len, replacement)) => {
+                self.replace_at(row, col, len, &replacement);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Edits go through the textarea so link positions are shifted along with the text
+    fn replace_at(&mut self, row: usize, col: usize, len: usize, replacement: &str) {
+        self.body.move_cursor(CursorMove::Jump(row as u16, col as u16));
+        for _ in 0..len {
+            self.body.delete_next_char();
+        }
+        self.body.insert_str(replacement);
+    }
+
+    /// Flips `- [ ]` and `- [x]` on the cursor line, returning false if it isn't a task
+    pub(crate) fn toggle_checkbox(&mut self) -> bool {
+        let (row, col) = self.body.cursor();
+        let toggled = self.toggle_checkbox_at(row);
+        self.body.move_cursor(CursorMove::Jump(row as u16, col as u16));
+        toggled
+    }
+
+    /// Toggles every task line in `start..=end`, other lines are left alone
+    fn toggle_checkboxes(&mut self, start: usize, end: usize) {
+        let (row, col) = self.body.cursor();
+        let last_row = self.body.lines().len().saturating_sub(1);
+        for task_row in start..=std::cmp::min(end, last_row) {
+            self.toggle_checkbox_at(task_row);
+        }
+        self.body.move_cursor(CursorMove::Jump(row as u16, col as u16));
+    }
+
+    fn toggle_checkbox_at(&mut self, row: usize) -> bool {
+        let line = &self.body.lines()[row];
+        let Some(box_col) = checkbox_col(line) else {
+            return false;
+        };
+        let mark = match line.chars().nth(box_col) {
+            Some(' ') => "x",
+            _ => " ",
+        };
+        self.replace_at(row, box_col, 1, mark);
+        true
+    }
+
+    pub(crate) fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    /// Handles a key and records the edit it made as an undo group. Typing in insert
+    /// mode is coalesced until the mode changes, the cursor moves or typing pauses.
+    pub(crate) fn handle_input(&mut self, input: Input) {
+        if self.markdown_preview {
+            self.scroll_preview(input);
+            return;
+        }
+        if self.read_only && self.is_edit_input(&input) {
+            return;
+        }
+
+        let history_key = self.mode == EditorMode::Normal
+            && self.cmd_state == CommandState::NoCommand
+            && matches!(
+                input,
+                Input {
+                    key: Key::Char('u' | 'r'),
+                    ctrl: false,
+                    ..
+                }
+            );
+        if history_key {
+            self.dispatch_input(input);
+            return;
+        }
+
+        if self.open_group.is_some() && self.last_insert.elapsed() >= UNDO_GROUP_IDLE {
+            self.close_undo_group();
+        }
+        // Keys typed into an open group don't need the text as it was before them
+        let before = match self.open_group {
+            Some(_) => None,
+            None => Some((self.body.lines().to_vec(), self.link_state())),
+        };
+
+        let moved = matches!(
+            input.key,
+            Key::Up
+                | Key::Down
+                | Key::Left
+                | Key::Right
+                | Key::Home
+                | Key::End
+                | Key::PageUp
+                | Key::PageDown
+        );
+        self.dispatch_input(input);
+
+        match (&self.mode, before) {
+            (EditorMode::Insert, Some(before)) => self.open_group = Some(before),
+            (EditorMode::Insert, None) if moved => {
+                self.close_undo_group();
+                self.open_group = Some((self.body.lines().to_vec(), self.link_state()));
+            }
+            (EditorMode::Insert, None) => {}
+            (_, None) => self.close_undo_group(),
+            (_, Some((before, before_links))) => self.push_undo_group(before, before_links),
+        }
+        self.last_insert = Instant::now();
+    }
+
+    fn close_undo_group(&mut self) {
+        if let Some((before, before_links)) = self.open_group.take() {
+            self.push_undo_group(before, before_links);
+        }
+    }
+
+    fn push_undo_group(&mut self, before: Vec<String>, before_links: LinkState) {
+        let after = self.body.lines().to_vec();
+        if before != after {
+            self.undo_groups.push(UndoGroup {
+                before,
+                after,
+                before_links,
+                after_links: None,
+            });
+            self.redo_groups.clear();
+        }
+    }
+
+    fn link_state(&self) -> LinkState {
+        LinkState {
+            ta_links: self.body.links.clone(),
+            links: self.links.clone(),
+            deleted_link_ids: self.deleted_link_ids.clone(),
+        }
+    }
+
+    fn restore_links(&mut self, state: LinkState) {
+        self.body.links = state.ta_links;
+        self.body.deleted_link_ids.clear();
+        self.links = state.links;
+        self.deleted_link_ids = state.deleted_link_ids;
+    }
+
+    /// The latest undo groups in a form that outlives the session, an open insert
+    /// group is closed first
+    pub(crate) fn saved_undo(&mut self) -> Vec<SavedUndo> {
+        self.close_undo_group();
+        let current = self.link_state();
+        let start = self.undo_groups.len().saturating_sub(UNDO_HISTORY_CAP);
+        let groups = &self.undo_groups[start..];
+
+        // A group ends where the next one starts, the last ends at the current state
+        groups
+            .iter()
+            .enumerate()
+            .map(|(idx, group)| {
+                let after_links = groups.get(idx + 1).map_or(&current, |next| &next.before_links);
+                SavedUndo {
+                    before: group.before.clone(),
+                    after: group.after.clone(),
+                    before_links: db_links(&group.before_links),
+                    after_links: db_links(after_links),
+                }
+            })
+            .collect()
+    }
+
+    /// Puts back undo groups from an earlier session, unless the text has changed
+    /// since they were saved. Undoing past the loaded links marks them for the next save.
+    pub(crate) fn restore_undo(&mut self, saved: Vec<SavedUndo>) {
+        if !saved.last().is_some_and(|group| group.after == self.body.lines()) {
+            return;
+        }
+        let loaded = self.links.clone();
+        self.undo_groups = saved
+            .into_iter()
+            .map(|group| UndoGroup {
+                before: group.before,
+                after: group.after,
+                before_links: restored_link_state(group.before_links, &loaded),
+                after_links: Some(restored_link_state(group.after_links, &loaded)),
+            })
+            .collect();
+        self.redo_groups.clear();
+    }
+
+    /// Rebuilds the textarea when its own history can't get back to `lines`,
+    /// as with groups restored from an earlier session
+    fn set_lines(&mut self, lines: Vec<String>) {
+        let cursor = self.body.cursor();
+        let ta_links = self.body.links.clone();
+        self.body = build_body(&self.theme, lines, ta_links, self.max_col);
+        self.body
+            .move_cursor(CursorMove::Jump(cursor.0 as u16, cursor.1 as u16));
+    }
+
+    fn clear_undo_groups(&mut self) {
+        self.undo_groups.clear();
+        self.redo_groups.clear();
+        self.open_group = None;
+    }
+
+    /// Steps the textarea history back until the text is as it was before the last group
+    /// and puts the links back as they were with it
+    pub(crate) fn undo(&mut self) {
+        match self.undo_groups.pop() {
+            Some(mut group) => {
+                group.after_links = Some(self.link_state());
+                while self.body.lines() != group.before.as_slice() {
+                    if !self.body.undo() {
+                        break;
+                    }
+                }
+                if self.body.lines() != group.before.as_slice() {
+                    self.set_lines(group.before.clone());
+                }
+                self.restore_links(group.before_links.clone());
+                self.redo_groups.push(group);
+            }
+            None => {
+                self.body.undo();
+            }
+        }
+    }
+
+    pub(crate) fn redo(&mut self) {
+        if let Some(group) = self.redo_groups.pop() {
+            while self.body.lines() != group.after.as_slice() {
+                if !self.body.redo() {
+                    break;
+                }
+            }
+            if self.body.lines() != group.after.as_slice() {
+                self.set_lines(group.after.clone());
+            }
+            if let Some(after_links) = group.after_links.clone() {
+                self.restore_links(after_links);
+            }
+            self.undo_groups.push(group);
+        }
+    }
+
+    fn dispatch_input(&mut self, input: Input) {
+        let num_buf_len = self.num_buf.len() as u32;
+        //info!("editor::handle_input\nmode: {:?}\ncommand state: {:?}\ninput: {:?}", self.mode, self.cmd_state, input);
+        match self.mode {
+            EditorMode::Insert => match input {
+                Input { key: Key::Esc, .. } => {
+                    if let Some(block_insert) = self.block_insert.take() {
+                        self.finish_block_insert(block_insert);
+                    }
+                    self.set_mode(EditorMode::Normal);
+                }
+                Input {
+                    key: Key::Up,
+                    shift: false,
+                    ..
+                }
+                | Input {
+                    key: Key::Down,
+                    shift: false,
+                    ..
+                }
+                | Input {
+                    key: Key::Up,
+                    shift: true,
+                    ..
+                }
+                | Input {
+                    key: Key::Down,
+                    shift: true,
+                    ..
+                } => {
+                    self.body.input(input);
                     self.jump_cursor_to_prev_col();
                 }
                 Input {
@@ -304,6 +1460,72 @@ impl<'a> Editor<'a> {
                     self.body.input(input);
                     self.set_prev_cursor_col();
                 }
+                Input {
+                    key: Key::Char('w'),
+                    ctrl: true,
+                    alt: false,
+                    ..
+                } => {
+                    self.body.delete_word();
+                }
+                // The textarea would undo on Ctrl-u, here it deletes back to the line start
+                Input {
+                    key: Key::Char('u'),
+                    ctrl: true,
+                    alt: false,
+                    ..
+                } => {
+                    self.body.delete_line_by_head();
+                }
+                Input { key: Key::Home, .. } => {
+                    self.body.move_cursor(CursorMove::Head);
+                    self.set_prev_cursor_col();
+                }
+                Input { key: Key::End, .. } => {
+                    self.body.move_cursor(CursorMove::End);
+                    self.set_prev_cursor_col();
+                }
+                Input {
+                    key: Key::PageUp, ..
+                } => self.page_motion(false),
+                Input {
+                    key: Key::PageDown,
+                    ..
+                } => self.page_motion(true),
+                Input {
+                    key: Key::Enter,
+                    ctrl: false,
+                    alt: false,
+                    ..
+                } => self.insert_newline_with_indent(),
+                Input {
+                    key: Key::Tab,
+                    ctrl: false,
+                    alt: false,
+                    ..
+                } => self.insert_tab(),
+                Input {
+                    key: Key::Backspace,
+                    ctrl: false,
+                    alt: false,
+                    ..
+                } if self.in_soft_tab_indent() => self.delete_soft_tab(),
+                // Going through the textarea again lets a typed `]` still make a link
+                Input {
+                    key: Key::Char(c),
+                    ctrl: false,
+                    alt: false,
+                    ..
+                } if self.skips_over(c) => {
+                    self.body.delete_next_char();
+                    self.body.input(input);
+                }
+                Input {
+                    key: Key::Char(c),
+                    ctrl: false,
+                    alt: false,
+                    ..
+                } if self.auto_pair(c).is_some() => self.insert_pair(input),
                 input => {
                     self.body.input(input);
                 }
@@ -315,20 +1537,42 @@ impl<'a> Editor<'a> {
                     CommandState::GoTo
                     | CommandState::Delete
                     | CommandState::Yank
+                    | CommandState::Change
+                    | CommandState::InnerObject
+                    | CommandState::AroundObject
                     | CommandState::FindForward
                     | CommandState::FindBackward
                     | CommandState::PrimeHop
-                    | CommandState::ExecuteHop,
+                    | CommandState::ExecuteHop
+                    | CommandState::Register,
                 ) => {
                     if input.key == Key::Esc {
-                        self.cmd_buf.clear();
-                        self.num_buf.clear();
-                        self.body.clear_hop();
-                        self.cmd_state = CommandState::NoCommand;
+                        self.cancel_command();
                     } else {
                         self.process_command_key_inputs(input)
                     }
                 }
+                (Input { key: Key::Home, .. }, CommandState::NoCommand) => {
+                    self.body.move_cursor(CursorMove::Head);
+                    self.set_prev_cursor_col();
+                }
+                (Input { key: Key::End, .. }, CommandState::NoCommand) => {
+                    self.body.move_cursor(CursorMove::End);
+                    self.set_prev_cursor_col();
+                }
+                (
+                    Input {
+                        key: Key::PageUp, ..
+                    },
+                    CommandState::NoCommand,
+                ) => self.page_motion(false),
+                (
+                    Input {
+                        key: Key::PageDown,
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => self.page_motion(true),
                 // Move left
                 (
                     Input {
@@ -357,15 +1601,7 @@ impl<'a> Editor<'a> {
                     CommandState::NoCommand,
                 )
                 | (Input { key: Key::Down, .. }, CommandState::NoCommand) => {
-                    match num_buf_len {
-                        0 => {
-                            self.body.move_cursor(CursorMove::Down);
-                        }
-                        _ => self.repeat_action(num_buf_len, move |editor| {
-                            editor.body.move_cursor(CursorMove::Down);
-                        }),
-                    }
-                    self.jump_cursor_to_prev_col();
+                    self.vertical_motion(num_buf_len, true);
                 }
                 // Move Up
                 (
@@ -376,15 +1612,7 @@ impl<'a> Editor<'a> {
                     CommandState::NoCommand,
                 )
                 | (Input { key: Key::Up, .. }, CommandState::NoCommand) => {
-                    match num_buf_len {
-                        0 => {
-                            self.body.move_cursor(CursorMove::Up);
-                        }
-                        _ => self.repeat_action(num_buf_len, move |editor| {
-                            editor.body.move_cursor(CursorMove::Up);
-                        }),
-                    }
-                    self.jump_cursor_to_prev_col();
+                    self.vertical_motion(num_buf_len, false);
                 }
                 // Move Right
                 (
@@ -464,6 +1692,17 @@ impl<'a> Editor<'a> {
                     self.body.move_cursor(CursorMove::End);
                     self.set_prev_cursor_col();
                 }
+                (
+                    Input {
+                        key: Key::Tab,
+                        shift,
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => {
+                    self.jump_to_link(!shift);
+                    self.set_prev_cursor_col();
+                }
                 (
                     Input {
                         key: Key::Char('G'),
@@ -482,6 +1721,7 @@ impl<'a> Editor<'a> {
                     CommandState::NoCommand,
                 ) => {
                     self.body.delete_line_by_end();
+                    self.yank_kind = YankKind::CharWise;
                 }
                 (
                     Input {
@@ -491,9 +1731,23 @@ impl<'a> Editor<'a> {
                     CommandState::NoCommand,
                 ) => {
                     self.body.delete_line_by_end();
+                    self.yank_kind = YankKind::CharWise;
                     self.set_mode(EditorMode::Insert);
                     self.set_prev_cursor_col();
                 }
+                (
+                    Input {
+                        key: Key::Char(' '),
+                        ctrl: true,
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => {
+                    let row = self.body.cursor().0;
+                    let count = std::cmp::max(self.get_num_from_buf(num_buf_len), 1) as usize;
+                    self.toggle_checkboxes(row, row + count - 1);
+                    self.num_buf.clear();
+                }
                 (
                     Input {
                         key: Key::Char('p'),
@@ -501,8 +1755,20 @@ impl<'a> Editor<'a> {
                     },
                     _,
                 ) => {
-                    self.body.paste();
-                    self.set_prev_cursor_col();
+                    let count = std::cmp::max(self.get_num_from_buf(num_buf_len), 1) as usize;
+                    self.num_buf.clear();
+                    self.put(count, true);
+                }
+                (
+                    Input {
+                        key: Key::Char('P'),
+                        ..
+                    },
+                    _,
+                ) => {
+                    let count = std::cmp::max(self.get_num_from_buf(num_buf_len), 1) as usize;
+                    self.num_buf.clear();
+                    self.put(count, false);
                 }
                 (
                     Input {
@@ -512,7 +1778,7 @@ impl<'a> Editor<'a> {
                     },
                     _,
                 ) => {
-                    self.body.undo();
+                    self.undo();
                     self.set_prev_cursor_col();
                 }
                 (
@@ -523,7 +1789,7 @@ impl<'a> Editor<'a> {
                     },
                     _,
                 ) => {
-                    self.body.redo();
+                    self.redo();
                     self.set_prev_cursor_col();
                 }
                 (
@@ -533,14 +1799,9 @@ impl<'a> Editor<'a> {
                     },
                     _,
                 ) => {
-                    match num_buf_len {
-                        0 => {
-                            self.body.delete_next_char();
-                        }
-                        _ => self.repeat_action(num_buf_len, move |editor| {
-                            editor.body.delete_next_char();
-                        }),
-                    }
+                    let count = std::cmp::max(self.get_num_from_buf(num_buf_len), 1) as usize;
+                    self.num_buf.clear();
+                    self.cut_chars(count);
                     self.set_prev_cursor_col();
                 }
                 (
@@ -551,9 +1812,11 @@ impl<'a> Editor<'a> {
                     },
                     _,
                 ) => {
-                    self.body.move_cursor(CursorMove::Up);
-                    self.body.move_cursor(CursorMove::End);
+                    let indent = self.line_indent();
+                    self.body.move_cursor(CursorMove::Head);
                     self.body.insert_newline();
+                    self.body.move_cursor(CursorMove::Up);
+                    self.body.insert_str(indent);
                     self.set_mode(EditorMode::Insert);
                 }
                 (
@@ -564,8 +1827,10 @@ impl<'a> Editor<'a> {
                     },
                     _,
                 ) => {
+                    let indent = self.line_indent();
                     self.body.move_cursor(CursorMove::End);
                     self.body.insert_newline();
+                    self.body.insert_str(indent);
                     self.set_mode(EditorMode::Insert);
                 }
                 (
@@ -577,6 +1842,7 @@ impl<'a> Editor<'a> {
                     },
                     _,
                 ) => {
+                    self.restore_search();
                     self.body.search_forward(false);
                     self.set_prev_cursor_col();
                 }
@@ -589,6 +1855,7 @@ impl<'a> Editor<'a> {
                     },
                     _,
                 ) => {
+                    self.restore_search();
                     self.body.search_back(false);
                     self.set_prev_cursor_col();
                 }
@@ -657,10 +1924,22 @@ impl<'a> Editor<'a> {
                     self.set_mode(EditorMode::VisualLine);
                     self.body.move_cursor(CursorMove::End);
                 }
+                (
+                    Input {
+                        key: Key::Char('v'),
+                        ctrl: true,
+                        ..
+                    },
+                    _,
+                ) => {
+                    self.set_mode(EditorMode::VisualBlock);
+                }
                 (input, CommandState::NoCommand) => self.prime_command_state(input),
             },
+            EditorMode::VisualBlock => self.handle_block_input(input),
             EditorMode::Visual | EditorMode::VisualLine => match (input, &self.cmd_state) {
                 (Input { key: Key::Esc, .. }, _) => {
+                    self.cancel_command();
                     self.set_mode(EditorMode::Normal);
                 }
                 // Handle multi-key commands
@@ -829,56 +2108,520 @@ impl<'a> Editor<'a> {
                 }
                 (
                     Input {
-                        key: Key::Char('y'),
+                        key: Key::Char('o'),
+                        ctrl: false,
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => self.swap_selection_ends(),
+                (
+                    Input {
+                        key: Key::Char(' '),
+                        ctrl: true,
                         ..
                     },
                     CommandState::NoCommand,
                 ) => {
-                    self.body.copy();
+                    let start = self
+                        .body
+                        .get_selection_start()
+                        .expect("Visual modes always have a selection")
+                        .0;
+                    let end = self.body.cursor().0;
+                    self.body.cancel_selection();
+                    self.toggle_checkboxes(std::cmp::min(start, end), std::cmp::max(start, end));
                     self.set_mode(EditorMode::Normal);
                 }
                 (
                     Input {
-                        key: Key::Char('x'),
+                        key: Key::Char('d' | 'x'),
                         ..
                     },
                     CommandState::NoCommand,
-                ) => {
-                    self.body.cut();
+                ) if self.mode == EditorMode::VisualLine => {
+                    let (top, bottom) = self.visual_rows();
+                    self.delete_lines(top, bottom);
                     self.set_mode(EditorMode::Normal);
                 }
                 (
                     Input {
-                        key: Key::Char('1'),
+                        key: Key::Char('y'),
                         ..
                     },
                     CommandState::NoCommand,
-                ) => {
-                    self.body.set_alignment(Alignment::Left);
+                ) if self.mode == EditorMode::VisualLine => {
+                    let (top, bottom) = self.visual_rows();
+                    self.yank_lines(top, bottom);
+                    self.set_mode(EditorMode::Normal);
+                    self.body.move_cursor(CursorMove::Jump(top as u16, 0));
                 }
                 (
                     Input {
-                        key: Key::Char('2'),
+                        key: Key::Char('>'),
                         ..
                     },
                     CommandState::NoCommand,
                 ) => {
-                    self.body.set_alignment(Alignment::Center);
+                    let (top, bottom) = self.visual_rows();
+                    self.set_mode(EditorMode::Normal);
+                    self.indent_lines(top, bottom);
                 }
                 (
                     Input {
-                        key: Key::Char('3'),
+                        key: Key::Char('<'),
                         ..
                     },
                     CommandState::NoCommand,
                 ) => {
-                    self.body.set_alignment(Alignment::Right);
+                    let (top, bottom) = self.visual_rows();
+                    self.set_mode(EditorMode::Normal);
+                    self.outdent_lines(top, bottom);
                 }
-                _ => {}
+                (
+                    Input {
+                        key: Key::Char('d'),
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => {
+                    self.body.cut();
+                    self.yank_kind = YankKind::CharWise;
+                    self.set_mode(EditorMode::Normal);
+                }
+                (
+                    Input {
+                        key: Key::Char('y'),
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => {
+                    self.body.copy();
+                    self.yank_kind = YankKind::CharWise;
+                    self.set_mode(EditorMode::Normal);
+                }
+                (
+                    Input {
+                        key: Key::Char('x'),
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => {
+                    self.body.cut();
+                    self.yank_kind = YankKind::CharWise;
+                    self.set_mode(EditorMode::Normal);
+                }
+                (
+                    Input {
+                        key: Key::Char(c),
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) if self.settings.auto_pairs && closing_pair(c).is_some() => {
+                    if let Some(close) = closing_pair(c) {
+                        self.wrap_selection(c, close);
+                    }
+                }
+                (
+                    Input {
+                        key: Key::Char('1'),
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => {
+                    self.body.set_alignment(Alignment::Left);
+                }
+                (
+                    Input {
+                        key: Key::Char('2'),
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => {
+                    self.body.set_alignment(Alignment::Center);
+                }
+                (
+                    Input {
+                        key: Key::Char('3'),
+                        ..
+                    },
+                    CommandState::NoCommand,
+                ) => {
+                    self.body.set_alignment(Alignment::Right);
+                }
+                _ => {}
             },
         }
     }
 
+    /// First and last rows touched by the visual selection
+    fn visual_rows(&self) -> (usize, usize) {
+        let start = self
+            .body
+            .get_selection_start()
+            .expect("Visual modes always have a selection")
+            .0;
+        let end = self.body.cursor().0;
+        (std::cmp::min(start, end), std::cmp::max(start, end))
+    }
+
+    /// Deletes rows `top..=bottom` along with their line breaks
+    pub(crate) fn delete_lines(&mut self, top: usize, bottom: usize) {
+        let last_row = self.body.lines().len() - 1;
+        let bottom = std::cmp::min(bottom, last_row);
+        let deleted = self.body.lines()[top..=bottom].join("\n");
+        self.body.cancel_selection();
+
+        if bottom < last_row {
+            self.body.move_cursor(CursorMove::Jump(top as u16, 0));
+            self.body.start_selection();
+            self.body
+                .move_cursor(CursorMove::Jump(bottom as u16 + 1, 0));
+        } else if top > 0 {
+            // Nothing follows the last line, so take the break before the first one
+            self.body.move_cursor(CursorMove::Jump(top as u16 - 1, 0));
+            self.body.move_cursor(CursorMove::End);
+            self.body.start_selection();
+            self.body.move_cursor(CursorMove::Jump(bottom as u16, 0));
+            self.body.move_cursor(CursorMove::End);
+        } else {
+            self.body.move_cursor(CursorMove::Jump(0, 0));
+            self.body.start_selection();
+            self.body.move_cursor(CursorMove::Jump(bottom as u16, 0));
+            self.body.move_cursor(CursorMove::End);
+        }
+        self.body.cut();
+        // Which line break the cut took depends on the rows, so store the lines alone
+        self.body.set_yank_text(deleted.clone());
+        self.yank_kind = YankKind::LineWise;
+        self.delete_registers.insert(0, deleted);
+        self.delete_registers.truncate(DELETE_REGISTERS);
+
+        let row = std::cmp::min(top, self.body.lines().len() - 1);
+        self.body.move_cursor(CursorMove::Jump(row as u16, 0));
+        self.set_prev_cursor_col();
+    }
+
+    pub(crate) fn yank_lines(&mut self, top: usize, bottom: usize) {
+        let text = self.body.lines()[top..=bottom].join("\n");
+        self.body.set_yank_text(text);
+        self.yank_kind = YankKind::LineWise;
+    }
+
+    /// Cuts up to `count` characters from the cursor without running onto the next line
+    fn cut_chars(&mut self, count: usize) {
+        let (row, col) = self.body.cursor();
+        let line_len = self.body.lines()[row].chars().count();
+        let end = std::cmp::min(col + count, line_len);
+        if end == col {
+            return;
+        }
+        self.body.start_selection();
+        self.body.move_cursor(CursorMove::Jump(row as u16, end as u16));
+        self.body.cut();
+        self.yank_kind = YankKind::CharWise;
+    }
+
+    /// `p` and `P`, `count` times. Whole lines go below or above the cursor line,
+    /// anything else after or at the cursor.
+    pub(crate) fn put(&mut self, count: usize, after: bool) {
+        let (row, col) = self.body.cursor();
+        match (self.yank_kind, after) {
+            (YankKind::LineWise, true) => {
+                for _ in 0..count {
+                    self.body.move_cursor(CursorMove::End);
+                    self.body.insert_newline();
+                    self.body.paste();
+                }
+                self.body.move_cursor(CursorMove::Jump(row as u16 + 1, 0));
+            }
+            (YankKind::LineWise, false) => {
+                self.body.move_cursor(CursorMove::Head);
+                for _ in 0..count {
+                    self.body.paste();
+                    self.body.insert_newline();
+                }
+                self.body.move_cursor(CursorMove::Jump(row as u16, 0));
+            }
+            (YankKind::CharWise, _) => {
+                let line_len = self.body.lines()[row].chars().count();
+                if after && col < line_len {
+                    self.body.move_cursor(CursorMove::Forward);
+                }
+                for _ in 0..count {
+                    self.body.paste();
+                }
+            }
+        }
+        self.set_prev_cursor_col();
+    }
+
+    /// Visual `o`, moves the cursor to the other end of the selection so it can be
+    /// extended from that side
+    fn swap_selection_ends(&mut self) {
+        let Some((anchor_row, anchor_col)) = self.body.get_selection_start() else {
+            return;
+        };
+        let (row, col) = self.body.cursor();
+        self.body.cancel_selection();
+        self.body.move_cursor(CursorMove::Jump(row as u16, col as u16));
+        self.body.start_selection();
+        self.body.move_cursor(CursorMove::Jump(anchor_row as u16, anchor_col as u16));
+        self.set_prev_cursor_col();
+    }
+
+    /// `"Np` and `"NP`, once the whole command is typed. Puts the lines of the Nth
+    /// last delete without touching what `p` puts.
+    fn execute_register(&mut self) {
+        let keys = self.cmd_buf.chars().skip(1).collect::<Vec<char>>();
+        let (n, after) = match keys.as_slice() {
+            [n] if n.is_ascii_digit() && *n != '0' => return,
+            [n, p @ ('p' | 'P')] => (n.to_digit(10).unwrap_or(0) as usize, *p == 'p'),
+            _ => return self.cancel_command(),
+        };
+
+        let num_buf_len = self.num_buf.len() as u32;
+        let count = std::cmp::max(self.get_num_from_buf(num_buf_len), 1) as usize;
+        let text = n
+            .checked_sub(1)
+            .and_then(|idx| self.delete_registers.get(idx))
+            .cloned();
+        if let Some(text) = text {
+            let (yank_text, yank_kind) = (self.body.yank_text(), self.yank_kind);
+            self.body.set_yank_text(text);
+            self.yank_kind = YankKind::LineWise;
+            self.put(count, after);
+            self.body.set_yank_text(yank_text);
+            self.yank_kind = yank_kind;
+        }
+        self.cancel_command();
+    }
+
+    /// Indents by one `tab_width` of spaces, or a tab without `expand_tab`
+    pub(crate) fn indent_lines(&mut self, top: usize, bottom: usize) {
+        let indent = match self.settings.expand_tab {
+            true => " ".repeat(self.settings.tab_width),
+            false => "\t".to_owned(),
+        };
+        for row in top..=bottom {
+            if !self.body.lines()[row].is_empty() {
+                self.replace_at(row, 0, 0, &indent);
+            }
+        }
+        self.body.move_cursor(CursorMove::Jump(top as u16, 0));
+        self.body.move_cursor(CursorMove::Head);
+    }
+
+    /// Removes up to one indent level, a tab or `tab_width` spaces
+    pub(crate) fn outdent_lines(&mut self, top: usize, bottom: usize) {
+        for row in top..=bottom {
+            let line = &self.body.lines()[row];
+            let len = match line.starts_with('\t') {
+                true => 1,
+                false => line
+                    .chars()
+                    .take(self.settings.tab_width)
+                    .take_while(|&c| c == ' ')
+                    .count(),
+            };
+            if len > 0 {
+                self.replace_at(row, 0, len, "");
+            }
+        }
+        self.body.move_cursor(CursorMove::Jump(top as u16, 0));
+        self.body.move_cursor(CursorMove::Head);
+    }
+
+    fn handle_block_input(&mut self, input: Input) {
+        let num_buf_len = self.num_buf.len() as u32;
+        let motion = match input.key {
+            Key::Char('h') | Key::Left => Some(CursorMove::Back),
+            Key::Char('j') | Key::Down => Some(CursorMove::Down),
+            Key::Char('k') | Key::Up => Some(CursorMove::Up),
+            Key::Char('l') | Key::Right => Some(CursorMove::Forward),
+            Key::Char('w') => Some(CursorMove::WordForward),
+            Key::Char('b') => Some(CursorMove::WordBack),
+            Key::Char('^') => Some(CursorMove::Head),
+            Key::Char('$') => Some(CursorMove::End),
+            _ => None,
+        };
+
+        if let Some(motion) = motion {
+            match num_buf_len {
+                0 => self.body.move_cursor(motion),
+                _ => self.repeat_action(num_buf_len, move |editor| {
+                    editor.body.move_cursor(motion.clone());
+                }),
+            }
+            return;
+        }
+
+        match input.key {
+            Key::Esc => self.set_mode(EditorMode::Normal),
+            Key::Char('d') | Key::Char('x') => self.delete_block(),
+            Key::Char('y') => self.yank_block(),
+            Key::Char('I') => self.start_block_insert(false),
+            Key::Char('A') => self.start_block_insert(true),
+            Key::Char(c) if c.is_ascii_digit() => self.num_buf.push(c.to_digit(10).unwrap_or(0)),
+            _ => {}
+        }
+    }
+
+    /// Visual block as inclusive (top, bottom, left, right) rows and char cols
+    pub(crate) fn block_region(&self) -> (usize, usize, usize, usize) {
+        let (anchor_row, anchor_col) = self.block_anchor;
+        let (row, col) = self.body.cursor();
+        (
+            std::cmp::min(anchor_row, row),
+            std::cmp::max(anchor_row, row),
+            std::cmp::min(anchor_col, col),
+            std::cmp::max(anchor_col, col),
+        )
+    }
+
+    /// Removes the block's columns from every line, lines shorter than the block are
+    /// cut back to its left edge
+    pub(crate) fn delete_block(&mut self) {
+        let (top, bottom, left, right) = self.block_region();
+        for row in top..=bottom {
+            let len = self.body.lines()[row].chars().count();
+            if left < len {
+                let end = std::cmp::min(right + 1, len);
+                self.replace_at(row, left, end - left, "");
+            }
+        }
+        self.set_mode(EditorMode::Normal);
+        self.body.move_cursor(CursorMove::Jump(top as u16, left as u16));
+        self.set_prev_cursor_col();
+    }
+
+    pub(crate) fn yank_block(&mut self) {
+        let (top, bottom, left, right) = self.block_region();
+        let text = self.body.lines()[top..=bottom]
+            .iter()
+            .map(|line| {
+                line.chars()
+                    .skip(left)
+                    .take(right + 1 - left)
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        self.body.set_yank_text(text);
+        self.yank_kind = YankKind::CharWise;
+        self.set_mode(EditorMode::Normal);
+        self.body.move_cursor(CursorMove::Jump(top as u16, left as u16));
+        self.set_prev_cursor_col();
+    }
+
+    /// Starts inserting on the block's top line, before the block for `I` or after it
+    /// for `A`. The text is copied to the other lines when insert mode ends.
+    fn start_block_insert(&mut self, append: bool) {
+        let (top, bottom, left, right) = self.block_region();
+        let col = match append {
+            true => right + 1,
+            false => left,
+        };
+        if append {
+            self.pad_line(top, col);
+        }
+        self.set_mode(EditorMode::Insert);
+        self.body.move_cursor(CursorMove::Jump(top as u16, col as u16));
+        self.block_insert = Some(BlockInsert {
+            top,
+            bottom,
+            col,
+            line_len: self.body.lines()[top].chars().count(),
+            append,
+        });
+    }
+
+    fn finish_block_insert(&mut self, block_insert: BlockInsert) {
+        let BlockInsert {
+            top,
+            bottom,
+            col,
+            line_len,
+            append,
+        } = block_insert;
+
+        let top_line = &self.body.lines()[top];
+        let added = top_line.chars().count().saturating_sub(line_len);
+        // Nothing to repeat, or the insert didn't stay on the top line
+        if added == 0 || self.body.cursor().0 != top {
+            return;
+        }
+        let text = top_line.chars().skip(col).take(added).collect::<String>();
+
+        for row in top + 1..=bottom {
+            if append {
+                self.pad_line(row, col);
+            } else if self.body.lines()[row].chars().count() < col {
+                continue;
+            }
+            self.replace_at(row, col, 0, &text);
+        }
+        self.body.move_cursor(CursorMove::Jump(top as u16, col as u16));
+    }
+
+    /// Appends spaces so `row` is at least `len` chars long
+    fn pad_line(&mut self, row: usize, len: usize) {
+        let line_len = self.body.lines()[row].chars().count();
+        if line_len < len {
+            self.body
+                .move_cursor(CursorMove::Jump(row as u16, line_len as u16));
+            self.body.insert_str(" ".repeat(len - line_len));
+        }
+    }
+
+    /// Row and first and last columns of the word under the cursor. Hyphens and
+    /// underscores count as part of the word.
+    pub(crate) fn word_under_cursor(&self) -> Option<(usize, usize, usize)> {
+        let (row, col) = self.body.cursor();
+        let chars = self.body.lines()[row].chars().collect::<Vec<char>>();
+        let is_word = |c: &char| c.is_alphanumeric() || *c == '_' || *c == '-';
+        if !chars.get(col).is_some_and(is_word) {
+            return None;
+        }
+
+        let start = chars[..col]
+            .iter()
+            .rposition(|c| !is_word(c))
+            .map_or(0, |idx| idx + 1);
+        let end = chars[col..]
+            .iter()
+            .position(|c| !is_word(c))
+            .map_or(chars.len(), |idx| col + idx);
+        Some((row, start, end - 1))
+    }
+
+    /// Moves the cursor to the start of the next link, or the previous one when
+    /// `forward` is false, wrapping around at either end of the note
+    pub(crate) fn jump_to_link(&mut self, forward: bool) {
+        let mut starts = self
+            .links
+            .values()
+            .filter(|link| !link.deleted)
+            .map(|link| (link.row, link.start_col))
+            .collect::<Vec<(usize, usize)>>();
+        starts.sort_unstable();
+
+        let cursor = self.body.cursor();
+        let target = match forward {
+            true => starts
+                .iter()
+                .find(|&&start| start > cursor)
+                .or(starts.first()),
+            false => starts
+                .iter()
+                .rev()
+                .find(|&&start| start < cursor)
+                .or(starts.last()),
+        };
+        if let Some(&(row, col)) = target {
+            self.body.move_cursor(CursorMove::Jump(row as u16, col as u16));
+        }
+    }
+
     fn set_prev_cursor_col(&mut self) {
         self.prev_cursor_col = self.body.cursor().1;
     }
@@ -914,6 +2657,10 @@ impl<'a> Editor<'a> {
                             self.cmd_buf.push(c);
                             self.cmd_state = CommandState::Yank;
                         }
+                        ('c', CommandState::NoCommand) => {
+                            self.cmd_buf.push(c);
+                            self.cmd_state = CommandState::Change;
+                        }
                         ('f', CommandState::NoCommand) => {
                             self.cmd_buf.push(c);
                             self.cmd_state = CommandState::FindForward;
@@ -925,6 +2672,10 @@ impl<'a> Editor<'a> {
                         ('s', CommandState::NoCommand) => {
                             self.cmd_state = CommandState::PrimeHop;
                         }
+                        ('"', CommandState::NoCommand) => {
+                            self.cmd_buf.push(c);
+                            self.cmd_state = CommandState::Register;
+                        }
                         _ => {
                             self.cmd_state = CommandState::NoCommand;
                             self.cmd_buf.clear();
@@ -941,41 +2692,109 @@ impl<'a> Editor<'a> {
     }
 
     fn process_command_key_inputs(&mut self, input: Input) {
+        if self.cmd_state == CommandState::PrimeHop {
+            match input.key {
+                Key::Enter => return self.commit_hop(),
+                Key::Backspace => {
+                    self.cmd_buf.pop();
+                    match self.cmd_buf.is_empty() {
+                        true => self.cancel_command(),
+                        false => {
+                            let search_str = self.cmd_buf.clone();
+                            self.prime_hop(&search_str);
+                        }
+                    }
+                    return;
+                }
+                _ => {}
+            }
+        }
+
         if let Key::Char(c) = input.key {
             self.cmd_buf.push(c);
 
-            if DELETE_COMMANDS.contains(&c) && self.cmd_state == CommandState::Delete {
+            let operator_pending = matches!(
+                self.cmd_state,
+                CommandState::Delete | CommandState::Yank | CommandState::Change
+            );
+
+            if operator_pending && (c == 'i' || c == 'a') {
+                self.cmd_state = match c {
+                    'i' => CommandState::InnerObject,
+                    _ => CommandState::AroundObject,
+                };
+            } else if matches!(
+                self.cmd_state,
+                CommandState::InnerObject | CommandState::AroundObject
+            ) {
+                self.execute_text_object(c);
+            } else if DELETE_COMMANDS.contains(&c) && self.cmd_state == CommandState::Delete {
                 self.execute_delete(c);
             } else if YANK_COMMANDS.contains(&c) && self.cmd_state == CommandState::Yank {
                 self.execute_yank(c);
-            } else if c == GOTO_COMMAND && self.cmd_state == CommandState::GoTo {
+            } else if matches!(c, GOTO_COMMAND | 'd' | 'f' | 'j' | 'k')
+                && self.cmd_state == CommandState::GoTo
+            {
                 self.execute_goto(c);
             } else if self.cmd_state == CommandState::FindForward {
                 self.execute_find(c, true);
             } else if self.cmd_state == CommandState::FindBackward {
                 self.execute_find(c, false);
             } else if self.cmd_state == CommandState::PrimeHop {
-                if self.cmd_buf.len() == 2 {
-                    let search_str = self.cmd_buf.clone();
-                    self.prime_hop(&search_str);
+                if self.cmd_buf.chars().count() == 1 {
+                    self.body.init_hop();
                 }
+                let search_str = self.cmd_buf.clone();
+                self.prime_hop(&search_str);
             } else if self.cmd_state == CommandState::ExecuteHop {
                 self.body.hop_pending = false;
-                if let Some(num) = c.to_digit(10) {
-                    self.num_buf.push(num);
-                    if self.num_buf.len() == 2 {
-                        self.execute_hop();
-                    }
-                }
+                self.hop_typed.push(c);
+                self.execute_hop();
+            } else if self.cmd_state == CommandState::Register {
+                self.execute_register();
             } else {
-                self.cmd_state = CommandState::NoCommand;
+                self.cancel_command();
             }
         }
     }
 
+    /// Drops a half typed command along with its count
+    fn cancel_command(&mut self) {
+        self.cmd_buf.clear();
+        self.num_buf.clear();
+        self.body.clear_hop();
+        self.hop_targets.clear();
+        self.hop_typed.clear();
+        self.cmd_state = CommandState::NoCommand;
+    }
+
+    /// Keys of an unfinished command as typed, e.g. `2d` or `f`, empty when none is pending
+    pub(crate) fn pending_keys(&self) -> String {
+        let count = self
+            .num_buf
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<String>();
+        match self.cmd_state {
+            // The hop label is typed after the search characters
+            CommandState::ExecuteHop => format!("s{}", self.cmd_buf),
+            CommandState::PrimeHop => format!("{}s{}", count, self.cmd_buf),
+            _ => format!("{}{}", count, self.cmd_buf),
+        }
+    }
+
     fn execute_delete(&mut self, modifier: char) {
+        self.yank_kind = YankKind::CharWise;
         match modifier {
-            'd' | 'j' => {
+            'd' => {
+                let num_buf_len = self.num_buf.len() as u32;
+                let count = std::cmp::max(self.get_num_from_buf(num_buf_len), 1) as usize;
+                let row = self.body.cursor().0;
+                self.delete_lines(row, row + count - 1);
+                self.num_buf.clear();
+                self.cmd_buf.clear();
+            }
+            'j' => {
                 let actions = move |editor: &mut Editor<'a>| {
                     editor.body.delete_line(false);
                 };
@@ -1069,8 +2888,17 @@ impl<'a> Editor<'a> {
 
     fn execute_yank(&mut self, modifier: char) {
         let (start_row, start_col) = self.body.cursor();
+        self.yank_kind = YankKind::CharWise;
 
         match modifier {
+            'y' => {
+                let num_buf_len = self.num_buf.len() as u32;
+                let count = std::cmp::max(self.get_num_from_buf(num_buf_len), 1) as usize;
+                let bottom = std::cmp::min(start_row + count, self.body.lines().len()) - 1;
+                self.yank_lines(start_row, bottom);
+                self.num_buf.clear();
+                self.cmd_buf.clear();
+            }
             'h' => {
                 self.body.start_selection();
                 let actions = move |editor: &mut Editor<'a>| {
@@ -1190,6 +3018,54 @@ impl<'a> Editor<'a> {
         self.cmd_state = CommandState::NoCommand;
     }
 
+    /// Deletes, yanks or changes the text between the delimiters around the cursor,
+    /// with the operator taken from the start of `cmd_buf`
+    fn execute_text_object(&mut self, delimiter: char) {
+        let around = self.cmd_state == CommandState::AroundObject;
+        let operator = self.cmd_buf.chars().next();
+        self.cmd_buf.clear();
+        self.num_buf.clear();
+        self.cmd_state = CommandState::NoCommand;
+
+        let Some((start, end)) =
+            text_object_range(self.body.lines(), self.body.cursor(), delimiter, around)
+        else {
+            return;
+        };
+
+        self.body.cancel_selection();
+        self.body
+            .move_cursor(CursorMove::Jump(start.0 as u16, start.1 as u16));
+        if start != end {
+            self.body.start_selection();
+            self.body
+                .move_cursor(CursorMove::Jump(end.0 as u16, end.1 as u16));
+        }
+
+        match operator {
+            Some('d') | Some('c') => {
+                if start != end {
+                    self.body.cut();
+                    self.yank_kind = YankKind::CharWise;
+                }
+                if operator == Some('c') {
+                    self.set_mode(EditorMode::Insert);
+                }
+            }
+            Some('y') => {
+                if start != end {
+                    self.body.copy();
+                    self.yank_kind = YankKind::CharWise;
+                }
+                self.body.cancel_selection();
+                self.body
+                    .move_cursor(CursorMove::Jump(start.0 as u16, start.1 as u16));
+            }
+            _ => self.body.cancel_selection(),
+        }
+        self.set_prev_cursor_col();
+    }
+
     fn execute_goto(&mut self, modifier: char) {
         match modifier {
             'g' => {
@@ -1204,6 +3080,23 @@ impl<'a> Editor<'a> {
                 }
                 self.cmd_buf.clear();
             }
+            'd' => {
+                self.preview_requested = true;
+                self.cmd_buf.clear();
+                self.num_buf.clear();
+            }
+            'f' => {
+                self.link_word_requested = true;
+                self.cmd_buf.clear();
+                self.num_buf.clear();
+            }
+            // `j`/`k` already go by screen row when soft wrapped, so these are the same
+            'j' | 'k' => {
+                let num_buf_len = self.num_buf.len() as u32;
+                self.vertical_motion(num_buf_len, modifier == 'j');
+                self.cmd_buf.clear();
+                self.num_buf.clear();
+            }
             _ => {
                 self.cmd_buf.clear();
                 self.num_buf.clear();
@@ -1240,24 +3133,57 @@ impl<'a> Editor<'a> {
         self.cmd_state = CommandState::NoCommand;
     }
 
+    /// Re-runs the search as the pattern grows, labelling every match starting from the
+    /// top of the screen so the visible matches get the short labels
     fn prime_hop(&mut self, target: &str) {
-        self.body.init_hop();
-        match self.body.set_hop_pattern(target) {
-            Ok(_) => info!("found: {}", target),
-            Err(e) => error!("Hop error: {}", e),
+        let mut matches = hop_matches(self.body.lines(), target);
+        let first_visible = matches
+            .iter()
+            .position(|&(row, _)| row >= self.scroll_top)
+            .unwrap_or(0);
+        matches.rotate_left(first_visible);
+
+        if let Err(e) = self.body.set_hop_pattern(target) {
+            error!("Hop error: {}", e);
         }
+        self.hop_targets = hop_labels(matches.len()).into_iter().zip(matches).collect();
+    }
+
+    /// Enter ends the pattern, the labels are shown and the next keys pick one
+    fn commit_hop(&mut self) {
+        if self.hop_targets.is_empty() {
+            info!("commit_hop::no match for {}", self.cmd_buf);
+            self.cancel_command();
+            return;
+        }
+        self.hop_typed.clear();
+        self.num_buf.clear();
         self.cmd_state = CommandState::ExecuteHop;
     }
 
+    /// Jumps once the typed keys spell a label, gives up when they can't start one
     fn execute_hop(&mut self) {
-        let num_buf_len = self.num_buf.len();
-        let idx = self.get_num_from_buf(num_buf_len as u32);
-        self.body.hop_to_idx(idx as usize);
-        self.body.clear_hop();
-        self.cmd_buf.clear();
-        self.num_buf.clear();
-        self.cmd_state = CommandState::NoCommand;
-        self.mode = EditorMode::Insert;
+        let target = self
+            .hop_targets
+            .iter()
+            .find(|(label, _)| *label == self.hop_typed)
+            .map(|&(_, pos)| pos);
+
+        match target {
+            Some((row, col)) => {
+                self.body.move_cursor(CursorMove::Jump(row as u16, col as u16));
+                self.cancel_command();
+                if !self.read_only {
+                    self.mode = EditorMode::Insert;
+                }
+            }
+            None => {
+                let typed = &self.hop_typed;
+                if !self.hop_targets.iter().any(|(label, _)| label.starts_with(typed.as_str())) {
+                    self.cancel_command();
+                }
+            }
+        }
     }
 
     fn repeat_action<F>(&mut self, num_buf_len: u32, mut action: F)
@@ -1273,28 +3199,416 @@ impl<'a> Editor<'a> {
         self.num_buf.clear();
     }
 
+    /// The typed count, no more than `max_count` however many digits were typed
     fn get_num_from_buf(&self, num_buf_len: u32) -> u16 {
-        self.num_buf
+        let num = self
+            .num_buf
             .iter()
-            .enumerate()
-            .fold(0u32, |mut acc, (idx, n)| {
-                acc += std::cmp::max(*n, (10u32).pow(num_buf_len - 1 - idx as u32) * *n);
-                acc
-            }) as u16
+            .take(num_buf_len as usize)
+            .fold(0u64, |acc, n| acc.saturating_mul(10).saturating_add(*n as u64));
+        std::cmp::min(num, self.settings.max_count as u64) as u16
     }
 }
 
-impl<'a> Widget for Editor<'a> {
-    fn render(mut self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
-    where
-        Self: Sized,
+/// Range of a text object as (start, end) positions, end exclusive, in (row, char col).
+/// Quotes pair up on the cursor line, the pair around the cursor or else the next one.
+/// Brackets may span lines and skip over nested pairs.
+pub(crate) fn text_object_range(
+    lines: &[String],
+    cursor: (usize, usize),
+    delimiter: char,
+    around: bool,
+) -> Option<((usize, usize), (usize, usize))> {
+    let (open, close) = match delimiter {
+        '(' | ')' => ('(', ')'),
+        '{' | '}' => ('{', '}'),
+        '[' | ']' => ('[', ']'),
+        '"' | '\'' | '`' => (delimiter, delimiter),
+        _ => return None,
+    };
+    let chars = lines
+        .iter()
+        .map(|line| line.chars().collect::<Vec<char>>())
+        .collect::<Vec<Vec<char>>>();
+
+    let (open_pos, close_pos) = match open == close {
+        true => {
+            let row = cursor.0;
+            let quotes = chars
+                .get(row)?
+                .iter()
+                .enumerate()
+                .filter(|(_, &c)| c == open)
+                .map(|(col, _)| col)
+                .collect::<Vec<usize>>();
+            let (first, last) = quotes
+                .chunks_exact(2)
+                .map(|pair| (pair[0], pair[1]))
+                .find(|&(first, last)| first <= cursor.1 && cursor.1 <= last)
+                .or_else(|| {
+                    quotes
+                        .chunks_exact(2)
+                        .map(|pair| (pair[0], pair[1]))
+                        .find(|&(first, _)| first > cursor.1)
+                })?;
+            ((row, first), (row, last))
+        }
+        false => {
+            let open_pos = find_open_bracket(&chars, cursor, open, close)?;
+            let close_pos = find_close_bracket(&chars, open_pos, open, close)?;
+            (open_pos, close_pos)
+        }
+    };
+
+    match around {
+        true => Some((open_pos, (close_pos.0, close_pos.1 + 1))),
+        false => Some(((open_pos.0, open_pos.1 + 1), close_pos)),
+    }
+}
+
+/// Nearest unmatched `open` at or before `cursor`
+fn find_open_bracket(
+    chars: &[Vec<char>],
+    cursor: (usize, usize),
+    open: char,
+    close: char,
+) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    for row in (0..=cursor.0).rev() {
+        let line = chars.get(row)?;
+        let last_col = match row == cursor.0 {
+            true => std::cmp::min(cursor.1 + 1, line.len()),
+            false => line.len(),
+        };
+        for col in (0..last_col).rev() {
+            // A closing bracket under the cursor belongs to the pair being looked for
+            if line[col] == close && (row, col) != cursor {
+                depth += 1;
+            } else if line[col] == open {
+                if depth == 0 {
+                    return Some((row, col));
+                }
+                depth -= 1;
+            }
+        }
+    }
+    None
+}
+
+/// The `close` matching the `open` at `open_pos`
+fn find_close_bracket(
+    chars: &[Vec<char>],
+    open_pos: (usize, usize),
+    open: char,
+    close: char,
+) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    for (row, line) in chars.iter().enumerate().skip(open_pos.0) {
+        let first_col = match row == open_pos.0 {
+            true => open_pos.1 + 1,
+            false => 0,
+        };
+        for (col, &c) in line.iter().enumerate().skip(first_col) {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                if depth == 0 {
+                    return Some((row, col));
+                }
+                depth -= 1;
+            }
+        }
+    }
+    None
+}
+
+fn closing_pair(open: char) -> Option<char> {
+    AUTO_PAIRS
+        .iter()
+        .find(|&&(pair_open, _)| pair_open == open)
+        .map(|&(_, close)| close)
+}
+
+/// Column of the box in a `- [ ]` or `- [x]` task line, indentation allowed
+fn checkbox_col(line: &str) -> Option<usize> {
+    let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+    let marker = line.chars().skip(indent).take(5).collect::<String>();
+    match marker.as_str() {
+        "- [ ]" | "- [x]" | "- [X]" => Some(indent + 3),
+        _ => None,
+    }
+}
+
+/// `#` count of a Markdown heading line, e.g. 2 for "## Sub"
+pub(crate) fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    match (1..=6).contains(&level) && line[level..].starts_with(' ') {
+        true => Some(level),
+        false => None,
+    }
+}
+
+/// Theme style of a heading line, None for other lines
+pub(crate) fn heading_style(theme: &EditorTheme, line: &str) -> Option<Style> {
+    match heading_level(line)? {
+        1 => Some(
+            theme
+                .main_heading_modifiers
+                .iter()
+                .fold(Style::default().fg(theme.main_heading), |style, m| {
+                    style.add_modifier(*m)
+                }),
+        ),
+        _ => Some(
+            theme
+                .sub_heading_modifiers
+                .iter()
+                .fold(Style::default().fg(theme.sub_heading), |style, m| {
+                    style.add_modifier(*m)
+                }),
+        ),
+    }
+}
+
+impl<'a> Editor<'a> {
+    /// `heading_style` with this editor's theme
+    fn heading_style(&self, line: &str) -> Option<Style> {
+        heading_style(&self.theme, line)
+    }
+
+    /// The textarea has no notion of wrapped rows and scrolls long lines sideways, so
+    /// with soft wrap its text is painted over row by row. The textarea still holds the
+    /// text, links and cursor, only the layout on screen comes from here.
+    fn render_wrapped(&self, area: Rect, buf: &mut Buffer, text_style: Style, cursor_style: Style) {
+        // Border plus padding on each side
+        let text_x = self.text_x(area);
+        let text_y = area.y + 2;
+        let text_right = area.right().saturating_sub(2);
+        let visible_rows = area.height.saturating_sub(4);
+
+        for y in text_y..text_y + visible_rows {
+            for x in text_x..text_right {
+                buf.get_mut(x, y).reset();
+                buf.get_mut(x, y).set_style(text_style);
+            }
+        }
+
+        let cursor = self.body.cursor();
+        let selection = self.body.get_selection_start().map(|start| match start <= cursor {
+            true => (start, cursor),
+            false => (cursor, start),
+        });
+        let lines = self.body.lines();
+        let rows = self.wrapped_rows(visible_rows as usize);
+        let last_row = rows.last().map_or(self.scroll_top, |&(row, _, _)| row);
+        let code = code_styles(lines, self.scroll_top..last_row + 1);
+
+        for (screen_row, (row, start, end)) in rows.into_iter().enumerate() {
+            let y = text_y + screen_row as u16;
+            let runs = code.get(&row);
+            let line_style = match runs {
+                Some(_) => text_style,
+                None => self.heading_style(&lines[row]).unwrap_or(text_style),
+            };
+
+            for (offset, c) in lines[row].chars().skip(start).take(end - start).enumerate() {
+                let x = text_x + offset as u16;
+                if x >= text_right {
+                    break;
+                }
+                let col = start + offset;
+                let in_link = self.body.links.values().any(|link| {
+                    !link.deleted && link.row == row && (link.start_col..=link.end_col).contains(&col)
+                });
+                let mut style = match (in_link, runs.and_then(|runs| style_at(runs, col))) {
+                    (true, _) => text_style.fg(self.theme.links),
+                    (false, Some(code_style)) => text_style.patch(code_style),
+                    (false, None) => line_style,
+                };
+                if let Some(color) = self.theme.cursor_line.filter(|_| row == cursor.0) {
+                    style = style.bg(color);
+                }
+                if selection.is_some_and(|(first, last)| first <= (row, col) && (row, col) <= last) {
+                    style = style.bg(self.theme.select);
+                }
+                buf.get_mut(x, y).set_char(c).set_style(style);
+            }
+
+            let on_row = cursor.0 == row
+                && start <= cursor.1
+                && (cursor.1 < end || end == lines[row].chars().count());
+            let cursor_x = text_x + cursor.1.saturating_sub(start) as u16;
+            if on_row && cursor_x < text_right {
+                buf.get_mut(cursor_x, y).set_style(cursor_style);
+            }
+        }
+    }
+
+    /// Restyles visible headings and code inside fenced blocks on top of the rendered
+    /// textarea, a `#` line in code isn't a heading. Link cells are skipped so links
+    /// keep their highlight.
+    fn style_lines(&self, area: Rect, buf: &mut Buffer) {
+        // Border plus padding on each side
+        let text_x = self.text_x(area);
+        let text_y = area.y + 2;
+        let visible_rows = area.height.saturating_sub(4) as usize;
+        let text_right = area.right().saturating_sub(2);
+        let lines = self.body.lines();
+        let code = code_styles(lines, self.scroll_top..self.scroll_top + visible_rows);
+
+        for (screen_row, (row, line)) in lines
+            .iter()
+            .enumerate()
+            .skip(self.scroll_top)
+            .take(visible_rows)
+            .enumerate()
+        {
+            let runs = code.get(&row);
+            let heading = match runs {
+                Some(_) => None,
+                None => self.heading_style(line),
+            };
+            if runs.is_none() && heading.is_none() {
+                continue;
+            }
+
+            let y = text_y + screen_row as u16;
+            for col in 0..line.chars().count() {
+                let x = text_x + col as u16;
+                if x >= text_right {
+                    break;
+                }
+                let in_link = self.body.links.values().any(|link| {
+                    !link.deleted && link.row == row && (link.start_col..=link.end_col).contains(&col)
+                });
+                let style = runs.map_or(heading, |runs| style_at(runs, col));
+                if let Some(style) = style.filter(|_| !in_link) {
+                    buf.get_mut(x, y).set_style(style);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Editor<'a> {
+    /// Highlights the visible part of the visual block, padding past the end of short lines
+    fn style_block_selection(&self, area: Rect, buf: &mut Buffer) {
+        let (top, bottom, left, right) = self.block_region();
+        let text_x = self.text_x(area);
+        let text_y = area.y + 2;
+        let visible_rows = area.height.saturating_sub(4) as usize;
+        let text_right = area.right().saturating_sub(2);
+        let style = Style::default().bg(self.theme.select);
+        if visible_rows == 0 {
+            return;
+        }
+
+        for row in top.max(self.scroll_top)..=bottom.min(self.scroll_top + visible_rows - 1) {
+            let y = text_y + (row - self.scroll_top) as u16;
+            for col in left..=right {
+                let x = text_x + col as u16;
+                if x >= text_right {
+                    break;
+                }
+                buf.get_mut(x, y).set_style(style);
+            }
+        }
+    }
+
+    /// A block in normal and visual modes. Insert mode underlines the cell instead, the
+    /// closest a drawn cursor gets to a bar since the terminal's own cursor stays hidden.
+    fn cursor_style(&self) -> Style {
+        match (self.mode == EditorMode::Insert, self.theme.cursor) {
+            (true, Some(color)) => Style::default().fg(color).add_modifier(Modifier::UNDERLINED),
+            (true, None) => Style::default().add_modifier(Modifier::UNDERLINED),
+            (false, Some(color)) => Style::default().bg(color),
+            (false, None) => Style::default().add_modifier(Modifier::REVERSED),
+        }
+    }
+
+    /// Numbers each line on the first screen row it takes up
+    fn render_line_numbers(&self, area: Rect, buf: &mut Buffer) {
+        let width = self.gutter_width() as usize - 1;
+        let text_y = area.y + 2;
+        let visible_rows = area.height.saturating_sub(4) as usize;
+        let style = Style::default().fg(self.theme.borders).dim();
+        let rows = match self.settings.soft_wrap {
+            true => self
+                .wrapped_rows(visible_rows)
+                .into_iter()
+                .map(|(row, start, _)| (start == 0).then_some(row))
+                .collect::<Vec<Option<usize>>>(),
+            false => (self.scroll_top..self.body.lines().len())
+                .take(visible_rows)
+                .map(Some)
+                .collect(),
+        };
+
+        for (screen_row, row) in rows.into_iter().enumerate() {
+            if let Some(row) = row {
+                let number = format!("{:>width$}", row + 1, width = width);
+                buf.set_string(area.x + 2, text_y + screen_row as u16, number, style);
+            }
+        }
+    }
+
+    /// Paints each hop label over the start of its match, hiding labels that no longer
+    /// fit what has been typed
+    fn render_hop_labels(&self, area: Rect, buf: &mut Buffer) {
+        let text_x = self.text_x(area);
+        let text_y = area.y + 2;
+        let visible_rows = area.height.saturating_sub(4) as usize;
+        let text_right = area.right().saturating_sub(2);
+        let style = Style::default()
+            .fg(self.theme.hop)
+            .add_modifier(Modifier::BOLD | Modifier::REVERSED);
+        let wrapped_rows = match self.settings.soft_wrap {
+            true => self.wrapped_rows(visible_rows),
+            false => vec![],
+        };
+
+        for (label, (row, col)) in self.hop_targets.iter() {
+            if !label.starts_with(self.hop_typed.as_str()) {
+                continue;
+            }
+            let screen_pos = match self.settings.soft_wrap {
+                true => wrapped_rows
+                    .iter()
+                    .position(|&(r, start, end)| r == *row && start <= *col && *col < end)
+                    .map(|screen_row| (screen_row, col - wrapped_rows[screen_row].1)),
+                false => (self.scroll_top..self.scroll_top + visible_rows)
+                    .contains(row)
+                    .then(|| (row - self.scroll_top, *col)),
+            };
+            let Some((screen_row, screen_col)) = screen_pos else {
+                continue;
+            };
+
+            let y = text_y + screen_row as u16;
+            for (offset, c) in label.chars().enumerate() {
+                let x = text_x + (screen_col + offset) as u16;
+                if x >= text_right {
+                    break;
+                }
+                buf.get_mut(x, y).set_char(c).set_style(style);
+            }
+        }
+    }
+}
+
+impl<'a> Widget for Editor<'a> {
+    fn render(mut self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
     {
-        let info_style = match self.mode {
-            EditorMode::Normal => Style::default().bold().fg(self.theme.normal_mode),
-            EditorMode::Insert => Style::default().bold().fg(self.theme.insert_mode),
-            EditorMode::Visual => Style::default().bold().fg(self.theme.visual_mode),
-            EditorMode::VisualLine => Style::default().bold().fg(self.theme.visual_line_mode),
+        let info_color = match (self.searchbar_open, &self.mode) {
+            (true, _) => self.theme.search_mode,
+            (false, EditorMode::Normal) => self.theme.normal_mode,
+            (false, EditorMode::Insert) => self.theme.insert_mode,
+            (false, EditorMode::Visual | EditorMode::VisualBlock) => self.theme.visual_mode,
+            (false, EditorMode::VisualLine) => self.theme.visual_line_mode,
         };
+        let info_style = Style::default().bold().fg(info_color);
 
         let (title_style, key_hint_style, text_style) = match self.state {
             ComponentState::Active => (
@@ -1310,59 +3624,96 @@ impl<'a> Widget for Editor<'a> {
             _ => (Style::default(), Style::default(), Style::default()),
         };
 
-        let block_info_len = self.block_info.len();
+        let mode_text = match self.hide_hints {
+            true => String::new(),
+            false => self.block_info.clone(),
+        };
+        let block_info_len = mode_text.chars().count();
 
-        let (mode_span, key_hint_span) = match self.searchbar_open {
-            true => (
-                Span::styled("", Style::default()),
-                Span::styled("", key_hint_style),
-            ),
-            false => (
-                Span::styled(self.block_info, info_style),
-                Span::styled(
-                    " | <Alt-q> quit | <Alt-s/l/d/n> save/load/delete/new | <Alt-t> edit title ",
-                    key_hint_style,
-                ),
-            ),
+        // While searching the mode stays visible in the search colour, without key hints
+        let key_hint_text = match self.searchbar_open || self.hide_hints {
+            true => "",
+            false => " | <Alt-q> quit | <Alt-s/l/d/n> save/load/delete/new | <Alt-t> edit title ",
         };
 
+        // Corners next to the sidebar or searchbar join onto their borders
+        let set = self.theme.border.set();
+        let joins = self.theme.border.joins();
         let (file_explorer_hint_text, cursor_style, top_right, bottom_left, bottom_right) =
             match (self.sidebar_open, self.searchbar_open) {
-                (true, true) => ("".to_owned(), Style::default(), "┬", "├", "┤"),
-                (false, true) => ("".to_owned(), Style::default(), "╮", "├", "┤"),
+                (true, true) => (
+                    "".to_owned(),
+                    Style::default(),
+                    joins.top,
+                    joins.left,
+                    joins.right,
+                ),
+                (false, true) => (
+                    "".to_owned(),
+                    Style::default(),
+                    set.top_right,
+                    joins.left,
+                    joins.right,
+                ),
                 (true, false) => (
                     "".to_owned(),
-                    Style::default().add_modifier(Modifier::REVERSED),
-                    "┬",
-                    "╰",
-                    "┴",
+                    self.cursor_style(),
+                    joins.top,
+                    set.bottom_left,
+                    joins.bottom,
+                ),
+                (false, false) if self.hide_hints => (
+                    "".to_owned(),
+                    self.cursor_style(),
+                    set.top_right,
+                    set.bottom_left,
+                    set.bottom_right,
                 ),
                 (false, false) => (
                     " <Alt-f> show files ".to_owned(),
-                    Style::default().add_modifier(Modifier::REVERSED),
-                    "╮",
-                    "╰",
-                    "╯",
+                    self.cursor_style(),
+                    set.top_right,
+                    set.bottom_left,
+                    set.bottom_right,
                 ),
             };
 
+        // Space between the corners, the hints are cut short before the mode is
+        let tb_room = (area.width as usize).saturating_sub(5);
+        let file_explorer_hint_text = match block_info_len + file_explorer_hint_text.len() > tb_room {
+            true => "".to_owned(),
+            false => file_explorer_hint_text,
+        };
         let feh_len = file_explorer_hint_text.len();
+        let key_hint_text = elide(key_hint_text, tb_room.saturating_sub(block_info_len + feh_len));
+        let mode_span = Span::styled(mode_text, info_style);
+        let key_hint_span = Span::styled(key_hint_text, key_hint_style);
 
-        let title_text = format!(" {} ", self.title);
+        let title_room = (area.width as usize).saturating_sub(4);
+        let title_text = match self.read_only {
+            true => format!(" {} 🔒 ", elide(&self.title, title_room.saturating_sub(3))),
+            false => format!(" {} ", elide(&self.title, title_room)),
+        };
+        let title_text = match self.markdown_preview {
+            true => format!("{}[preview] ", title_text),
+            false => title_text,
+        };
         let title = Span::styled(title_text, title_style);
         let file_explorer_hint = Span::styled(
             file_explorer_hint_text,
             Style::default().add_modifier(Modifier::BOLD),
         );
 
-        let kht_len = key_hint_span.content.len();
-        let tb_text_len = (kht_len + block_info_len + feh_len) as u16;
+        let kht_len = key_hint_span.content.chars().count();
         let tb_padding_width = match self.sidebar_open {
             true => 0,
-            false => (area.width - tb_text_len - 5) as usize,
+            false => tb_room.saturating_sub(kht_len + block_info_len + feh_len),
         };
-        let tb_padding = Span::styled("─".repeat(tb_padding_width), Style::default());
-        let prefix_padding = Span::styled("─", Style::default());
+        let tb_padding = Span::styled(
+            set.horizontal_bottom.repeat(tb_padding_width),
+            Style::default(),
+        );
+        let prefix_padding = Span::styled(set.horizontal_bottom, Style::default());
 
         let editor_block = Block::default()
             .title(Title::from(title).alignment(Alignment::Left))
@@ -1375,23 +3726,66 @@ impl<'a> Widget for Editor<'a> {
             ]))
             .borders(Borders::ALL)
             .border_set(border::Set {
-                top_left: "╭",
                 top_right,
                 bottom_left,
                 bottom_right,
-                vertical_left: "│",
-                vertical_right: "│",
-                horizontal_top: "─",
-                horizontal_bottom: "─",
+                ..set
             })
             .border_style(Style::default().fg(self.theme.borders))
-            .padding(Padding::new(1, 1, 1, 1));
+            .padding(Padding::new(1 + self.gutter_width(), 1, 1, 1));
+
+        if self.markdown_preview {
+            Paragraph::new(preview_lines(self.body.lines(), &self.links, &self.theme))
+                .block(editor_block)
+                .style(text_style)
+                .wrap(Wrap { trim: false })
+                .scroll((self.preview_scroll as u16, 0))
+                .render(area, buf);
+            return;
+        }
 
         self.body.set_block(editor_block);
         self.body.set_style(text_style);
         self.body.set_cursor_style(cursor_style);
 
+        let total_rows = self.body.lines().len();
+        let cursor_row = self.body.cursor().0;
+
         self.body.widget().render(area, buf);
+        match self.settings.soft_wrap {
+            true => self.render_wrapped(area, buf, text_style, cursor_style),
+            false => {
+                self.style_lines(area, buf);
+                if self.mode == EditorMode::VisualBlock {
+                    self.style_block_selection(area, buf);
+                }
+            }
+        }
+        if self.cmd_state == CommandState::ExecuteHop {
+            self.render_hop_labels(area, buf);
+        }
+        if self.gutter_width() > 0 {
+            self.render_line_numbers(area, buf);
+        }
+
+        // Borders plus top/bottom padding
+        let visible_rows = area.height.saturating_sub(4) as usize;
+        if total_rows > visible_rows {
+            let mut scrollbar_state = ScrollbarState::new(total_rows)
+                .position(cursor_row)
+                .viewport_content_length(visible_rows);
+
+            StatefulWidget::render(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .style(Style::default().fg(self.theme.borders)),
+                area.inner(&Margin {
+                    vertical: 1,
+                    horizontal: 0,
+                }),
+                buf,
+                &mut scrollbar_state,
+            );
+        }
     }
 }
 
@@ -1412,13 +3806,18 @@ mod tests {
             insert_mode: THEME_COLOR,
             visual_mode: THEME_COLOR,
             visual_line_mode: THEME_COLOR,
+            search_mode: THEME_COLOR,
             select: THEME_COLOR,
             search: THEME_COLOR,
             links: THEME_COLOR,
+            hop: THEME_COLOR,
+            cursor: None,
+            cursor_line: None,
             main_heading: THEME_COLOR,
             main_heading_modifiers: modifiers.clone(),
             sub_heading: THEME_COLOR,
             sub_heading_modifiers: modifiers,
+            border: BorderStyle::Rounded,
         }
     }
 
@@ -1628,6 +4027,52 @@ mod tests {
         assert_eq!(editor.body.lines(), vec!["7".to_string()]);
     }
 
+    #[test]
+    fn test_huge_count_is_clamped() {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["one".to_string(), "two".to_string(), "three".to_string()],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.set_mode(EditorMode::Normal);
+        editor.num_buf = vec![9; 6];
+        assert_eq!(editor.get_num_from_buf(6), 10_000);
+        editor.num_buf = vec![9; 30];
+        assert_eq!(editor.get_num_from_buf(30), 10_000);
+
+        for c in "999999dd".chars() {
+            editor.handle_input(Input {
+                key: Key::Char(c),
+                ..Default::default()
+            });
+        }
+        assert_eq!(editor.body.lines(), vec!["".to_string()]);
+        assert!(editor.num_buf.is_empty());
+    }
+
+    #[test]
+    fn test_count_near_u16_max_does_not_wrap() {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["".to_string()],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.settings.max_count = u16::MAX;
+        editor.num_buf = vec![6, 5, 5, 3, 4];
+        assert_eq!(editor.get_num_from_buf(5), 65_534);
+        // 65537 used to be cast down to 1
+        editor.num_buf = vec![6, 5, 5, 3, 7];
+        assert_eq!(editor.get_num_from_buf(5), u16::MAX);
+    }
+
     #[test]
     fn test_goto_top_of_note() {
         let mut editor = Editor::new(
@@ -1701,4 +4146,1259 @@ mod tests {
         });
         assert_eq!(editor.body.cursor(), (4, 0));
     }
+
+    #[test]
+    fn test_cursor_from_click() {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["First line".to_string(), "Second".to_string()],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        let area = Rect::new(0, 0, 40, 10);
+
+        // Text starts inside the border and padding
+        assert_eq!(editor.cursor_from_click(area, 5, 2), Some((0, 3)));
+        // Clicking past the end of a line lands on the end of the line
+        assert_eq!(editor.cursor_from_click(area, 30, 3), Some((1, 6)));
+        // Clicking below the last line lands on the last line
+        assert_eq!(editor.cursor_from_click(area, 3, 6), Some((1, 1)));
+        // Borders and padding aren't text
+        assert_eq!(editor.cursor_from_click(area, 1, 2), None);
+        assert_eq!(editor.cursor_from_click(area, 5, 0), None);
+
+        editor.handle_click(area, 5, 3);
+        assert_eq!(editor.body.cursor(), (1, 3));
+    }
+
+    #[test]
+    fn test_cursor_from_click_scrolled() {
+        let lines = (0..20).map(|n| format!("Line {}", n)).collect::<Vec<String>>();
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            lines,
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        let area = Rect::new(0, 0, 40, 10);
+
+        editor.body.move_cursor(CursorMove::Jump(15, 0));
+        editor.update_viewport(6);
+
+        assert_eq!(editor.scroll_top, 10);
+        assert_eq!(editor.cursor_from_click(area, 2, 2), Some((10, 0)));
+        assert_eq!(editor.screen_position(area, (10, 0)), Some((2, 2)));
+        assert_eq!(editor.screen_position(area, (9, 0)), None);
+    }
+
+    #[test]
+    fn test_status_line_shows_cursor_position() {
+        let link = Link {
+            id: 7,
+            text_id: 0,
+            linked_id: 2,
+            row: 0,
+            start_col: 0,
+            end_col: 3,
+            saved: true,
+            updated: false,
+            deleted: false,
+            anchor: None,
+        };
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["Link one".to_string(), "two".to_string(), "three".to_string()],
+            HashMap::from([(0, link)]),
+            Some(7),
+            true,
+            36,
+            theme(),
+        );
+        editor.body.move_cursor(CursorMove::Jump(2, 3));
+        let area = Rect::new(0, 0, 50, 1);
+        let mut buf = Buffer::empty(area);
+
+        editor
+            .status_line(&StatuslineSettings::default())
+            .render(area, &mut buf);
+
+        let rendered = (0..area.width)
+            .map(|x| buf.get(x, 0).symbol())
+            .collect::<String>();
+        assert_eq!(rendered.trim_end(), " 3:4 | 3 lines | note 7 | 1 links");
+
+        let settings = StatuslineSettings {
+            line_count: false,
+            link_count: false,
+            ..StatuslineSettings::default()
+        };
+        assert_eq!(editor.status_line(&settings).text(), " 3:4 | note 7 ");
+    }
+
+    fn wrapped_editor() -> Editor<'static> {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["the quick brown fox jumps".to_string(), "end".to_string()],
+            HashMap::new(),
+            None,
+            false,
+            10,
+            theme(),
+        );
+        editor.settings.soft_wrap = true;
+        editor
+    }
+
+    #[test]
+    fn test_soft_wrap_moves_by_screen_row() {
+        let mut editor = wrapped_editor();
+        editor.body.move_cursor(CursorMove::Jump(0, 2));
+
+        editor.handle_input(key(Key::Char('j')));
+        assert_eq!(editor.body.cursor(), (0, 12));
+        editor.handle_input(key(Key::Char('j')));
+        assert_eq!(editor.body.cursor(), (0, 22));
+        editor.handle_input(key(Key::Char('j')));
+        assert_eq!(editor.body.cursor(), (1, 2));
+
+        editor.handle_input(key(Key::Char('k')));
+        assert_eq!(editor.body.cursor(), (0, 22));
+        type_keys(&mut editor, "2k");
+        assert_eq!(editor.body.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn test_visual_o_extends_from_other_end() {
+        let mut editor = line_editor("one two three", 4);
+        type_keys(&mut editor, "vlllo");
+        assert_eq!(editor.body.cursor(), (0, 4));
+        assert_eq!(editor.body.get_selection_start(), Some((0, 7)));
+
+        type_keys(&mut editor, "hh");
+        assert_eq!(editor.mode, EditorMode::Visual);
+        assert_eq!(editor.body.cursor(), (0, 2));
+        assert_eq!(editor.body.get_selection_start(), Some((0, 7)));
+
+        type_keys(&mut editor, "o");
+        assert_eq!(editor.body.cursor(), (0, 7));
+        assert_eq!(editor.body.get_selection_start(), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_numbered_registers_keep_older_deletes() {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            ["one", "two", "three", "four"].map(String::from).to_vec(),
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        type_keys(&mut editor, "dddddd");
+        assert_eq!(editor.body.lines(), ["four"]);
+        assert_eq!(editor.delete_registers, ["three", "two", "one"]);
+
+        type_keys(&mut editor, "\"3p");
+        assert_eq!(editor.body.lines(), ["four", "one"]);
+        assert_eq!(editor.cmd_state, CommandState::NoCommand);
+
+        type_keys(&mut editor, "p");
+        assert_eq!(editor.body.lines(), ["four", "one", "three"]);
+    }
+
+    #[test]
+    fn test_insert_ctrl_w_and_ctrl_u_delete_backwards() {
+        let ctrl = |c| Input {
+            key: Key::Char(c),
+            ctrl: true,
+            ..Default::default()
+        };
+
+        let mut editor = insert_editor("one two three", 13);
+        editor.handle_input(ctrl('w'));
+        assert_eq!(editor.body.lines(), ["one two "]);
+        assert_eq!(editor.mode, EditorMode::Insert);
+
+        let mut editor = insert_editor("one two three", 7);
+        editor.handle_input(ctrl('u'));
+        assert_eq!(editor.body.lines(), [" three"]);
+        assert_eq!(editor.body.cursor(), (0, 0));
+        editor.handle_input(key(Key::Esc));
+        editor.handle_input(key(Key::Char('u')));
+        assert_eq!(editor.body.lines(), ["one two three"]);
+    }
+
+    #[test]
+    fn test_home_end_move_to_line_ends() {
+        let mut editor = lines_editor(2, 2);
+        editor.handle_input(key(Key::End));
+        assert_eq!(editor.body.cursor(), (2, 5));
+        editor.handle_input(key(Key::Home));
+        assert_eq!(editor.body.cursor(), (2, 0));
+
+        editor.set_mode(EditorMode::Insert);
+        editor.handle_input(key(Key::End));
+        assert_eq!(editor.body.cursor(), (2, 5));
+        editor.handle_input(key(Key::Home));
+        assert_eq!(editor.body.cursor(), (2, 0));
+    }
+
+    #[test]
+    fn test_page_keys_move_by_viewport() {
+        let lines = (0..20).map(|n| format!("line {}", n)).collect::<Vec<String>>();
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            lines,
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.update_viewport(6);
+
+        editor.handle_input(key(Key::PageDown));
+        assert_eq!(editor.body.cursor().0, 6);
+        editor.set_mode(EditorMode::Insert);
+        editor.handle_input(key(Key::PageDown));
+        assert_eq!(editor.body.cursor().0, 12);
+        editor.handle_input(key(Key::PageDown));
+        assert_eq!(editor.body.cursor().0, 18);
+        editor.handle_input(key(Key::PageUp));
+        assert_eq!(editor.body.cursor().0, 12);
+    }
+
+    #[test]
+    fn test_gj_gk_move_by_screen_row() {
+        let mut editor = wrapped_editor();
+        editor.body.move_cursor(CursorMove::Jump(0, 2));
+
+        type_keys(&mut editor, "gj");
+        assert_eq!(editor.body.cursor(), (0, 12));
+        type_keys(&mut editor, "gj");
+        assert_eq!(editor.body.cursor(), (0, 22));
+        type_keys(&mut editor, "2gk");
+        assert_eq!(editor.body.cursor(), (0, 2));
+        assert_eq!(editor.cmd_state, CommandState::NoCommand);
+
+        let mut editor = lines_editor(0, 1);
+        type_keys(&mut editor, "gj");
+        assert_eq!(editor.body.cursor(), (1, 1));
+        type_keys(&mut editor, "gk");
+        assert_eq!(editor.body.cursor(), (0, 1));
+    }
+
+    #[test]
+    fn test_soft_wrap_viewport_counts_screen_rows() {
+        let mut editor = wrapped_editor();
+        editor.body.move_cursor(CursorMove::Jump(1, 0));
+
+        editor.update_viewport(2);
+
+        assert_eq!(editor.scroll_top, 1);
+        assert_eq!(editor.wrapped_rows(2), vec![(1, 0, 3)]);
+    }
+
+    fn scrolloff_editor(scrolloff: usize) -> Editor<'static> {
+        let lines = (0..30).map(|n| format!("Line {}", n)).collect::<Vec<String>>();
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            lines,
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.settings.scrolloff = scrolloff;
+        editor
+    }
+
+    #[test]
+    fn test_scrolloff_keeps_context_mid_note() {
+        let mut editor = scrolloff_editor(3);
+
+        editor.body.move_cursor(CursorMove::Jump(15, 0));
+        editor.update_viewport(10);
+        assert_eq!(editor.scroll_top, 9);
+
+        editor.body.move_cursor(CursorMove::Jump(11, 0));
+        editor.update_viewport(10);
+        assert_eq!(editor.scroll_top, 8);
+
+        // Moving within the margins doesn't scroll
+        editor.body.move_cursor(CursorMove::Jump(14, 0));
+        editor.update_viewport(10);
+        assert_eq!(editor.scroll_top, 8);
+    }
+
+    #[test]
+    fn test_scrolloff_at_note_boundaries() {
+        let mut editor = scrolloff_editor(3);
+
+        editor.body.move_cursor(CursorMove::Jump(29, 0));
+        editor.update_viewport(10);
+        assert_eq!(editor.scroll_top, 20);
+
+        editor.body.move_cursor(CursorMove::Jump(1, 0));
+        editor.update_viewport(10);
+        assert_eq!(editor.scroll_top, 0);
+
+        // Too small a viewport for the full margin still keeps the cursor visible
+        let mut editor = scrolloff_editor(10);
+        editor.body.move_cursor(CursorMove::Jump(20, 0));
+        editor.update_viewport(4);
+        assert_eq!(editor.scroll_top, 18);
+    }
+
+    #[test]
+    fn test_tab_cycles_links_in_position_order() {
+        let link = |text_id: i64, row: usize, start_col: usize| Link {
+            id: 1,
+            text_id,
+            linked_id: 2,
+            row,
+            start_col,
+            end_col: start_col + 3,
+            saved: true,
+            updated: false,
+            deleted: false,
+            anchor: None,
+        };
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["abc one two".to_string(), "three".to_string(), "four".to_string()],
+            HashMap::from([(0, link(0, 2, 0)), (1, link(1, 0, 8)), (2, link(2, 0, 4))]),
+            Some(1),
+            true,
+            140,
+            theme(),
+        );
+
+        let mut visited = vec![];
+        for _ in 0..4 {
+            editor.handle_input(key(Key::Tab));
+            visited.push(editor.body.cursor());
+        }
+        assert_eq!(visited, [(0, 4), (0, 8), (2, 0), (0, 4)]);
+
+        let shift_tab = Input {
+            key: Key::Tab,
+            shift: true,
+            ..Default::default()
+        };
+        editor.handle_input(shift_tab);
+        assert_eq!(editor.body.cursor(), (2, 0));
+    }
+
+    fn insert_editor(line: &str, col: u16) -> Editor<'static> {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec![line.to_string()],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.set_mode(EditorMode::Insert);
+        editor.body.move_cursor(CursorMove::Jump(0, col));
+        editor
+    }
+
+    fn key(key: Key) -> Input {
+        Input {
+            key,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tab_expands_to_next_tab_stop() {
+        let mut editor = insert_editor("", 0);
+        editor.handle_input(key(Key::Tab));
+        assert_eq!(editor.body.lines(), ["    "]);
+
+        let mut editor = insert_editor("ab", 2);
+        editor.handle_input(key(Key::Tab));
+        assert_eq!(editor.body.lines(), ["ab  "]);
+        assert_eq!(editor.body.cursor(), (0, 4));
+    }
+
+    #[test]
+    fn test_tab_without_expand_inserts_literal_tab() {
+        let mut editor = insert_editor("ab", 2);
+        editor.settings.expand_tab = false;
+
+        editor.handle_input(key(Key::Tab));
+
+        assert_eq!(editor.body.lines(), ["ab\t"]);
+    }
+
+    #[test]
+    fn test_soft_tab_backspace() {
+        // Full indent level
+        let mut editor = insert_editor("        text", 8);
+        editor.handle_input(key(Key::Backspace));
+        assert_eq!(editor.body.lines(), ["    text"]);
+
+        // Part way through an indent level only goes back to the previous stop
+        let mut editor = insert_editor("      text", 6);
+        editor.handle_input(key(Key::Backspace));
+        assert_eq!(editor.body.lines(), ["    text"]);
+
+        // After text, backspace deletes a single character
+        let mut editor = insert_editor("text    ", 8);
+        editor.handle_input(key(Key::Backspace));
+        assert_eq!(editor.body.lines(), ["text   "]);
+
+        // Without expand_tab, backspace deletes a single space
+        let mut editor = insert_editor("        text", 8);
+        editor.settings.expand_tab = false;
+        editor.handle_input(key(Key::Backspace));
+        assert_eq!(editor.body.lines(), ["       text"]);
+    }
+
+    #[test]
+    fn test_enter_carries_indent() {
+        let mut editor = insert_editor("    - item", 10);
+
+        editor.handle_input(key(Key::Enter));
+
+        assert_eq!(editor.body.lines(), ["    - item", "    "]);
+        assert_eq!(editor.body.cursor(), (1, 4));
+    }
+
+    #[test]
+    fn test_enter_on_blank_line_does_not_accumulate_indent() {
+        let mut editor = insert_editor("    - item", 10);
+
+        editor.handle_input(key(Key::Enter));
+        editor.handle_input(key(Key::Enter));
+
+        assert_eq!(editor.body.lines(), ["    - item", "", "    "]);
+    }
+
+    #[test]
+    fn test_enter_without_auto_indent() {
+        let mut editor = insert_editor("    - item", 10);
+        editor.settings.auto_indent = false;
+
+        editor.handle_input(key(Key::Enter));
+
+        assert_eq!(editor.body.lines(), ["    - item", ""]);
+    }
+
+    #[test]
+    fn test_open_line_inherits_indent() {
+        let mut editor = insert_editor("  - item", 0);
+        editor.set_mode(EditorMode::Normal);
+
+        editor.handle_input(key(Key::Char('o')));
+        assert_eq!(editor.body.lines(), ["  - item", "  "]);
+        assert_eq!(editor.mode, EditorMode::Insert);
+
+        let mut editor = insert_editor("  - item", 3);
+        editor.set_mode(EditorMode::Normal);
+        editor.handle_input(Input {
+            key: Key::Char('O'),
+            shift: true,
+            ..Default::default()
+        });
+        assert_eq!(editor.body.lines(), ["  ", "  - item"]);
+        assert_eq!(editor.body.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn test_toggle_checkbox() {
+        let mut editor = insert_editor("- [ ] buy milk", 8);
+        editor.set_mode(EditorMode::Normal);
+
+        assert!(editor.toggle_checkbox());
+        assert_eq!(editor.body.lines(), ["- [x] buy milk"]);
+        assert_eq!(editor.body.cursor(), (0, 8));
+
+        assert!(editor.toggle_checkbox());
+        assert_eq!(editor.body.lines(), ["- [ ] buy milk"]);
+
+        let mut editor = insert_editor("- buy milk", 0);
+        assert!(!editor.toggle_checkbox());
+        assert_eq!(editor.body.lines(), ["- buy milk"]);
+    }
+
+    #[test]
+    fn test_toggle_indented_checkbox() {
+        let mut editor = insert_editor("    - [ ] subtask", 0);
+        editor.set_mode(EditorMode::Normal);
+
+        editor.handle_input(Input {
+            key: Key::Char(' '),
+            ctrl: true,
+            ..Default::default()
+        });
+
+        assert_eq!(editor.body.lines(), ["    - [x] subtask"]);
+    }
+
+    fn type_keys(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            editor.handle_input(key(Key::Char(c)));
+        }
+    }
+
+    #[test]
+    fn test_word_under_cursor() {
+        let mut editor = insert_editor("see shopping-list, now", 7);
+        assert_eq!(editor.word_under_cursor(), Some((0, 4, 16)));
+
+        editor.body.move_cursor(CursorMove::Jump(0, 17));
+        assert_eq!(editor.word_under_cursor(), None);
+    }
+
+    #[test]
+    fn test_auto_pairs_close_and_skip_over() {
+        let mut editor = insert_editor("", 0);
+        editor.settings.auto_pairs = true;
+
+        type_keys(&mut editor, "f(");
+        assert_eq!(editor.body.lines(), ["f()"]);
+        assert_eq!(editor.body.cursor(), (0, 2));
+
+        type_keys(&mut editor, "x)");
+        assert_eq!(editor.body.lines(), ["f(x)"]);
+        assert_eq!(editor.body.cursor(), (0, 4));
+
+        // A quote after a word is an apostrophe, a quote before its twin skips it
+        type_keys(&mut editor, " don't \"a\"");
+        assert_eq!(editor.body.lines(), ["f(x) don't \"a\""]);
+        assert_eq!(editor.body.cursor(), (0, 14));
+    }
+
+    #[test]
+    fn test_auto_pairs_off_types_single_chars() {
+        let mut editor = insert_editor("", 0);
+
+        type_keys(&mut editor, "{(\"");
+        assert_eq!(editor.body.lines(), ["{(\""]);
+        assert_eq!(editor.body.cursor(), (0, 3));
+    }
+
+    #[test]
+    fn test_auto_pairs_wrap_visual_selection() {
+        let mut editor = insert_editor("say hello there", 0);
+        editor.settings.auto_pairs = true;
+        editor.set_mode(EditorMode::Normal);
+        editor.body.move_cursor(CursorMove::Jump(0, 4));
+
+        type_keys(&mut editor, "vllll(");
+        assert_eq!(editor.body.lines(), ["say (hello) there"]);
+        assert_eq!(editor.mode, EditorMode::Normal);
+
+        type_keys(&mut editor, "V\"");
+        assert_eq!(editor.body.lines(), ["\"say (hello) there\""]);
+    }
+
+    #[test]
+    fn test_undo_removes_typed_word() {
+        let mut editor = insert_editor("note", 4);
+        editor.set_mode(EditorMode::Normal);
+
+        editor.handle_input(key(Key::Char('a')));
+        type_keys(&mut editor, " hello");
+        editor.handle_input(key(Key::Esc));
+        editor.handle_input(key(Key::Char('u')));
+
+        assert_eq!(editor.body.lines(), ["note"]);
+
+        editor.handle_input(key(Key::Char('r')));
+        assert_eq!(editor.body.lines(), ["note hello"]);
+    }
+
+    #[test]
+    fn test_motion_breaks_undo_group() {
+        let mut editor = insert_editor("", 0);
+        editor.set_mode(EditorMode::Normal);
+
+        editor.handle_input(key(Key::Char('i')));
+        type_keys(&mut editor, "ab");
+        editor.handle_input(key(Key::Left));
+        type_keys(&mut editor, "c");
+        editor.handle_input(key(Key::Esc));
+
+        editor.handle_input(key(Key::Char('u')));
+        assert_eq!(editor.body.lines(), ["ab"]);
+        editor.handle_input(key(Key::Char('u')));
+        assert_eq!(editor.body.lines(), [""]);
+    }
+
+    #[test]
+    fn test_undo_groups_survive_a_resize() {
+        let mut editor = insert_editor("note", 4);
+        editor.set_mode(EditorMode::Normal);
+
+        editor.handle_input(key(Key::Char('a')));
+        type_keys(&mut editor, " hello");
+        editor.set_max_col(80);
+        type_keys(&mut editor, " there");
+        editor.handle_input(key(Key::Esc));
+
+        editor.handle_input(key(Key::Char('u')));
+        assert_eq!(editor.body.lines(), ["note"]);
+        editor.handle_input(key(Key::Char('r')));
+        assert_eq!(editor.body.lines(), ["note hello there"]);
+    }
+
+    #[test]
+    fn test_counted_delete_is_one_undo_group() {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["one".to_string(), "two".to_string(), "three".to_string()],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+
+        type_keys(&mut editor, "2dd");
+        assert_eq!(editor.body.lines().len(), 1);
+
+        editor.handle_input(key(Key::Char('u')));
+        assert_eq!(editor.body.lines(), ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn test_saved_undo_round_trips_into_a_new_session() {
+        let mut editor = insert_editor("note", 4);
+        editor.set_mode(EditorMode::Normal);
+        editor.handle_input(key(Key::Char('a')));
+        type_keys(&mut editor, " one");
+        editor.handle_input(key(Key::Esc));
+        editor.handle_input(key(Key::Char('a')));
+        type_keys(&mut editor, " two");
+        editor.handle_input(key(Key::Esc));
+
+        let json = serde_json::to_string(&editor.saved_undo()).unwrap();
+        let saved = serde_json::from_str::<Vec<SavedUndo>>(&json).unwrap();
+        assert_eq!(saved.len(), 2);
+
+        let mut reloaded = insert_editor("note one two", 12);
+        reloaded.set_mode(EditorMode::Normal);
+        reloaded.restore_undo(saved.clone());
+
+        reloaded.handle_input(key(Key::Char('u')));
+        assert_eq!(reloaded.body.lines(), ["note one"]);
+        reloaded.handle_input(key(Key::Char('u')));
+        assert_eq!(reloaded.body.lines(), ["note"]);
+        reloaded.handle_input(key(Key::Char('r')));
+        assert_eq!(reloaded.body.lines(), ["note one"]);
+
+        // History for other text is dropped
+        let mut edited = insert_editor("changed", 7);
+        edited.restore_undo(saved);
+        assert!(edited.undo_groups.is_empty());
+    }
+
+    fn lines_editor(row: u16, col: u16) -> Editor<'static> {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["one".to_string(), "two".to_string(), "three".to_string()],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.body.move_cursor(CursorMove::Jump(row, col));
+        editor
+    }
+
+    fn line_editor(line: &str, col: u16) -> Editor<'static> {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec![line.to_string()],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.body.move_cursor(CursorMove::Jump(0, col));
+        editor
+    }
+
+    #[test]
+    fn test_delete_inner_quotes() {
+        let mut editor = line_editor("say \"hello there\" now", 8);
+        type_keys(&mut editor, "di\"");
+        assert_eq!(editor.body.lines(), ["say \"\" now"]);
+        assert_eq!(editor.body.cursor(), (0, 5));
+
+        // Before the first quote the next pair is used
+        let mut editor = line_editor("say \"hello\"", 0);
+        type_keys(&mut editor, "ci\"");
+        assert_eq!(editor.body.lines(), ["say \"\""]);
+        assert_eq!(editor.mode, EditorMode::Insert);
+    }
+
+    #[test]
+    fn test_delete_around_parens() {
+        let mut editor = line_editor("call(a, b) end", 6);
+        type_keys(&mut editor, "da(");
+        assert_eq!(editor.body.lines(), ["call end"]);
+
+        let mut editor = line_editor("call(a, b) end", 9);
+        type_keys(&mut editor, "yi)");
+        assert_eq!(editor.body.lines(), ["call(a, b) end"]);
+        type_keys(&mut editor, "$p");
+        assert_eq!(editor.body.lines(), ["call(a, b) enda, b"]);
+    }
+
+    #[test]
+    fn test_delete_inner_nested_braces() {
+        let mut editor = line_editor("x { a { b } c }", 12);
+        type_keys(&mut editor, "di{");
+        assert_eq!(editor.body.lines(), ["x {}"]);
+
+        let mut editor = line_editor("x { a { b } c }", 8);
+        type_keys(&mut editor, "di}");
+        assert_eq!(editor.body.lines(), ["x { a {} c }"]);
+    }
+
+    #[test]
+    fn test_linewise_paste_goes_on_own_lines() {
+        let mut editor = lines_editor(0, 1);
+        type_keys(&mut editor, "yyp");
+        assert_eq!(editor.body.lines(), ["one", "one", "two", "three"]);
+        assert_eq!(editor.body.cursor(), (1, 0));
+
+        type_keys(&mut editor, "j2P");
+        assert_eq!(
+            editor.body.lines(),
+            ["one", "one", "one", "one", "two", "three"]
+        );
+
+        let mut editor = lines_editor(0, 0);
+        type_keys(&mut editor, "ddp");
+        assert_eq!(editor.body.lines(), ["two", "one", "three"]);
+    }
+
+    #[test]
+    fn test_yank_survives_a_resize() {
+        let mut editor = lines_editor(0, 1);
+        type_keys(&mut editor, "yy");
+        editor.set_max_col(80);
+
+        type_keys(&mut editor, "p");
+        assert_eq!(editor.body.lines(), ["one", "one", "two", "three"]);
+
+        editor.set_max_col(100);
+        editor.handle_input(key(Key::Char('u')));
+        assert_eq!(editor.body.lines(), ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn test_charwise_paste_goes_inline() {
+        let mut editor = lines_editor(1, 0);
+        type_keys(&mut editor, "x");
+        assert_eq!(editor.body.lines()[1], "wo");
+
+        type_keys(&mut editor, "2p");
+        assert_eq!(editor.body.lines(), ["one", "wtto", "three"]);
+
+        let mut editor = lines_editor(2, 2);
+        type_keys(&mut editor, "ylP");
+        assert_eq!(editor.body.lines(), ["one", "two", "thrree"]);
+    }
+
+    #[test]
+    fn test_esc_cancels_pending_delete() {
+        let mut editor = lines_editor(0, 0);
+        type_keys(&mut editor, "2d");
+        assert_eq!(editor.pending_keys(), "2d");
+        assert!(editor
+            .status_line(&StatuslineSettings::default())
+            .text()
+            .ends_with("| 2d "));
+
+        editor.handle_input(key(Key::Esc));
+        assert_eq!(editor.cmd_state, CommandState::NoCommand);
+        assert_eq!(editor.pending_keys(), "");
+
+        type_keys(&mut editor, "j");
+        assert_eq!(editor.body.lines(), ["one", "two", "three"]);
+        assert_eq!(editor.body.cursor().0, 1);
+
+        type_keys(&mut editor, "f");
+        assert_eq!(editor.pending_keys(), "f");
+        editor.handle_input(key(Key::Esc));
+        type_keys(&mut editor, "x");
+        assert_eq!(editor.body.lines(), ["one", "wo", "three"]);
+    }
+
+    #[test]
+    fn test_hop_jumps_to_chosen_label() {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["to be or".to_string(), "not to be".to_string()],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.set_mode(EditorMode::Normal);
+
+        type_keys(&mut editor, "sto");
+        editor.handle_input(key(Key::Enter));
+        assert_eq!(editor.cmd_state, CommandState::ExecuteHop);
+        assert_eq!(
+            editor.hop_targets,
+            [("a".to_owned(), (0, 0)), ("s".to_owned(), (1, 4))]
+        );
+
+        type_keys(&mut editor, "s");
+        assert_eq!(editor.body.cursor(), (1, 4));
+        assert_eq!(editor.cmd_state, CommandState::NoCommand);
+        assert!(editor.hop_targets.is_empty());
+
+        editor.set_mode(EditorMode::Normal);
+        type_keys(&mut editor, "sbe");
+        editor.handle_input(key(Key::Enter));
+        type_keys(&mut editor, "z");
+        assert_eq!(editor.body.cursor(), (1, 4));
+        assert_eq!(editor.cmd_state, CommandState::NoCommand);
+    }
+
+    #[test]
+    fn test_longer_hop_pattern_narrows_targets() {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["tea time".to_string(), "ten tents".to_string()],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.set_mode(EditorMode::Normal);
+
+        type_keys(&mut editor, "ste");
+        assert_eq!(editor.cmd_state, CommandState::PrimeHop);
+        assert_eq!(editor.hop_targets.len(), 3);
+
+        type_keys(&mut editor, "n");
+        assert_eq!(editor.pending_keys(), "sten");
+        assert_eq!(editor.hop_targets.len(), 2);
+
+        editor.handle_input(key(Key::Backspace));
+        type_keys(&mut editor, "a");
+        assert_eq!(editor.hop_targets, [("a".to_owned(), (0, 0))]);
+
+        editor.handle_input(key(Key::Enter));
+        type_keys(&mut editor, "a");
+        assert_eq!(editor.body.cursor(), (0, 0));
+        assert_eq!(editor.cmd_state, CommandState::NoCommand);
+    }
+
+    #[test]
+    fn test_read_only_ignores_edits_but_not_motions() {
+        let mut editor = lines_editor(0, 0);
+        editor.toggle_read_only();
+        type_keys(&mut editor, "ihello");
+        type_keys(&mut editor, "xddp");
+        type_keys(&mut editor, "Vd");
+        assert_eq!(editor.body.lines(), ["one", "two", "three"]);
+
+        editor.set_mode(EditorMode::Normal);
+        type_keys(&mut editor, "j");
+        assert_eq!(editor.body.cursor().0, 1);
+        assert_eq!(editor.mode, EditorMode::Normal);
+    }
+
+    #[test]
+    fn test_markdown_preview_scrolls_without_editing() {
+        let mut editor = lines_editor(1, 0);
+        editor.scroll_top = 1;
+        editor.toggle_markdown_preview();
+        assert_eq!(editor.preview_scroll, 1);
+
+        type_keys(&mut editor, "jjjddx");
+        assert_eq!(editor.preview_scroll, 2);
+        assert_eq!(editor.body.lines(), ["one", "two", "three"]);
+
+        editor.toggle_markdown_preview();
+        assert_eq!(editor.body.cursor(), (1, 0));
+        assert_eq!(editor.scroll_top, 1);
+    }
+
+    #[test]
+    fn test_visual_line_delete_whole_lines() {
+        let mut editor = lines_editor(0, 2);
+        type_keys(&mut editor, "Vjd");
+        assert_eq!(editor.body.lines(), ["three"]);
+        assert_eq!(editor.mode, EditorMode::Normal);
+
+        // Selection ending on the last line
+        let mut editor = lines_editor(2, 1);
+        type_keys(&mut editor, "Vkd");
+        assert_eq!(editor.body.lines(), ["one"]);
+
+        let mut editor = lines_editor(1, 0);
+        type_keys(&mut editor, "Vd");
+        assert_eq!(editor.body.lines(), ["one", "three"]);
+        assert_eq!(editor.body.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn test_visual_line_indent() {
+        let mut editor = lines_editor(0, 1);
+        type_keys(&mut editor, "Vj>");
+        assert_eq!(editor.body.lines(), ["    one", "    two", "three"]);
+
+        type_keys(&mut editor, "Vj<");
+        assert_eq!(editor.body.lines(), ["one", "two", "three"]);
+    }
+
+    fn block_editor() -> Editor<'static> {
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec![
+                "abcdef".to_string(),
+                "ghijkl".to_string(),
+                "mnopqr".to_string(),
+            ],
+            HashMap::new(),
+            None,
+            false,
+            140,
+            theme(),
+        );
+        editor.body.move_cursor(CursorMove::Jump(0, 1));
+        editor.handle_input(Input {
+            key: Key::Char('v'),
+            ctrl: true,
+            ..Default::default()
+        });
+        editor
+    }
+
+    #[test]
+    fn test_visual_block_delete() {
+        let mut editor = block_editor();
+        type_keys(&mut editor, "jjll");
+        assert_eq!(editor.block_region(), (0, 2, 1, 3));
+
+        editor.handle_input(key(Key::Char('d')));
+
+        assert_eq!(editor.body.lines(), ["aef", "gkl", "mqr"]);
+        assert_eq!(editor.body.cursor(), (0, 1));
+        assert_eq!(editor.mode, EditorMode::Normal);
+    }
+
+    #[test]
+    fn test_visual_block_insert() {
+        let mut editor = block_editor();
+        type_keys(&mut editor, "jjI");
+        type_keys(&mut editor, "> ");
+        editor.handle_input(key(Key::Esc));
+
+        assert_eq!(editor.body.lines(), ["a> bcdef", "g> hijkl", "m> nopqr"]);
+
+        // The whole block insert is a single undo step
+        editor.handle_input(key(Key::Char('u')));
+        assert_eq!(editor.body.lines(), ["abcdef", "ghijkl", "mnopqr"]);
+    }
+
+    #[test]
+    fn test_render_mode_indicator_uses_theme() {
+        let mut theme = theme();
+        theme.normal_mode = Color::Blue;
+        theme.search_mode = Color::Yellow;
+        let editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["text".to_string()],
+            HashMap::new(),
+            None,
+            true,
+            76,
+            theme,
+        );
+        let area = Rect::new(0, 0, 80, 10);
+
+        let mut buf = Buffer::empty(area);
+        editor.clone().render(area, &mut buf);
+        assert_eq!(buf.get(3, 9).symbol(), "<");
+        assert_eq!(buf.get(3, 9).fg, Color::Blue);
+
+        let mut searching = editor;
+        searching.searchbar_open = true;
+        let mut buf = Buffer::empty(area);
+        searching.render(area, &mut buf);
+        assert_eq!(buf.get(3, 9).fg, Color::Yellow);
+    }
+
+    #[test]
+    fn test_render_narrow_elides_title_and_hints() {
+        let editor = Editor::new(
+            "A very long note title that will not fit".to_string(),
+            vec!["one".to_string()],
+            HashMap::new(),
+            None,
+            false,
+            20,
+            theme(),
+        );
+        let area = Rect::new(0, 0, 24, 6);
+        let row = |buf: &Buffer, y: u16| {
+            (0..area.width)
+                .map(|x| buf.get(x, y).symbol().to_owned())
+                .collect::<String>()
+        };
+
+        let mut buf = Buffer::empty(area);
+        editor.clone().render(area, &mut buf);
+        assert!(row(&buf, 0).contains("A very long note ti…"));
+        assert!(row(&buf, 5).contains('…'));
+
+        let tiny = Rect::new(0, 0, 10, 6);
+        let mut buf = Buffer::empty(tiny);
+        editor.render(tiny, &mut buf);
+    }
+
+    #[test]
+    fn test_render_cursor_line_uses_theme() {
+        let mut highlighted = theme();
+        highlighted.cursor_line = Some(Color::Cyan);
+        let editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["one".to_string(), "two".to_string()],
+            HashMap::new(),
+            None,
+            true,
+            36,
+            highlighted,
+        );
+        let area = Rect::new(0, 0, 40, 10);
+
+        let mut buf = Buffer::empty(area);
+        editor.clone().render(area, &mut buf);
+        assert_eq!(buf.get(3, 2).bg, Color::Cyan);
+        assert_ne!(buf.get(3, 3).bg, Color::Cyan);
+
+        let mut wrapped = editor;
+        wrapped.settings.soft_wrap = true;
+        let mut buf = Buffer::empty(area);
+        wrapped.render(area, &mut buf);
+        assert_eq!(buf.get(3, 2).bg, Color::Cyan);
+        assert_ne!(buf.get(3, 3).bg, Color::Cyan);
+
+        let plain = Editor::new(
+            "Test Note".to_string(),
+            vec!["one".to_string()],
+            HashMap::new(),
+            None,
+            true,
+            36,
+            theme(),
+        );
+        let mut buf = Buffer::empty(area);
+        plain.render(area, &mut buf);
+        assert_ne!(buf.get(3, 2).bg, Color::Cyan);
+    }
+
+    #[test]
+    fn test_render_selection_uses_theme() {
+        let mut theme = theme();
+        theme.select = Color::Cyan;
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["hello".to_string()],
+            HashMap::new(),
+            None,
+            true,
+            36,
+            theme,
+        );
+        editor.set_mode(EditorMode::Visual);
+        editor.body.move_cursor(CursorMove::Forward);
+        editor.body.move_cursor(CursorMove::Forward);
+        let area = Rect::new(0, 0, 40, 10);
+        let mut buf = Buffer::empty(area);
+
+        editor.render(area, &mut buf);
+
+        assert_eq!(buf.get(2, 2).bg, Color::Cyan);
+        assert_eq!(buf.get(3, 2).bg, Color::Cyan);
+        assert_ne!(buf.get(6, 2).bg, Color::Cyan);
+    }
+
+    #[test]
+    fn test_cleared_search_highlight_comes_back_with_n() {
+        let mut theme = theme();
+        theme.search = Color::Cyan;
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["one two".to_string(), "two three".to_string()],
+            HashMap::new(),
+            None,
+            true,
+            36,
+            theme,
+        );
+        editor.set_mode(EditorMode::Normal);
+        let area = Rect::new(0, 0, 40, 10);
+        let highlighted = |editor: &Editor| {
+            let mut buf = Buffer::empty(area);
+            editor.clone().render(area, &mut buf);
+            buf.get(7, 2).bg == Color::Cyan
+        };
+
+        assert!(editor.set_search("two"));
+        assert!(highlighted(&editor));
+        editor.clear_search_highlight();
+        assert!(!highlighted(&editor));
+        assert_eq!(editor.search_pattern, "two");
+
+        editor.handle_input(Input {
+            key: Key::Char('n'),
+            ..Default::default()
+        });
+        assert_eq!(editor.body.cursor(), (0, 4));
+        assert!(highlighted(&editor));
+    }
+
+    #[test]
+    fn test_render_line_numbers_shift_text() {
+        let lines = (1..=10).map(|n| format!("line {}", n)).collect();
+        let mut editor = Editor::new(
+            "Test Note".to_string(),
+            lines,
+            HashMap::new(),
+            None,
+            true,
+            36,
+            theme(),
+        );
+        editor.settings.line_numbers = true;
+        let area = Rect::new(0, 0, 40, 16);
+        let mut buf = Buffer::empty(area);
+
+        assert_eq!(editor.gutter_width(), 3);
+        assert_eq!(editor.screen_position(area, (1, 0)), Some((5, 3)));
+        editor.render(area, &mut buf);
+
+        let row = |y: u16| (2..11).map(|x| buf.get(x, y).symbol()).collect::<String>();
+        assert_eq!(row(2), " 1 line 1");
+        assert_eq!(row(11), "10 line 1");
+    }
+
+    #[test]
+    fn test_render_code_block_styled_apart_from_prose() {
+        let mut theme = theme();
+        theme.main_heading = Color::Green;
+        let lines = ["Some prose", "```rust", "# not a heading", "```", "more prose"];
+        let editor = Editor::new(
+            "Test Note".to_string(),
+            lines.map(str::to_owned).to_vec(),
+            HashMap::new(),
+            None,
+            true,
+            36,
+            theme,
+        );
+        let area = Rect::new(0, 0, 40, 10);
+        let mut buf = Buffer::empty(area);
+
+        editor.render(area, &mut buf);
+
+        assert_eq!(buf.get(2, 2).fg, THEME_COLOR);
+        assert_eq!(buf.get(2, 6).fg, THEME_COLOR);
+        let code_cell = buf.get(4, 4);
+        assert_eq!(code_cell.symbol(), "n");
+        assert_ne!(code_cell.fg, THEME_COLOR);
+        assert_ne!(code_cell.fg, Color::Green);
+    }
+
+    #[test]
+    fn test_heading_level() {
+        assert_eq!(heading_level("# Title"), Some(1));
+        assert_eq!(heading_level("### Sub"), Some(3));
+        assert_eq!(heading_level("#Title"), None);
+        assert_eq!(heading_level("####### Too deep"), None);
+        assert_eq!(heading_level("Not # a heading"), None);
+    }
+
+    #[test]
+    fn test_render_headings_use_theme() {
+        let mut theme = theme();
+        theme.main_heading = Color::Green;
+        theme.main_heading_modifiers = vec![Modifier::UNDERLINED];
+        theme.sub_heading = Color::Magenta;
+        let editor = Editor::new(
+            "Test Note".to_string(),
+            vec![
+                "# Title".to_string(),
+                "## Sub".to_string(),
+                "text".to_string(),
+            ],
+            HashMap::new(),
+            None,
+            true,
+            36,
+            theme,
+        );
+        let area = Rect::new(0, 0, 40, 10);
+        let mut buf = Buffer::empty(area);
+
+        editor.render(area, &mut buf);
+
+        let title_cell = buf.get(4, 2);
+        assert_eq!(title_cell.symbol(), "T");
+        assert_eq!(title_cell.fg, Color::Green);
+        assert!(title_cell.modifier.contains(Modifier::UNDERLINED));
+        assert_eq!(buf.get(5, 3).fg, Color::Magenta);
+        assert_ne!(buf.get(2, 4).fg, Color::Green);
+    }
+
+    #[test]
+    fn test_render_heading_keeps_link_cells() {
+        let mut theme = theme();
+        theme.main_heading = Color::Green;
+        let link = Link {
+            id: 1,
+            text_id: 0,
+            linked_id: 2,
+            row: 0,
+            start_col: 2,
+            end_col: 5,
+            saved: true,
+            updated: false,
+            deleted: false,
+            anchor: None,
+        };
+        let editor = Editor::new(
+            "Test Note".to_string(),
+            vec!["# Link".to_string()],
+            HashMap::from([(0, link)]),
+            Some(1),
+            true,
+            36,
+            theme,
+        );
+        let area = Rect::new(0, 0, 40, 10);
+        let mut buf = Buffer::empty(area);
+
+        editor.render(area, &mut buf);
+
+        assert_eq!(buf.get(2, 2).fg, Color::Green);
+        assert_ne!(buf.get(4, 2).fg, Color::Green);
+    }
 }
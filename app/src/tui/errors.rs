@@ -1,9 +1,4 @@
-use color_eyre::{
-    config::HookBuilder,
-    eyre::{self, eyre},
-};
-use std::error::Error;
-use std::fmt;
+use color_eyre::{config::HookBuilder, eyre};
 use std::panic;
 
 use crate::tui::utils;
@@ -30,30 +25,3 @@ pub fn install_hooks() -> color_eyre::Result<()> {
 
     Ok(())
 }
-
-#[derive(Debug)]
-pub(crate) struct DbError(pub Box<dyn Error + Send + Sync>);
-impl fmt::Display for DbError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
-
-impl Error for DbError {}
-
-impl From<eyre::Report> for DbError {
-    fn from(report: eyre::Report) -> Self {
-        DbError(report.into())
-    }
-}
-
-impl From<sqlx::Error> for DbError {
-    fn from(err: sqlx::Error) -> Self {
-        DbError(err.into())
-    }
-}
-
-pub(crate) fn create_db_error(msg: String) -> DbError {
-    let err = eyre!(msg);
-    DbError::from(err)
-}
@@ -1,24 +1,63 @@
 use color_eyre::eyre::{eyre, Context, Result};
-use crossterm::event::{self, Event, KeyEventKind};
-use log::{error, info};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::layout::Rect;
+use log::{error, info, warn};
+use sqlx::{Pool, Sqlite};
 use std::cmp;
-use std::collections::HashMap;
-use tuipaz_textarea::{Input, Key};
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tuipaz_textarea::{CursorMove, Input, Key, Link as TextAreaLink};
 
-use crate::db::db_mac::{DbMac, DbNoteLink, NoteIdentifier};
+use crate::db::{
+    db_mac::{DbNoteLink, NoteIdentifier},
+    errors::DuplicateTitleError,
+    import,
+    init_db::open_db,
+    store::{BodyRewrite, LinkChanges, NoteStore, Store},
+};
 
 use super::{
-    app::{ActiveWidget, App, AppState, ComponentState, Screen, SearchbarState, SidebarState},
+    app::{
+        ActiveWidget, App, AppState, ComponentState, Screen, SearchbarState, SidebarState,
+        ZenLayout, MAX_SIDEBAR_SIZE, MIN_SIDEBAR_SIZE,
+    },
+    auto_link::{wiki_links, WikiLink},
     buttons::ButtonAction,
-    editor::{EditorMode, Link},
-    inputs::{InputAction, UserInput},
-    note_list::{NoteListAction, NoteListMode},
+    commands::{parse_command, Command, SetOption},
+    config::{get_action_name, AutoLink, ConfirmQuit, Startup},
+    editor::{CommandState, EditorMode, Link, SavedUndo},
+    external,
+    graph::{GraphView, LinkGraph},
+    inputs::{capture_title, clean_title, numbered_title, InputAction, UserInput},
+    jump_list::{JumpList, JUMP_LIST_CAP},
+    link_list::LinkList,
+    link_preview::{LinkPreview, PREVIEW_LINES},
+    link_suggest::{link_trigger, LinkSuggestions},
+    note_list::{NoteList, NoteListAction, NoteListMode, NOTE_PAGE_SIZE},
+    palette::CommandPalette,
+    picker::{picker_input, Picker, PickerEntry, PickerEvent},
+    recent::push_recent,
+    rename::retitle_links,
+    searchbar::{ReplaceScope, SearchbarAction, Substitute, SEARCH_HISTORY_LEN},
+    session::Session,
+    stats::{VaultStats, MOST_LINKED_LIMIT},
+    templates::{expand_template, template_picker, today},
     user_messages::{MessageType, UserMessage},
+    utils::{self, Tui},
+    vaults::{parse_destination, vault_names, vault_path, vault_picker},
 };
 
-const DELETE_KEYS: [Key; 10] = [
+/// A leader sequence left unfinished this long is dropped
+const LEADER_TIMEOUT: Duration = Duration::from_secs(2);
+
+const DELETE_KEYS: [Key; 11] = [
     Key::Char('d'),
     Key::Char('w'),
+    Key::Char('u'),
     Key::Char('b'),
     Key::Char('j'),
     Key::Char('k'),
@@ -48,6 +87,36 @@ pub(crate) enum Action {
     DecreaseSidebar,
     InsertLink(Input),
     SwitchActiveWidget,
+    ShowHelp(Input),
+    NextTab,
+    PrevTab,
+    CloseTab,
+    OpenInTab,
+    ShowGraph,
+    ShowRecentNotes,
+    JumpBack,
+    JumpForward,
+    DuplicateNote,
+    ToggleReadOnly,
+    NewNoteFromTemplate,
+    EditExternally,
+    SwitchVault,
+    TogglePin,
+    ShowCommandPalette,
+    LinkExistingNote,
+    QuickCapture,
+    ShowLinkList,
+    ToggleZenMode,
+    TogglePreview,
+    ImportNotes,
+    ShowStats,
+    RevealNote,
+    PrevLink,
+    TagMarked,
+    ToggleMark(Input),
+    ShowPosition,
+    ExtractNote,
+    MoveNote,
     Confirm,
     Cancel,
     Activate(Input),
@@ -78,6 +147,36 @@ impl PartialEq for Action {
             (Action::DecreaseSidebar, Action::DecreaseSidebar) => true,
             (Action::InsertLink(_), Action::InsertLink(_)) => true,
             (Action::SwitchActiveWidget, Action::SwitchActiveWidget) => true,
+            (Action::ShowHelp(_), Action::ShowHelp(_)) => true,
+            (Action::ToggleMark(_), Action::ToggleMark(_)) => true,
+            (Action::NextTab, Action::NextTab) => true,
+            (Action::PrevTab, Action::PrevTab) => true,
+            (Action::CloseTab, Action::CloseTab) => true,
+            (Action::OpenInTab, Action::OpenInTab) => true,
+            (Action::ShowGraph, Action::ShowGraph) => true,
+            (Action::ShowRecentNotes, Action::ShowRecentNotes) => true,
+            (Action::JumpBack, Action::JumpBack) => true,
+            (Action::JumpForward, Action::JumpForward) => true,
+            (Action::DuplicateNote, Action::DuplicateNote) => true,
+            (Action::ToggleReadOnly, Action::ToggleReadOnly) => true,
+            (Action::NewNoteFromTemplate, Action::NewNoteFromTemplate) => true,
+            (Action::EditExternally, Action::EditExternally) => true,
+            (Action::SwitchVault, Action::SwitchVault) => true,
+            (Action::TogglePin, Action::TogglePin) => true,
+            (Action::ShowCommandPalette, Action::ShowCommandPalette) => true,
+            (Action::LinkExistingNote, Action::LinkExistingNote) => true,
+            (Action::QuickCapture, Action::QuickCapture) => true,
+            (Action::ShowLinkList, Action::ShowLinkList) => true,
+            (Action::ToggleZenMode, Action::ToggleZenMode) => true,
+            (Action::TogglePreview, Action::TogglePreview) => true,
+            (Action::ImportNotes, Action::ImportNotes) => true,
+            (Action::ShowStats, Action::ShowStats) => true,
+            (Action::RevealNote, Action::RevealNote) => true,
+            (Action::PrevLink, Action::PrevLink) => true,
+            (Action::TagMarked, Action::TagMarked) => true,
+            (Action::ShowPosition, Action::ShowPosition) => true,
+            (Action::ExtractNote, Action::ExtractNote) => true,
+            (Action::MoveNote, Action::MoveNote) => true,
             (Action::Confirm, Action::Confirm) => true,
             (Action::Cancel, Action::Cancel) => true,
             (Action::Activate(_), Action::Activate(_)) => true,
@@ -95,117 +194,305 @@ impl PartialEq for Action {
 
 impl Eq for Action {}
 
+impl Action {
+    /// Actions that act on the key itself carry the one that was pressed
+    fn with_input(self, input: Input) -> Self {
+        match self {
+            Action::ToggleSearchbar(_) => Action::ToggleSearchbar(input),
+            Action::InsertLink(_) => Action::InsertLink(input),
+            Action::ShowHelp(_) => Action::ShowHelp(input),
+            Action::ToggleMark(_) => Action::ToggleMark(input),
+            Action::Activate(_) => Action::Activate(input),
+            Action::Up(_) => Action::Up(input),
+            Action::Down(_) => Action::Down(input),
+            Action::Edit(_) => Action::Edit(input),
+            action => action,
+        }
+    }
+
+    /// The key pressed for an action that carries one
+    fn input(&self) -> Option<Input> {
+        match self {
+            Action::ToggleSearchbar(input)
+            | Action::InsertLink(input)
+            | Action::ShowHelp(input)
+            | Action::ToggleMark(input)
+            | Action::Activate(input)
+            | Action::Up(input)
+            | Action::Down(input)
+            | Action::Edit(input) => Some(*input),
+            _ => None,
+        }
+    }
+}
+
 pub(crate) struct Events {}
 
 impl Events {
     pub(crate) async fn handle_events(app: &mut App<'_>) -> Result<()> {
-        match event::read()? {
+        let event = event::read()?;
+        if let Some(lock) = app.idle_lock.as_mut() {
+            lock.last_input = Instant::now();
+        }
+
+        match event {
+            Event::Key(key_event)
+                if key_event.kind == KeyEventKind::Press && app.current_screen == Screen::Locked =>
+            {
+                Self::locked_input(app, key_event.into());
+                Ok(())
+            }
+            Event::Mouse(_) if app.current_screen == Screen::Locked => Ok(()),
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                let action = Self::handle_key_event(key_event.into());
+                // Shift-Tab comes as its own key code, which the textarea has no key for
+                let input = match key_event.code {
+                    KeyCode::BackTab => Input {
+                        key: Key::Tab,
+                        shift: true,
+                        ..Default::default()
+                    },
+                    _ => key_event.into(),
+                };
+                let action = match Self::leader_action(app, input) {
+                    Some(action) => action,
+                    None => Self::handle_key_event(input, &app.keymap),
+                };
                 let result = Self::execute_action(app, action).await;
                 result.wrap_err_with(|| format!("handling key event failed:\n{key_event:#?}"))
             }
+            Event::Mouse(mouse_event) => {
+                let result = Self::handle_mouse_event(app, mouse_event).await;
+                result.wrap_err_with(|| format!("handling mouse event failed:\n{mouse_event:#?}"))
+            }
+            Event::Resize(width, _) => {
+                Self::handle_resize(app, width);
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
 
-    fn handle_key_event(input: Input) -> Action {
+    /// Passphrase typing on the lock screen, keys bypass the keymap so none of them
+    /// reach the notes underneath
+    fn locked_input(app: &mut App<'_>, input: Input) {
+        let Some(lock) = app.idle_lock.as_mut() else {
+            return;
+        };
         match input {
-            Input {
-                key: Key::Char('q'),
-                alt: true,
-                ..
-            } => Action::ShowExitScreen,
-            Input { key: Key::Esc, .. } => Action::Esc,
-            Input {
-                key: Key::Tab,
-                alt: false,
-                ..
-            } => Action::Tab,
             Input {
                 key: Key::Enter, ..
-            } => Action::Activate(input),
-            Input {
-                key: Key::Char('s'),
-                alt: true,
-                ..
-            } => Action::SaveNote,
-            Input {
-                key: Key::Char('l'),
-                alt: true,
-                ..
-            } => Action::LoadNote,
-            Input {
-                key: Key::Char('n'),
-                alt: true,
-                ..
-            } => Action::NewNote,
-            Input {
-                key: Key::Char('t'),
-                alt: true,
-                ..
-            } => Action::NewTitle,
-            Input {
-                key: Key::Char('d'),
-                alt: true,
-                ..
-            } => Action::DeleteNote,
-            Input {
-                key: Key::Char('/'),
-                ..
-            } => Action::ToggleSearchbar(input),
-            Input {
-                key: Key::Char('f'),
-                alt: true,
-                ..
-            } => Action::ToggleSidebar,
-            Input {
-                key: Key::Char(','),
-                alt: true,
-                ..
-            } => Action::IncreaseSidebar,
-            Input {
-                key: Key::Char('.'),
-                alt: true,
-                ..
-            } => Action::DecreaseSidebar,
+            } => {
+                if let Some((current, prev)) = lock.unlock() {
+                    app.current_screen = current;
+                    app.prev_screen = prev;
+                }
+            }
             Input {
-                key: Key::Char(']'),
+                key: Key::Backspace,
                 ..
-            } => Action::InsertLink(input),
+            } => {
+                lock.entry.pop();
+            }
+            Input { key: Key::Esc, .. } => lock.entry.clear(),
             Input {
-                key: Key::Tab,
-                alt: true,
+                key: Key::Char(c),
+                ctrl: false,
+                alt: false,
                 ..
-            } => Action::SwitchActiveWidget,
+            } => lock.entry.push(c),
+            _ => {}
+        }
+    }
+
+    /// Background tabs are reflowed when they are focused again
+    fn handle_resize(app: &mut App<'_>, width: u16) {
+        info!("handle_resize::width: {}", width);
+        app.max_col = width.saturating_sub(4);
+        app.resize_editor();
+        let max_col = app.get_max_col();
+        app.searchbar.set_max_col(max_col);
+    }
+
+    /// Collects the keys after the leader until they spell a leader binding. A key that
+    /// can't lead to one, or Esc, cancels the sequence and is dropped. None leaves the
+    /// key to the keymap.
+    fn leader_action(app: &mut App, input: Input) -> Option<Action> {
+        let Some((mut keys, started)) = app.pending_leader.take() else {
+            let starts_sequence = input.key == Key::Char(app.leader)
+                && !input.ctrl
+                && !input.alt
+                && !app.leader_bindings.is_empty()
+                && app.current_screen == Screen::Main
+                && app.active_widget == Some(ActiveWidget::Editor)
+                && app.editor.mode == EditorMode::Normal
+                && app.editor.cmd_state == CommandState::NoCommand
+                && app.editor.num_buf.is_empty()
+                && app.command_line.is_none();
+            if starts_sequence {
+                app.pending_leader = Some((String::new(), Instant::now()));
+                return Some(Action::Null);
+            }
+            return None;
+        };
+
+        if started.elapsed() > LEADER_TIMEOUT {
+            info!("leader_action::sequence {:?} timed out", keys);
+            return None;
+        }
+        let Input {
+            key: Key::Char(c),
+            ctrl: false,
+            alt: false,
+            ..
+        } = input
+        else {
+            return Some(Action::Null);
+        };
+
+        keys.push(c);
+        if let Some(action) = app.leader_bindings.get(&keys) {
+            return Some(*action);
+        }
+        if app.leader_bindings.keys().any(|binding| binding.starts_with(&keys)) {
+            app.pending_leader = Some((keys, started));
+        }
+        Some(Action::Null)
+    }
+
+    /// Every binding comes from the keymap, so rebinding an action moves it off its
+    /// default key. j and k stay as list movement next to the bound arrows. When a
+    /// config binds one key to several actions, the action name first in sort order wins.
+    fn handle_key_event(input: Input, keymap: &HashMap<Action, Input>) -> Action {
+        let bound = keymap
+            .iter()
+            .filter(|(action, binding)| {
+                **action != Action::Null && Self::matches_binding(&input, binding)
+            })
+            .map(|(action, _)| action)
+            .min_by_key(|action| get_action_name(action))
+            .map(|action| action.with_input(input));
+        if let Some(action) = bound {
+            return action;
+        }
+
+        match input {
             Input {
-                key: Key::Backspace,
-                ..
-            } => Action::DeleteChar,
-            Input { key: Key::Down, .. }
-            | Input {
                 key: Key::Char('j'),
+                ctrl: false,
+                alt: false,
                 ..
             } => Action::Down(input),
-            Input { key: Key::Up, .. }
-            | Input {
+            Input {
                 key: Key::Char('k'),
+                ctrl: false,
+                alt: false,
                 ..
             } => Action::Up(input),
-            Input {
-                key: Key::Char('y'),
-                ..
-            } => Action::Confirm,
-            Input {
-                key: Key::Char('n'),
-                ..
-            } => Action::Cancel,
             input => Action::Edit(input),
         }
     }
 
+    async fn handle_mouse_event(app: &mut App<'_>, mouse_event: MouseEvent) -> Result<()> {
+        let (column, row) = (mouse_event.column, mouse_event.row);
+        let clicked_in = |area: Rect| {
+            (area.x..area.x + area.width).contains(&column)
+                && (area.y..area.y + area.height).contains(&row)
+        };
+
+        match (app.current_screen, mouse_event.kind) {
+            (Screen::Main, MouseEventKind::Down(MouseButton::Left)) => {
+                if app.sidebar_state == SidebarState::Open && clicked_in(app.note_list_area) {
+                    app.set_active_widget(ActiveWidget::Sidebar);
+                    if let Some(idx) = app.note_list.index_at(app.note_list_area, row) {
+                        app.note_list.selected = idx;
+                        // Clicking a folder opens or closes it
+                        if !app.note_list.toggle_folder() {
+                            if let Some(id) = app.note_list.selected_id() {
+                                Self::load_note(app, id).await?;
+                            }
+                        }
+                    }
+                } else if clicked_in(app.editor_area) {
+                    app.set_active_widget(ActiveWidget::Editor);
+                    app.editor.handle_click(app.editor_area, column, row);
+                }
+            }
+            (Screen::LoadNote, MouseEventKind::Down(MouseButton::Left)) => {
+                if let Some(idx) = app.note_list.index_at(app.note_list_area, row) {
+                    app.note_list.selected = idx;
+                    if let Some(nid) = app.note_list.note_identifiers.get(idx) {
+                        let id = nid.id;
+                        Self::load_note(app, id).await?;
+                    }
+                }
+            }
+            (Screen::Main | Screen::LoadNote, MouseEventKind::ScrollDown) => {
+                match (app.current_screen, app.active_widget) {
+                    (Screen::LoadNote, _) | (_, Some(ActiveWidget::Sidebar)) => {
+                        Self::note_list_next(app).await?
+                    }
+                    (_, Some(ActiveWidget::Editor)) => app.editor.handle_input(Input {
+                        key: Key::Down,
+                        ..Default::default()
+                    }),
+                    _ => {}
+                }
+            }
+            (Screen::Main | Screen::LoadNote, MouseEventKind::ScrollUp) => {
+                match (app.current_screen, app.active_widget) {
+                    (Screen::LoadNote, _) | (_, Some(ActiveWidget::Sidebar)) => {
+                        Self::note_list_prev(app).await?
+                    }
+                    (_, Some(ActiveWidget::Editor)) => app.editor.handle_input(Input {
+                        key: Key::Up,
+                        ..Default::default()
+                    }),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Shift is already part of the char for printable keys, so it's only compared for
+    // the rest, where it tells Tab from Shift-Tab
+    fn matches_binding(input: &Input, binding: &Input) -> bool {
+        input.key == binding.key
+            && input.ctrl == binding.ctrl
+            && input.alt == binding.alt
+            && (matches!(input.key, Key::Char(_)) || input.shift == binding.shift)
+    }
+
     async fn execute_action(app: &mut App<'_>, action: Action) -> Result<()> {
+        if Self::command_line_input(app, &action) {
+            return Ok(());
+        }
+        // The help key is printable, so it should still be typed when editing text
+        let action = match action {
+            Action::ShowHelp(input) if !Self::can_show_help(app) => Action::Edit(input),
+            Action::ToggleMark(input) if !Self::in_note_list(app) => Action::Edit(input),
+            action => action,
+        };
+        if Self::link_suggestions_input(app, &action).await? {
+            return Ok(());
+        }
+
         match (app.current_screen, action) {
+            (Screen::Help, Action::Esc) | (Screen::Help, Action::ShowHelp(_)) => {
+                app.switch_to_prev_screen();
+            }
+            (Screen::Help, Action::Up(_)) => {
+                app.help_scroll = app.help_scroll.saturating_sub(1);
+            }
+            (Screen::Help, Action::Down(_)) => {
+                app.help_scroll = app.help_scroll.saturating_add(1);
+            }
+            (_, Action::ShowHelp(_)) => {
+                app.prev_screen = app.current_screen;
+                app.help_scroll = 0;
+                app.current_screen = Screen::Help;
+            }
             (Screen::Welcome, Action::ShowExitScreen) => {
                 app.prev_screen = app.current_screen;
                 Self::show_exit_screen(app);
@@ -224,18 +511,100 @@ impl Events {
                 app.prev_screen = app.current_screen;
                 Self::show_exit_screen(app);
             }
+            (Screen::Main, Action::SaveNote) if app.editor.read_only => {
+                Self::warn_user(app, "Note is read-only, <Alt-R> to make it editable");
+            }
+            (Screen::Main, Action::ToggleReadOnly) => app.editor.toggle_read_only(),
+            (Screen::Main, Action::EditExternally) if app.editor.read_only => {
+                Self::warn_user(app, "Note is read-only, <Alt-R> to make it editable");
+            }
+            // The terminal is only reachable from the main loop, which picks this up
+            (Screen::Main, Action::EditExternally) => app.external_edit_requested = true,
             (Screen::Main, Action::SaveNote) => {
                 let has_links = !matches!(app.editor.body.links.len(), 0);
                 let title = app.editor.title.clone();
                 let body = app.editor.body.lines().join("\n");
                 let note_id = app.editor.note_id;
 
-                Self::save_note(app, &title, &body, has_links, note_id).await?;
+                if let Err(err) = Self::save_note(app, &title, &body, has_links, note_id).await {
+                    if !err.is::<DuplicateTitleError>() {
+                        return Err(err);
+                    }
+                }
             }
             (Screen::Main, Action::LoadNote) => {
                 app.prev_screen = app.current_screen;
                 app.switch_to_load_note();
             }
+            (Screen::Main, Action::DuplicateNote) => {
+                Self::duplicate_note(app).await?;
+            }
+            (Screen::Main, Action::TogglePin) => {
+                let note_id = match app.active_widget {
+                    Some(ActiveWidget::Sidebar) => Self::selected_note_id(app),
+                    _ => app.editor.note_id,
+                };
+                if let Some(id) = note_id {
+                    let pinned = app.note_list.toggle_pin(id);
+                    app.store.set_pinned(id, pinned).await?;
+                }
+            }
+            (Screen::Main | Screen::LoadNote, Action::ToggleMark(_)) => {
+                if !app.note_list.toggle_mark() {
+                    Self::warn_user(app, "No note is selected");
+                }
+            }
+            (Screen::Main | Screen::LoadNote, Action::TagMarked) if Self::in_note_list(app) => {
+                match app.note_list.marked.is_empty() {
+                    true => Self::warn_user(app, "No notes are marked, <Space> marks them"),
+                    false => {
+                        app.prev_screen = app.current_screen;
+                        app.user_input =
+                            UserInput::new(ComponentState::Active, InputAction::TagNotes);
+                        app.switch_to_new_note(InputAction::TagNotes);
+                    }
+                }
+            }
+            (Screen::Main, Action::LinkExistingNote) if app.editor.read_only => {
+                Self::warn_user(app, "Note is read-only, <Alt-R> to make it editable");
+            }
+            (Screen::Main, Action::LinkExistingNote) => {
+                app.prev_screen = app.current_screen;
+                app.current_screen = Screen::LinkExistingNote;
+                app.note_list.set_mode(NoteListMode::Fullscreen);
+                app.note_list.set_action(NoteListAction::InsertLink);
+                app.set_active_widget(ActiveWidget::NoteList);
+            }
+            (Screen::Main, Action::ExtractNote) => Self::open_extract_prompt(app),
+            (Screen::Main, Action::MoveNote) => Self::open_move_prompt(app),
+            (Screen::Main, Action::ShowLinkList) => {
+                app.link_list = Some(LinkList::new(
+                    &app.editor.links,
+                    &app.note_list.note_identifiers,
+                ));
+                app.prev_screen = app.current_screen;
+                app.current_screen = Screen::LinkList;
+            }
+            (Screen::Main, Action::ShowCommandPalette) => {
+                app.command_palette = Some(CommandPalette::new(app.current_screen, &app.keymap));
+                app.prev_screen = app.current_screen;
+                app.current_screen = Screen::CommandPalette;
+            }
+            (Screen::Main, Action::SwitchVault) => {
+                app.vault_switcher = Some(vault_picker(&app.vaults, &app.active_vault));
+                app.prev_screen = app.current_screen;
+                app.current_screen = Screen::VaultSwitcher;
+            }
+            (Screen::Main, Action::NewNoteFromTemplate) => {
+                match app.templates.is_empty() {
+                    true => Self::warn_user(app, "No templates, add some under [templates] in the config"),
+                    false => {
+                        app.template_picker = Some(template_picker(&app.templates));
+                        app.prev_screen = app.current_screen;
+                        app.current_screen = Screen::TemplatePicker;
+                    }
+                }
+            }
             (Screen::Main, Action::NewNote) => {
                 app.prev_screen = app.current_screen;
                 app.switch_to_new_note(InputAction::Note);
@@ -244,6 +613,16 @@ impl Events {
                 app.prev_screen = app.current_screen;
                 app.switch_to_new_note(InputAction::NoteTitle);
             }
+            (Screen::Main, Action::QuickCapture) => {
+                app.prev_screen = app.current_screen;
+                app.user_input = UserInput::new(ComponentState::Active, InputAction::QuickCapture);
+                app.switch_to_new_note(InputAction::QuickCapture);
+            }
+            (Screen::Main, Action::ImportNotes) => {
+                app.prev_screen = app.current_screen;
+                app.user_input = UserInput::new(ComponentState::Active, InputAction::ImportDir);
+                app.switch_to_new_note(InputAction::ImportDir);
+            }
             (Screen::Main, Action::DeleteChar) => {
                 let input = Input {
                     key: Key::Backspace,
@@ -251,12 +630,13 @@ impl Events {
                 };
                 match app.active_widget {
                     Some(ActiveWidget::Editor) => app.editor.handle_input(input),
-                    Some(ActiveWidget::Searchbar) => {
-                        app.searchbar.input.delete_char();
-                    }
+                    Some(ActiveWidget::Searchbar) => Self::searchbar_input(app, input),
                     Some(_) | None => {}
                 }
             }
+            (Screen::Main, Action::DeleteNote) if !app.confirm_delete => {
+                Self::delete_open_note(app).await?;
+            }
             (Screen::Main, Action::DeleteNote) => {
                 app.prev_screen = app.current_screen;
                 app.current_screen = Screen::DeleteNoteConfirmation;
@@ -266,28 +646,78 @@ impl Events {
                         app.editor.title
                     ),
                     MessageType::Warning,
-                    None,
                 );
             }
             (Screen::Main, Action::ToggleSearchbar(input)) => {
-                if app.editor.mode == EditorMode::Normal {
+                // `/` separates the parts of a substitute command
+                if app.active_widget == Some(ActiveWidget::Searchbar) {
+                    Self::searchbar_input(app, input);
+                } else if app.editor.mode == EditorMode::Normal {
                     Self::toggle_searchbar(app);
                 } else {
                     app.editor.handle_input(input);
                 }
             }
+            (Screen::Main, Action::NextTab) => {
+                let next = (app.active_tab + 1) % app.tabs.len();
+                Self::focus_tab(app, next);
+            }
+            (Screen::Main, Action::PrevTab) => {
+                let prev = (app.active_tab + app.tabs.len() - 1) % app.tabs.len();
+                Self::focus_tab(app, prev);
+            }
+            (Screen::Main, Action::CloseTab) => match app.editor.is_dirty() {
+                true => {
+                    app.prev_screen = app.current_screen;
+                    app.current_screen = Screen::CloseTabConfirmation;
+                    app.user_msg = UserMessage::new(
+                        format!(
+                            "{} has unsaved changes, close it anyway? (y/n)",
+                            app.editor.title.trim()
+                        ),
+                        MessageType::Warning,
+                    );
+                }
+                false => Self::close_tab(app),
+            },
+            (Screen::Main, Action::OpenInTab) => {
+                if app.active_widget == Some(ActiveWidget::Sidebar) {
+                    if let Some(id) = Self::selected_note_id(app) {
+                        Self::open_note_in_tab(app, id).await?;
+                    }
+                }
+            }
             (Screen::Main, Action::ToggleSidebar) => {
                 Self::toggle_sidebar(app);
             }
+            (Screen::Main, Action::RevealNote) => Self::reveal_note(app).await?,
+            // Tab reaches the editor as the next link motion, this is its reverse
+            (Screen::Main, Action::PrevLink) => {
+                if app.active_widget == Some(ActiveWidget::Editor)
+                    && app.editor.mode != EditorMode::Insert
+                {
+                    app.editor.jump_to_link(false);
+                }
+            }
+            (Screen::Main, Action::ShowPosition) => {
+                if app.active_widget == Some(ActiveWidget::Editor) {
+                    let report = app.editor.position_report();
+                    app.show_status(report, MessageType::Info);
+                }
+            }
+            (Screen::Main, Action::ToggleZenMode) => Self::toggle_zen_mode(app),
+            (Screen::Main, Action::TogglePreview) => app.editor.toggle_markdown_preview(),
             (Screen::Main, Action::IncreaseSidebar) => match app.sidebar_state {
                 SidebarState::Open => {
-                    app.sidebar_size = cmp::min(app.sidebar_size + 2, 70);
+                    app.sidebar_size = cmp::min(app.sidebar_size + 2, MAX_SIDEBAR_SIZE);
+                    app.resize_editor();
                 }
                 SidebarState::Hidden(_) => {}
             },
             (Screen::Main, Action::DecreaseSidebar) => match app.sidebar_state {
                 SidebarState::Open => {
-                    app.sidebar_size = cmp::max(app.sidebar_size - 2, 12);
+                    app.sidebar_size = cmp::max(app.sidebar_size - 2, MIN_SIDEBAR_SIZE);
+                    app.resize_editor();
                 }
                 SidebarState::Hidden(_) => {}
             },
@@ -296,24 +726,7 @@ impl Events {
 
                 // If there is a new link in the textarea
                 if app.editor.body.new_link {
-                    app.pending_link = Some(
-                        *app.editor
-                            .body
-                            .links
-                            .get(&(app.editor.body.next_link_id - 1))
-                            .expect("Link should be present"),
-                    );
-
-                    // Set the user_input widget to create a new linked note
-                    app.prev_screen = app.current_screen;
-                    app.current_screen = Screen::NewLinkedNote;
-                    app.user_input.set_action(InputAction::LinkedNote);
-                    app.user_input.set_state(ComponentState::Active);
-                    app.active_widget = Some(ActiveWidget::NoteTitleInput);
-                    app.note_list.set_state(ComponentState::Inactive);
-                    app.note_list.set_mode(NoteListMode::Fullscreen);
-                    app.note_list.set_action(NoteListAction::LinkNote);
-                    app.editor.body.new_link = false;
+                    Self::open_linked_note_prompt(app);
                 }
             }
             (Screen::Main, Action::SwitchActiveWidget) => match app.active_widget {
@@ -325,40 +738,38 @@ impl Events {
                 Some(ActiveWidget::Editor) => {
                     match app.editor.body.in_link(app.editor.body.cursor()) {
                         Some(link_id) => {
-                            let linked_note_id = app
-                                .editor
-                                .links
-                                .values()
-                                .find(|link| link.text_id == link_id as i64)
-                                .expect("Link should be set up")
-                                .linked_id;
-
-                            Self::load_note(app, linked_note_id).await?;
+                            Self::follow_link(app, link_id).await?;
                         }
+                        // Insert mode handles auto-indent
+                        None if app.editor.mode == EditorMode::Insert => {
+                            app.editor.handle_input(input);
+                        }
+                        None if app.editor.read_only => {}
                         None => {
                             app.editor.body.input(input);
                         }
                     }
                 }
                 Some(ActiveWidget::Sidebar) => {
-                    let note_idx = app.note_list.selected;
-                    let id = app.note_list.note_identifiers[note_idx].id;
-                    Self::load_note(app, id).await?;
-                }
-                Some(ActiveWidget::Searchbar) => {
-                    app.searchbar.clear_search();
-                    Self::toggle_searchbar(app);
+                    if !app.note_list.toggle_folder() {
+                        if let Some(id) = Self::selected_note_id(app) {
+                            Self::load_note(app, id).await?;
+                        }
+                    }
                 }
+                Some(ActiveWidget::Searchbar) => Self::searchbar_input(app, input),
                 Some(_) | None => {}
             },
             (Screen::Main, Action::Up(input)) => match app.active_widget {
                 Some(ActiveWidget::Editor) => app.editor.handle_input(input),
-                Some(ActiveWidget::Sidebar) => app.note_list.prev(),
+                Some(ActiveWidget::Sidebar) => Self::note_list_prev(app).await?,
+                Some(ActiveWidget::Searchbar) => Self::searchbar_input(app, input),
                 Some(_) | None => {}
             },
             (Screen::Main, Action::Down(input)) => match app.active_widget {
                 Some(ActiveWidget::Editor) => app.editor.handle_input(input),
-                Some(ActiveWidget::Sidebar) => app.note_list.next(),
+                Some(ActiveWidget::Sidebar) => Self::note_list_next(app).await?,
+                Some(ActiveWidget::Searchbar) => Self::searchbar_input(app, input),
                 Some(_) | None => {}
             },
             (Screen::Main, Action::Confirm)
@@ -374,13 +785,7 @@ impl Events {
                     Some(ActiveWidget::Editor) => {
                         app.editor.handle_input(*input);
                     }
-                    Some(ActiveWidget::Searchbar) => {
-                        app.searchbar.handle_input(*input);
-                        if app.searchbar.state == ComponentState::Inactive {
-                            app.editor.searchbar_open = false;
-                            app.searchbar_state = SearchbarState::Hidden;
-                        }
-                    }
+                    Some(ActiveWidget::Searchbar) => Self::searchbar_input(app, *input),
                     _ => {}
                 }
             }
@@ -388,6 +793,16 @@ impl Events {
                 Some(ActiveWidget::Editor) => {
                     app.editor.handle_input(input);
 
+                    if app.editor.preview_requested {
+                        app.editor.preview_requested = false;
+                        Self::show_link_preview(app).await?;
+                    }
+
+                    if app.editor.link_word_requested {
+                        app.editor.link_word_requested = false;
+                        Self::link_word(app).await?;
+                    }
+
                     if let Some(key) = DELETE_KEYS.iter().find(|&&k| k == input.key) {
                         Self::check_link_deletion(app, key);
                     }
@@ -396,33 +811,47 @@ impl Events {
                         Self::check_link_edits(app);
                     } else if input.key == Key::Char('p') {
                         Self::check_link_paste(app);
+                    } else if input.key == Key::Char('n') || input.key == Key::Char('N') {
+                        Self::update_search_counter(app);
                     }
 
                     if !app.editor.links.is_empty() {
                         Self::check_link_moved(app);
                     }
                 }
-                Some(ActiveWidget::Searchbar) => {
-                    app.searchbar.input.input(input);
-                    let search_pattern = &app.searchbar.get_search_text();
-                    match app.editor.body.set_search_pattern(search_pattern) {
-                        Ok(_) => info!("Searching for {:?}", search_pattern),
-                        Err(e) => error!("Error searching for {:?}: {:?}", search_pattern, e),
-                    }
-                }
+                Some(ActiveWidget::Searchbar) => Self::searchbar_input(app, input),
                 Some(_) | None => {}
             },
             (Screen::NewNote, Action::ShowExitScreen) => {
                 app.prev_screen = app.current_screen;
                 Self::show_exit_screen(app);
             }
+            (Screen::NewNote, Action::Esc)
+                if matches!(app.user_input.get_action(), InputAction::TagNotes) =>
+            {
+                Self::close_tag_input(app);
+            }
             (Screen::NewNote, Action::Esc) => {
+                if let InputAction::QuickCapture
+                | InputAction::ImportDir
+                | InputAction::ExtractNote
+                | InputAction::MoveNote = app.user_input.get_action()
+                {
+                    app.user_input = UserInput::new(ComponentState::Active, InputAction::Note);
+                }
+                app.pending_template = None;
+                app.pending_extract = None;
                 app.switch_to_prev_screen();
                 app.active_widget = Some(ActiveWidget::Editor);
             }
             (Screen::NewNote, Action::Activate(_)) => match app.user_input.get_action() {
-                InputAction::NoteTitle => Self::input_new_note_title(app),
+                InputAction::NoteTitle => Self::input_new_note_title(app).await?,
                 InputAction::Note => Self::input_new_note(app, false).await?,
+                InputAction::QuickCapture => Self::quick_capture(app).await?,
+                InputAction::ImportDir => Self::import_notes(app).await?,
+                InputAction::TagNotes => Self::tag_marked_notes(app).await?,
+                InputAction::ExtractNote => Self::extract_note(app).await?,
+                InputAction::MoveNote => Self::move_open_note(app).await?,
                 _ => {}
             },
             (Screen::NewNote, Action::DeleteChar) => {
@@ -442,40 +871,60 @@ impl Events {
             }
             (Screen::NewNote, Action::Edit(input)) => {
                 app.user_input.text.input(input);
+                if app.user_input.get_state() == ComponentState::Error {
+                    app.user_input.set_state(ComponentState::Active);
+                }
             }
             (Screen::NewLinkedNote, Action::ShowExitScreen) => {
                 app.prev_screen = app.current_screen;
                 Self::show_exit_screen(app);
             }
             (Screen::NewLinkedNote, Action::Esc) => {
-                app.switch_to_prev_screen();
-                app.note_list.set_mode(NoteListMode::Sidebar);
-                app.note_list.set_action(NoteListAction::LoadNote);
-                app.active_widget = Some(ActiveWidget::Editor);
-                app.editor
-                    .body
-                    .delete_link(app.editor.body.next_link_id - 1);
+                Self::cancel_linked_note(app);
             }
             (Screen::NewLinkedNote, Action::Activate(_)) => {
                 if app.active_widget == Some(ActiveWidget::NoteTitleInput) {
                     Self::input_new_note(app, true).await?;
                 } else if app.active_widget == Some(ActiveWidget::NoteList) {
-                    let selected = app.note_list.selected;
-                    let nid = &app.note_list.note_identifiers[selected];
-                    Self::link_note(app, nid.id);
+                    if let Some(id) = app.note_list.selected_id() {
+                        Self::link_note(app, id);
+                    }
                 }
             }
+            // j/k/y/n are letters in a title or filter, only the arrows move the selection
+            (
+                Screen::NewLinkedNote,
+                Action::Down(
+                    input @ Input {
+                        key: Key::Char(_), ..
+                    },
+                )
+                | Action::Up(
+                    input @ Input {
+                        key: Key::Char(_), ..
+                    },
+                ),
+            ) => {
+                Self::link_target_input(app, input).await?;
+            }
+            (Screen::NewLinkedNote, Action::Confirm | Action::Cancel) => {
+                let input = *app
+                    .keymap
+                    .get(&action)
+                    .expect("keymap should contain bindings for all actions");
+                Self::link_target_input(app, input).await?;
+            }
             (Screen::NewLinkedNote, Action::Down(_)) => {
                 if app.active_widget == Some(ActiveWidget::NoteList) {
-                    app.note_list.next();
+                    Self::note_list_next(app).await?;
                 }
             }
             (Screen::NewLinkedNote, Action::Up(_)) => {
                 if app.active_widget == Some(ActiveWidget::NoteList) {
-                    app.note_list.prev();
+                    Self::note_list_prev(app).await?;
                 }
             }
-            (Screen::NewLinkedNote, Action::SwitchActiveWidget) => {
+            (Screen::NewLinkedNote, Action::SwitchActiveWidget | Action::Tab) => {
                 if app.active_widget == Some(ActiveWidget::NoteList) {
                     app.set_active_widget(ActiveWidget::NoteTitleInput);
                 } else if app.active_widget == Some(ActiveWidget::NoteTitleInput) {
@@ -488,13 +937,56 @@ impl Events {
                     if app.user_input.get_state() == ComponentState::Error {
                         app.user_input.set_state(ComponentState::Active);
                     }
+                } else if app.active_widget == Some(ActiveWidget::NoteList) {
+                    app.note_list.pop_filter();
                 }
             }
             (Screen::NewLinkedNote, Action::Edit(input)) => {
-                if app.active_widget == Some(ActiveWidget::NoteTitleInput) {
-                    app.user_input.text.input(input);
+                Self::link_target_input(app, input).await?;
+            }
+            (Screen::LinkExistingNote, Action::Esc) => {
+                Self::close_link_picker(app);
+            }
+            (Screen::LinkExistingNote, Action::Activate(_)) => {
+                if let Some(id) = Self::selected_note_id(app) {
+                    Self::close_link_picker(app);
+                    Self::insert_link_to_note(app, id);
                 }
             }
+            (
+                Screen::LinkExistingNote,
+                Action::Down(
+                    input @ Input {
+                        key: Key::Char(_), ..
+                    },
+                )
+                | Action::Up(
+                    input @ Input {
+                        key: Key::Char(_), ..
+                    },
+                ),
+            ) => {
+                Self::link_target_input(app, input).await?;
+            }
+            (Screen::LinkExistingNote, Action::Confirm | Action::Cancel) => {
+                let input = *app
+                    .keymap
+                    .get(&action)
+                    .expect("keymap should contain bindings for all actions");
+                Self::link_target_input(app, input).await?;
+            }
+            (Screen::LinkExistingNote, Action::Down(_)) => {
+                Self::note_list_next(app).await?;
+            }
+            (Screen::LinkExistingNote, Action::Up(_)) => {
+                Self::note_list_prev(app).await?;
+            }
+            (Screen::LinkExistingNote, Action::DeleteChar) => {
+                app.note_list.pop_filter();
+            }
+            (Screen::LinkExistingNote, Action::Edit(input)) => {
+                Self::link_target_input(app, input).await?;
+            }
             (Screen::LoadNote, Action::ShowExitScreen) => {
                 app.prev_screen = app.current_screen;
                 Self::show_exit_screen(app);
@@ -503,55 +995,253 @@ impl Events {
                 app.switch_to_prev_screen();
             }
             (Screen::LoadNote, Action::Down(_)) => {
-                app.note_list.next();
+                Self::note_list_next(app).await?;
             }
             (Screen::LoadNote, Action::Up(_)) => {
-                app.note_list.prev();
+                Self::note_list_prev(app).await?;
             }
             (Screen::LoadNote, Action::Activate(_)) => {
-                let note_idx = app.note_list.selected;
-                let id = app.note_list.note_identifiers[note_idx].id;
-                Self::load_note(app, id).await?;
+                if let Some(id) = Self::selected_note_id(app) {
+                    Self::load_note(app, id).await?;
+                }
             }
-            (Screen::Popup, _) => {
-                if let Some(screen) = app.user_msg.next_screen {
-                    app.current_screen = screen;
-                } else {
-                    app.current_screen = app.prev_screen;
+            (Screen::LoadNote, Action::OpenInTab) => {
+                if let Some(id) = Self::selected_note_id(app) {
+                    Self::open_note_in_tab(app, id).await?;
                 }
             }
-            (Screen::DeleteNoteConfirmation, Action::Esc) => {
+            (Screen::Main, Action::ShowGraph) | (Screen::LoadNote, Action::ShowGraph) => {
+                Self::show_graph(app).await?;
+            }
+            (Screen::Main, Action::ShowRecentNotes) => {
+                Self::show_recent_notes(app).await?;
+            }
+            (Screen::Main, Action::ShowStats) => {
+                Self::show_stats(app).await?;
+            }
+            (Screen::Main, Action::JumpBack) => {
+                Self::jump(app, true).await?;
+            }
+            (Screen::Main, Action::JumpForward) => {
+                Self::jump(app, false).await?;
+            }
+            (Screen::LinkPreview, Action::Esc) => {
+                app.link_preview = None;
+                app.switch_to_main();
+            }
+            (Screen::LinkPreview, Action::Activate(_)) => {
+                if let Some(preview) = app.link_preview.take() {
+                    app.switch_to_main();
+                    Self::open_note_in_tab(app, preview.note_id).await?;
+                }
+            }
+            (Screen::RecentNotes, Action::ShowRecentNotes) => {
+                app.recent_switcher = None;
                 app.switch_to_prev_screen();
             }
-            (Screen::DeleteNoteConfirmation, Action::Confirm) => {
-                if let Some(note_id) = app.editor.note_id {
-                    DbMac::delete_note(&app.db, note_id).await?;
-                    app.note_list.remove(note_id);
-                    app.current_screen = Screen::Welcome;
-                } else {
-                    app.current_screen = Screen::Popup;
-                    app.user_msg = UserMessage::new(
-                        format!("Error: couldn't delete {}", app.editor.title),
-                        MessageType::Error,
-                        Some(Screen::Main),
-                    );
+            (Screen::RecentNotes, _) => {
+                let Some(recent) = app.recent_switcher.as_mut() else {
+                    return Ok(());
+                };
+                match picker_input(recent, &action) {
+                    PickerEvent::Close => {
+                        app.recent_switcher = None;
+                        app.switch_to_prev_screen();
+                    }
+                    PickerEvent::Pick(id) => Self::switch_to_recent_note(app, id).await?,
+                    PickerEvent::Stay => {}
                 }
             }
-            (Screen::DeleteNoteConfirmation, Action::Cancel) => {
+            (Screen::TemplatePicker, _) => {
+                let Some(picker) = app.template_picker.as_mut() else {
+                    return Ok(());
+                };
+                match picker_input(picker, &action) {
+                    PickerEvent::Close => {
+                        app.template_picker = None;
+                        app.switch_to_prev_screen();
+                    }
+                    PickerEvent::Pick(idx) => Self::pick_template(app, idx),
+                    PickerEvent::Stay => {}
+                }
+            }
+            (Screen::CommandPalette, Action::ShowCommandPalette) => {
+                app.command_palette = None;
                 app.switch_to_prev_screen();
-                app.active_widget = Some(ActiveWidget::Editor);
             }
-            (Screen::Exiting, Action::Confirm) => {
-                Self::exit(app);
+            (Screen::CommandPalette, _) => {
+                let Some(palette) = app.command_palette.as_mut() else {
+                    return Ok(());
+                };
+                match picker_input(&mut palette.picker, &action) {
+                    PickerEvent::Close => {
+                        app.command_palette = None;
+                        app.switch_to_prev_screen();
+                    }
+                    PickerEvent::Pick(idx) => {
+                        let selected = palette.action_at(idx);
+                        Self::run_palette_action(app, selected).await?;
+                    }
+                    PickerEvent::Stay => {}
+                }
             }
-            (Screen::Exiting, Action::Cancel) => {
+            (Screen::LinkList, Action::ShowLinkList) => {
+                app.link_list = None;
                 app.switch_to_prev_screen();
-                app.active_widget = Some(ActiveWidget::Editor);
             }
-            _ => {}
-        }
-        Ok(())
-    }
+            (Screen::LinkList, _) => {
+                let Some(link_list) = app.link_list.as_mut() else {
+                    return Ok(());
+                };
+                match picker_input(&mut link_list.picker, &action) {
+                    PickerEvent::Close => {
+                        app.link_list = None;
+                        app.switch_to_prev_screen();
+                    }
+                    PickerEvent::Pick(idx) => {
+                        let selected = link_list.pos_at(idx);
+                        Self::jump_to_listed_link(app, selected);
+                    }
+                    PickerEvent::Stay => {}
+                }
+            }
+            (Screen::VaultSwitcher, Action::SwitchVault) => {
+                app.vault_switcher = None;
+                app.switch_to_prev_screen();
+            }
+            (Screen::VaultSwitcher, _) => {
+                let Some(switcher) = app.vault_switcher.as_mut() else {
+                    return Ok(());
+                };
+                match picker_input(switcher, &action) {
+                    PickerEvent::Close => {
+                        app.vault_switcher = None;
+                        app.switch_to_prev_screen();
+                    }
+                    PickerEvent::Pick(idx) => Self::switch_vault(app, idx).await?,
+                    PickerEvent::Stay => {}
+                }
+            }
+            (Screen::Graph, Action::Esc) => {
+                app.graph = None;
+                app.switch_to_prev_screen();
+            }
+            (Screen::Graph, Action::Down(_)) => {
+                if let Some(graph) = app.graph.as_mut() {
+                    graph.next();
+                }
+            }
+            (Screen::Graph, Action::Up(_)) => {
+                if let Some(graph) = app.graph.as_mut() {
+                    graph.prev();
+                }
+            }
+            (Screen::Graph, Action::Activate(_)) => {
+                if let Some(id) = app.graph.as_ref().and_then(|graph| graph.selected_id()) {
+                    app.graph = None;
+                    Self::load_note(app, id).await?;
+                }
+            }
+            (Screen::Graph, Action::Edit(input)) => {
+                if let Some(graph) = app.graph.as_mut() {
+                    match input.key {
+                        Key::Char('f') => graph.focus_selected(),
+                        Key::Char(c @ '1'..='9') => graph.set_depth(c as usize - '0' as usize),
+                        _ => {}
+                    }
+                }
+            }
+            (Screen::Stats, Action::Esc) => {
+                app.stats = None;
+                app.switch_to_prev_screen();
+            }
+            (Screen::Stats, Action::Down(_)) => {
+                if let Some(stats) = app.stats.as_mut() {
+                    stats.scroll_down();
+                }
+            }
+            (Screen::Stats, Action::Up(_)) => {
+                if let Some(stats) = app.stats.as_mut() {
+                    stats.scroll_up();
+                }
+            }
+            (Screen::DeleteNoteConfirmation, Action::Esc) => {
+                app.switch_to_prev_screen();
+            }
+            (Screen::DeleteNoteConfirmation, Action::Confirm) => {
+                Self::delete_open_note(app).await?;
+            }
+            (Screen::DeleteNoteConfirmation, Action::Cancel) => {
+                app.switch_to_prev_screen();
+                app.active_widget = Some(ActiveWidget::Editor);
+            }
+            (Screen::ReplaceConfirmation, Action::Confirm) => {
+                if let Some(sub) = app.pending_replace.clone() {
+                    app.editor.replace_current(&sub);
+                    Self::sync_replaced_links(app);
+                    Self::next_replace(app, sub, true);
+                }
+            }
+            (Screen::ReplaceConfirmation, Action::Cancel) => {
+                if let Some(sub) = app.pending_replace.clone() {
+                    Self::next_replace(app, sub, false);
+                }
+            }
+            (Screen::ReplaceConfirmation, Action::Esc) => {
+                app.pending_replace = None;
+                app.switch_to_main();
+            }
+            (Screen::CloseTabConfirmation, Action::Confirm) => {
+                app.switch_to_prev_screen();
+                Self::close_tab(app);
+            }
+            (Screen::CloseTabConfirmation, Action::Cancel)
+            | (Screen::CloseTabConfirmation, Action::Esc) => {
+                app.switch_to_prev_screen();
+            }
+            (Screen::NavigateConfirmation, Action::Confirm) => {
+                Self::resolve_navigation(app, true).await?;
+            }
+            (Screen::NavigateConfirmation, Action::Cancel) => {
+                Self::resolve_navigation(app, false).await?;
+            }
+            (Screen::RenameLinksConfirmation, Action::Confirm) => {
+                app.switch_to_prev_screen();
+                if let Some((id, title)) = app.pending_rename.take() {
+                    Self::retitle_backlinks(app, id, &title).await?;
+                }
+            }
+            (Screen::RenameLinksConfirmation, Action::Cancel)
+            | (Screen::RenameLinksConfirmation, Action::Esc) => {
+                app.pending_rename = None;
+                app.switch_to_prev_screen();
+            }
+            (Screen::CreateLinkTargetConfirmation, Action::Confirm) => {
+                app.switch_to_prev_screen();
+                if let Some(link_id) = app.pending_link_target.take() {
+                    Self::create_link_target(app, link_id).await?;
+                }
+            }
+            (Screen::CreateLinkTargetConfirmation, Action::Cancel)
+            | (Screen::CreateLinkTargetConfirmation, Action::Esc) => {
+                app.pending_link_target = None;
+                app.switch_to_prev_screen();
+            }
+            (Screen::NavigateConfirmation, Action::Esc) => {
+                app.pending_navigation = None;
+                app.switch_to_prev_screen();
+            }
+            (Screen::Exiting, Action::Confirm) => {
+                Self::exit(app);
+            }
+            (Screen::Exiting, Action::Cancel) => {
+                app.switch_to_prev_screen();
+                app.active_widget = Some(ActiveWidget::Editor);
+            }
+            _ => {}
+        }
+        Self::update_link_suggestions(app).await
+    }
 
     async fn save_note(
         app: &mut App<'_>,
@@ -560,13 +1250,21 @@ impl Events {
         has_links: bool,
         note_id: Option<i64>,
     ) -> Result<()> {
+        let (unresolved, missing) = match app.editor.settings.auto_link {
+            AutoLink::Off => (vec![], vec![]),
+            AutoLink::Existing | AutoLink::Create => {
+                Self::auto_link(app, note_id.unwrap_or_default()).await?
+            }
+        };
+        let has_links = has_links || !app.editor.links.is_empty() || !missing.is_empty();
+        let body = &app.seal(body)?;
         let (save_note_result, updated) = match note_id {
             Some(id) => (
-                DbMac::update_note(&app.db, title, body, has_links, id).await,
+                app.store.update_note(title, body, has_links, id).await,
                 true,
             ),
             None => (
-                DbMac::save_note(&app.db, title, body, has_links).await,
+                app.store.save_note(title, body, has_links).await,
                 false,
             ),
         };
@@ -578,6 +1276,10 @@ impl Events {
 
         match save_note_result {
             Ok(parent_id) => {
+                app.editor.mark_saved();
+                if let Err(e) = Self::save_undo_history(app, parent_id).await {
+                    error!("save_note::undo history not saved: {:?}", e);
+                }
                 if updated {
                     let new_nid = NoteIdentifier {
                         id: parent_id,
@@ -596,7 +1298,13 @@ impl Events {
                     app.note_list.update(new_nid);
                     // Triggers update_note on next save
                     app.editor.note_id = Some(parent_id);
+                    // Links auto-linked before the note had an id
+                    for link in app.editor.links.values_mut() {
+                        link.id = parent_id;
+                    }
                 }
+                // Made only now, so a note that failed to save leaves no empty notes behind
+                Self::create_link_targets(app, parent_id, missing).await?;
 
                 match has_links {
                     true => {
@@ -604,13 +1312,7 @@ impl Events {
                         info!("sync_db_links_result: {:?}", sync_db_links_result);
                         match sync_db_links_result {
                             Ok(_) => {
-                                app.user_msg = UserMessage::new(
-                                    "Note saved!".to_string(),
-                                    MessageType::Info,
-                                    None,
-                                );
-                                app.prev_screen = app.current_screen;
-                                app.current_screen = Screen::Popup;
+                                Self::show_saved(app, &unresolved);
                                 // Don't resave the same links over and over
                                 for link in app.editor.links.values_mut() {
                                     if !link.saved {
@@ -620,231 +1322,368 @@ impl Events {
                                 Ok(())
                             }
                             Err(err) => {
-                                app.user_msg = UserMessage::new(
+                                app.show_status(
                                     format!("Error saving note links!: {:?}", err),
                                     MessageType::Error,
-                                    None,
                                 );
-                                app.prev_screen = app.current_screen;
-                                app.current_screen = Screen::Popup;
                                 Err(err)
                             }
                         }
                     }
                     false => {
-                        app.user_msg =
-                            UserMessage::new("Note saved!".to_string(), MessageType::Info, None);
-                        app.prev_screen = app.current_screen;
-                        app.current_screen = Screen::Popup;
+                        Self::show_saved(app, &unresolved);
                         Ok(())
                     }
                 }
             }
             Err(err) => {
-                app.user_msg = UserMessage::new(
-                    format!("Error saving note!: {:?}", err),
-                    MessageType::Error,
-                    None,
-                );
-                app.prev_screen = app.current_screen;
-                app.current_screen = Screen::Popup;
+                // Title clashes get a readable message rather than the raw sqlx error
+                let msg = match err.downcast_ref::<DuplicateTitleError>() {
+                    Some(dup_err) => dup_err.to_string(),
+                    None => format!("Error saving note!: {:?}", err),
+                };
+                app.show_status(msg, MessageType::Error);
                 Err(err)
             }
         }
     }
 
-    fn check_links_to_update(link: &&Link) -> bool {
-        !link.deleted && link.saved && link.updated
+    /// "Note saved!", or the `[[Title]]`s saving couldn't link
+    fn show_saved(app: &mut App, unresolved: &[String]) {
+        match unresolved.is_empty() {
+            true => app.show_status("Note saved!".to_string(), MessageType::Info),
+            false => {
+                let msg = format!("Note saved, no note titled {}", unresolved.join(", "));
+                Self::warn_user(app, &msg);
+            }
+        }
     }
 
-    fn check_links_to_save(link: &&Link) -> bool {
-        !link.deleted && !link.saved
-    }
+    /// Links every `[[Title]]` in the note that isn't a link yet to the note with that
+    /// title. The titles no note has are returned, to be flagged, or with
+    /// `auto_link = "create"` as the `[[Title]]`s whose empty notes are still to be made.
+    async fn auto_link(
+        app: &mut App<'_>,
+        parent_id: i64,
+    ) -> Result<(Vec<String>, Vec<WikiLink>)> {
+        let found = wiki_links(app.editor.body.lines());
+        if found.is_empty() {
+            return Ok((vec![], vec![]));
+        }
+        let ids = app
+            .store
+            .load_note_identifiers()
+            .await?
+            .into_iter()
+            .map(|nid| (nid.title, nid.id))
+            .collect::<HashMap<String, i64>>();
 
-    fn check_links_to_delete(link: &&Link) -> bool {
-        link.deleted && link.saved
+        let mut unresolved: Vec<String> = vec![];
+        let mut missing = vec![];
+        for wiki in found {
+            let linked = (wiki.start_col..=wiki.end_col)
+                .any(|col| app.editor.body.in_link((wiki.row, col)).is_some());
+            if linked {
+                continue;
+            }
+            match ids.get(&wiki.title) {
+                Some(id) => Self::insert_auto_link(app, parent_id, wiki, *id),
+                None if app.editor.settings.auto_link == AutoLink::Create => missing.push(wiki),
+                None => {
+                    if !unresolved.contains(&wiki.title) {
+                        unresolved.push(wiki.title);
+                    }
+                }
+            }
+        }
+        Ok((unresolved, missing))
     }
 
-    async fn update_links(
-        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    /// Makes an empty note for each title `auto_link` found no note for and links to it
+    async fn create_link_targets(
         app: &mut App<'_>,
+        parent_id: i64,
+        missing: Vec<WikiLink>,
     ) -> Result<()> {
-        let links_to_update = app
-            .editor
-            .links
-            .values()
-            .filter(Self::check_links_to_update)
-            .map(|link| link.to_db_link())
-            .collect::<Vec<DbNoteLink>>();
+        let mut created = HashMap::new();
+        for wiki in missing {
+            let linked_id = match created.get(&wiki.title) {
+                Some(id) => *id,
+                None => {
+                    let id = app.store.save_note(&wiki.title, &app.seal("")?, false).await?;
+                    app.note_list.update(NoteIdentifier {
+                        id,
+                        title: wiki.title.clone(),
+                    });
+                    created.insert(wiki.title.clone(), id);
+                    id
+                }
+            };
+            Self::insert_auto_link(app, parent_id, wiki, linked_id);
+        }
+        Ok(())
+    }
+
+    /// Adds an unsaved link over `wiki` to the textarea and the editor
+    fn insert_auto_link(app: &mut App<'_>, parent_id: i64, wiki: WikiLink, linked_id: i64) {
+        let text_id = app.editor.body.next_link_id;
+        app.editor.body.links.insert(
+            text_id,
+            TextAreaLink {
+                id: text_id,
+                row: wiki.row,
+                start_col: wiki.start_col,
+                end_col: wiki.end_col,
+                edited: false,
+                deleted: false,
+            },
+        );
+        app.editor.body.next_link_id += 1;
+        app.editor.links.insert(
+            text_id as i64,
+            Link {
+                id: parent_id,
+                text_id: text_id as i64,
+                linked_id,
+                row: wiki.row,
+                start_col: wiki.start_col,
+                end_col: wiki.end_col,
+                saved: false,
+                updated: false,
+                deleted: false,
+                anchor: wiki.heading,
+            },
+        );
+    }
 
-        info!("fn update_links\nlinks_to_update: {:?}", links_to_update);
+    /// Copies the current note and its links under a free "<title> (copy)" title,
+    /// then switches to the copy
+    async fn duplicate_note(app: &mut App<'_>) -> Result<()> {
+        if app.editor.title == " Untitled " {
+            Self::warn_user(app, "Give the note a title before duplicating it");
+            return Ok(());
+        }
+
+        // The copy is taken from the db, so the source has to be saved first
+        if app.editor.note_id.is_none() || app.editor.is_dirty() {
+            let has_links = !matches!(app.editor.body.links.len(), 0);
+            let title = app.editor.title.clone();
+            let body = app.editor.body.lines().join("\n");
+            let note_id = app.editor.note_id;
+
+            if let Err(err) = Self::save_note(app, &title, &body, has_links, note_id).await {
+                return match err.is::<DuplicateTitleError>() {
+                    true => Ok(()),
+                    false => Err(err),
+                };
+            }
+        }
+        let Some(source_id) = app.editor.note_id else {
+            return Ok(());
+        };
 
-        if !links_to_update.is_empty() {
-            DbMac::update_links(
-                tx,
-                &app.db,
-                links_to_update,
-                app.editor.note_id.expect("Note should have an id"),
-            )
+        let source = app.store.load_note(source_id).await?;
+        let links = app.store.load_note_links(source_id).await?;
+        let titles = app
+            .store
+            .load_note_identifiers()
             .await?
+            .into_iter()
+            .map(|nid| nid.title)
+            .collect::<HashSet<String>>();
+        let title = numbered_title(&format!("{} (copy)", source.title.trim()), |title| {
+            titles.contains(title)
+        });
+
+        let body = source.body.unwrap_or_default();
+        let copy_id = app.store.save_note(&title, &body, !links.is_empty()).await?;
+        if !links.is_empty() {
+            let changes = LinkChanges {
+                saved: links,
+                ..Default::default()
+            };
+            app.store.sync_links(copy_id, changes).await?;
         }
+        info!("duplicate_note::copied note {} to {}", source_id, copy_id);
 
-        Ok(())
+        app.note_list.update(NoteIdentifier { id: copy_id, title });
+        Self::load_note(app, copy_id).await
     }
 
-    async fn save_links(
-        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-        app: &mut App<'_>,
-    ) -> Result<()> {
-        let links_to_save = app
-            .editor
-            .links
-            .values()
-            .filter(Self::check_links_to_save)
-            .map(|link| link.to_db_link())
-            .collect::<Vec<DbNoteLink>>();
+    fn check_links_to_update(link: &&Link) -> bool {
+        !link.deleted && link.saved && link.updated
+    }
+
+    fn check_links_to_save(link: &&Link) -> bool {
+        !link.deleted && !link.saved
+    }
 
-        info!("fn save_links\nlinks_to_save: {:?}", links_to_save);
+    fn check_links_to_delete(link: &&Link) -> bool {
+        link.deleted && link.saved
+    }
 
-        if !links_to_save.is_empty() {
-            DbMac::save_links(
-                tx,
-                &app.db,
-                links_to_save,
-                app.editor.note_id.expect("Note should have an id"),
-            )
-            .await?
+    async fn sync_db_links(app: &mut App<'_>) -> Result<()> {
+        info!("fn sync_db_links\neditor links: {:?}", app.editor.links);
+        let to_db_links = |filter: fn(&&Link) -> bool| {
+            app.editor
+                .links
+                .values()
+                .filter(filter)
+                .map(|link| link.to_db_link())
+                .collect::<Vec<DbNoteLink>>()
+        };
+        let changes = LinkChanges {
+            updated: to_db_links(Self::check_links_to_update),
+            saved: to_db_links(Self::check_links_to_save),
+            deleted: to_db_links(Self::check_links_to_delete)
+                .into_iter()
+                .map(|link| link.textarea_id)
+                .collect(),
+        };
+        info!("sync_db_links::changes: {:?}", changes);
+        if changes.is_empty() {
+            return Ok(());
         }
 
-        Ok(())
+        let parent_note_id = app
+            .editor
+            .note_id
+            .ok_or_else(|| eyre!("Links can't be saved before their note"))?;
+        app.store.sync_links(parent_note_id, changes).await
     }
 
-    async fn delete_links(
-        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-        app: &mut App<'_>,
-    ) -> Result<()> {
-        let parent_note_id = app.editor.note_id.expect("Note should have an id");
+    /// Title, body lines and editor links of a stored note
+    async fn read_note(
+        app: &App<'_>,
+        id: i64,
+    ) -> Result<(String, Vec<String>, HashMap<i64, Link>)> {
+        let note = app.store.load_note(id).await?;
+        let body = match note.body {
+            Some(text) => app
+                .open(text)?
+                .split('\n')
+                .map(|line| line.to_owned())
+                .collect::<Vec<String>>(),
+            None => vec!["".to_owned()],
+        };
 
-        let links_to_delete = app
-            .editor
-            .links
-            .values()
-            .filter(Self::check_links_to_delete)
-            .map(|link| (parent_note_id, link.text_id))
-            .collect::<Vec<(i64, i64)>>();
+        let db_links = match note.has_links {
+            true => app.store.load_note_links(id).await?,
+            false => vec![],
+        };
+
+        info!("read_note::db_links: {:?}", db_links);
+
+        let links = match db_links.len() {
+            0 => HashMap::new(),
+            _ => db_links
+                .into_iter()
+                .map(|link| (link.textarea_id, Link::from_db_link(link)))
+                .collect::<HashMap<i64, Link>>(),
+        };
+
+        info!("read_note::links for editor: {:?}", links);
 
-        info!("fn delete_links\nlinks_to_delete: {:?}", links_to_delete);
+        Ok((note.title, body, links))
+    }
 
-        if !links_to_delete.is_empty() {
-            DbMac::delete_links(tx, &app.db, links_to_delete).await?
+    async fn load_note(app: &mut App<'_>, id: i64) -> Result<()> {
+        if app.editor.note_id != Some(id) && Self::large_unsaved_edit(app) {
+            app.pending_navigation = Some(id);
+            app.prev_screen = app.current_screen;
+            app.current_screen = Screen::NavigateConfirmation;
+            app.user_msg = UserMessage::new(
+                format!(
+                    "{} has a large unsaved edit, save changes before navigating? (y/n/<Esc> cancel)",
+                    app.editor.title.trim()
+                ),
+                MessageType::Warning,
+            );
+            return Ok(());
         }
 
+        let from = app.editor.note_id;
+        Self::replace_note(app, id).await?;
+        if app.editor.note_id == Some(id) {
+            Self::record_jump(app, from, id);
+        }
         Ok(())
     }
 
-    async fn sync_db_links(app: &mut App<'_>) -> Result<()> {
-        let mut tx = app.db.begin().await?;
-        info!("fn sync_db_links\neditor links: {:?}", app.editor.links);
-        let update_links_result = Self::update_links(&mut tx, app).await;
-        let save_links_result = Self::save_links(&mut tx, app).await;
-        let delete_links_result = Self::delete_links(&mut tx, app).await;
-        info!(
-            "update_links_result: {:?}\nsave_links_result: {:?}\ndelete_links_result: {:?}",
-            update_links_result, save_links_result, delete_links_result
-        );
+    fn large_unsaved_edit(app: &App) -> bool {
+        let (lines, chars) = app.editor.unsaved_change_size();
+        (app.unsaved_prompt_lines > 0 && lines >= app.unsaved_prompt_lines)
+            || (app.unsaved_prompt_chars > 0 && chars >= app.unsaved_prompt_chars)
+    }
 
-        match (save_links_result, delete_links_result, update_links_result) {
-            (Ok(_), Ok(_), Ok(_)) => {
-                tx.commit().await?;
-                Ok(())
-            }
-            (Err(se), Ok(_), Ok(_)) => {
-                tx.rollback().await?;
-                Err(eyre!("Transaction error::update_links: {:?}", se))
-            }
-            (Ok(_), Err(de), Ok(_)) => {
-                tx.rollback().await?;
-                Err(eyre!("Transaction error::delete_links: {:?}", de))
-            }
-            (Ok(_), Ok(_), Err(ue)) => {
-                tx.rollback().await?;
-                Err(eyre!("Transaction error::update_links: {:?}", ue))
-            }
-            (Err(se), Err(de), Ok(_)) => {
-                tx.rollback().await?;
-                Err(eyre!(
-                    "Transaction errors\n
-                    save_links: {:?}\n
-                    delete_links: {:?}",
-                    se,
-                    de
-                ))
-            }
-            (Ok(_), Err(de), Err(ue)) => {
-                tx.rollback().await?;
-                Err(eyre!(
-                    "Transaction errors\n
-                    delete_links: {:?}\n
-                    update_links: {:?}",
-                    de,
-                    ue
-                ))
-            }
-            (Err(se), Ok(_), Err(ue)) => {
-                tx.rollback().await?;
-                Err(eyre!(
-                    "Transaction errors\n
-                    save_links: {:?}\n
-                    update_links: {:?}",
-                    se,
-                    ue
-                ))
-            }
-            (Err(se), Err(de), Err(ue)) => {
-                tx.rollback().await?;
-                Err(eyre!(
-                    "Transaction errors\n
-                    save_links: {:?}\n
-                    delete_links: {:?}\n
-                    update_links: {:?}",
-                    se,
-                    de,
-                    ue
-                ))
+    /// Loads the note waiting on the unsaved edit prompt, saving the current one first
+    /// unless the edit is being thrown away
+    async fn resolve_navigation(app: &mut App<'_>, save: bool) -> Result<()> {
+        app.switch_to_prev_screen();
+        let Some(id) = app.pending_navigation.take() else {
+            return Ok(());
+        };
+
+        let from = app.editor.note_id;
+        match save {
+            true => Self::replace_note(app, id).await?,
+            false => {
+                let (title, body, links) = Self::read_note(app, id).await?;
+                Self::show_note(app, id, title, body, links);
+                Self::restore_undo_history(app, id).await;
             }
         }
+        if app.editor.note_id == Some(id) {
+            Self::record_jump(app, from, id);
+        }
+        Ok(())
     }
 
-    async fn load_note(app: &mut App<'_>, id: i64) -> Result<()> {
-        let result = DbMac::load_note(&app.db, id).await;
-
-        match result {
-            Ok(note) => {
-                let body = match note.body {
-                    Some(text) => text
-                        .split('\n')
-                        .map(|line| line.to_owned())
-                        .collect::<Vec<String>>(),
-                    None => vec!["".to_owned()],
-                };
+    /// Both ends of a jump are recorded, so going back also works from notes that
+    /// were opened some other way
+    fn record_jump(app: &mut App, from: Option<i64>, to: i64) {
+        if let Some(from) = from {
+            app.jump_list.push(from);
+        }
+        app.jump_list.push(to);
+    }
 
-                let db_links = match note.has_links {
-                    true => DbMac::load_note_links(&app.db, id).await?,
-                    false => vec![],
-                };
+    /// Walks the jumplist, running the usual save of the current note before leaving it
+    async fn jump(app: &mut App<'_>, back: bool) -> Result<()> {
+        let target = match back {
+            true => app.jump_list.back(),
+            false => app.jump_list.forward(),
+        };
+        let Some(id) = target else {
+            return Ok(());
+        };
 
-                info!("load_note::db_links: {:?}", db_links);
+        match app.store.note_exists(id).await? {
+            true => match app.tab_index(id) {
+                Some(idx) => Self::focus_tab(app, idx),
+                None => Self::replace_note(app, id).await?,
+            },
+            false => {
+                app.show_status("That note no longer exists".to_string(), MessageType::Warning);
+            }
+        }
 
-                let links = match db_links.len() {
-                    0 => HashMap::new(),
-                    _ => db_links
-                        .into_iter()
-                        .map(|link| (link.textarea_id, Link::from_db_link(link)))
-                        .collect::<HashMap<i64, Link>>(),
-                };
+        // Stay on the same jumplist entry when the note didn't change
+        if app.editor.note_id != Some(id) {
+            match back {
+                true => app.jump_list.forward(),
+                false => app.jump_list.back(),
+            };
+        }
+        Ok(())
+    }
 
-                info!("load_note::links for editor: {:?}", links);
+    /// Saves the current note and loads `id` in its place
+    async fn replace_note(app: &mut App<'_>, id: i64) -> Result<()> {
+        let result = Self::read_note(app, id).await;
 
+        match result {
+            Ok((title, body, links)) => {
                 // Save current note before loading new one
                 let has_links = !matches!(app.editor.links.len(), 0);
                 let sync_title = &app.editor.title.clone();
@@ -858,334 +1697,4348 @@ impl Events {
 
                 match sync_note_db_result {
                     Ok(_) => {
-                        app.editor
-                            .refresh(note.title, body, links, Some(note.id), app.max_col);
-
-                        app.switch_to_main();
+                        Self::show_note(app, id, title, body, links);
+                        Self::restore_undo_history(app, id).await;
                         Ok(())
                     }
+                    // save_note has already shown the duplicate title error,
+                    // stay on the current note so the unsaved edits aren't lost
+                    Err(err) if err.is::<DuplicateTitleError>() => Ok(()),
                     Err(err) => {
-                        app.user_msg = UserMessage::new(
+                        app.show_status(
                             format!("Error saving current note!: {:?}", err),
                             MessageType::Error,
-                            None,
                         );
-                        app.prev_screen = app.current_screen;
-                        app.current_screen = Screen::Popup;
                         Err(err)
                     }
                 }
             }
             Err(err) => {
-                app.user_msg = UserMessage::new(
-                    format!("Error saving note links!: {:?}", err),
-                    MessageType::Error,
-                    None,
-                );
-                app.prev_screen = app.current_screen;
-                app.current_screen = Screen::Popup;
+                app.show_status(format!("Error loading note!: {:?}", err), MessageType::Error);
                 Err(err)
             }
         }
     }
 
-    fn switch_btns(app: &mut App) {
-        if app.current_btn().get_state() != ComponentState::Unavailable {
-            app.current_btn().set_state(ComponentState::Inactive);
+    /// Undo groups kept with a note by an earlier save
+    async fn read_undo_history(app: &App<'_>, id: i64) -> Result<Vec<SavedUndo>> {
+        match app.store.load_undo_history(id).await? {
+            Some(stored) => Ok(serde_json::from_str(&app.open(stored)?)?),
+            None => Ok(vec![]),
         }
+    }
 
-        app.btn_idx = (app.btn_idx + 1) % app.btns.len();
-
-        if app.current_btn().get_state() != ComponentState::Unavailable {
-            app.current_btn().set_state(ComponentState::Active);
+    /// A history that can't be read only costs the old undo steps
+    async fn restore_undo_history(app: &mut App<'_>, id: i64) {
+        match Self::read_undo_history(app, id).await {
+            Ok(history) => app.editor.restore_undo(history),
+            Err(e) => error!("restore_undo_history::undo history not loaded: {:?}", e),
         }
     }
 
-    fn btn_action(app: &mut App) {
-        match app.btns[app.btn_idx].get_action() {
-            ButtonAction::RenderMainScreen => {
-                app.switch_to_main();
-            }
-            ButtonAction::RenderNewNoteScreen => {
-                app.user_input = UserInput::new(ComponentState::Active, InputAction::Note);
-                app.switch_to_new_note(InputAction::Note);
-            }
-            ButtonAction::RenderLoadNoteScreen => {
-                app.switch_to_load_note();
+    /// Encrypted like the body, the history holds earlier versions of it
+    async fn save_undo_history(app: &mut App<'_>, note_id: i64) -> Result<()> {
+        let json = serde_json::to_string(&app.editor.saved_undo())?;
+        app.store.save_undo_history(note_id, &app.seal(&json)?).await
+    }
+
+    fn show_note(
+        app: &mut App,
+        id: i64,
+        title: String,
+        body: Vec<String>,
+        links: HashMap<i64, Link>,
+    ) {
+        app.editor
+            .refresh(title, body, links, Some(id), app.get_max_col());
+        push_recent(&mut app.recent_notes, id, app.recent_notes_cap);
+
+        app.switch_to_main();
+    }
+
+    /// Restores the last session's layout and opens what the `startup` setting asks for.
+    /// The welcome screen stays up when there's no note to open.
+    pub(crate) async fn start(app: &mut App<'_>, session: Option<Session>) -> Result<()> {
+        if let Some(session) = session {
+            Self::restore_session(app, session).await?;
+        }
+
+        match app.startup {
+            Startup::Welcome | Startup::LastOpened => {}
+            Startup::Blank => app.switch_to_main(),
+            Startup::MostRecent => {
+                if let Some(id) = app.store.load_most_recent_note_id().await? {
+                    Self::load_note(app, id).await?;
+                }
             }
         }
+        Ok(())
     }
 
-    async fn input_new_note<'a>(app: &mut App<'a>, linked: bool) -> Result<()> {
-        let linked_title = app.user_input.text.lines()[0].clone();
-
-        match app
-            .note_list
-            .note_identifiers
-            .iter()
-            .any(|nid| nid.title == linked_title)
-        {
-            // If any pre-exisiting notes have that title, warn user with input error state
+    /// The last note is only reopened with the `last_opened` startup
+    pub(crate) async fn restore_session(app: &mut App<'_>, session: Session) -> Result<()> {
+        let sidebar_size = session
+            .sidebar_size
+            .clamp(MIN_SIDEBAR_SIZE, MAX_SIDEBAR_SIZE);
+        match session.sidebar_open {
             true => {
-                app.user_input.set_state(ComponentState::Error);
-                Ok(())
+                app.sidebar_state = SidebarState::Open;
+                app.sidebar_size = sidebar_size;
+                app.editor.sidebar_open = true;
+                app.searchbar.sidebar_open = true;
+                app.resize_editor();
             }
-            // If no pre-exisiting notes have that title, create and save new note with that title
-            false => {
-                let linked_body = "";
-                let result = DbMac::save_note(&app.db, &linked_title, linked_body, false).await;
+            false => app.sidebar_state = SidebarState::Hidden(sidebar_size),
+        }
 
-                match result {
-                    Ok(id) => {
-                        let new_nid = NoteIdentifier {
-                            id,
-                            title: linked_title.clone(),
-                        };
+        app.searchbar.history = session.search_history;
+        app.searchbar.history.truncate(SEARCH_HISTORY_LEN);
 
-                        if linked {
-                            // link the new note to the parent
-                            Self::link_note(app, new_nid.id);
-
-                            // Save parent note to preserve link in textarea
-                            let parent_title = app.editor.title.clone();
-                            let parent_body = app.editor.body.lines().join("\n");
-                            let has_links = true;
-                            let note_id = app.editor.note_id;
-                            let parent_result = Self::save_note(
-                                app,
-                                &parent_title,
-                                &parent_body,
-                                has_links,
-                                note_id,
-                            )
-                            .await;
-
-                            match parent_result {
-                                Ok(_) => {
-                                    // If parent note saved correctly, switch editor to linked) note
-                                    app.editor.refresh(
-                                        linked_title,
-                                        vec![linked_body.to_owned()],
-                                        HashMap::new(),
-                                        Some(id),
-                                        app.get_max_col(),
-                                    );
-                                    app.note_list.update(new_nid);
-                                    app.switch_to_main();
-                                    Ok(())
-                                }
-                                Err(err) => Err(err),
-                            }
-                        } else {
-                            // if not linked to another note, simply switch to editor with new note
-                            app.editor.refresh(
-                                linked_title,
-                                vec![linked_body.to_owned()],
-                                HashMap::new(),
-                                Some(id),
-                                app.get_max_col(),
-                            );
+        if let (Startup::LastOpened, Some(id)) = (app.startup, session.last_note_id) {
+            // The note may have been deleted since the session was saved
+            if app.store.note_exists(id).await? {
+                Self::load_note(app, id).await?;
 
-                            app.note_list.update(new_nid);
-                            app.switch_to_main();
-                            Ok(())
-                        }
-                    }
-                    Err(err) => Err(err),
+                if session.searchbar_open {
+                    Self::toggle_searchbar(app);
                 }
+            } else {
+                info!("restore_session::last note {} no longer exists", id);
             }
         }
-    }
 
-    fn link_note(app: &mut App, linked_id: i64) {
-        let textarea_link = app
-            .pending_link
-            .expect("Should be a pending link if we reached this far");
-
-        let parent_nid = app
-            .note_list
-            .note_identifiers
-            .iter()
-            .find(|nid| nid.title == app.editor.title)
-            .expect("Parent note should already be saved and added to app.note_list");
+        Ok(())
+    }
 
-        let new_link = Link {
-            id: parent_nid.id,
-            text_id: textarea_link.id as i64,
-            linked_id,
-            row: textarea_link.row,
-            start_col: textarea_link.start_col,
-            end_col: textarea_link.end_col,
-            saved: false,
-            updated: false,
-            deleted: false,
-        };
+    /// Loads every page the note list doesn't have yet
+    async fn load_rest_of_note_list(app: &mut App<'_>) -> Result<()> {
+        if app.note_list.fully_loaded() {
+            return Ok(());
+        }
+        let offset = app.note_list.paged as i64;
+        let remaining = app.note_list.total.saturating_sub(app.note_list.paged) as i64;
+        let rest = app.store.load_note_identifiers_page(offset, remaining).await?;
+        app.note_list.append_page(rest);
+        Ok(())
+    }
 
-        let link_id = new_link.text_id;
-        app.editor.links.insert(link_id, new_link);
-        app.switch_to_main();
-        app.editor.body.new_link = false;
+    async fn note_list_next(app: &mut App<'_>) -> Result<()> {
+        if app.note_list.needs_next_page() {
+            let offset = app.note_list.paged as i64;
+            let page = app.store.load_note_identifiers_page(offset, NOTE_PAGE_SIZE).await?;
+            app.note_list.append_page(page);
+        }
+        app.note_list.next();
+        Ok(())
     }
 
-    fn check_link_edits(app: &mut App<'_>) {
-        for link in app.editor.links.values_mut() {
-            let ta_link = app
-                .editor
-                .body
-                .links
-                .get(&(link.text_id as usize))
-                .expect("editor links and textarea links should be synced");
-            link.deleted = ta_link.deleted;
+    async fn note_list_prev(app: &mut App<'_>) -> Result<()> {
+        // Wrapping from the top needs the rest of the list to land on the real last note
+        if app.note_list.selected == 0 {
+            Self::load_rest_of_note_list(app).await?;
         }
+        app.note_list.prev();
+        Ok(())
     }
 
-    fn check_link_paste(app: &mut App<'_>) {
-        for ta_link in app.editor.body.links.values_mut() {
-            if let Some(ed_link) = app.editor.links.get_mut(&(ta_link.id as i64)) {
-                if ed_link.moved(ta_link) {
-                    ed_link.row = ta_link.row;
-                    ed_link.start_col = ta_link.start_col;
-                    ed_link.end_col = ta_link.end_col;
-                    ed_link.updated = true;
-                }
-            } else {
-                let prev_copy_id = app
-                    .editor
-                    .body
-                    .copied_link_ids
-                    .remove(&ta_link.id)
-                    .expect("link already exists");
-                let linked_note_id = app
-                    .editor
-                    .links
-                    .get(&(prev_copy_id as i64))
-                    .expect("prev copy should exist");
+    /// Shows the start of the note linked under the cursor without leaving the current note
+    async fn show_link_preview(app: &mut App<'_>) -> Result<()> {
+        let Some(link_id) = app.editor.body.in_link(app.editor.body.cursor()) else {
+            return Ok(());
+        };
+        let linked_note_id = app
+            .editor
+            .links
+            .values()
+            .find(|link| link.text_id == link_id as i64)
+            .map(|link| link.linked_id);
 
-                let copied_link = Link {
-                    id: app.editor.note_id.expect("Note should have an id"),
-                    text_id: ta_link.id as i64,
-                    linked_id: linked_note_id.linked_id,
-                    row: ta_link.row,
-                    start_col: ta_link.start_col,
-                    end_col: ta_link.end_col,
-                    saved: false,
-                    updated: false,
-                    deleted: false,
-                };
+        let preview = match linked_note_id {
+            Some(id) if app.store.note_exists(id).await? => {
+                let (title, lines) = app
+                    .store
+                    .load_note_preview(id, PREVIEW_LINES, app.cipher.as_ref())
+                    .await?;
+                Some(LinkPreview::new(id, title, lines))
+            }
+            _ => None,
+        };
 
-                app.editor.links.insert(copied_link.text_id, copied_link);
+        match preview {
+            Some(preview) => {
+                app.link_preview = Some(preview);
+                app.current_screen = Screen::LinkPreview;
+            }
+            None => {
+                app.show_status(
+                    "The note this link points to no longer exists".to_string(),
+                    MessageType::Warning,
+                );
             }
         }
+        Ok(())
     }
 
-    fn check_link_deletion(app: &mut App<'_>, key: &Key) {
-        let delete_amount = app.editor.body.deleted_link_ids.len();
+    async fn follow_link(app: &mut App<'_>, link_id: usize) -> Result<()> {
+        let link = app
+            .editor
+            .links
+            .values()
+            .find(|link| link.text_id == link_id as i64);
+        let linked_note_id = link.map(|link| link.linked_id);
+        let anchor = link.and_then(|link| link.anchor.clone());
 
-        if DELETE_KEYS.contains(key) && delete_amount > 0 {
-            for _ in 0..delete_amount {
-                let textarea_id = app
-                    .editor
-                    .body
-                    .deleted_link_ids
-                    .pop()
-                    .expect("Link to delete should exist");
+        let target_exists = match linked_note_id {
+            Some(id) => app.store.note_exists(id).await?,
+            None => false,
+        };
 
-                // guards against cases where link hasn't been saved to editor yet
-                if !app.editor.links.is_empty() {
-                    let ta_id_int = textarea_id as i64;
-                    let link = app
-                        .editor
-                        .links
-                        .get_mut(&ta_id_int)
-                        .expect("editor and textarea links should be synced");
-                    link.deleted = true;
-                    app.editor.deleted_link_ids.push(ta_id_int);
+        match (linked_note_id, Self::link_text(app, link_id)) {
+            (Some(id), _) if target_exists => {
+                Self::open_note_in_tab(app, id).await?;
+                if let Some(anchor) = anchor {
+                    if !app.editor.jump_to_heading(&anchor) {
+                        Self::warn_user(app, &format!("No heading \"{}\" in this note", anchor));
+                    }
                 }
+                Ok(())
+            }
+            (_, Some(title)) => {
+                app.pending_link_target = Some(link_id);
+                app.prev_screen = app.current_screen;
+                app.current_screen = Screen::CreateLinkTargetConfirmation;
+                app.user_msg = UserMessage::new(
+                    format!("There is no note \"{}\" yet, create it? (y/n)", title),
+                    MessageType::Info,
+                );
+                Ok(())
+            }
+            _ => {
+                warn!("follow_link::missing link target for textarea link {}", link_id);
+                app.show_status(
+                    "The note this link points to no longer exists".to_string(),
+                    MessageType::Warning,
+                );
+                Ok(())
             }
         }
     }
 
-    fn check_link_moved(app: &mut App) {
-        for link in app.editor.links.values_mut() {
-            let ta_link = app
-                .editor
-                .body
-                .links
-                .get(&(link.text_id as usize))
-                .expect("Same links should be present in editor and textarea");
+    /// The text shown for a link, trimmed, None if it is blank
+    fn link_text(app: &App, link_id: usize) -> Option<String> {
+        let ta_link = app.editor.body.links.get(&link_id)?;
+        let line = app.editor.body.lines().get(ta_link.row)?;
+        let text = line
+            .chars()
+            .skip(ta_link.start_col)
+            .take(ta_link.end_col + 1 - ta_link.start_col)
+            .collect::<String>();
 
-            if link.moved(ta_link) {
-                link.row = ta_link.row;
-                link.start_col = ta_link.start_col;
-                link.end_col = ta_link.end_col;
-                link.updated = true;
-            }
+        match text.trim().is_empty() {
+            true => None,
+            false => Some(text.trim().to_owned()),
         }
     }
 
-    fn input_new_note_title(app: &mut App) {
-        let title = app.user_input.text.lines()[0].clone();
+    /// Points a broken link at the note titled with its text, creating that note
+    /// if there isn't one, then opens it
+    async fn create_link_target(app: &mut App<'_>, link_id: usize) -> Result<()> {
+        let Some(title) = Self::link_text(app, link_id) else {
+            return Ok(());
+        };
 
-        match app
-            .note_list
-            .note_identifiers
-            .iter()
-            .any(|nid| nid.title == title)
-        {
-            true => {
-                app.user_input.set_state(ComponentState::Error);
+        let existing = app
+            .store
+            .load_note_identifiers()
+            .await?
+            .into_iter()
+            .find(|nid| nid.title.trim() == title);
+        let target_id = match existing {
+            Some(nid) => nid.id,
+            None => {
+                let id = app.store.save_note(&title, "", false).await?;
+                app.note_list.update(NoteIdentifier { id, title });
+                id
             }
-            false => {
-                app.editor.set_title(title);
-                app.switch_to_main();
+        };
+
+        let Some(link) = app
+            .editor
+            .links
+            .values_mut()
+            .find(|link| link.text_id == link_id as i64)
+        else {
+            warn!("create_link_target::no editor link for textarea link {}", link_id);
+            return Ok(());
+        };
+        link.linked_id = target_id;
+
+        // Unsaved links pick up the new target on the next save
+        if link.saved {
+            let db_link = link.to_db_link();
+            let parent_id = db_link.parent_note_id;
+            let changes = LinkChanges {
+                deleted: vec![db_link.textarea_id],
+                saved: vec![db_link],
+                ..Default::default()
+            };
+            app.store.sync_links(parent_id, changes).await?;
+        }
+
+        Self::open_note_in_tab(app, target_id).await
+    }
+
+    /// Notes that have since been deleted are dropped from the list
+    async fn show_recent_notes(app: &mut App<'_>) -> Result<()> {
+        let mut entries = vec![];
+        for &id in app.recent_notes.iter() {
+            if let Ok(note) = app.store.load_note(id).await {
+                entries.push(NoteIdentifier {
+                    id,
+                    title: note.title,
+                });
             }
         }
+        app.recent_notes
+            .retain(|id| entries.iter().any(|nid| nid.id == *id));
+
+        let entries = entries.into_iter().map(PickerEntry::from).collect();
+        app.recent_switcher = Some(Picker::new(" Recent Notes ", entries));
+        app.prev_screen = app.current_screen;
+        app.current_screen = Screen::RecentNotes;
+        Ok(())
     }
 
-    fn toggle_searchbar(app: &mut App) {
-        match app.searchbar_state {
-            SearchbarState::Open => {
-                app.searchbar_state = SearchbarState::Hidden;
-                app.editor.searchbar_open = false;
-                app.set_active_widget(ActiveWidget::Editor);
-                app.searchbar.state = ComponentState::Inactive;
+    /// Suspends the TUI while the note body is edited in `$EDITOR`
+    pub(crate) fn edit_externally(app: &mut App<'_>, terminal: &mut Tui) -> Result<()> {
+        app.external_edit_requested = false;
+        let body = app.editor.body.lines().join("\n");
+
+        utils::suspend().wrap_err("failed to suspend terminal")?;
+        let edited = external::edit_externally(&body);
+        utils::resume(terminal).wrap_err("failed to resume terminal")?;
+
+        match edited {
+            Ok(Some(lines)) => app.editor.replace_lines(lines),
+            Ok(None) => info!("edit_externally::body unchanged"),
+            Err(e) => Self::warn_user(app, &format!("External editor failed: {}", e)),
+        }
+        Ok(())
+    }
+
+    /// Pinned notes can sit in pages the sidebar hasn't loaded yet
+    pub(crate) async fn load_pins(app: &mut App<'_>) -> Result<()> {
+        let pinned = app.store.load_pinned_identifiers().await?;
+        app.note_list.pin_notes(pinned);
+        Ok(())
+    }
+
+    pub(crate) async fn load_folders(app: &mut App<'_>) -> Result<()> {
+        let folders = app.store.load_note_folders().await?;
+        app.note_list.set_folders(folders);
+        // A folder can hold notes from any page, the tree is built from all of them
+        if app.note_list.is_tree() {
+            Self::load_rest_of_note_list(app).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn load_tags(app: &mut App<'_>) -> Result<()> {
+        let tags = app.store.load_note_tags().await?;
+        app.note_list.set_tags(tags);
+        Ok(())
+    }
+
+    /// Reconnects to the picked vault, unsaved notes have to be dealt with first
+    async fn switch_vault(app: &mut App<'_>, idx: Option<i64>) -> Result<()> {
+        let Some(name) = idx.and_then(|idx| vault_names(&app.vaults).into_iter().nth(idx as usize))
+        else {
+            return Ok(());
+        };
+        app.vault_switcher = None;
+        app.switch_to_prev_screen();
+        if name == app.active_vault {
+            return Ok(());
+        }
+
+        if (0..app.tabs.len()).any(|idx| app.tab(idx).is_dirty()) {
+            Self::warn_user(app, "Save or close unsaved notes before switching vaults");
+            return Ok(());
+        }
+
+        let Some(path) = vault_path(&app.vaults, &name) else {
+            return Ok(());
+        };
+        match open_db(&path, app.allow_newer_schema).await {
+            Ok((db, newer_schema)) => {
+                Self::use_vault(app, name, &path, db).await?;
+                if let Some(newer) = newer_schema {
+                    Self::warn_user(app, &newer.to_string());
+                }
+                Ok(())
             }
-            SearchbarState::Hidden => {
-                app.searchbar_state = SearchbarState::Open;
-                app.editor.searchbar_open = true;
-                app.set_active_widget(ActiveWidget::Searchbar);
-                app.searchbar.state = ComponentState::Active;
+            Err(e) => {
+                Self::warn_user(app, &format!("Could not open vault {}: {}", name, e));
+                Ok(())
             }
         }
     }
 
-    fn toggle_sidebar(app: &mut App) {
-        match app.sidebar_state {
-            SidebarState::Open => {
-                app.sidebar_state = SidebarState::Hidden(app.sidebar_size);
-                app.sidebar_size = 0;
-                app.editor.sidebar_open = false;
-                app.searchbar.sidebar_open = false;
-                match app.editor.searchbar_open {
-                    true => app.set_active_widget(ActiveWidget::Searchbar),
-                    false => app.set_active_widget(ActiveWidget::Editor),
+    /// Swaps in another vault's database, closing every tab since their notes belong to the old one
+    pub(crate) async fn use_vault(
+        app: &mut App<'_>,
+        name: String,
+        path: &Path,
+        db: Pool<Sqlite>,
+    ) -> Result<()> {
+        if let Some(cipher) = &app.cipher {
+            match cipher.for_db(&db).await {
+                Ok(cipher) => app.cipher = Some(cipher),
+                Err(e) => {
+                    db.close().await;
+                    Self::warn_user(app, &format!("Can't open {}: {}", name, e));
+                    return Ok(());
                 }
             }
-            SidebarState::Hidden(n) => {
-                app.sidebar_size = n;
-                app.sidebar_state = SidebarState::Open;
-                app.editor.sidebar_open = true;
-                app.searchbar.sidebar_open = true;
-                app.set_active_widget(ActiveWidget::Sidebar);
+        }
+        let old_db = mem::replace(&mut app.db, db);
+        old_db.close().await;
+        app.store = Store::new(app.storage, &app.db, path);
+
+        let note_identifiers = app.store.load_note_identifiers_page(0, NOTE_PAGE_SIZE).await?;
+        let note_count = app.store.count_notes().await? as usize;
+        let wrap_selection = app.note_list.wrap_selection;
+        app.note_list = NoteList::new(
+            note_identifiers,
+            note_count,
+            app.note_list.action,
+            app.note_list.state,
+            app.note_list.theme.clone(),
+        );
+        app.note_list.wrap_selection = wrap_selection;
+
+        Self::load_pins(app).await?;
+        Self::load_folders(app).await?;
+        Self::load_tags(app).await?;
+
+        while app.close_tab() {}
+        app.editor.refresh(
+            " Untitled ".to_owned(),
+            vec!["".to_owned()],
+            HashMap::new(),
+            None,
+            app.get_max_col(),
+        );
+        app.editor.deleted_link_ids.clear();
+        app.pending_link = None;
+        app.pending_navigation = None;
+        app.pending_rename = None;
+        app.pending_link_target = None;
+        app.recent_notes.clear();
+        app.jump_list = JumpList::new(JUMP_LIST_CAP);
+
+        info!("use_vault::switched to {}", name);
+        app.active_vault = name;
+        Ok(())
+    }
+
+    /// A note that was never saved can't be deleted, that is reported instead
+    async fn delete_open_note(app: &mut App<'_>) -> Result<()> {
+        match app.editor.note_id {
+            Some(note_id) => {
+                app.store.delete_note(note_id).await?;
+                app.note_list.remove(note_id);
+                app.current_screen = Screen::Welcome;
+            }
+            None => {
+                app.current_screen = Screen::Main;
+                app.show_status(
+                    format!("Error: couldn't delete {}", app.editor.title),
+                    MessageType::Error,
+                );
             }
         }
+        Ok(())
     }
 
-    fn show_exit_screen(app: &mut App) {
-        app.current_screen = Screen::Exiting;
+    /// Closes the palette and runs the action as if its key had been pressed where the
+    /// palette was opened
+    async fn run_palette_action(app: &mut App<'_>, action: Option<Action>) -> Result<()> {
+        let Some(action) = action else {
+            return Ok(());
+        };
+        app.command_palette = None;
+        app.current_screen = app.prev_screen;
+        Box::pin(Self::execute_action(app, action)).await
     }
 
-    fn exit(app: &mut App) {
-        app.state = AppState::Exit;
+    fn jump_to_listed_link(app: &mut App<'_>, pos: Option<(usize, usize)>) {
+        let Some((row, col)) = pos else {
+            return;
+        };
+        app.link_list = None;
+        app.current_screen = app.prev_screen;
+        app.editor.body.move_cursor(CursorMove::Jump(row as u16, col as u16));
+    }
+
+    /// Remembers the picked template and asks for the new note's title
+    fn pick_template(app: &mut App<'_>, idx: Option<i64>) {
+        let Some(body) = idx.and_then(|idx| app.templates.values().nth(idx as usize)) else {
+            return;
+        };
+        app.pending_template = Some(body.clone());
+        app.template_picker = None;
+        app.switch_to_new_note(InputAction::Note);
+    }
+
+    async fn switch_to_recent_note(app: &mut App<'_>, id: Option<i64>) -> Result<()> {
+        if let Some(id) = id {
+            app.recent_switcher = None;
+            app.current_screen = app.prev_screen;
+            Self::load_note(app, id).await?;
+        }
+        Ok(())
+    }
+
+    /// Counts are of saved bodies, unsaved edits in open tabs aren't included
+    async fn show_stats(app: &mut App<'_>) -> Result<()> {
+        let bodies = app
+            .store
+            .load_note_bodies()
+            .await?
+            .into_iter()
+            .map(|body| app.open(body))
+            .collect::<Result<Vec<String>>>()?;
+        let most_linked = app.store.load_most_linked(MOST_LINKED_LIMIT).await?;
+        let orphans = app.store.load_orphan_notes().await?;
+
+        app.stats = Some(VaultStats::new(&bodies, most_linked, orphans));
+        app.prev_screen = app.current_screen;
+        app.current_screen = Screen::Stats;
+        Ok(())
+    }
+
+    /// Centres the graph on the open note, or the first note if there isn't one
+    async fn show_graph(app: &mut App<'_>) -> Result<()> {
+        let note_identifiers = app.store.load_note_identifiers().await?;
+        let links = app.store.load_all_links().await?;
+        let focus = match app.editor.note_id {
+            Some(id) => Some(id),
+            None => note_identifiers.first().map(|nid| nid.id),
+        };
+
+        match focus {
+            Some(focus) => {
+                let graph = LinkGraph::new(&note_identifiers, &links);
+                app.graph = Some(GraphView::new(graph, focus));
+                app.prev_screen = app.current_screen;
+                app.current_screen = Screen::Graph;
+            }
+            None => {
+                app.show_status(
+                    "There are no notes to show in the graph".to_string(),
+                    MessageType::Info,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Focuses the tab already showing the note, otherwise opens it in a new one
+    async fn open_note_in_tab(app: &mut App<'_>, id: i64) -> Result<()> {
+        Self::record_jump(app, app.editor.note_id, id);
+        match app.tab_index(id) {
+            Some(idx) => Self::focus_tab(app, idx),
+            None => {
+                let (title, body, links) = Self::read_note(app, id).await?;
+                let mut editor = app.editor.clone();
+                editor.refresh(title, body, links, Some(id), app.get_max_col());
+                match Self::read_undo_history(app, id).await {
+                    Ok(history) => editor.restore_undo(history),
+                    Err(e) => error!("open_note_in_tab::undo history not loaded: {:?}", e),
+                }
+                editor.set_mode(EditorMode::Normal);
+                app.open_tab(editor);
+                push_recent(&mut app.recent_notes, id, app.recent_notes_cap);
+                Self::focus_tab(app, app.active_tab);
+            }
+        }
+        app.switch_to_main();
+        Ok(())
+    }
+
+    /// Layout flags are only kept up to date on the focused editor
+    fn focus_tab(app: &mut App, idx: usize) {
+        app.switch_tab(idx);
+        app.editor.sidebar_open = app.sidebar_state == SidebarState::Open;
+        app.editor.searchbar_open = app.searchbar_state == SearchbarState::Open;
+        app.resize_editor();
+        if let Some(widget) = app.active_widget {
+            app.set_active_widget(widget);
+        }
+    }
+
+    /// Closing the last tab leaves an empty note behind the welcome screen
+    fn close_tab(app: &mut App) {
+        match app.close_tab() {
+            true => Self::focus_tab(app, app.active_tab),
+            false => {
+                app.editor.refresh(
+                    " Untitled ".to_owned(),
+                    vec!["".to_owned()],
+                    HashMap::new(),
+                    None,
+                    app.get_max_col(),
+                );
+                app.current_screen = Screen::Welcome;
+            }
+        }
+    }
+
+    /// Opens the command line on `:` in normal mode and takes every key while it is
+    /// open, returns whether the key went to it
+    fn command_line_input(app: &mut App, action: &Action) -> bool {
+        // Plain keys like `y` and `n` resolve to actions that don't carry their input
+        let input = action.input().or_else(|| app.keymap.get(action).copied());
+        let typed = match input {
+            Some(Input {
+                key: Key::Char(c),
+                ctrl: false,
+                alt: false,
+                ..
+            }) => Some(c),
+            _ => None,
+        };
+        let Some(text) = app.command_line.as_mut() else {
+            let opens = typed == Some(':')
+                && app.current_screen == Screen::Main
+                && app.active_widget == Some(ActiveWidget::Editor)
+                && app.editor.mode == EditorMode::Normal
+                && app.editor.cmd_state == CommandState::NoCommand
+                && app.editor.num_buf.is_empty();
+            if opens {
+                app.command_line = Some(String::new());
+            }
+            return opens;
+        };
+
+        match (action, typed) {
+            (Action::Esc, _) => app.command_line = None,
+            (Action::Activate(_), _) => {
+                let line = app.command_line.take().unwrap_or_default();
+                Self::run_command(app, &line);
+            }
+            (Action::DeleteChar, _) => {
+                if text.pop().is_none() {
+                    app.command_line = None;
+                }
+            }
+            (_, Some(c)) => text.push(c),
+            _ => {}
+        }
+        true
+    }
+
+    fn run_command(app: &mut App, line: &str) {
+        let command = match parse_command(line) {
+            Ok(command) => command,
+            Err(msg) => return Self::warn_user(app, &msg),
+        };
+        match command {
+            Command::Set(option, value) => {
+                let editor = &mut app.editor;
+                let flag = match option {
+                    SetOption::Number => &mut editor.settings.line_numbers,
+                    SetOption::Wrap => &mut editor.settings.soft_wrap,
+                    SetOption::ReadOnly => &mut editor.read_only,
+                    SetOption::AutoPairs => &mut editor.settings.auto_pairs,
+                };
+                *flag = value.unwrap_or(!*flag);
+            }
+            Command::GoTo { line, col } => app.editor.go_to(line, col.unwrap_or(1)),
+            Command::NoHighlight => app.editor.clear_search_highlight(),
+        }
+    }
+
+    /// Whether the note list has the keys, as the sidebar or the load screen
+    fn in_note_list(app: &App) -> bool {
+        match app.current_screen {
+            Screen::LoadNote => true,
+            Screen::Main => app.active_widget == Some(ActiveWidget::Sidebar),
+            _ => false,
+        }
+    }
+
+    fn can_show_help(app: &App) -> bool {
+        match app.current_screen {
+            Screen::Welcome | Screen::LoadNote | Screen::Help => true,
+            Screen::Main => match app.active_widget {
+                Some(ActiveWidget::Editor) => app.editor.mode == EditorMode::Normal,
+                Some(ActiveWidget::Sidebar) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn switch_btns(app: &mut App) {
+        if app.current_btn().get_state() != ComponentState::Unavailable {
+            app.current_btn().set_state(ComponentState::Inactive);
+        }
+
+        app.btn_idx = (app.btn_idx + 1) % app.btns.len();
+
+        if app.current_btn().get_state() != ComponentState::Unavailable {
+            app.current_btn().set_state(ComponentState::Active);
+        }
+    }
+
+    fn btn_action(app: &mut App) {
+        match app.btns[app.btn_idx].get_action() {
+            ButtonAction::RenderMainScreen => {
+                app.switch_to_main();
+            }
+            ButtonAction::RenderNewNoteScreen => {
+                app.user_input = UserInput::new(ComponentState::Active, InputAction::Note);
+                app.switch_to_new_note(InputAction::Note);
+            }
+            ButtonAction::RenderLoadNoteScreen => {
+                app.switch_to_load_note();
+            }
+        }
+    }
+
+    async fn input_new_note<'a>(app: &mut App<'a>, linked: bool) -> Result<()> {
+        let Some(linked_title) = clean_title(&app.user_input.text.lines()[0]) else {
+            app.user_input.set_state(ComponentState::Error);
+            return Ok(());
+        };
+
+        match app
+            .note_list
+            .note_identifiers
+            .iter()
+            .any(|nid| nid.title == linked_title)
+        {
+            // If any pre-exisiting notes have that title, warn user with input error state
+            true => {
+                app.user_input.set_state(ComponentState::Error);
+                Ok(())
+            }
+            // If no pre-exisiting notes have that title, create and save new note with that title
+            false => {
+                // Kept until the note is made, so a failed save can be retried with it
+                let linked_body = match app.pending_template.as_deref() {
+                    Some(template) => expand_template(template, &linked_title, &today()),
+                    None => String::new(),
+                };
+                let linked_lines = linked_body
+                    .split('\n')
+                    .map(String::from)
+                    .collect::<Vec<String>>();
+                let sealed_body = app.seal(&linked_body)?;
+                let result = app.store.save_note(&linked_title, &sealed_body, false).await;
+
+                match result {
+                    Ok(id) => {
+                        let new_nid = NoteIdentifier {
+                            id,
+                            title: linked_title.clone(),
+                        };
+
+                        if linked {
+                            // link the new note to the parent
+                            Self::link_note(app, new_nid.id);
+
+                            // Save parent note to preserve link in textarea
+                            let parent_title = app.editor.title.clone();
+                            let parent_body = app.editor.body.lines().join("\n");
+                            let has_links = true;
+                            let note_id = app.editor.note_id;
+                            let parent_result = Self::save_note(
+                                app,
+                                &parent_title,
+                                &parent_body,
+                                has_links,
+                                note_id,
+                            )
+                            .await;
+
+                            match parent_result {
+                                Ok(_) => {
+                                    // If parent note saved correctly, switch editor to linked) note
+                                    app.editor.refresh(
+                                        linked_title,
+                                        linked_lines,
+                                        HashMap::new(),
+                                        Some(id),
+                                        app.get_max_col(),
+                                    );
+                                    app.note_list.update(new_nid);
+                                    app.switch_to_main();
+                                    Ok(())
+                                }
+                                Err(err) => Err(err),
+                            }
+                        } else {
+                            // if not linked to another note, simply switch to editor with new note
+                            app.editor.refresh(
+                                linked_title,
+                                linked_lines,
+                                HashMap::new(),
+                                Some(id),
+                                app.get_max_col(),
+                            );
+
+                            app.note_list.update(new_nid);
+                            app.switch_to_main();
+                            Ok(())
+                        }
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        }
+    }
+
+    /// Saves the typed text as a note of its own, the open note stays as it was
+    async fn quick_capture(app: &mut App<'_>) -> Result<()> {
+        let text = app.user_input.text.lines().join("\n").trim().to_owned();
+        if text.is_empty() {
+            app.user_input.set_state(ComponentState::Error);
+            return Ok(());
+        }
+
+        let title = capture_title(&text, |title| {
+            app.note_list
+                .note_identifiers
+                .iter()
+                .any(|nid| nid.title == title)
+        });
+        let id = match app.store.save_note(&title, &app.seal(&text)?, false).await {
+            Ok(id) => id,
+            Err(err) if err.is::<DuplicateTitleError>() => {
+                // Taken by a note the list hasn't loaded, the capture stays in the prompt
+                app.user_input.set_state(ComponentState::Error);
+                app.show_status(err.to_string(), MessageType::Error);
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        app.note_list.update(NoteIdentifier {
+            id,
+            title: title.clone(),
+        });
+        app.user_input = UserInput::new(ComponentState::Active, InputAction::Note);
+
+        app.switch_to_main();
+        app.show_status(format!("Captured \"{}\"", title), MessageType::Info);
+        Ok(())
+    }
+
+    /// Asks the title of a new note for the visual selection, which stays put until one
+    /// is given
+    fn open_extract_prompt(app: &mut App) {
+        let selection = match app.active_widget {
+            Some(ActiveWidget::Editor) => app.editor.selection_bounds(),
+            _ => None,
+        };
+        let Some((start, end)) = selection else {
+            Self::warn_user(app, "Select the text to move into a new note first");
+            return;
+        };
+        if app.editor.read_only {
+            Self::warn_user(app, "Note is read-only, <Alt-R> to make it editable");
+            return;
+        }
+        if app.editor.note_id.is_none() {
+            Self::warn_user(app, "Save the note before linking it to another");
+            return;
+        }
+        // Links would lose their place in the text, so they have to be moved by hand
+        let has_link = app.editor.body.links.values().any(|link| {
+            !link.deleted && (link.row, link.end_col) >= start && (link.row, link.start_col) < end
+        });
+        if has_link {
+            Self::warn_user(app, "The selection has links in it, they can't be moved");
+            return;
+        }
+        if start == end {
+            Self::warn_user(app, "The selection is empty");
+            return;
+        }
+
+        app.editor.set_mode(EditorMode::Normal);
+        app.pending_extract = Some((start, end));
+        app.prev_screen = app.current_screen;
+        app.user_input = UserInput::new(ComponentState::Active, InputAction::ExtractNote);
+        app.switch_to_new_note(InputAction::ExtractNote);
+    }
+
+    /// Asks where to move the open note, only a saved note has a folder to change
+    fn open_move_prompt(app: &mut App) {
+        if app.editor.note_id.is_none() {
+            Self::warn_user(app, "Save the note before moving it");
+            return;
+        }
+        app.prev_screen = app.current_screen;
+        app.user_input = UserInput::new(ComponentState::Active, InputAction::MoveNote);
+        app.switch_to_new_note(InputAction::MoveNote);
+    }
+
+    /// Puts the open note in the typed folder. Naming another vault copies the note
+    /// there and deletes it here, which needs it saved first.
+    async fn move_open_note(app: &mut App<'_>) -> Result<()> {
+        let destination = parse_destination(&app.user_input.text.lines()[0], &app.vaults);
+        app.user_input = UserInput::new(ComponentState::Active, InputAction::Note);
+        app.switch_to_prev_screen();
+        app.active_widget = Some(ActiveWidget::Editor);
+        let Some(note_id) = app.editor.note_id else {
+            return Ok(());
+        };
+
+        let vault = destination.vault.filter(|vault| *vault != app.active_vault);
+        let Some(vault) = vault else {
+            app.store.set_folder(note_id, destination.folder.as_deref()).await?;
+            app.note_list.set_folder(note_id, destination.folder.clone());
+            if app.note_list.is_tree() {
+                Self::load_rest_of_note_list(app).await?;
+            }
+            let place = destination.folder.unwrap_or_else(|| "the root".to_owned());
+            app.show_status(format!("Moved {} to {}", app.editor.title, place), MessageType::Info);
+            return Ok(());
+        };
+
+        if app.editor.is_dirty() {
+            Self::warn_user(app, "Save the note before moving it to another vault");
+            return Ok(());
+        }
+        let Some(path) = vault_path(&app.vaults, &vault) else {
+            return Ok(());
+        };
+        match open_db(&path, app.allow_newer_schema).await {
+            Ok((db, _)) => {
+                let moved =
+                    Self::move_note_to_vault(app, note_id, &vault, &path, &db, destination.folder)
+                        .await;
+                db.close().await;
+                moved
+            }
+            Err(e) => {
+                Self::warn_user(app, &format!("Could not open vault {}: {}", vault, e));
+                Ok(())
+            }
+        }
+    }
+
+    /// Copies a note into another vault's database and deletes it from this one. Links
+    /// follow their targets by title, the ones the other vault has no note for are
+    /// dropped and named in a warning, as are the notes here that linked to it.
+    pub(crate) async fn move_note_to_vault(
+        app: &mut App<'_>,
+        note_id: i64,
+        vault: &str,
+        path: &Path,
+        db: &Pool<Sqlite>,
+        folder: Option<String>,
+    ) -> Result<()> {
+        let dest = Store::new(app.storage, db, path);
+        let note = app.store.load_note(note_id).await?;
+        let body = app.open(note.body.unwrap_or_default())?;
+        let body = match &app.cipher {
+            Some(cipher) => cipher.for_db(db).await?.seal(&body)?,
+            None => body,
+        };
+
+        let titles_here = app
+            .store
+            .load_note_identifiers()
+            .await?
+            .into_iter()
+            .map(|nid| (nid.id, nid.title))
+            .collect::<HashMap<i64, String>>();
+        let ids_there = dest
+            .load_note_identifiers()
+            .await?
+            .into_iter()
+            .map(|nid| (nid.title, nid.id))
+            .collect::<HashMap<String, i64>>();
+
+        // None stands for a link to the note itself, its id there isn't known yet
+        let mut kept: Vec<(DbNoteLink, Option<i64>)> = vec![];
+        let mut missing: Vec<String> = vec![];
+        for link in app.store.load_note_links(note_id).await? {
+            if link.linked_note_id == note_id {
+                kept.push((link, None));
+                continue;
+            }
+            let title = titles_here.get(&link.linked_note_id).cloned().unwrap_or_default();
+            match ids_there.get(&title) {
+                Some(id) => kept.push((link, Some(*id))),
+                None if !missing.contains(&title) => missing.push(title),
+                None => {}
+            }
+        }
+
+        let new_id = match dest.save_note(&note.title, &body, !kept.is_empty()).await {
+            Ok(id) => id,
+            Err(err) if err.is::<DuplicateTitleError>() => {
+                let msg = format!("{} already has a note titled \"{}\"", vault, note.title);
+                Self::warn_user(app, &msg);
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        if !kept.is_empty() {
+            let saved = kept
+                .into_iter()
+                .map(|(link, linked_id)| DbNoteLink {
+                    parent_note_id: new_id,
+                    linked_note_id: linked_id.unwrap_or(new_id),
+                    ..link
+                })
+                .collect();
+            let changes = LinkChanges {
+                saved,
+                ..Default::default()
+            };
+            dest.sync_links(new_id, changes).await?;
+        }
+        dest.set_folder(new_id, folder.as_deref()).await?;
+
+        let mut linked_from = app
+            .store
+            .load_backlinks(note_id)
+            .await?
+            .into_iter()
+            .map(|link| link.parent_note_id)
+            .filter(|id| *id != note_id)
+            .collect::<Vec<i64>>();
+        linked_from.sort();
+        linked_from.dedup();
+
+        app.store.delete_note(note_id).await?;
+        app.note_list.remove(note_id);
+        Self::close_tab(app);
+
+        let mut msg = format!("Moved {} to {}", note.title, vault);
+        if !missing.is_empty() {
+            msg.push_str(&format!(", links to {} were dropped", missing.join(", ")));
+        }
+        if !linked_from.is_empty() {
+            msg.push_str(&format!(", {} notes here linked to it", linked_from.len()));
+        }
+        match missing.is_empty() && linked_from.is_empty() {
+            true => app.show_status(msg, MessageType::Info),
+            false => Self::warn_user(app, &msg),
+        }
+        Ok(())
+    }
+
+    /// Saves the selected text as a note with the typed title and swaps the text for a
+    /// link to it
+    async fn extract_note(app: &mut App<'_>) -> Result<()> {
+        let Some(title) = clean_title(&app.user_input.text.lines()[0]) else {
+            app.user_input.set_state(ComponentState::Error);
+            return Ok(());
+        };
+        if app
+            .note_list
+            .note_identifiers
+            .iter()
+            .any(|nid| nid.title == title)
+        {
+            app.user_input.set_state(ComponentState::Error);
+            return Ok(());
+        }
+        let Some((start, end)) = app.pending_extract else {
+            warn!("extract_note::no selection to extract");
+            return Ok(());
+        };
+
+        let text = app.editor.text_between(start, end);
+        // The list may not hold every title, the store has the final say
+        let id = match app.store.save_note(&title, &app.seal(&text)?, false).await {
+            Ok(id) => id,
+            Err(err) if err.is::<DuplicateTitleError>() => {
+                app.user_input.set_state(ComponentState::Error);
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        app.pending_extract = None;
+        app.note_list.update(NoteIdentifier {
+            id,
+            title: title.clone(),
+        });
+        app.editor.delete_between(start, end);
+        Self::insert_link_to_note(app, id);
+        app.user_input = UserInput::new(ComponentState::Active, InputAction::Note);
+
+        app.switch_to_main();
+        app.show_status(format!("Moved the selection to \"{}\"", title), MessageType::Info);
+        Ok(())
+    }
+
+    /// Imports the Markdown files under the typed directory, a bad path is reported
+    /// without leaving the prompt
+    async fn import_notes(app: &mut App<'_>) -> Result<()> {
+        let dir = app.user_input.text.lines()[0].trim().to_owned();
+        if dir.is_empty() {
+            app.user_input.set_state(ComponentState::Error);
+            return Ok(());
+        }
+
+        let imported = import::import_dir(&app.store, Path::new(&dir), app.cipher.as_ref()).await;
+        let summary = match imported {
+            Ok(summary) => summary,
+            Err(e) => {
+                Self::warn_user(app, &e.to_string());
+                return Ok(());
+            }
+        };
+        let imported = summary.imported.len();
+        for nid in summary.imported {
+            app.note_list.update(nid);
+        }
+        app.user_input = UserInput::new(ComponentState::Active, InputAction::Note);
+
+        app.switch_to_main();
+        app.show_status(
+            format!("Imported {} notes, skipped {}", imported, summary.skipped),
+            MessageType::Info,
+        );
+        Ok(())
+    }
+
+    /// Tags every marked note with the typed tag, "-tag" takes it off them instead
+    async fn tag_marked_notes(app: &mut App<'_>) -> Result<()> {
+        let text = app.user_input.text.lines()[0].trim().to_owned();
+        let (tag, tagged) = match text.strip_prefix('-') {
+            Some(tag) => (tag.trim(), false),
+            None => (text.strip_prefix('+').unwrap_or(&text).trim(), true),
+        };
+        if tag.is_empty() || tag.contains(char::is_whitespace) {
+            app.user_input.set_state(ComponentState::Error);
+            return Ok(());
+        }
+
+        let ids = app.note_list.marked.iter().copied().collect::<Vec<i64>>();
+        app.store.tag_notes(&ids, tag, tagged).await?;
+        app.note_list.tag_notes(&ids, tag, tagged);
+        app.note_list.marked.clear();
+
+        let msg = match tagged {
+            true => format!("Tagged {} notes #{}", ids.len(), tag),
+            false => format!("Removed #{} from {} notes", tag, ids.len()),
+        };
+        Self::close_tag_input(app);
+        app.show_status(msg, MessageType::Info);
+        Ok(())
+    }
+
+    /// Back to the note list the tagging started from
+    fn close_tag_input(app: &mut App) {
+        app.user_input = UserInput::new(ComponentState::Active, InputAction::Note);
+        app.switch_to_prev_screen();
+        match app.current_screen {
+            Screen::LoadNote => app.set_active_widget(ActiveWidget::NoteList),
+            _ => app.set_active_widget(ActiveWidget::Sidebar),
+        }
+    }
+
+    /// Shows a warning in the status line, the current screen stays put
+    fn warn_user(app: &mut App, msg: &str) {
+        warn!("{}", msg);
+        app.show_status(msg.to_owned(), MessageType::Warning);
+    }
+
+    /// The note under the list selection, the selection can briefly outlive the note
+    fn selected_note_id(app: &mut App) -> Option<i64> {
+        let id = app.note_list.selected_id();
+        if id.is_none() {
+            Self::warn_user(app, "No note is selected");
+        }
+        id
+    }
+
+    /// Asks for the target of the link just added to the textarea
+    fn open_linked_note_prompt(app: &mut App) {
+        let link_id = app.editor.body.next_link_id.saturating_sub(1);
+        let Some(link) = app.editor.body.links.get(&link_id) else {
+            warn!("open_linked_note_prompt::missing textarea link {}", link_id);
+            app.editor.body.new_link = false;
+            return;
+        };
+        app.pending_link = Some(*link);
+
+        // Set the user_input widget to create a new linked note
+        app.prev_screen = app.current_screen;
+        app.current_screen = Screen::NewLinkedNote;
+        app.user_input.set_action(InputAction::LinkedNote);
+        app.user_input.set_state(ComponentState::Active);
+        app.active_widget = Some(ActiveWidget::NoteTitleInput);
+        app.note_list.set_state(ComponentState::Inactive);
+        app.note_list.set_mode(NoteListMode::Fullscreen);
+        app.note_list.set_action(NoteListAction::LinkNote);
+        app.editor.body.new_link = false;
+    }
+
+    /// Typing goes to the new note title, or filters the existing notes to link to
+    async fn link_target_input(app: &mut App<'_>, input: Input) -> Result<()> {
+        match app.active_widget {
+            Some(ActiveWidget::NoteTitleInput) => {
+                app.user_input.text.input(input);
+                if app.user_input.get_state() == ComponentState::Error {
+                    app.user_input.set_state(ComponentState::Active);
+                }
+            }
+            Some(ActiveWidget::NoteList) => {
+                if let Input {
+                    key: Key::Char(c),
+                    ctrl: false,
+                    alt: false,
+                    ..
+                } = input
+                {
+                    // Matches may sit in pages that haven't been loaded yet
+                    Self::load_rest_of_note_list(app).await?;
+                    app.note_list.push_filter(c);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Leaves the linked note prompt, removing the pending link so the editor isn't
+    /// left with a link to nothing
+    fn cancel_linked_note(app: &mut App) {
+        app.switch_to_prev_screen();
+        app.note_list.set_mode(NoteListMode::Sidebar);
+        app.note_list.set_action(NoteListAction::LoadNote);
+        app.active_widget = Some(ActiveWidget::Editor);
+
+        let link_id = app
+            .pending_link
+            .take()
+            .map_or(app.editor.body.next_link_id.saturating_sub(1), |link| link.id);
+        app.editor.body.delete_link(link_id);
+        app.user_input = UserInput::new(ComponentState::Active, InputAction::Note);
+        app.note_list.clear_filter();
+    }
+
+    fn link_note(app: &mut App, linked_id: i64) {
+        let Some(textarea_link) = app.pending_link.take() else {
+            app.note_list.clear_filter();
+            app.switch_to_main();
+            Self::warn_user(app, "There is no link waiting for a note");
+            return;
+        };
+
+        let parent_id = app.editor.note_id.or_else(|| {
+            app.note_list
+                .note_identifiers
+                .iter()
+                .find(|nid| nid.title == app.editor.title)
+                .map(|nid| nid.id)
+        });
+        let Some(parent_id) = parent_id else {
+            app.editor.body.delete_link(textarea_link.id);
+            app.note_list.clear_filter();
+            app.switch_to_main();
+            Self::warn_user(app, "Save the note before linking it to another");
+            return;
+        };
+
+        let new_link = Link {
+            id: parent_id,
+            text_id: textarea_link.id as i64,
+            linked_id,
+            row: textarea_link.row,
+            start_col: textarea_link.start_col,
+            end_col: textarea_link.end_col,
+            saved: false,
+            updated: false,
+            deleted: false,
+            anchor: None,
+        };
+
+        let link_id = new_link.text_id;
+        app.editor.links.insert(link_id, new_link);
+        app.note_list.clear_filter();
+        app.switch_to_main();
+        app.editor.body.new_link = false;
+    }
+
+    fn close_link_picker(app: &mut App) {
+        app.note_list.clear_filter();
+        app.note_list.set_mode(NoteListMode::Sidebar);
+        app.note_list.set_action(NoteListAction::LoadNote);
+        app.switch_to_prev_screen();
+        app.set_active_widget(ActiveWidget::Editor);
+    }
+
+    /// `gf`, makes the word under the cursor a link to the note with that title.
+    /// Without one, the note is made as if the word had been typed as a new link.
+    async fn link_word(app: &mut App<'_>) -> Result<()> {
+        if app.editor.read_only {
+            Self::warn_user(app, "Note is read-only, <Alt-R> to make it editable");
+            return Ok(());
+        }
+        let Some((row, start_col, end_col)) = app.editor.word_under_cursor() else {
+            Self::warn_user(app, "There is no word under the cursor");
+            return Ok(());
+        };
+        if app.editor.body.in_link((row, start_col)).is_some() {
+            Self::warn_user(app, "The cursor is already on a link");
+            return Ok(());
+        }
+        if app.editor.note_id.is_none() {
+            Self::warn_user(app, "Save the note before linking it to another");
+            return Ok(());
+        }
+        let word = app.editor.body.lines()[row]
+            .chars()
+            .skip(start_col)
+            .take(end_col + 1 - start_col)
+            .collect::<String>();
+        Self::link_span(app, row, start_col, end_col, &word).await
+    }
+
+    /// Makes `row`, `start_col..=end_col` a link to the note titled `title`, which is
+    /// made first if there's no such note
+    async fn link_span(
+        app: &mut App<'_>,
+        row: usize,
+        start_col: usize,
+        end_col: usize,
+        title: &str,
+    ) -> Result<()> {
+        let text_id = app.editor.body.next_link_id;
+        let textarea_link = TextAreaLink {
+            id: text_id,
+            row,
+            start_col,
+            end_col,
+            edited: false,
+            deleted: false,
+        };
+        app.editor.body.links.insert(text_id, textarea_link);
+        app.editor.body.next_link_id += 1;
+        app.pending_link = Some(textarea_link);
+
+        let existing = app
+            .store
+            .load_note_identifiers()
+            .await?
+            .into_iter()
+            .find(|nid| nid.title == title);
+        match existing {
+            Some(nid) => Self::link_note(app, nid.id),
+            None => {
+                app.user_input = UserInput::new(ComponentState::Active, InputAction::LinkedNote);
+                app.user_input.text.insert_str(title);
+                Self::input_new_note(app, true).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keys the `[[` popup takes over, the rest edit the text as usual
+    async fn link_suggestions_input(app: &mut App<'_>, action: &Action) -> Result<bool> {
+        let Some(suggestions) = app.link_suggestions.as_mut() else {
+            return Ok(false);
+        };
+        match action {
+            Action::Esc => app.link_suggestions = None,
+            Action::Tab | Action::Down(Input { key: Key::Down, .. }) => suggestions.picker.next(),
+            Action::PrevLink | Action::Up(Input { key: Key::Up, .. }) => suggestions.picker.prev(),
+            Action::Activate(_) => Self::accept_link_suggestion(app).await?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Opens the `[[` popup in insert mode, or refilters it on the text typed since
+    async fn update_link_suggestions(app: &mut App<'_>) -> Result<()> {
+        let editing = app.current_screen == Screen::Main
+            && app.active_widget == Some(ActiveWidget::Editor)
+            && app.editor.mode == EditorMode::Insert;
+        if !editing {
+            app.link_suggestions = None;
+            return Ok(());
+        }
+        let cursor = app.editor.body.cursor();
+        let line = app.editor.body.lines()[cursor.0].clone();
+
+        if let Some(suggestions) = app.link_suggestions.as_mut() {
+            match suggestions.query(&line, cursor) {
+                Some(query) => suggestions.set_query(&query),
+                None => app.link_suggestions = None,
+            }
+        } else if let Some(anchor) = link_trigger(&line, cursor) {
+            let entries = app.store.load_note_identifiers().await?;
+            app.link_suggestions = Some(LinkSuggestions::new(anchor, entries));
+        }
+        Ok(())
+    }
+
+    /// Swaps the `[[` and the text typed after it for a link to the picked note. With
+    /// no match the typed text becomes the title of a new linked note.
+    async fn accept_link_suggestion(app: &mut App<'_>) -> Result<()> {
+        let Some(suggestions) = app.link_suggestions.take() else {
+            return Ok(());
+        };
+        let title = suggestions
+            .selected_title()
+            .or_else(|| clean_title(&suggestions.picker.filter));
+        let Some(title) = title else {
+            return Ok(());
+        };
+        if app.editor.note_id.is_none() {
+            Self::warn_user(app, "Save the note before linking it to another");
+            return Ok(());
+        }
+
+        let (row, col) = suggestions.anchor;
+        let cursor_col = app.editor.body.cursor().1;
+        // `auto_pairs` may have closed the `[[` with `]]`, which are removed as well
+        let closing = app.editor.body.lines()[row]
+            .chars()
+            .skip(cursor_col)
+            .take(2)
+            .take_while(|&c| c == ']')
+            .count();
+        app.editor
+            .body
+            .move_cursor(CursorMove::Jump(row as u16, col as u16));
+        for _ in 0..(cursor_col - col + closing) {
+            app.editor.body.delete_next_char();
+        }
+        let text = match &suggestions.heading {
+            Some(heading) => format!("{}#{}", title, heading),
+            None => title.clone(),
+        };
+        app.editor.body.insert_str(&text);
+        Self::check_link_moved(app);
+
+        let end_col = col + text.chars().count() - 1;
+        let text_id = app.editor.body.next_link_id as i64;
+        Self::link_span(app, row, col, end_col, &title).await?;
+        // Only an existing note can have the heading, a new one opens empty
+        if let Some(link) = app.editor.links.get_mut(&text_id) {
+            link.anchor = suggestions.heading;
+        }
+        Ok(())
+    }
+
+    /// Types the title of `linked_id` at the cursor and makes it a link to that note
+    fn insert_link_to_note(app: &mut App, linked_id: i64) {
+        let Some(title) = app
+            .note_list
+            .note_identifiers
+            .iter()
+            .find(|nid| nid.id == linked_id)
+            .map(|nid| nid.title.clone())
+        else {
+            warn!("insert_link_to_note::note {} is not in the list", linked_id);
+            return;
+        };
+        let parent_id = app.editor.note_id.or_else(|| {
+            app.note_list
+                .note_identifiers
+                .iter()
+                .find(|nid| nid.title == app.editor.title)
+                .map(|nid| nid.id)
+        });
+        let Some(parent_id) = parent_id else {
+            Self::warn_user(app, "Save the note before linking it to another");
+            return;
+        };
+        let (row, col) = app.editor.body.cursor();
+        if app.editor.body.in_link((row, col)).is_some() {
+            Self::warn_user(app, "The cursor is already on a link");
+            return;
+        }
+
+        app.editor.body.insert_str(&title);
+        // Links after the cursor on the same row have moved along
+        Self::check_link_paste(app);
+
+        let text_id = app.editor.body.next_link_id;
+        let end_col = col + title.chars().count() - 1;
+        app.editor.body.links.insert(
+            text_id,
+            TextAreaLink {
+                id: text_id,
+                row,
+                start_col: col,
+                end_col,
+                edited: false,
+                deleted: false,
+            },
+        );
+        app.editor.body.next_link_id += 1;
+        app.editor.links.insert(
+            text_id as i64,
+            Link {
+                id: parent_id,
+                text_id: text_id as i64,
+                linked_id,
+                row,
+                start_col: col,
+                end_col,
+                saved: false,
+                updated: false,
+                deleted: false,
+                anchor: None,
+            },
+        );
+    }
+
+    fn check_link_edits(app: &mut App<'_>) {
+        for link in app.editor.links.values_mut() {
+            let Some(ta_link) = app.editor.body.links.get(&(link.text_id as usize)) else {
+                warn!("check_link_edits::no textarea link for {}", link.text_id);
+                continue;
+            };
+            link.deleted = ta_link.deleted;
+        }
+    }
+
+    fn check_link_paste(app: &mut App<'_>) {
+        for ta_link in app.editor.body.links.values_mut() {
+            if let Some(ed_link) = app.editor.links.get_mut(&(ta_link.id as i64)) {
+                if ed_link.moved(ta_link) {
+                    ed_link.row = ta_link.row;
+                    ed_link.start_col = ta_link.start_col;
+                    ed_link.end_col = ta_link.end_col;
+                    ed_link.updated = true;
+                }
+            } else {
+                let Some(prev_copy_id) = app.editor.body.copied_link_ids.remove(&ta_link.id)
+                else {
+                    warn!("check_link_paste::unknown pasted link {}", ta_link.id);
+                    continue;
+                };
+                let Some(linked_note_id) = app.editor.links.get(&(prev_copy_id as i64)) else {
+                    warn!("check_link_paste::copied link {} is gone", prev_copy_id);
+                    continue;
+                };
+                let Some(note_id) = app.editor.note_id else {
+                    warn!("check_link_paste::pasted link into an unsaved note");
+                    continue;
+                };
+
+                let copied_link = Link {
+                    id: note_id,
+                    text_id: ta_link.id as i64,
+                    linked_id: linked_note_id.linked_id,
+                    row: ta_link.row,
+                    start_col: ta_link.start_col,
+                    end_col: ta_link.end_col,
+                    saved: false,
+                    updated: false,
+                    deleted: false,
+                    anchor: linked_note_id.anchor.clone(),
+                };
+
+                app.editor.links.insert(copied_link.text_id, copied_link);
+            }
+        }
+    }
+
+    fn check_link_deletion(app: &mut App<'_>, key: &Key) {
+        let delete_amount = app.editor.body.deleted_link_ids.len();
+
+        if DELETE_KEYS.contains(key) && delete_amount > 0 {
+            while let Some(textarea_id) = app.editor.body.deleted_link_ids.pop() {
+                // guards against cases where link hasn't been saved to editor yet
+                let ta_id_int = textarea_id as i64;
+                if let Some(link) = app.editor.links.get_mut(&ta_id_int) {
+                    link.deleted = true;
+                    app.editor.deleted_link_ids.push(ta_id_int);
+                }
+            }
+        }
+    }
+
+    fn check_link_moved(app: &mut App) {
+        for link in app.editor.links.values_mut() {
+            let Some(ta_link) = app.editor.body.links.get(&(link.text_id as usize)) else {
+                warn!("check_link_moved::no textarea link for {}", link.text_id);
+                continue;
+            };
+
+            if link.moved(ta_link) {
+                link.row = ta_link.row;
+                link.start_col = ta_link.start_col;
+                link.end_col = ta_link.end_col;
+                link.updated = true;
+            }
+        }
+    }
+
+    async fn input_new_note_title(app: &mut App<'_>) -> Result<()> {
+        let Some(title) = clean_title(&app.user_input.text.lines()[0]) else {
+            app.user_input.set_state(ComponentState::Error);
+            return Ok(());
+        };
+
+        match app
+            .note_list
+            .note_identifiers
+            .iter()
+            .any(|nid| nid.title == title)
+        {
+            true => {
+                app.user_input.set_state(ComponentState::Error);
+            }
+            false => {
+                app.editor.set_title(title.clone());
+                app.switch_to_main();
+                if let Some(id) = app.editor.note_id {
+                    Self::rename_note(app, id, title).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves a note's new title, leaving unsaved body edits unsaved, then offers to
+    /// retitle the links other notes have to it
+    async fn rename_note(app: &mut App<'_>, id: i64, title: String) -> Result<()> {
+        let stored = app.store.load_note(id).await?;
+        let body = stored.body.unwrap_or_default();
+        if let Err(err) = app.store.update_note(&title, &body, stored.has_links, id).await {
+            return match err.downcast_ref::<DuplicateTitleError>() {
+                Some(dup_err) => {
+                    app.show_status(dup_err.to_string(), MessageType::Error);
+                    Ok(())
+                }
+                None => Err(err),
+            };
+        }
+        app.editor.saved_title = title.clone();
+        app.note_list.replace(NoteIdentifier {
+            id,
+            title: title.clone(),
+        });
+
+        let backlinks = app.store.load_backlinks(id).await?;
+        if backlinks.is_empty() {
+            return Ok(());
+        }
+
+        app.pending_rename = Some((id, title.trim().to_owned()));
+        app.prev_screen = Screen::Main;
+        app.current_screen = Screen::RenameLinksConfirmation;
+        app.user_msg = UserMessage::new(
+            format!(
+                "Update {} link(s) to show the new title \"{}\"? (y/n)",
+                backlinks.len(),
+                title.trim()
+            ),
+            MessageType::Info,
+        );
+        Ok(())
+    }
+
+    /// Rewrites the link text in every note linking to the renamed note, all of them or
+    /// none. Notes open with unsaved edits are left alone, the edits would overwrite
+    /// the new text.
+    async fn retitle_backlinks(app: &mut App<'_>, id: i64, title: &str) -> Result<()> {
+        let mut parent_ids = app
+            .store
+            .load_backlinks(id)
+            .await?
+            .into_iter()
+            .map(|link| link.parent_note_id)
+            .collect::<Vec<i64>>();
+        parent_ids.sort();
+        parent_ids.dedup();
+
+        let mut skipped = 0;
+        let mut rewrites = vec![];
+        for parent_id in parent_ids {
+            if app
+                .tab_index(parent_id)
+                .is_some_and(|idx| app.tab(idx).is_dirty())
+            {
+                skipped += 1;
+                continue;
+            }
+
+            let note = app.store.load_note(parent_id).await?;
+            let mut lines = app
+                .open(note.body.unwrap_or_default())?
+                .split('\n')
+                .map(|line| line.to_owned())
+                .collect::<Vec<String>>();
+            let mut links = app.store.load_note_links(parent_id).await?;
+
+            if retitle_links(&mut lines, &mut links, id, title) > 0 {
+                rewrites.push(BodyRewrite {
+                    id: parent_id,
+                    body: app.seal(&lines.join("\n"))?,
+                    links,
+                });
+            }
+        }
+        let retitled = rewrites.iter().map(|rewrite| rewrite.id).collect::<Vec<i64>>();
+        app.store.rewrite_note_bodies(rewrites).await?;
+
+        // Open tabs would otherwise still show, and later save, the old text
+        for parent_id in retitled {
+            if let Some(idx) = app.tab_index(parent_id) {
+                let (title, body, links) = Self::read_note(app, parent_id).await?;
+                let max_col = app.get_max_col();
+                let editor = match idx == app.active_tab {
+                    true => &mut app.editor,
+                    false => &mut app.tabs[idx],
+                };
+                editor.refresh(title, body, links, Some(parent_id), max_col);
+            }
+        }
+
+        if skipped > 0 {
+            Self::warn_user(
+                app,
+                &format!("{} note(s) with unsaved edits kept the old link text", skipped),
+            );
+        }
+        Ok(())
+    }
+
+    /// Runs a `s/pat/rep/` command, straight away or one confirmed match at a time
+    fn start_replace(app: &mut App, sub: Substitute) {
+        match sub.scope {
+            ReplaceScope::Confirm => {
+                app.prev_screen = Screen::Main;
+                Self::next_replace(app, sub, true);
+            }
+            ReplaceScope::Next | ReplaceScope::All => {
+                let replaced = app.editor.replace(&sub);
+                info!("Replaced {} matches of {:?}", replaced, sub.pattern);
+                Self::sync_replaced_links(app);
+            }
+        }
+    }
+
+    /// Moves to the next match to confirm, or back to the editor once there are none left
+    fn next_replace(app: &mut App, sub: Substitute, inclusive: bool) {
+        let cursor = app.editor.body.cursor();
+        match app.editor.next_substitution(&sub, cursor, inclusive) {
+            Some((row, col)) => {
+                app.editor
+                    .body
+                    .move_cursor(CursorMove::Jump(row as u16, col as u16));
+                app.pending_replace = Some(sub);
+                app.current_screen = Screen::ReplaceConfirmation;
+                app.user_msg = UserMessage::new(
+                    "Replace this match? (y/n)".to_owned(),
+                    MessageType::Info,
+                );
+            }
+            None => {
+                app.pending_replace = None;
+                app.switch_to_main();
+            }
+        }
+    }
+
+    /// Replacements delete and insert text, so the editor links must follow the textarea
+    fn sync_replaced_links(app: &mut App) {
+        Self::check_link_deletion(app, &Key::Delete);
+        Self::check_link_moved(app);
+    }
+
+    /// An invalid regex is shown in the searchbar and the previous pattern stays active
+    fn update_search(app: &mut App) {
+        let search_pattern = match app.searchbar.build_pattern() {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                app.searchbar.set_error(&e);
+                return;
+            }
+        };
+        app.searchbar.error = None;
+
+        if app.editor.set_search(&search_pattern) {
+            info!("Searching for {:?}", search_pattern);
+            app.editor.body.search_forward(true);
+            app.searchbar.pattern = search_pattern;
+        }
+        Self::update_search_counter(app);
+    }
+
+    /// Applies a key typed into the searchbar, which stays open until Esc or a replace
+    fn searchbar_input(app: &mut App, input: Input) {
+        match app.searchbar.handle_input(input) {
+            SearchbarAction::Update => Self::update_search(app),
+            SearchbarAction::NextMatch => {
+                app.editor.body.search_forward(false);
+                Self::update_search_counter(app);
+            }
+            SearchbarAction::PrevMatch => {
+                app.editor.body.search_back(false);
+                Self::update_search_counter(app);
+            }
+            SearchbarAction::Replace => Self::replace_from_searchbar(app),
+            SearchbarAction::Close => {
+                app.editor.searchbar_open = false;
+                app.searchbar_state = SearchbarState::Hidden;
+                app.set_active_widget(ActiveWidget::Editor);
+            }
+        }
+    }
+
+    fn replace_from_searchbar(app: &mut App) {
+        let substitute = app.searchbar.substitute();
+        app.searchbar.clear_search();
+        Self::toggle_searchbar(app);
+
+        match substitute {
+            Some(Ok(_)) if app.editor.read_only => {
+                Self::warn_user(app, "Note is read-only, <Alt-R> to make it editable");
+            }
+            Some(Ok(sub)) => Self::start_replace(app, sub),
+            Some(Err(e)) => {
+                app.show_status(format!("Invalid replace pattern: {}", e), MessageType::Error);
+            }
+            None => {}
+        }
+    }
+
+    fn update_search_counter(app: &mut App) {
+        let matches = app.editor.find_matches(&app.searchbar.pattern);
+        app.searchbar
+            .update_matches(&matches, app.editor.body.cursor());
+    }
+
+    fn toggle_searchbar(app: &mut App) {
+        match app.searchbar_state {
+            SearchbarState::Open => {
+                app.searchbar_state = SearchbarState::Hidden;
+                app.editor.searchbar_open = false;
+                app.set_active_widget(ActiveWidget::Editor);
+                app.searchbar.state = ComponentState::Inactive;
+            }
+            SearchbarState::Hidden => {
+                app.searchbar_state = SearchbarState::Open;
+                app.editor.searchbar_open = true;
+                app.set_active_widget(ActiveWidget::Searchbar);
+                app.searchbar.state = ComponentState::Active;
+            }
+        }
+    }
+
+    fn toggle_sidebar(app: &mut App) {
+        match app.sidebar_state {
+            SidebarState::Open => {
+                app.sidebar_state = SidebarState::Hidden(app.sidebar_size);
+                app.sidebar_size = 0;
+                app.editor.sidebar_open = false;
+                app.searchbar.sidebar_open = false;
+                app.resize_editor();
+                match app.editor.searchbar_open {
+                    true => app.set_active_widget(ActiveWidget::Searchbar),
+                    false => app.set_active_widget(ActiveWidget::Editor),
+                }
+            }
+            SidebarState::Hidden(n) => {
+                app.sidebar_size = n;
+                app.sidebar_state = SidebarState::Open;
+                app.editor.sidebar_open = true;
+                app.searchbar.sidebar_open = true;
+                app.resize_editor();
+                app.set_active_widget(ActiveWidget::Sidebar);
+            }
+        }
+    }
+
+    /// Selects the open note in the sidebar, opening the sidebar when it's hidden
+    async fn reveal_note(app: &mut App<'_>) -> Result<()> {
+        let Some(note_id) = app.editor.note_id else {
+            Self::warn_user(app, "The note isn't saved yet, so it isn't in the note list");
+            return Ok(());
+        };
+
+        // The note may sit in a page that hasn't been loaded yet
+        let listed = app.note_list.note_identifiers.iter().any(|nid| nid.id == note_id);
+        if !listed {
+            Self::load_rest_of_note_list(app).await?;
+        }
+
+        app.note_list.clear_filter();
+        if !app.note_list.select_note(note_id) {
+            Self::warn_user(app, "The note isn't in the note list");
+            return Ok(());
+        }
+        if let SidebarState::Hidden(_) = app.sidebar_state {
+            Self::toggle_sidebar(app);
+        }
+        Ok(())
+    }
+
+    /// Hides everything but the editor, turning it off puts the layout back as it was
+    fn toggle_zen_mode(app: &mut App) {
+        let Some(layout) = app.zen_layout.take() else {
+            app.zen_layout = Some(ZenLayout {
+                sidebar_state: app.sidebar_state,
+                sidebar_size: app.sidebar_size,
+                searchbar_state: app.searchbar_state,
+                active_widget: app.active_widget,
+            });
+            if app.sidebar_state == SidebarState::Open {
+                Self::toggle_sidebar(app);
+            }
+            if app.searchbar_state == SearchbarState::Open {
+                Self::toggle_searchbar(app);
+            }
+            app.set_active_widget(ActiveWidget::Editor);
+            return;
+        };
+
+        let sidebar_open = layout.sidebar_state == SidebarState::Open;
+        app.sidebar_state = layout.sidebar_state;
+        app.sidebar_size = layout.sidebar_size;
+        app.editor.sidebar_open = sidebar_open;
+        app.searchbar.sidebar_open = sidebar_open;
+        app.resize_editor();
+        if app.searchbar_state != layout.searchbar_state {
+            Self::toggle_searchbar(app);
+        }
+        if let Some(active) = layout.active_widget {
+            app.set_active_widget(active);
+        }
+    }
+
+    /// Asks first unless `confirm_quit` says otherwise, any unsaved tab counts as dirty
+    fn show_exit_screen(app: &mut App) {
+        let confirm = match app.confirm_quit {
+            ConfirmQuit::Always => true,
+            ConfirmQuit::IfDirty => (0..app.tabs.len()).any(|idx| app.tab(idx).is_dirty()),
+            ConfirmQuit::Never => false,
+        };
+        match confirm {
+            true => app.current_screen = Screen::Exiting,
+            false => Self::exit(app),
+        }
+    }
+
+    fn exit(app: &mut App) {
+        app.state = AppState::Exit;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{db_mac::DbMac, init_db::create_test_db},
+        tui::{
+            config::Config,
+            lock::{hash_passphrase, IdleLock},
+            note_list::TreeRow,
+            vaults::DEFAULT_VAULT,
+        },
+    };
+
+    async fn test_app<'a>(titles: &[&str]) -> App<'a> {
+        let db = create_test_db().await;
+        for title in titles {
+            DbMac::save_note(&db, title, "", false)
+                .await
+                .expect("test note should save");
+        }
+        let note_identifiers = DbMac::load_note_identifiers(&db)
+            .await
+            .expect("test notes should load");
+        let note_count = note_identifiers.len();
+
+        App::new(Config::default(), db, note_identifiers, note_count, 140)
+    }
+
+    #[tokio::test]
+    async fn test_save_note_duplicate_title_shows_error() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let second_id = app.note_list.note_identifiers[1].id;
+
+        Events::load_note(&mut app, second_id)
+            .await
+            .expect("note should load");
+        app.editor.set_title("First".to_owned());
+        app.editor.body.insert_str("unsaved edit");
+
+        let result = Events::execute_action(&mut app, Action::SaveNote).await;
+
+        assert!(result.is_ok());
+        assert_eq!(app.current_screen, Screen::Main);
+        let status = app.status_msg.as_ref().expect("the error should be shown");
+        assert!(matches!(status.typ, MessageType::Error));
+        assert_eq!(status.msg, "A note titled \"First\" already exists");
+        // Editor keeps the unsaved state
+        assert_eq!(app.editor.title, "First");
+        assert_eq!(app.editor.note_id, Some(second_id));
+        assert_eq!(app.editor.body.lines(), vec!["unsaved edit".to_string()]);
+
+        let db_note = DbMac::load_note(&app.db, second_id)
+            .await
+            .expect("note should still exist");
+        assert_eq!(db_note.title, "Second");
+    }
+
+    /// Loads "Parent" with a fresh textarea link on "Taken" waiting for a target
+    async fn pending_link_app<'a>(titles: &[&str]) -> App<'a> {
+        let mut app = test_app(titles).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, parent_id).await.unwrap();
+        app.switch_to_main();
+
+        app.editor.body.insert_str("see Taken");
+        app.editor.body.links.insert(
+            0,
+            TextAreaLink {
+                id: 0,
+                row: 0,
+                start_col: 4,
+                end_col: 8,
+                edited: false,
+                deleted: false,
+            },
+        );
+        app.editor.body.next_link_id = 1;
+        Events::open_linked_note_prompt(&mut app);
+        app
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_linked_note_title_cancel_removes_link() {
+        let mut app = pending_link_app(&["Parent", "Taken"]).await;
+        app.user_input.text.insert_str("Taken");
+
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .unwrap();
+
+        // The prompt stays open so another title can be tried
+        assert_eq!(app.current_screen, Screen::NewLinkedNote);
+        assert_eq!(app.user_input.get_state(), ComponentState::Error);
+        assert!(app.pending_link.is_some());
+
+        Events::execute_action(&mut app, Action::Esc).await.unwrap();
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert!(app.pending_link.is_none());
+        assert!(app
+            .editor
+            .body
+            .links
+            .get(&0)
+            .map_or(true, |link| link.deleted));
+        assert!(app.editor.links.is_empty());
+        assert_eq!(app.user_input.get_state(), ComponentState::Active);
+    }
+
+    #[tokio::test]
+    async fn test_link_target_fuzzy_filter() {
+        let mut app =
+            pending_link_app(&["Parent", "Meeting notes", "Groceries", "Monthly goals"]).await;
+        let meeting_id = app.note_list.note_identifiers[1].id;
+
+        Events::execute_action(&mut app, Action::Tab).await.unwrap();
+        assert_eq!(app.active_widget, Some(ActiveWidget::NoteList));
+
+        type_str(&mut app, "mt").await;
+        let titles = app
+            .note_list
+            .filtered()
+            .into_iter()
+            .map(|idx| app.note_list.note_identifiers[idx].title.clone())
+            .collect::<Vec<String>>();
+        assert_eq!(titles, vec!["Meeting notes", "Monthly goals"]);
+
+        // 'n' is bound to cancel, but here it's part of the filter
+        let n = Input {
+            key: Key::Char('n'),
+            ..Default::default()
+        };
+        let action = Events::handle_key_event(n, &app.keymap);
+        Events::execute_action(&mut app, action).await.unwrap();
+        assert_eq!(app.note_list.filter, "mtn");
+        assert_eq!(app.note_list.selected_id(), Some(meeting_id));
+
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .unwrap();
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.links[&0].linked_id, meeting_id);
+        assert!(app.note_list.filter.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_link_preview_leaves_editor_and_db_alone() {
+        let mut app = test_app(&["Parent"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        let target_id = DbMac::save_note(&app.db, "Target", "first\nsecond", false)
+            .await
+            .unwrap();
+
+        let link = Link {
+            id: parent_id,
+            text_id: 0,
+            linked_id: target_id,
+            row: 0,
+            start_col: 0,
+            end_col: 5,
+            saved: true,
+            updated: false,
+            deleted: false,
+            anchor: None,
+        };
+        app.editor.refresh(
+            "Parent".to_owned(),
+            vec!["Target link".to_owned(), "unsaved".to_owned()],
+            HashMap::from([(0, link)]),
+            Some(parent_id),
+            app.max_col,
+        );
+        app.switch_to_main();
+
+        type_str(&mut app, "gd").await;
+
+        assert_eq!(app.current_screen, Screen::LinkPreview);
+        let preview = app.link_preview.clone().expect("preview should be open");
+        assert_eq!(preview.title, "Target");
+        assert_eq!(preview.lines, vec!["first", "second"]);
+        assert_eq!(app.editor.note_id, Some(parent_id));
+        assert_eq!(app.editor.body.lines(), ["Target link", "unsaved"]);
+        let parent = DbMac::load_note(&app.db, parent_id).await.unwrap();
+        assert_eq!(parent.body.unwrap_or_default(), "");
+
+        Events::execute_action(&mut app, Action::Esc).await.unwrap();
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert!(app.link_preview.is_none());
+        assert_eq!(app.editor.note_id, Some(parent_id));
+    }
+
+    #[tokio::test]
+    async fn test_follow_link_with_anchor_jumps_to_heading() {
+        let mut app = test_app(&["Parent"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        let mut body = (1..=30).map(|n| format!("line {}", n)).collect::<Vec<String>>();
+        body.push("## Next Steps".to_owned());
+        body.push("call Sam".to_owned());
+        let target_id = DbMac::save_note(&app.db, "Target", &body.join("\n"), false)
+            .await
+            .unwrap();
+        app.note_list.update(NoteIdentifier {
+            id: target_id,
+            title: "Target".to_owned(),
+        });
+
+        let link = Link {
+            id: parent_id,
+            text_id: 0,
+            linked_id: target_id,
+            row: 0,
+            start_col: 0,
+            end_col: 16,
+            saved: false,
+            updated: false,
+            deleted: false,
+            anchor: Some("next steps".to_owned()),
+        };
+        app.editor.refresh(
+            "Parent".to_owned(),
+            vec!["Target#next steps".to_owned()],
+            HashMap::from([(0, link)]),
+            Some(parent_id),
+            app.max_col,
+        );
+        app.switch_to_main();
+        Events::save_note(&mut app, "Parent", "Target#next steps", true, Some(parent_id))
+            .await
+            .unwrap();
+        let saved = DbMac::load_note_links(&app.db, parent_id).await.unwrap();
+        assert_eq!(saved[0].anchor.as_deref(), Some("next steps"));
+
+        Events::load_note(&mut app, parent_id).await.unwrap();
+        app.status_msg = None;
+        Events::follow_link(&mut app, 0).await.unwrap();
+
+        assert_eq!(app.editor.note_id, Some(target_id));
+        assert_eq!(app.editor.body.cursor(), (30, 0));
+        app.editor.update_viewport(10);
+        assert!(app.editor.scroll_top > 0);
+        assert!(app.status_msg.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_jump_back_after_two_navigations() {
+        let mut app = test_app(&["A", "B", "C"]).await;
+        let ids = app
+            .note_list
+            .note_identifiers
+            .iter()
+            .map(|nid| nid.id)
+            .collect::<Vec<i64>>();
+        for &id in &ids {
+            Events::load_note(&mut app, id).await.unwrap();
+        }
+        app.editor.body.insert_str("edited ");
+
+        Events::execute_action(&mut app, Action::JumpBack).await.unwrap();
+        assert_eq!(app.editor.note_id, Some(ids[1]));
+        // Leaving C saved its edits as usual
+        let c = DbMac::load_note(&app.db, ids[2]).await.unwrap();
+        assert_eq!(c.body.unwrap_or_default(), "edited ");
+
+        Events::execute_action(&mut app, Action::JumpBack).await.unwrap();
+        assert_eq!(app.editor.note_id, Some(ids[0]));
+        Events::execute_action(&mut app, Action::JumpBack).await.unwrap();
+        assert_eq!(app.editor.note_id, Some(ids[0]));
+
+        Events::execute_action(&mut app, Action::JumpForward).await.unwrap();
+        assert_eq!(app.editor.note_id, Some(ids[1]));
+        assert_eq!(app.jump_list.entries, ids);
+    }
+
+    #[tokio::test]
+    async fn test_activate_on_empty_note_list_does_not_panic() {
+        let mut app = test_app(&[]).await;
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+
+        app.switch_to_load_note();
+        let result = Events::execute_action(&mut app, Action::Activate(enter)).await;
+        assert!(result.is_ok());
+        assert_eq!(app.current_screen, Screen::LoadNote);
+        assert!(app
+            .status_msg
+            .as_ref()
+            .is_some_and(|status| matches!(status.typ, MessageType::Warning)));
+
+        app.switch_to_main();
+        app.set_active_widget(ActiveWidget::Sidebar);
+        let result = Events::execute_action(&mut app, Action::OpenInTab).await;
+        assert!(result.is_ok());
+        assert_eq!(app.editor.note_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_link_note_without_pending_link_does_not_panic() {
+        let mut app = test_app(&["Parent", "Target"]).await;
+        let target_id = app.note_list.note_identifiers[1].id;
+
+        Events::link_note(&mut app, target_id);
+
+        assert!(app.editor.links.is_empty());
+        assert!(app.status_msg.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_follow_link_missing_target_asks_to_create_it() {
+        let mut app = test_app(&["Parent"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+
+        let dangling = Link {
+            id: parent_id,
+            text_id: 0,
+            linked_id: 999,
+            row: 0,
+            start_col: 0,
+            end_col: 4,
+            saved: true,
+            updated: false,
+            deleted: false,
+            anchor: None,
+        };
+        app.editor.refresh(
+            "Parent".to_owned(),
+            vec!["Gone".to_owned()],
+            HashMap::from([(0, dangling)]),
+            Some(parent_id),
+            app.max_col,
+        );
+        app.switch_to_main();
+
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        let result = Events::execute_action(&mut app, Action::Activate(enter)).await;
+
+        assert!(result.is_ok());
+        assert_eq!(app.current_screen, Screen::CreateLinkTargetConfirmation);
+        assert_eq!(app.pending_link_target, Some(0));
+
+        Events::execute_action(&mut app, Action::Cancel)
+            .await
+            .expect("cancelling should not fail");
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.note_id, Some(parent_id));
+        assert_eq!(app.note_list.note_identifiers.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_follow_broken_link_creates_target() {
+        let mut app = test_app(&["Parent", "Target"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        let old_target_id = app.note_list.note_identifiers[1].id;
+        DbMac::update_note(&app.db, "Parent", "see Target", true, parent_id)
+            .await
+            .unwrap();
+        let link = DbNoteLink {
+            parent_note_id: parent_id,
+            textarea_id: 0,
+            textarea_row: 0,
+            start_col: 4,
+            end_col: 9,
+            linked_note_id: old_target_id,
+            anchor: None,
+        };
+        let mut tx = app.db.begin().await.unwrap();
+        DbMac::save_links(&mut tx, vec![link], parent_id).await.unwrap();
+        tx.commit().await.unwrap();
+        Events::load_note(&mut app, parent_id)
+            .await
+            .expect("note should load");
+        // The target goes away while the parent is still open
+        DbMac::delete_note(&app.db, old_target_id).await.unwrap();
+        app.note_list.remove(old_target_id);
+        app.switch_to_main();
+        app.editor.body.move_cursor(CursorMove::Jump(0, 5));
+
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("following a broken link should not fail");
+        Events::execute_action(&mut app, Action::Confirm)
+            .await
+            .expect("creating the target should not fail");
+
+        let target_id = app.editor.note_id.expect("target should be open");
+        assert_ne!(target_id, old_target_id);
+        assert_eq!(app.editor.title, "Target");
+        assert_eq!(app.tabs.len(), 2);
+        let links = DbMac::load_note_links(&app.db, parent_id).await.unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].linked_note_id, target_id);
+        assert_eq!((links[0].start_col, links[0].end_col), (4, 9));
+    }
+
+    #[tokio::test]
+    async fn test_note_list_scroll_fetches_next_page() {
+        let titles = (0..15).map(|i| format!("Note {}", i)).collect::<Vec<String>>();
+        let mut app = test_app(&titles.iter().map(|t| t.as_str()).collect::<Vec<_>>()).await;
+        let first_page = DbMac::load_note_identifiers_page(&app.db, 0, 2).await.unwrap();
+        app.note_list = NoteList::new(
+            first_page,
+            15,
+            NoteListAction::LoadNote,
+            ComponentState::Active,
+            app.note_list.theme.clone(),
+        );
+
+        app.note_list.next();
+        app.note_list.next();
+        // Selection doesn't wrap while pages are still unloaded
+        assert_eq!(app.note_list.selected, 1);
+
+        Events::note_list_next(&mut app).await.unwrap();
+
+        assert_eq!(app.note_list.note_identifiers.len(), 15);
+        assert!(app.note_list.fully_loaded());
+        assert_eq!(app.note_list.selected, 2);
+        assert_eq!(app.note_list.note_identifiers[2].title, "Note 2");
+    }
+
+    #[tokio::test]
+    async fn test_folders_load_every_page_into_the_tree() {
+        let titles = (0..120).map(|i| format!("Note {}", i)).collect::<Vec<String>>();
+        let mut app = test_app(&titles.iter().map(|t| t.as_str()).collect::<Vec<_>>()).await;
+        let first_page = DbMac::load_note_identifiers_page(&app.db, 0, NOTE_PAGE_SIZE)
+            .await
+            .unwrap();
+        let last_id = app.note_list.note_identifiers[119].id;
+        DbMac::set_folder(&app.db, last_id, Some("work")).await.unwrap();
+        app.note_list = NoteList::new(
+            first_page,
+            120,
+            NoteListAction::LoadNote,
+            ComponentState::Active,
+            app.note_list.theme.clone(),
+        );
+        app.note_list.set_mode(NoteListMode::Sidebar);
+
+        Events::load_folders(&mut app).await.unwrap();
+
+        assert!(app.note_list.is_tree());
+        assert!(app.note_list.fully_loaded());
+        let rows = app.note_list.tree_rows();
+        assert_eq!(rows.len(), 121);
+        assert!(matches!(rows[1], TreeRow::Note { depth: 1, .. }));
+
+        // Wrapping from the last row lands on the folder, no page is left to fetch
+        app.note_list.selected = 120;
+        Events::note_list_next(&mut app).await.unwrap();
+        assert_eq!(app.note_list.selected, 0);
+        assert_eq!(app.note_list.note_identifiers.len(), 120);
+    }
+
+    #[tokio::test]
+    async fn test_note_list_prev_wraps_to_last_unloaded_note() {
+        let titles = (0..5).map(|i| format!("Note {}", i)).collect::<Vec<String>>();
+        let mut app = test_app(&titles.iter().map(|t| t.as_str()).collect::<Vec<_>>()).await;
+        let first_page = DbMac::load_note_identifiers_page(&app.db, 0, 2).await.unwrap();
+        app.note_list = NoteList::new(
+            first_page,
+            5,
+            NoteListAction::LoadNote,
+            ComponentState::Active,
+            app.note_list.theme.clone(),
+        );
+
+        Events::note_list_prev(&mut app).await.unwrap();
+
+        assert_eq!(app.note_list.selected, 4);
+        assert_eq!(app.note_list.note_identifiers[4].title, "Note 4");
+    }
+
+    #[tokio::test]
+    async fn test_restore_session_loads_last_note() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let second_id = app.note_list.note_identifiers[1].id;
+        let session = Session {
+            last_note_id: Some(second_id),
+            sidebar_open: true,
+            sidebar_size: 30,
+            searchbar_open: false,
+            search_history: vec!["foo".to_owned()],
+        };
+
+        Events::restore_session(&mut app, session).await.unwrap();
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.note_id, Some(second_id));
+        assert_eq!(app.editor.title, "Second");
+        assert_eq!(app.sidebar_state, SidebarState::Open);
+        assert_eq!(app.sidebar_size, 30);
+        assert_eq!(app.searchbar.history, ["foo"]);
+    }
+
+    #[tokio::test]
+    async fn test_startup_modes_pick_the_first_screen() {
+        let last_session = |id| Session {
+            last_note_id: Some(id),
+            ..Session::default()
+        };
+
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        app.startup = Startup::Welcome;
+        Events::start(&mut app, Some(last_session(first_id))).await.unwrap();
+        assert_eq!(app.current_screen, Screen::Welcome);
+        assert_eq!(app.editor.note_id, None);
+
+        let mut app = test_app(&["First"]).await;
+        app.startup = Startup::Blank;
+        Events::start(&mut app, None).await.unwrap();
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.note_id, None);
+
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        Events::start(&mut app, Some(last_session(first_id))).await.unwrap();
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.note_id, Some(first_id));
+
+        let mut app = test_app(&["First", "Second"]).await;
+        Events::start(&mut app, None).await.unwrap();
+        assert_eq!(app.current_screen, Screen::Welcome);
+    }
+
+    #[tokio::test]
+    async fn test_most_recent_startup_opens_the_last_saved_note() {
+        let mut app = test_app(&[]).await;
+        app.startup = Startup::MostRecent;
+        Events::start(&mut app, None).await.unwrap();
+        assert_eq!(app.current_screen, Screen::Welcome);
+
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        sqlx::query("UPDATE notes SET updated_at = updated_at + 60 WHERE id = ?")
+            .bind(first_id)
+            .execute(&app.db)
+            .await
+            .unwrap();
+        app.startup = Startup::MostRecent;
+
+        Events::start(&mut app, None).await.unwrap();
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.note_id, Some(first_id));
+        assert_eq!(app.editor.title, "First");
+    }
+
+    #[tokio::test]
+    async fn test_restore_session_stale_note_falls_back_to_welcome() {
+        let mut app = test_app(&["First"]).await;
+        let session = Session {
+            last_note_id: Some(999),
+            ..Session::default()
+        };
+
+        Events::restore_session(&mut app, session).await.unwrap();
+
+        assert_eq!(app.current_screen, Screen::Welcome);
+        assert_eq!(app.editor.note_id, None);
+        assert_eq!(app.sidebar_state, SidebarState::Hidden(18));
+    }
+
+    async fn type_str(app: &mut App<'_>, text: &str) {
+        for c in text.chars() {
+            let input = Input {
+                key: Key::Char(c),
+                ..Default::default()
+            };
+            Events::execute_action(app, Action::Edit(input))
+                .await
+                .expect("typing should not fail");
+        }
+    }
+
+    async fn search_app<'a>() -> App<'a> {
+        let mut app = test_app(&[]).await;
+        DbMac::save_note(&app.db, "Search", "foo bar foo\nbaz\nfoo", false)
+            .await
+            .expect("test note should save");
+        let note_id = DbMac::load_note_identifiers(&app.db)
+            .await
+            .expect("test notes should load")[0]
+            .id;
+        Events::load_note(&mut app, note_id)
+            .await
+            .expect("note should load");
+        Events::toggle_searchbar(&mut app);
+
+        app
+    }
+
+    #[tokio::test]
+    async fn test_search_counter_several_matches() {
+        let mut app = search_app().await;
+
+        type_str(&mut app, "foo").await;
+
+        assert_eq!(app.searchbar.match_count, 3);
+        assert_eq!(app.searchbar.match_counter(), "1/3");
+        assert_eq!(app.editor.body.cursor(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_search_counter_no_matches() {
+        let mut app = search_app().await;
+
+        type_str(&mut app, "qux").await;
+
+        assert_eq!(app.searchbar.match_count, 0);
+        assert_eq!(app.searchbar.match_idx, None);
+        assert_eq!(app.searchbar.match_counter(), "0/0");
+    }
+
+    #[tokio::test]
+    async fn test_search_counter_follows_cursor() {
+        let mut app = search_app().await;
+        type_str(&mut app, "foo").await;
+
+        app.editor.body.search_forward(false);
+        Events::update_search_counter(&mut app);
+
+        assert_eq!(app.editor.body.cursor(), (0, 8));
+        assert_eq!(app.searchbar.match_counter(), "2/3");
+    }
+
+    #[tokio::test]
+    async fn test_search_enter_steps_through_matches() {
+        let mut app = search_app().await;
+        type_str(&mut app, "foo").await;
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("moving to the next match should not fail");
+        assert_eq!(app.editor.body.cursor(), (0, 8));
+        assert_eq!(app.searchbar.match_counter(), "2/3");
+        assert_eq!(app.searchbar_state, SearchbarState::Open);
+        assert_eq!(app.active_widget, Some(ActiveWidget::Searchbar));
+        assert_eq!(app.searchbar.get_search_text(), "foo");
+
+        let ctrl_p = Input {
+            key: Key::Char('p'),
+            ctrl: true,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Edit(ctrl_p))
+            .await
+            .expect("moving to the previous match should not fail");
+        assert_eq!(app.searchbar.match_counter(), "1/3");
+
+        Events::execute_action(&mut app, Action::Esc)
+            .await
+            .expect("closing the searchbar should not fail");
+        assert_eq!(app.searchbar_state, SearchbarState::Hidden);
+        assert_eq!(app.active_widget, Some(ActiveWidget::Editor));
+    }
+
+    #[tokio::test]
+    async fn test_search_case_insensitive_toggle() {
+        let mut app = search_app().await;
+        type_str(&mut app, "FOO").await;
+        assert_eq!(app.searchbar.match_count, 0);
+
+        let toggle = Input {
+            key: Key::Char('c'),
+            alt: true,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Edit(toggle))
+            .await
+            .expect("toggle should not fail");
+
+        assert!(app.searchbar.case_insensitive);
+        assert_eq!(app.searchbar.match_counter(), "1/3");
+    }
+
+    #[tokio::test]
+    async fn test_search_malformed_regex_keeps_previous_pattern() {
+        let mut app = search_app().await;
+        let toggle = Input {
+            key: Key::Char('r'),
+            alt: true,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Edit(toggle))
+            .await
+            .expect("toggle should not fail");
+
+        type_str(&mut app, "ba(").await;
+
+        assert!(app.searchbar.error.is_some());
+        assert_eq!(app.searchbar.pattern, "ba");
+        assert_eq!(app.searchbar.match_count, 2);
+        assert_eq!(app.current_screen, Screen::Main);
+    }
+
+    async fn run_search_command(app: &mut App<'_>, command: &str) {
+        type_str(app, command).await;
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(app, Action::Activate(enter))
+            .await
+            .expect("running the command should not fail");
+    }
+
+    #[tokio::test]
+    async fn test_replace_all_multi_line() {
+        let mut app = search_app().await;
+
+        run_search_command(&mut app, "s/foo/qux/g").await;
+
+        assert_eq!(app.editor.body.lines(), ["qux bar qux", "baz", "qux"]);
+        assert_eq!(app.searchbar_state, SearchbarState::Hidden);
+    }
+
+    #[tokio::test]
+    async fn test_replace_confirm_each() {
+        let mut app = search_app().await;
+
+        run_search_command(&mut app, "s/foo/qux/c").await;
+        assert_eq!(app.current_screen, Screen::ReplaceConfirmation);
+
+        for action in [Action::Confirm, Action::Cancel, Action::Confirm] {
+            Events::execute_action(&mut app, action)
+                .await
+                .expect("confirming should not fail");
+        }
+
+        assert_eq!(app.editor.body.lines(), ["qux bar foo", "baz", "qux"]);
+        assert_eq!(app.current_screen, Screen::Main);
+        assert!(app.pending_replace.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replace_shifts_link_columns() {
+        let mut app = search_app().await;
+        let note_id = app.editor.note_id;
+        let link = Link {
+            id: note_id.expect("loaded note should have an id"),
+            text_id: 0,
+            linked_id: 99,
+            row: 0,
+            start_col: 4,
+            end_col: 7,
+            saved: true,
+            updated: false,
+            deleted: false,
+            anchor: None,
+        };
+        app.editor.refresh(
+            "Search".to_owned(),
+            vec!["see Link here".to_owned()],
+            HashMap::from([(0, link)]),
+            note_id,
+            app.get_max_col(),
+        );
+
+        run_search_command(&mut app, "s/see/look at/").await;
+
+        let link = &app.editor.links[&0];
+        assert_eq!(app.editor.body.lines(), ["look at Link here"]);
+        assert_eq!((link.start_col, link.end_col), (8, 11));
+        assert!(link.updated);
+        assert!(!link.deleted);
+    }
+
+    #[tokio::test]
+    async fn test_replace_across_link_boundary_deletes_link() {
+        let mut app = search_app().await;
+        let note_id = app.editor.note_id.expect("loaded note should have an id");
+        let link = |text_id: i64, start_col: usize, end_col: usize| Link {
+            id: note_id,
+            text_id,
+            linked_id: 99,
+            row: 0,
+            start_col,
+            end_col,
+            saved: true,
+            updated: false,
+            deleted: false,
+            anchor: None,
+        };
+        app.editor.refresh(
+            "Search".to_owned(),
+            vec!["see Link and Other".to_owned()],
+            HashMap::from([(0, link(0, 4, 7)), (1, link(1, 13, 17))]),
+            Some(note_id),
+            app.get_max_col(),
+        );
+
+        // The match starts before the first link and ends inside it
+        run_search_command(&mut app, "s/e Li/X/").await;
+
+        assert_eq!(app.editor.body.lines(), ["seXnk and Other"]);
+        assert!(app.editor.links[&0].deleted);
+        assert_eq!(app.editor.deleted_link_ids, [0]);
+        let other = &app.editor.links[&1];
+        assert_eq!((other.start_col, other.end_col), (10, 14));
+        assert!(other.updated && !other.deleted);
+    }
+
+    #[tokio::test]
+    async fn test_open_note_in_tab() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        let second_id = app.note_list.note_identifiers[1].id;
+
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        Events::open_note_in_tab(&mut app, second_id)
+            .await
+            .expect("note should open in a tab");
+
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(app.active_tab, 1);
+        assert_eq!(app.editor.note_id, Some(second_id));
+        assert_eq!(app.tab(0).note_id, Some(first_id));
+
+        // Opening an already open note focuses its tab
+        Events::open_note_in_tab(&mut app, first_id)
+            .await
+            .expect("note should open in a tab");
+        assert_eq!(app.tabs.len(), 2);
+        assert_eq!(app.active_tab, 0);
+        assert_eq!(app.editor.note_id, Some(first_id));
+    }
+
+    #[tokio::test]
+    async fn test_switch_tabs_keeps_edits() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        let second_id = app.note_list.note_identifiers[1].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        app.editor.body.insert_str("unsaved");
+        Events::open_note_in_tab(&mut app, second_id)
+            .await
+            .expect("note should open in a tab");
+
+        Events::execute_action(&mut app, Action::NextTab)
+            .await
+            .expect("switching tabs should not fail");
+
+        assert_eq!(app.active_tab, 0);
+        assert_eq!(app.editor.note_id, Some(first_id));
+        assert_eq!(app.editor.body.lines(), ["unsaved"]);
+        assert!(app.editor.is_dirty());
+        assert!(!app.tab(1).is_dirty());
+
+        Events::execute_action(&mut app, Action::PrevTab)
+            .await
+            .expect("switching tabs should not fail");
+        assert_eq!(app.active_tab, 1);
+        assert_eq!(app.editor.note_id, Some(second_id));
+    }
+
+    #[tokio::test]
+    async fn test_close_dirty_tab_asks_for_confirmation() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        let second_id = app.note_list.note_identifiers[1].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        Events::open_note_in_tab(&mut app, second_id)
+            .await
+            .expect("note should open in a tab");
+        app.editor.body.insert_str("unsaved");
+
+        Events::execute_action(&mut app, Action::CloseTab)
+            .await
+            .expect("closing should not fail");
+        assert_eq!(app.current_screen, Screen::CloseTabConfirmation);
+
+        Events::execute_action(&mut app, Action::Cancel)
+            .await
+            .expect("cancelling should not fail");
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.tabs.len(), 2);
+
+        Events::execute_action(&mut app, Action::CloseTab)
+            .await
+            .expect("closing should not fail");
+        Events::execute_action(&mut app, Action::Confirm)
+            .await
+            .expect("confirming should not fail");
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.tabs.len(), 1);
+        assert_eq!(app.active_tab, 0);
+        assert_eq!(app.editor.note_id, Some(first_id));
+    }
+
+    #[tokio::test]
+    async fn test_large_unsaved_edit_prompts_before_navigating() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        let second_id = app.note_list.note_identifiers[1].id;
+        app.unsaved_prompt_lines = 3;
+        app.unsaved_prompt_chars = 0;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+
+        // A one line edit is saved and left behind without asking
+        app.editor.body.insert_str("small");
+        Events::load_note(&mut app, second_id)
+            .await
+            .expect("note should load");
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.note_id, Some(second_id));
+
+        for _ in 0..3 {
+            app.editor.body.insert_str("big");
+            app.editor.body.insert_newline();
+        }
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("loading should not fail");
+        assert_eq!(app.current_screen, Screen::NavigateConfirmation);
+        assert_eq!(app.editor.note_id, Some(second_id));
+
+        Events::execute_action(&mut app, Action::Esc)
+            .await
+            .expect("cancelling should not fail");
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.note_id, Some(second_id));
+        assert!(app.editor.is_dirty());
+
+        // Answering no throws the edit away
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("loading should not fail");
+        Events::execute_action(&mut app, Action::Cancel)
+            .await
+            .expect("discarding should not fail");
+        assert_eq!(app.editor.note_id, Some(first_id));
+        assert_eq!(app.editor.body.lines(), ["small"]);
+        let (_, second_body, _) = Events::read_note(&app, second_id)
+            .await
+            .expect("note should be readable");
+        assert_eq!(second_body, [""]);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_note_copies_links() {
+        let mut app = test_app(&["Source", "Target A", "Target B", "Source (copy)"]).await;
+        let source_id = app.note_list.note_identifiers[0].id;
+        let target_ids = [
+            app.note_list.note_identifiers[1].id,
+            app.note_list.note_identifiers[2].id,
+        ];
+        let links = target_ids
+            .iter()
+            .enumerate()
+            .map(|(idx, &linked_note_id)| DbNoteLink {
+                parent_note_id: source_id,
+                textarea_id: idx as i64,
+                textarea_row: 0,
+                start_col: 0,
+                end_col: 4,
+                linked_note_id,
+                anchor: None,
+            })
+            .collect::<Vec<DbNoteLink>>();
+        let mut tx = app.db.begin().await.unwrap();
+        DbMac::save_links(&mut tx, links, source_id).await.unwrap();
+        tx.commit().await.unwrap();
+        Events::load_note(&mut app, source_id)
+            .await
+            .expect("note should load");
+
+        Events::execute_action(&mut app, Action::DuplicateNote)
+            .await
+            .expect("duplicating should not fail");
+
+        let copy_id = app.editor.note_id.expect("copy should be loaded");
+        assert_ne!(copy_id, source_id);
+        assert_eq!(app.editor.title, "Source (copy) (2)");
+        let mut copied_targets = DbMac::load_note_links(&app.db, copy_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|link| link.linked_note_id)
+            .collect::<Vec<i64>>();
+        copied_targets.sort();
+        assert_eq!(copied_targets, target_ids);
+        assert_eq!(
+            DbMac::load_note_links(&app.db, source_id).await.unwrap().len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_updates_backlink_text() {
+        let mut app = test_app(&["Target", "Parent"]).await;
+        let target_id = app.note_list.note_identifiers[0].id;
+        let parent_id = app.note_list.note_identifiers[1].id;
+        DbMac::update_note(&app.db, "Parent", "see Target, then Target", true, parent_id)
+            .await
+            .unwrap();
+        let links = [(0, 4, 9), (1, 17, 22)]
+            .into_iter()
+            .map(|(textarea_id, start_col, end_col)| DbNoteLink {
+                parent_note_id: parent_id,
+                textarea_id,
+                textarea_row: 0,
+                start_col,
+                end_col,
+                linked_note_id: target_id,
+                anchor: None,
+            })
+            .collect::<Vec<DbNoteLink>>();
+        let mut tx = app.db.begin().await.unwrap();
+        DbMac::save_links(&mut tx, links, parent_id).await.unwrap();
+        tx.commit().await.unwrap();
+        Events::load_note(&mut app, target_id)
+            .await
+            .expect("note should load");
+
+        app.switch_to_new_note(InputAction::NoteTitle);
+        app.user_input.text.insert_str("Renamed note");
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("renaming should not fail");
+        assert_eq!(app.current_screen, Screen::RenameLinksConfirmation);
+
+        Events::execute_action(&mut app, Action::Confirm)
+            .await
+            .expect("retitling links should not fail");
+
+        let (_, body, links) = Events::read_note(&app, parent_id)
+            .await
+            .expect("note should be readable");
+        assert_eq!(body, ["see Renamed note, then Renamed note"]);
+        assert_eq!((links[&0].start_col, links[&0].end_col), (4, 15));
+        assert_eq!((links[&1].start_col, links[&1].end_col), (23, 34));
+    }
+
+    #[tokio::test]
+    async fn test_new_note_from_template_expands_body() {
+        let mut app = test_app(&["First"]).await;
+        app.templates
+            .insert("daily".to_owned(), "{{date}}".to_owned());
+        app.templates
+            .insert("meeting".to_owned(), "# {{title}}\n\nAttendees:".to_owned());
+
+        Events::execute_action(&mut app, Action::NewNoteFromTemplate)
+            .await
+            .expect("opening the picker should not fail");
+        assert_eq!(app.current_screen, Screen::TemplatePicker);
+        let down = Input {
+            key: Key::Down,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Down(down))
+            .await
+            .expect("moving the selection should not fail");
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("picking a template should not fail");
+        assert_eq!(app.current_screen, Screen::NewNote);
+
+        app.user_input.text.insert_str("Standup");
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("creating the note should not fail");
+
+        let id = app.editor.note_id.expect("new note should be loaded");
+        let note = DbMac::load_note(&app.db, id).await.unwrap();
+        assert_eq!(note.body.unwrap_or_default(), "# Standup\n\nAttendees:");
+        assert_eq!(app.editor.body.lines(), ["# Standup", "", "Attendees:"]);
+        assert!(app.pending_template.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_use_vault_resets_editor_and_note_list() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        let second_id = app.note_list.note_identifiers[1].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        Events::open_note_in_tab(&mut app, second_id)
+            .await
+            .expect("note should open in a tab");
+
+        let work_db = create_test_db().await;
+        for title in ["Work A", "Work B", "Work C"] {
+            DbMac::save_note(&work_db, title, "", false).await.unwrap();
+        }
+        Events::use_vault(&mut app, "work".to_owned(), Path::new("work.db"), work_db)
+            .await
+            .expect("switching vaults should not fail");
+
+        assert_eq!(app.active_vault, "work");
+        assert_eq!(app.tabs.len(), 1);
+        assert_eq!(app.editor.note_id, None);
+        assert!(app.recent_notes.is_empty());
+        let titles = app
+            .note_list
+            .note_identifiers
+            .iter()
+            .map(|nid| nid.title.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(titles, ["Work A", "Work B", "Work C"]);
+        assert_eq!(app.note_list.total, 3);
+    }
+
+    #[tokio::test]
+    async fn test_switch_vault_refuses_unsaved_notes() {
+        let mut app = test_app(&["First"]).await;
+        app.vaults
+            .insert("work".to_owned(), "work.db".to_owned());
+        let first_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        app.editor.body.insert_str("unsaved edit");
+
+        Events::execute_action(&mut app, Action::SwitchVault)
+            .await
+            .expect("opening the switcher should not fail");
+        Events::switch_vault(&mut app, Some(1))
+            .await
+            .expect("switching vaults should not fail");
+
+        assert!(app.status_msg.is_some());
+        assert_eq!(app.active_vault, DEFAULT_VAULT);
+        assert_eq!(app.editor.note_id, Some(first_id));
+    }
+
+    #[tokio::test]
+    async fn test_delete_note_asks_unless_confirmation_is_off() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        app.switch_to_main();
+
+        Events::execute_action(&mut app, Action::DeleteNote)
+            .await
+            .expect("asking should not fail");
+        assert_eq!(app.current_screen, Screen::DeleteNoteConfirmation);
+        assert_eq!(app.note_list.note_identifiers.len(), 2);
+
+        app.switch_to_main();
+        app.confirm_delete = false;
+        Events::execute_action(&mut app, Action::DeleteNote)
+            .await
+            .expect("deleting should not fail");
+        assert_eq!(app.current_screen, Screen::Welcome);
+        assert!(app
+            .note_list
+            .note_identifiers
+            .iter()
+            .all(|nid| nid.id != first_id));
+
+        app.switch_to_main();
+        app.editor.note_id = None;
+        Events::execute_action(&mut app, Action::DeleteNote)
+            .await
+            .expect("an unsaved note should report rather than fail");
+        assert_eq!(app.current_screen, Screen::Main);
+        assert!(app.status_msg.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_insert_ctrl_u_deletes_link() {
+        let mut app = pending_link_app(&["Parent", "Taken"]).await;
+        let taken_id = app.note_list.note_identifiers[1].id;
+        Events::link_note(&mut app, taken_id);
+        app.editor.set_mode(EditorMode::Insert);
+        app.editor.body.move_cursor(CursorMove::End);
+
+        let ctrl_u = Input {
+            key: Key::Char('u'),
+            ctrl: true,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Edit(ctrl_u))
+            .await
+            .expect("deleting should not fail");
+
+        assert_eq!(app.editor.body.lines(), [""]);
+        assert!(app.editor.links[&0].deleted);
+        assert_eq!(app.editor.deleted_link_ids, [0]);
+    }
+
+    #[tokio::test]
+    async fn test_undo_history_survives_reloading_the_note() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        let second_id = app.note_list.note_identifiers[1].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        app.editor.set_mode(EditorMode::Normal);
+        for c in "ihello".chars() {
+            app.editor.handle_input(char_input(c));
+        }
+        app.editor.handle_input(Input {
+            key: Key::Esc,
+            ..Default::default()
+        });
+        Events::execute_action(&mut app, Action::SaveNote)
+            .await
+            .expect("note should save");
+        app.switch_to_main();
+
+        for id in [second_id, first_id] {
+            Events::load_note(&mut app, id)
+                .await
+                .expect("note should load");
+        }
+        assert_eq!(app.editor.body.lines(), ["hello"]);
+
+        app.editor.handle_input(char_input('u'));
+        assert_eq!(app.editor.body.lines(), [""]);
+        assert!(app.editor.is_dirty());
+    }
+
+    #[tokio::test]
+    async fn test_undo_restores_deleted_link() {
+        let mut app = pending_link_app(&["Parent", "Taken"]).await;
+        let taken_id = app.note_list.note_identifiers[1].id;
+        Events::link_note(&mut app, taken_id);
+        app.editor.set_mode(EditorMode::Normal);
+
+        for c in "dd".chars() {
+            let input = Input {
+                key: Key::Char(c),
+                ..Default::default()
+            };
+            Events::execute_action(&mut app, Action::Edit(input))
+                .await
+                .expect("deleting should not fail");
+        }
+        assert_eq!(app.editor.body.lines(), [""]);
+        assert!(app.editor.links[&0].deleted);
+        assert_eq!(app.editor.deleted_link_ids, [0]);
+
+        let undo = Input {
+            key: Key::Char('u'),
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Edit(undo))
+            .await
+            .expect("undoing should not fail");
+
+        assert_eq!(app.editor.body.lines(), ["see Taken"]);
+        assert!(!app.editor.links[&0].deleted);
+        assert_eq!(app.editor.links[&0].linked_id, taken_id);
+        assert!(app.editor.deleted_link_ids.is_empty());
+        assert!(!app.editor.body.links[&0].deleted);
+        assert_eq!(app.editor.body.in_link((0, 5)), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_link_existing_note_at_cursor() {
+        let mut app = test_app(&["Parent", "Target"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        let target_id = app.note_list.note_identifiers[1].id;
+        Events::load_note(&mut app, parent_id).await.unwrap();
+        app.switch_to_main();
+        app.editor.body.insert_str("see  now");
+        app.editor.body.move_cursor(CursorMove::Jump(0, 4));
+
+        Events::execute_action(&mut app, Action::LinkExistingNote)
+            .await
+            .expect("opening the picker should not fail");
+        assert_eq!(app.current_screen, Screen::LinkExistingNote);
+        app.note_list.selected = 1;
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("inserting the link should not fail");
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.body.lines(), ["see Target now"]);
+        let link = app
+            .editor
+            .links
+            .values()
+            .find(|link| link.linked_id == target_id)
+            .expect("the link should be registered");
+        assert_eq!((link.row, link.start_col, link.end_col), (0, 4, 9));
+        assert_eq!(link.id, parent_id);
+        let ta_link = app.editor.body.links[&(link.text_id as usize)];
+        assert_eq!((ta_link.row, ta_link.start_col, ta_link.end_col), (0, 4, 9));
+        assert_eq!(app.editor.body.in_link((0, 6)), Some(link.text_id as usize));
+    }
+
+    async fn press(app: &mut App<'_>, input: Input) {
+        let action = Events::handle_key_event(input, &app.keymap);
+        Events::execute_action(app, action)
+            .await
+            .expect("the key should not fail");
+    }
+
+    #[test]
+    fn test_conflicting_bindings_resolve_the_same_way_every_time() {
+        let alt_s = Input {
+            key: Key::Char('s'),
+            alt: true,
+            ..Default::default()
+        };
+        let mut keymap = Config::default().keymap;
+        keymap.insert(Action::LoadNote, alt_s);
+
+        for _ in 0..20 {
+            // A fresh map each time, so its iteration order changes between runs
+            let keymap = keymap.clone().into_iter().collect::<HashMap<Action, Input>>();
+            assert_eq!(Events::handle_key_event(alt_s, &keymap), Action::LoadNote);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rebound_prev_screen_key() {
+        let mut app = test_app(&["First"]).await;
+        let ctrl_c = Input {
+            key: Key::Char('c'),
+            ctrl: true,
+            ..Default::default()
+        };
+        let esc = Input {
+            key: Key::Esc,
+            ..Default::default()
+        };
+        app.keymap.insert(Action::Esc, ctrl_c);
+        app.switch_to_main();
+
+        app.prev_screen = app.current_screen;
+        app.switch_to_new_note(InputAction::Note);
+        press(&mut app, esc).await;
+        assert_eq!(app.current_screen, Screen::NewNote);
+        press(&mut app, ctrl_c).await;
+        assert_eq!(app.current_screen, Screen::Main);
+
+        app.prev_screen = app.current_screen;
+        app.switch_to_load_note();
+        press(&mut app, esc).await;
+        assert_eq!(app.current_screen, Screen::LoadNote);
+        press(&mut app, ctrl_c).await;
+        assert_eq!(app.current_screen, Screen::Main);
+    }
+
+    #[tokio::test]
+    async fn test_tag_marked_notes() {
+        let mut app = test_app(&["First", "Second", "Third"]).await;
+        let ids = app
+            .note_list
+            .note_identifiers
+            .iter()
+            .map(|nid| nid.id)
+            .collect::<Vec<i64>>();
+        let key = |key| Input {
+            key,
+            ..Default::default()
+        };
+        let tag_key = Input {
+            key: Key::Char('T'),
+            alt: true,
+            ..Default::default()
+        };
+        app.switch_to_main();
+        app.set_active_widget(ActiveWidget::Sidebar);
+
+        press(&mut app, key(Key::Char(' '))).await;
+        press(&mut app, key(Key::Char('j'))).await;
+        press(&mut app, key(Key::Char('j'))).await;
+        press(&mut app, key(Key::Char(' '))).await;
+        assert_eq!(app.note_list.marked.len(), 2);
+
+        press(&mut app, tag_key).await;
+        assert_eq!(app.current_screen, Screen::NewNote);
+        type_str(&mut app, "work").await;
+        press(&mut app, key(Key::Enter)).await;
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.active_widget, Some(ActiveWidget::Sidebar));
+        assert!(app.note_list.marked.is_empty());
+        let tags = DbMac::load_note_tags(&app.db).await.unwrap();
+        let tagged = tags.iter().map(|nt| (nt.id, nt.tag.as_str())).collect::<Vec<_>>();
+        assert_eq!(tagged, [(ids[0], "work"), (ids[2], "work")]);
+        assert!(app.note_list.tags[&ids[2]].contains("work"));
+
+        press(&mut app, key(Key::Char(' '))).await;
+        press(&mut app, tag_key).await;
+        type_str(&mut app, "-work").await;
+        press(&mut app, key(Key::Enter)).await;
+
+        let tags = DbMac::load_note_tags(&app.db).await.unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].id, ids[0]);
+        assert!(!app.note_list.tags.contains_key(&ids[2]));
+    }
+
+    #[tokio::test]
+    async fn test_confirm_quit_modes() {
+        let mut app = test_app(&["First"]).await;
+        let quit_key = app.keymap[&Action::ShowExitScreen];
+        app.switch_to_main();
+
+        app.confirm_quit = ConfirmQuit::IfDirty;
+        press(&mut app, quit_key).await;
+        assert_eq!(app.state, AppState::Exit);
+
+        app.state = AppState::Running;
+        app.editor.body.insert_str("unsaved");
+        press(&mut app, quit_key).await;
+        assert_eq!(app.current_screen, Screen::Exiting);
+        assert_ne!(app.state, AppState::Exit);
+
+        app.switch_to_main();
+        app.confirm_quit = ConfirmQuit::Never;
+        press(&mut app, quit_key).await;
+        assert_eq!(app.state, AppState::Exit);
+
+        app.state = AppState::Running;
+        app.editor.body.insert_str("saved");
+        app.editor.mark_saved();
+        app.confirm_quit = ConfirmQuit::Always;
+        press(&mut app, quit_key).await;
+        assert_eq!(app.current_screen, Screen::Exiting);
+        assert_ne!(app.state, AppState::Exit);
+    }
+
+    async fn command(app: &mut App<'_>, line: &str) {
+        for c in format!(":{}", line).chars() {
+            press(app, char_input(c)).await;
+        }
+        press(
+            app,
+            Input {
+                key: Key::Enter,
+                ..Default::default()
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_command_line_takes_keys_bound_to_actions() {
+        let mut app = test_app(&["First"]).await;
+        app.switch_to_main();
+        app.set_active_widget(ActiveWidget::Editor);
+        app.editor.set_mode(EditorMode::Normal);
+
+        let mut typed = String::new();
+        for c in ":set number".chars() {
+            let action = Events::handle_key_event(char_input(c), &app.keymap);
+            Events::execute_action(&mut app, action).await.unwrap();
+            if c != ':' {
+                typed.push(c);
+            }
+            assert_eq!(app.command_line.as_deref(), Some(typed.as_str()));
+        }
+        // `n` is Cancel in the default keymap, `y` is Confirm
+        assert_eq!(Events::handle_key_event(char_input('n'), &app.keymap), Action::Cancel);
+        let action = Events::handle_key_event(char_input('y'), &app.keymap);
+        Events::execute_action(&mut app, action).await.unwrap();
+        assert_eq!(app.command_line.as_deref(), Some("set numbery"));
+    }
+
+    #[tokio::test]
+    async fn test_set_command_toggles_options() {
+        let mut app = test_app(&["First"]).await;
+        app.switch_to_main();
+        app.set_active_widget(ActiveWidget::Editor);
+        app.editor.set_mode(EditorMode::Normal);
+
+        command(&mut app, "set number").await;
+        assert!(app.command_line.is_none());
+        assert!(app.editor.settings.line_numbers);
+        command(&mut app, "set nonumber").await;
+        assert!(!app.editor.settings.line_numbers);
+        command(&mut app, "set wrap!").await;
+        assert!(app.editor.settings.soft_wrap);
+        command(&mut app, "set readonly").await;
+        assert!(app.editor.read_only);
+
+        command(&mut app, "set spell").await;
+        assert_eq!(app.status_msg.as_ref().unwrap().msg, "Unknown option: spell");
+
+        // Esc drops the line without running it
+        for c in ":set number".chars() {
+            press(&mut app, char_input(c)).await;
+        }
+        assert_eq!(app.command_line.as_deref(), Some("set number"));
+        Events::execute_action(&mut app, Action::Esc).await.unwrap();
+        assert!(app.command_line.is_none());
+        assert!(!app.editor.settings.line_numbers);
+        assert_eq!(app.editor.body.lines(), [""]);
+    }
+
+    #[tokio::test]
+    async fn test_go_to_line_and_column() {
+        let mut app = test_app(&["First"]).await;
+        app.switch_to_main();
+        app.set_active_widget(ActiveWidget::Editor);
+        app.editor.set_mode(EditorMode::Normal);
+        let lines = ["one", "two", "three words here", "four"];
+        app.editor.replace_lines(lines.map(str::to_owned).to_vec());
+
+        command(&mut app, "3:5").await;
+        assert_eq!(app.editor.body.cursor(), (2, 4));
+        command(&mut app, "2:40").await;
+        assert_eq!(app.editor.body.cursor(), (1, 3));
+        command(&mut app, "99").await;
+        assert_eq!(app.editor.body.cursor(), (3, 0));
+
+        press(
+            &mut app,
+            Input {
+                key: Key::Char('g'),
+                ctrl: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        let report = &app.status_msg.as_ref().unwrap().msg;
+        assert!(report.ends_with("[Modified] line 4 of 4 --100%-- col 1"));
+    }
+
+    async fn gf(app: &mut App<'_>) {
+        for c in ['g', 'f'] {
+            Events::execute_action(app, Action::Edit(char_input(c)))
+                .await
+                .expect("gf should not fail");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gf_links_word_to_existing_note() {
+        let mut app = test_app(&["Parent", "Target"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        let target_id = app.note_list.note_identifiers[1].id;
+        Events::load_note(&mut app, parent_id).await.unwrap();
+        app.switch_to_main();
+        app.editor.body.insert_str("see Target now");
+        app.editor.body.move_cursor(CursorMove::Jump(0, 6));
+
+        gf(&mut app).await;
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.body.lines(), ["see Target now"]);
+        let link = app
+            .editor
+            .links
+            .values()
+            .find(|link| link.linked_id == target_id)
+            .expect("the word should link to the note");
+        assert_eq!((link.row, link.start_col, link.end_col), (0, 4, 9));
+        assert_eq!(app.editor.body.in_link((0, 4)), Some(link.text_id as usize));
+    }
+
+    #[tokio::test]
+    async fn test_link_suggestions_filter_and_insert_link() {
+        let mut app =
+            test_app(&["Parent", "Meeting notes", "Groceries", "Meetup ideas"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        let meetup_id = app.note_list.note_identifiers[3].id;
+        Events::load_note(&mut app, parent_id).await.unwrap();
+        app.switch_to_main();
+
+        type_str(&mut app, "isee [").await;
+        assert!(app.link_suggestions.is_none());
+        type_str(&mut app, "[meet").await;
+        let titles = app
+            .link_suggestions
+            .as_ref()
+            .expect("[[ should open the suggestions")
+            .picker
+            .filtered()
+            .into_iter()
+            .map(|entry| entry.label.clone())
+            .collect::<Vec<String>>();
+        assert_eq!(titles, ["Meeting notes", "Meetup ideas"]);
+
+        Events::execute_action(&mut app, Action::Tab).await.unwrap();
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .unwrap();
+
+        assert!(app.link_suggestions.is_none());
+        assert_eq!(app.editor.body.lines(), ["see Meetup ideas"]);
+        let link = app
+            .editor
+            .links
+            .values()
+            .find(|link| link.linked_id == meetup_id)
+            .expect("the picked note should be linked");
+        assert_eq!((link.row, link.start_col, link.end_col), (0, 4, 15));
+    }
+
+    #[tokio::test]
+    async fn test_gf_creates_note_for_new_word() {
+        let mut app = test_app(&["Parent"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, parent_id).await.unwrap();
+        app.switch_to_main();
+        app.editor.body.insert_str("buy groceries");
+        app.editor.body.move_cursor(CursorMove::Jump(0, 4));
+
+        gf(&mut app).await;
+
+        let new_note = app
+            .note_list
+            .note_identifiers
+            .iter()
+            .find(|nid| nid.title == "groceries")
+            .expect("the note should be created")
+            .clone();
+        assert_eq!(app.editor.note_id, Some(new_note.id));
+        let links = DbMac::load_note_links(&app.db, parent_id).await.unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].linked_note_id, new_note.id);
+        assert_eq!((links[0].start_col, links[0].end_col), (4, 12));
+    }
+
+    async fn submit_title(app: &mut App<'_>) {
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(app, Action::Activate(enter))
+            .await
+            .expect("submitting a title should not fail");
+    }
+
+    #[tokio::test]
+    async fn test_new_note_rejects_blank_titles() {
+        let mut app = test_app(&["First"]).await;
+        app.switch_to_main();
+        Events::execute_action(&mut app, Action::NewNote)
+            .await
+            .expect("opening the title input should not fail");
+
+        submit_title(&mut app).await;
+        assert_eq!(app.current_screen, Screen::NewNote);
+        assert_eq!(app.user_input.get_state(), ComponentState::Error);
+
+        type_str(&mut app, "   ").await;
+        assert_eq!(app.user_input.get_state(), ComponentState::Active);
+        submit_title(&mut app).await;
+        assert_eq!(app.current_screen, Screen::NewNote);
+        assert_eq!(app.user_input.get_state(), ComponentState::Error);
+        assert_eq!(app.note_list.note_identifiers.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_new_note_title_is_trimmed() {
+        let mut app = test_app(&["First"]).await;
+        app.switch_to_main();
+        Events::execute_action(&mut app, Action::NewNote)
+            .await
+            .expect("opening the title input should not fail");
+        type_str(&mut app, "  Spaced  ").await;
+        submit_title(&mut app).await;
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.title, "Spaced");
+        let id = app.editor.note_id.expect("the note should be saved");
+        assert_eq!(DbMac::load_note(&app.db, id).await.unwrap().title, "Spaced");
+    }
+
+    #[tokio::test]
+    async fn test_rename_rejects_blank_and_trims_title() {
+        let mut app = test_app(&["First"]).await;
+        let id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, id).await.unwrap();
+        app.switch_to_main();
+        Events::execute_action(&mut app, Action::NewTitle)
+            .await
+            .expect("opening the title input should not fail");
+
+        type_str(&mut app, " ").await;
+        submit_title(&mut app).await;
+        assert_eq!(app.current_screen, Screen::NewNote);
+        assert_eq!(app.user_input.get_state(), ComponentState::Error);
+        assert_eq!(app.editor.title, "First");
+
+        type_str(&mut app, "Renamed ").await;
+        submit_title(&mut app).await;
+        assert_eq!(app.editor.title, "Renamed");
+        assert_eq!(DbMac::load_note(&app.db, id).await.unwrap().title, "Renamed");
+    }
+
+    #[tokio::test]
+    async fn test_rename_leaves_body_edits_unsaved() {
+        let mut app = test_app(&["First"]).await;
+        let id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, id).await.unwrap();
+        app.switch_to_main();
+        app.editor.body.insert_str("draft");
+        Events::execute_action(&mut app, Action::NewTitle)
+            .await
+            .expect("opening the title input should not fail");
+
+        type_str(&mut app, "Renamed").await;
+        submit_title(&mut app).await;
+
+        let note = DbMac::load_note(&app.db, id).await.unwrap();
+        assert_eq!(note.title, "Renamed");
+        assert_eq!(note.body.as_deref(), Some(""));
+        assert_eq!(app.editor.body.lines(), ["draft"]);
+        assert!(app.editor.is_dirty());
+        assert_eq!(app.note_list.note_identifiers[0].title, "Renamed");
+    }
+
+    #[tokio::test]
+    async fn test_quick_capture_leaves_open_note() {
+        let mut app = test_app(&["Parent", "buy milk"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, parent_id).await.unwrap();
+        app.switch_to_main();
+        app.editor.body.insert_str("unsaved draft");
+        let cursor = app.editor.body.cursor();
+
+        Events::execute_action(&mut app, Action::QuickCapture)
+            .await
+            .expect("opening quick-capture should not fail");
+        assert_eq!(app.current_screen, Screen::NewNote);
+        type_str(&mut app, "buy milk").await;
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("capturing should not fail");
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert!(app.status_msg.is_some());
+        assert_eq!(app.editor.note_id, Some(parent_id));
+        assert_eq!(app.editor.title, "Parent");
+        assert_eq!(app.editor.body.lines(), ["unsaved draft"]);
+        assert_eq!(app.editor.body.cursor(), cursor);
+
+        let captured = app
+            .note_list
+            .note_identifiers
+            .iter()
+            .find(|nid| nid.title == "buy milk (2)")
+            .expect("the captured note should be listed");
+        let note = DbMac::load_note(&app.db, captured.id).await.unwrap();
+        assert_eq!(note.body.as_deref(), Some("buy milk"));
+    }
+
+    #[tokio::test]
+    async fn test_quick_capture_to_unlisted_taken_title_keeps_prompt() {
+        let mut app = test_app(&["Parent"]).await;
+        DbMac::save_note(&app.db, "buy milk", "", false).await.unwrap();
+        Events::execute_action(&mut app, Action::QuickCapture)
+            .await
+            .expect("opening quick-capture should not fail");
+
+        type_str(&mut app, "buy milk").await;
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("a taken title should not be an error");
+
+        assert_eq!(app.current_screen, Screen::NewNote);
+        assert_eq!(app.user_input.get_state(), ComponentState::Error);
+        assert_eq!(app.user_input.text.lines(), ["buy milk"]);
+        assert!(app.status_msg.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_extract_selection_into_linked_note() {
+        let mut app = test_app(&["Parent"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, parent_id).await.unwrap();
+        app.switch_to_main();
+        app.set_active_widget(ActiveWidget::Editor);
+        let lines = ["keep this", "move me", "and me too"];
+        app.editor.replace_lines(lines.map(str::to_owned).to_vec());
+        app.editor.body.move_cursor(CursorMove::Jump(0, 5));
+        app.editor.set_mode(EditorMode::Visual);
+        app.editor.body.move_cursor(CursorMove::Jump(1, 6));
+
+        press(
+            &mut app,
+            Input {
+                key: Key::Char('X'),
+                alt: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(app.current_screen, Screen::NewNote);
+        assert_eq!(app.editor.body.lines(), lines);
+        type_str(&mut app, "Moved").await;
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("extracting should not fail");
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.body.lines(), ["keep Moved", "and me too"]);
+        let moved = app
+            .note_list
+            .note_identifiers
+            .iter()
+            .find(|nid| nid.title == "Moved")
+            .expect("the new note should be listed");
+        let link = app
+            .editor
+            .links
+            .values()
+            .find(|link| link.linked_id == moved.id)
+            .expect("the selection should be a link to the new note");
+        assert_eq!((link.row, link.start_col, link.end_col), (0, 5, 9));
+        assert_eq!(link.id, parent_id);
+        let note = DbMac::load_note(&app.db, moved.id).await.unwrap();
+        assert_eq!(note.body.as_deref(), Some("this\nmove me"));
+    }
+
+    #[tokio::test]
+    async fn test_extract_to_unlisted_taken_title_keeps_prompt() {
+        let mut app = test_app(&["Parent"]).await;
+        let parent_id = app.note_list.note_identifiers[0].id;
+        DbMac::save_note(&app.db, "Elsewhere", "", false).await.unwrap();
+        Events::load_note(&mut app, parent_id).await.unwrap();
+        app.switch_to_main();
+        app.set_active_widget(ActiveWidget::Editor);
+        app.editor.replace_lines(vec!["keep this".to_owned()]);
+        app.editor.set_mode(EditorMode::Visual);
+        app.editor.body.move_cursor(CursorMove::Jump(0, 3));
+        Events::execute_action(&mut app, Action::ExtractNote)
+            .await
+            .expect("opening the extract prompt should not fail");
+
+        type_str(&mut app, "Elsewhere").await;
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("a taken title should not be an error");
+
+        assert_eq!(app.current_screen, Screen::NewNote);
+        assert_eq!(app.user_input.get_state(), ComponentState::Error);
+        assert!(app.pending_extract.is_some());
+        assert_eq!(app.editor.body.lines(), ["keep this"]);
+    }
+
+    #[tokio::test]
+    async fn test_save_auto_links_typed_titles() {
+        let mut app = test_app(&["Foo", "Notes"]).await;
+        let foo_id = app.note_list.note_identifiers[0].id;
+        let notes_id = app.note_list.note_identifiers[1].id;
+        Events::load_note(&mut app, notes_id).await.unwrap();
+        app.switch_to_main();
+        app.editor.settings.auto_link = AutoLink::Existing;
+        app.editor.replace_lines(vec!["intro".to_owned(), "see [[Foo]] and [[Bar]]".to_owned()]);
+
+        Events::execute_action(&mut app, Action::SaveNote)
+            .await
+            .expect("saving should not fail");
+
+        let links = DbMac::load_note_links(&app.db, notes_id).await.unwrap();
+        assert_eq!(links.len(), 1);
+        let link = &links[0];
+        assert_eq!(link.parent_note_id, notes_id);
+        assert_eq!(link.linked_note_id, foo_id);
+        assert_eq!((link.textarea_row, link.start_col, link.end_col), (1, 6, 8));
+        assert_eq!(link.anchor, None);
+        assert!(DbMac::load_note(&app.db, notes_id).await.unwrap().has_links);
+        let status = app.status_msg.as_ref().expect("the missing title should be flagged");
+        assert_eq!(status.msg, "Note saved, no note titled Bar");
+
+        // Saving again neither duplicates the link nor makes Bar without `create`
+        Events::execute_action(&mut app, Action::SaveNote).await.unwrap();
+        assert_eq!(DbMac::load_note_links(&app.db, notes_id).await.unwrap().len(), 1);
+        app.editor.settings.auto_link = AutoLink::Create;
+        Events::execute_action(&mut app, Action::SaveNote).await.unwrap();
+        assert!(app.note_list.note_identifiers.iter().any(|nid| nid.title == "Bar"));
+        assert_eq!(DbMac::load_note_links(&app.db, notes_id).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_save_creates_no_link_targets() {
+        let mut app = test_app(&["Taken"]).await;
+        app.editor.settings.auto_link = AutoLink::Create;
+        app.editor.replace_lines(vec!["see [[Ghost]]".to_owned()]);
+
+        let result = Events::save_note(&mut app, "Taken", "see [[Ghost]]", false, None).await;
+
+        assert!(result.is_err_and(|err| err.is::<DuplicateTitleError>()));
+        let titles = DbMac::load_note_identifiers(&app.db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|nid| nid.title)
+            .collect::<Vec<String>>();
+        assert_eq!(titles, ["Taken"]);
+    }
+
+    #[tokio::test]
+    async fn test_move_note_to_folder_sets_its_folder() {
+        let mut app = test_app(&["Minutes"]).await;
+        let note_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, note_id).await.unwrap();
+        app.switch_to_main();
+        app.set_active_widget(ActiveWidget::Editor);
+
+        press(
+            &mut app,
+            Input {
+                key: Key::Char('M'),
+                alt: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(app.current_screen, Screen::NewNote);
+        type_str(&mut app, "work//meetings").await;
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("moving should not fail");
+
+        assert_eq!(app.current_screen, Screen::Main);
+        let folders = DbMac::load_note_folders(&app.db).await.unwrap();
+        assert_eq!(folders.len(), 1);
+        assert_eq!((folders[0].id, folders[0].folder.as_str()), (note_id, "work/meetings"));
+        assert_eq!(app.note_list.folders.get(&note_id).map(String::as_str), Some("work/meetings"));
+    }
+
+    #[tokio::test]
+    async fn test_move_note_to_vault_copies_then_deletes() {
+        let mut app = test_app(&["Target", "Other"]).await;
+        let target_id = app.note_list.note_identifiers[0].id;
+        let other_id = app.note_list.note_identifiers[1].id;
+        let moved_id = DbMac::save_note(&app.db, "Moved", "Target and Other", true)
+            .await
+            .unwrap();
+        let links = [(0, 0, 6, target_id), (1, 11, 16, other_id)]
+            .into_iter()
+            .map(|(textarea_id, start_col, end_col, linked_note_id)| DbNoteLink {
+                parent_note_id: moved_id,
+                textarea_id,
+                textarea_row: 0,
+                start_col,
+                end_col,
+                linked_note_id,
+                anchor: None,
+            })
+            .collect::<Vec<DbNoteLink>>();
+        let mut tx = app.db.begin().await.unwrap();
+        DbMac::save_links(&mut tx, links, moved_id).await.unwrap();
+        tx.commit().await.unwrap();
+        app.note_list.update(NoteIdentifier {
+            id: moved_id,
+            title: "Moved".to_owned(),
+        });
+        Events::load_note(&mut app, moved_id).await.unwrap();
+
+        let vault_db = create_test_db().await;
+        let vault_target_id = DbMac::save_note(&vault_db, "Target", "", false).await.unwrap();
+        let (path, inbox) = (Path::new("work.db"), Some("inbox".to_owned()));
+        Events::move_note_to_vault(&mut app, moved_id, "work", path, &vault_db, inbox)
+            .await
+            .expect("moving should not fail");
+
+        let copied = DbMac::load_note_identifiers(&vault_db)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|nid| nid.title == "Moved")
+            .expect("the note should be copied to the vault");
+        let note = DbMac::load_note(&vault_db, copied.id).await.unwrap();
+        assert_eq!(note.body.as_deref(), Some("Target and Other"));
+        let copied_links = DbMac::load_note_links(&vault_db, copied.id).await.unwrap();
+        assert_eq!(copied_links.len(), 1);
+        assert_eq!(copied_links[0].linked_note_id, vault_target_id);
+        assert_eq!(copied_links[0].start_col, 0);
+        let folders = DbMac::load_note_folders(&vault_db).await.unwrap();
+        assert_eq!(folders[0].folder, "inbox");
+
+        assert!(!DbMac::note_exists(&app.db, moved_id).await.unwrap());
+        assert!(DbMac::load_note_links(&app.db, moved_id).await.unwrap().is_empty());
+        assert!(!app.note_list.note_identifiers.iter().any(|nid| nid.id == moved_id));
+        let status = app.status_msg.as_ref().expect("the dropped link should be reported");
+        assert!(matches!(status.typ, MessageType::Warning));
+        assert_eq!(status.msg, "Moved Moved to work, links to Other were dropped");
+    }
+
+    #[tokio::test]
+    async fn test_command_palette_runs_chosen_action() {
+        let mut app = test_app(&["First"]).await;
+        app.switch_to_main();
+
+        Events::execute_action(&mut app, Action::ShowCommandPalette)
+            .await
+            .expect("opening the palette should not fail");
+        assert_eq!(app.current_screen, Screen::CommandPalette);
+        for c in "rdonly".chars() {
+            let input = Input {
+                key: Key::Char(c),
+                ..Default::default()
+            };
+            Events::execute_action(&mut app, Action::Edit(input))
+                .await
+                .expect("filtering should not fail");
+        }
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Activate(enter))
+            .await
+            .expect("running the action should not fail");
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert!(app.command_palette.is_none());
+        assert!(app.editor.read_only);
+    }
+
+    #[tokio::test]
+    async fn test_pin_survives_reload() {
+        let mut app = test_app(&["First", "Second", "Third"]).await;
+        let third_id = app.note_list.note_identifiers[2].id;
+        app.switch_to_main();
+        app.set_active_widget(ActiveWidget::Sidebar);
+        app.note_list.selected = 2;
+
+        Events::execute_action(&mut app, Action::TogglePin)
+            .await
+            .expect("pinning should not fail");
+        assert_eq!(app.note_list.note_identifiers[0].id, third_id);
+
+        let note_identifiers = DbMac::load_note_identifiers_page(&app.db, 0, 2).await.unwrap();
+        let mut reloaded = App::new(Config::default(), app.db.clone(), note_identifiers, 3, 140);
+        Events::load_pins(&mut reloaded)
+            .await
+            .expect("pins should load");
+
+        let titles = reloaded
+            .note_list
+            .note_identifiers
+            .iter()
+            .map(|nid| nid.title.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(titles, ["Third", "First", "Second"]);
+        assert!(reloaded.note_list.pinned.contains(&third_id));
+    }
+
+    #[tokio::test]
+    async fn test_close_clean_tab_without_confirmation() {
+        let mut app = test_app(&["First", "Second"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        let second_id = app.note_list.note_identifiers[1].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        Events::open_note_in_tab(&mut app, second_id)
+            .await
+            .expect("note should open in a tab");
+
+        Events::execute_action(&mut app, Action::CloseTab)
+            .await
+            .expect("closing should not fail");
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.tabs.len(), 1);
+        assert_eq!(app.editor.note_id, Some(first_id));
+    }
+
+    #[tokio::test]
+    async fn test_recent_notes_mru_order() {
+        let mut app = test_app(&["First", "Second", "Third"]).await;
+        let ids = app
+            .note_list
+            .note_identifiers
+            .iter()
+            .map(|nid| nid.id)
+            .collect::<Vec<i64>>();
+
+        for idx in [0, 1, 2, 0] {
+            Events::load_note(&mut app, ids[idx])
+                .await
+                .expect("note should load");
+        }
+
+        assert_eq!(app.recent_notes, vec![ids[0], ids[2], ids[1]]);
+
+        Events::execute_action(&mut app, Action::ShowRecentNotes)
+            .await
+            .expect("switcher should open");
+        let input = Input {
+            key: Key::Char('2'),
+            ..Default::default()
+        };
+        Events::execute_action(&mut app, Action::Edit(input))
+            .await
+            .expect("note should load");
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.editor.note_id, Some(ids[2]));
+        assert_eq!(app.recent_notes, vec![ids[2], ids[0], ids[1]]);
+    }
+
+    #[tokio::test]
+    async fn test_recent_notes_cap() {
+        let mut app = test_app(&["First", "Second", "Third"]).await;
+        app.recent_notes_cap = 2;
+        let ids = app
+            .note_list
+            .note_identifiers
+            .iter()
+            .map(|nid| nid.id)
+            .collect::<Vec<i64>>();
+
+        for id in ids.iter() {
+            Events::load_note(&mut app, *id)
+                .await
+                .expect("note should load");
+        }
+
+        assert_eq!(app.recent_notes, vec![ids[2], ids[1]]);
+    }
+
+    #[tokio::test]
+    async fn test_zen_mode_restores_layout() {
+        let mut app = test_app(&["First"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        app.switch_to_main();
+        for action in [Action::ToggleSidebar, Action::IncreaseSidebar] {
+            Events::execute_action(&mut app, action)
+                .await
+                .expect("sidebar should open and grow");
+        }
+        Events::toggle_searchbar(&mut app);
+        let (sidebar_size, max_col, active) =
+            (app.sidebar_size, app.get_max_col(), app.active_widget);
+
+        Events::execute_action(&mut app, Action::ToggleZenMode)
+            .await
+            .expect("zen mode should turn on");
+        assert_eq!(app.sidebar_state, SidebarState::Hidden(sidebar_size));
+        assert_eq!(app.searchbar_state, SearchbarState::Hidden);
+        assert_eq!(app.active_widget, Some(ActiveWidget::Editor));
+        assert!(!app.editor.sidebar_open);
+
+        Events::execute_action(&mut app, Action::ToggleZenMode)
+            .await
+            .expect("zen mode should turn off");
+        assert_eq!(app.zen_layout, None);
+        assert_eq!(app.sidebar_state, SidebarState::Open);
+        assert_eq!(app.sidebar_size, sidebar_size);
+        assert_eq!(app.get_max_col(), max_col);
+        assert_eq!(app.searchbar_state, SearchbarState::Open);
+        assert_eq!(app.active_widget, active);
+    }
+
+    #[tokio::test]
+    async fn test_sidebar_resize_updates_max_col() {
+        let mut app = test_app(&["First"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        assert_eq!(app.get_max_col(), 136);
+        assert_eq!(app.editor.max_col, 136);
+
+        Events::execute_action(&mut app, Action::ToggleSidebar)
+            .await
+            .expect("sidebar should open");
+        let open_max_col = app.get_max_col();
+        assert!(open_max_col < 136);
+        assert_eq!(app.editor.max_col, open_max_col);
+
+        Events::execute_action(&mut app, Action::IncreaseSidebar)
+            .await
+            .expect("sidebar should grow");
+        assert!(app.get_max_col() < open_max_col);
+        assert_eq!(app.editor.max_col, app.get_max_col());
+
+        Events::execute_action(&mut app, Action::ToggleSidebar)
+            .await
+            .expect("sidebar should hide");
+        assert_eq!(app.editor.max_col, 136);
+        assert_eq!(app.sidebar_state, SidebarState::Hidden(20));
+    }
+
+    #[tokio::test]
+    async fn test_reveal_note_selects_the_open_note() {
+        let mut app = test_app(&["First", "Second", "Third"]).await;
+        app.switch_to_main();
+
+        Events::execute_action(&mut app, Action::RevealNote)
+            .await
+            .expect("reveal should run");
+        assert!(app.status_msg.is_some());
+
+        app.switch_to_main();
+        let third_id = app.note_list.note_identifiers[2].id;
+        Events::load_note(&mut app, third_id)
+            .await
+            .expect("note should load");
+        app.note_list.selected = 0;
+
+        Events::execute_action(&mut app, Action::RevealNote)
+            .await
+            .expect("reveal should run");
+
+        assert_eq!(app.current_screen, Screen::Main);
+        assert_eq!(app.note_list.selected, 2);
+        assert_eq!(app.note_list.selected_id(), Some(third_id));
+        assert_eq!(app.sidebar_state, SidebarState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_sidebar_size_round_trips_through_session() {
+        let mut app = test_app(&["First"]).await;
+        app.switch_to_main();
+        Events::execute_action(&mut app, Action::ToggleSidebar)
+            .await
+            .expect("sidebar should open");
+        for _ in 0..3 {
+            Events::execute_action(&mut app, Action::IncreaseSidebar)
+                .await
+                .expect("sidebar should grow");
+        }
+        let session = Session::from_app(&app);
+
+        let mut restored = test_app(&["First"]).await;
+        Events::restore_session(&mut restored, session)
+            .await
+            .expect("session should restore");
+
+        assert_eq!(restored.sidebar_state, SidebarState::Open);
+        assert_eq!(restored.sidebar_size, app.sidebar_size);
+        assert_eq!(restored.editor.max_col, restored.get_max_col());
+    }
+
+    #[tokio::test]
+    async fn test_resize_updates_max_col() {
+        let mut app = test_app(&["First"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        app.editor.body.insert_str("some text");
+        Events::toggle_searchbar(&mut app);
+        type_str(&mut app, "te").await;
+
+        Events::handle_resize(&mut app, 80);
+
+        assert_eq!(app.max_col, 76);
+        assert_eq!(app.get_max_col(), 76);
+        assert_eq!(app.editor.max_col, 76);
+        assert_eq!(app.searchbar.max_col, 76);
+        assert_eq!(app.editor.body.lines(), ["some text"]);
+        assert_eq!(app.searchbar.get_search_text(), "te");
+    }
+
+    fn char_input(c: char) -> Input {
+        Input {
+            key: Key::Char(c),
+            ..Default::default()
+        }
+    }
+
+    async fn leader_app<'a>() -> App<'a> {
+        let mut app = test_app(&["First"]).await;
+        let first_id = app.note_list.note_identifiers[0].id;
+        Events::load_note(&mut app, first_id)
+            .await
+            .expect("note should load");
+        app.switch_to_main();
+        app.editor.set_mode(EditorMode::Normal);
+        app.leader_bindings = [("sb".to_owned(), Action::ToggleSidebar)]
+            .into_iter()
+            .collect();
+        app
+    }
+
+    #[tokio::test]
+    async fn test_leader_sequence_dispatches_action() {
+        let mut app = leader_app().await;
+
+        assert_eq!(Events::leader_action(&mut app, char_input(' ')), Some(Action::Null));
+        assert_eq!(Events::leader_action(&mut app, char_input('s')), Some(Action::Null));
+        assert_eq!(
+            Events::leader_action(&mut app, char_input('b')),
+            Some(Action::ToggleSidebar)
+        );
+        assert!(app.pending_leader.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_leader_sequence_is_cancelled() {
+        let mut app = leader_app().await;
+
+        Events::leader_action(&mut app, char_input(' '));
+        assert_eq!(Events::leader_action(&mut app, char_input('x')), Some(Action::Null));
+        assert!(app.pending_leader.is_none());
+        assert_eq!(Events::leader_action(&mut app, char_input('b')), None);
+
+        Events::leader_action(&mut app, char_input(' '));
+        let esc = Input {
+            key: Key::Esc,
+            ..Default::default()
+        };
+        assert_eq!(Events::leader_action(&mut app, esc), Some(Action::Null));
+        assert!(app.pending_leader.is_none());
+
+        app.editor.set_mode(EditorMode::Insert);
+        assert_eq!(Events::leader_action(&mut app, char_input(' ')), None);
+    }
+
+    fn locked_app_input(app: &mut App<'_>, text: &str) {
+        for c in text.chars() {
+            Events::locked_input(app, char_input(c));
+        }
+        let enter = Input {
+            key: Key::Enter,
+            ..Default::default()
+        };
+        Events::locked_input(app, enter);
+    }
+
+    #[tokio::test]
+    async fn test_idle_lock_needs_the_passphrase() {
+        let mut app = test_app(&["First"]).await;
+        app.switch_to_main();
+        let hash = hash_passphrase("open sesame");
+        app.idle_lock = IdleLock::new(60, Some(&hash));
+
+        app.lock_if_idle();
+        assert_eq!(app.current_screen, Screen::Main);
+
+        if let Some(lock) = app.idle_lock.as_mut() {
+            lock.last_input = Instant::now() - Duration::from_secs(61);
+        }
+        app.lock_if_idle();
+        assert_eq!(app.current_screen, Screen::Locked);
+
+        locked_app_input(&mut app, "open sesam");
+        assert_eq!(app.current_screen, Screen::Locked);
+        assert!(app.idle_lock.as_ref().is_some_and(|lock| lock.failed && lock.entry.is_empty()));
+
+        locked_app_input(&mut app, "open sesame");
+        assert_eq!(app.current_screen, Screen::Main);
+        assert!(app.idle_lock.as_ref().is_some_and(|lock| !lock.is_locked()));
+
+        assert!(IdleLock::new(60, None).is_none());
+        assert!(IdleLock::new(0, Some(&hash)).is_none());
     }
 }
@@ -0,0 +1,124 @@
+use std::{
+    env,
+    fs::{self, DirBuilder, OpenOptions},
+    io::{self, ErrorKind, Write},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+    process::{self, Command},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+
+/// `$VISUAL`, then `$EDITOR`, then plain `vi`
+pub(crate) fn editor_command() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|cmd| !cmd.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_owned())
+}
+
+/// Opens `body` in the external editor and waits for it to exit.
+/// Returns the new lines, or None when the text came back unchanged.
+pub(crate) fn edit_externally(body: &str) -> io::Result<Option<Vec<String>>> {
+    let dir = private_temp_dir()?;
+    let path = dir.join("note.md");
+
+    let result = write_new(&path, body)
+        .and_then(|_| run_editor(&path))
+        .and_then(|_| fs::read_to_string(&path));
+    if let Err(e) = fs::remove_dir_all(&dir) {
+        warn!("edit_externally::failed to remove {:?}: {:?}", dir, e);
+    }
+
+    Ok(edited_lines(body, &result?))
+}
+
+fn run_editor(path: &Path) -> io::Result<()> {
+    let command = editor_command();
+    let mut parts = command.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = Command::new(program).args(parts).arg(path).status()?;
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(io::Error::new(
+            ErrorKind::Other,
+            format!("{} exited with {}", program, status),
+        )),
+    }
+}
+
+/// A new directory only the user can open, so the note can't be read or swapped out.
+/// Creating it fails rather than reusing a path someone else made first.
+fn private_temp_dir() -> io::Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or_default();
+    let dir = env::temp_dir().join(format!("tuipaz-{}-{}", process::id(), nanos));
+    DirBuilder::new().mode(0o700).create(&dir)?;
+    Ok(dir)
+}
+
+/// Writes `body` to a file that must not exist yet, readable by the user alone
+fn write_new(path: &Path, body: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(body.as_bytes())
+}
+
+/// Lines of the edited file, ignoring the trailing newline most editors add
+pub(crate) fn edited_lines(original: &str, edited: &str) -> Option<Vec<String>> {
+    let edited = edited
+        .strip_suffix("\r\n")
+        .or_else(|| edited.strip_suffix('\n'))
+        .unwrap_or(edited);
+
+    if edited == original {
+        return None;
+    }
+
+    let lines = edited.lines().map(String::from).collect::<Vec<String>>();
+    match lines.is_empty() {
+        true => Some(vec![String::new()]),
+        false => Some(lines),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edited_lines() {
+        assert_eq!(edited_lines("one\ntwo", "one\ntwo\n"), None);
+        assert_eq!(
+            edited_lines("one\ntwo", "one\n\nthree\r\n"),
+            Some(vec!["one".to_owned(), "".to_owned(), "three".to_owned()])
+        );
+        assert_eq!(edited_lines("one", ""), Some(vec![String::new()]));
+    }
+
+    #[test]
+    fn test_temp_file_is_private_and_new() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = private_temp_dir().unwrap();
+        let path = dir.join("note.md");
+        write_new(&path, "body").unwrap();
+
+        let dir_mode = fs::metadata(&dir).unwrap().permissions().mode();
+        let file_mode = fs::metadata(&path).unwrap().permissions().mode();
+        let rewrite = write_new(&path, "other");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(dir_mode & 0o777, 0o700);
+        assert_eq!(file_mode & 0o777, 0o600);
+        assert_eq!(rewrite.unwrap_err().kind(), ErrorKind::AlreadyExists);
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use ratatui::{
+    layout::Alignment,
+    prelude::{Buffer, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{block::Title, Block, BorderType, Borders, Padding, Paragraph, Widget},
+};
+
+use crate::db::db_mac::{DbNoteLink, NoteIdentifier};
+
+pub(crate) const MIN_GRAPH_DEPTH: usize = 1;
+pub(crate) const MAX_GRAPH_DEPTH: usize = 2;
+
+/// Notes and the links between them, ignoring how often or where a note links another
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LinkGraph {
+    pub(crate) titles: HashMap<i64, String>,
+    pub(crate) outgoing: HashMap<i64, BTreeSet<i64>>,
+    pub(crate) incoming: HashMap<i64, BTreeSet<i64>>,
+}
+
+impl LinkGraph {
+    pub(crate) fn new(note_identifiers: &[NoteIdentifier], links: &[DbNoteLink]) -> Self {
+        let titles = note_identifiers
+            .iter()
+            .map(|nid| (nid.id, nid.title.clone()))
+            .collect::<HashMap<i64, String>>();
+
+        let mut outgoing: HashMap<i64, BTreeSet<i64>> = HashMap::new();
+        let mut incoming: HashMap<i64, BTreeSet<i64>> = HashMap::new();
+
+        // Links to or from notes that no longer exist are left out
+        for link in links.iter().filter(|link| {
+            titles.contains_key(&link.parent_note_id) && titles.contains_key(&link.linked_note_id)
+        }) {
+            outgoing
+                .entry(link.parent_note_id)
+                .or_default()
+                .insert(link.linked_note_id);
+            incoming
+                .entry(link.linked_note_id)
+                .or_default()
+                .insert(link.parent_note_id);
+        }
+
+        Self {
+            titles,
+            outgoing,
+            incoming,
+        }
+    }
+
+    pub(crate) fn title(&self, id: i64) -> &str {
+        self.titles.get(&id).map_or("", |title| title.as_str())
+    }
+
+    pub(crate) fn outgoing(&self, id: i64) -> Vec<i64> {
+        self.outgoing
+            .get(&id)
+            .map_or(vec![], |ids| ids.iter().copied().collect())
+    }
+
+    pub(crate) fn incoming(&self, id: i64) -> Vec<i64> {
+        self.incoming
+            .get(&id)
+            .map_or(vec![], |ids| ids.iter().copied().collect())
+    }
+
+    /// Notes within `depth` links of `focus` in either direction, as (id, distance).
+    /// Sorted by distance and then title, `focus` always comes first.
+    pub(crate) fn neighbourhood(&self, focus: i64, depth: usize) -> Vec<(i64, usize)> {
+        if !self.titles.contains_key(&focus) {
+            return vec![];
+        }
+
+        let mut distances = HashMap::from([(focus, 0)]);
+        let mut queue = VecDeque::from([focus]);
+
+        while let Some(id) = queue.pop_front() {
+            let distance = distances[&id];
+            if distance == depth {
+                continue;
+            }
+            for next in self.outgoing(id).into_iter().chain(self.incoming(id)) {
+                if !distances.contains_key(&next) {
+                    distances.insert(next, distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut nodes = distances.into_iter().collect::<Vec<(i64, usize)>>();
+        nodes.sort_by(|a, b| (a.1, self.title(a.0)).cmp(&(b.1, self.title(b.0))));
+        nodes
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct GraphView {
+    pub(crate) graph: LinkGraph,
+    pub(crate) focus: i64,
+    pub(crate) depth: usize,
+    pub(crate) selected: usize,
+}
+
+impl GraphView {
+    pub(crate) fn new(graph: LinkGraph, focus: i64) -> Self {
+        Self {
+            graph,
+            focus,
+            depth: MIN_GRAPH_DEPTH,
+            selected: 0,
+        }
+    }
+
+    pub(crate) fn nodes(&self) -> Vec<(i64, usize)> {
+        self.graph.neighbourhood(self.focus, self.depth)
+    }
+
+    pub(crate) fn selected_id(&self) -> Option<i64> {
+        self.nodes().get(self.selected).map(|&(id, _)| id)
+    }
+
+    pub(crate) fn next(&mut self) {
+        let len = self.nodes().len();
+        if len > 0 {
+            self.selected = (self.selected + 1) % len;
+        }
+    }
+
+    pub(crate) fn prev(&mut self) {
+        let len = self.nodes().len();
+        if len > 0 {
+            self.selected = (self.selected + len - 1) % len;
+        }
+    }
+
+    pub(crate) fn set_depth(&mut self, depth: usize) {
+        self.depth = depth.clamp(MIN_GRAPH_DEPTH, MAX_GRAPH_DEPTH);
+        self.selected = 0;
+    }
+
+    /// Re-centres the neighbourhood on the selected note
+    pub(crate) fn focus_selected(&mut self) {
+        if let Some(id) = self.selected_id() {
+            self.focus = id;
+            self.selected = 0;
+        }
+    }
+
+    fn titles(&self, ids: Vec<i64>) -> String {
+        ids.into_iter()
+            .map(|id| self.graph.title(id))
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+}
+
+impl Widget for GraphView {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let title = Span::styled(
+            format!(" Link Graph: {} (depth {}) ", self.graph.title(self.focus), self.depth),
+            Style::default().bold().fg(Color::Yellow),
+        );
+        let info_line = Line::styled(
+            " <Esc> prev screen | <Enter> open | <f> focus | <1/2> depth ",
+            Style::default().bold(),
+        )
+        .alignment(Alignment::Center);
+
+        let graph_block = Block::default()
+            .title(Title::from(title).alignment(Alignment::Center))
+            .title_bottom(info_line)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        let lines = self
+            .nodes()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (id, distance))| {
+                let title_style = match idx == self.selected {
+                    true => Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                    false => Style::default().add_modifier(Modifier::BOLD),
+                };
+                Line::from(vec![
+                    Span::raw("  ".repeat(distance)),
+                    Span::styled(self.graph.title(id).to_owned(), title_style),
+                    Span::styled(
+                        format!("  → {}", self.titles(self.graph.outgoing(id))),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::styled(
+                        format!("  ← {}", self.titles(self.graph.incoming(id))),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                ])
+            })
+            .collect::<Vec<Line>>();
+
+        // Keep the selected note in view, the block takes 4 rows
+        let visible = area.height.saturating_sub(4) as usize;
+        let scroll = (self.selected + 1).saturating_sub(visible) as u16;
+
+        Paragraph::new(lines)
+            .block(graph_block)
+            .scroll((scroll, 0))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nid(id: i64, title: &str) -> NoteIdentifier {
+        NoteIdentifier {
+            id,
+            title: title.to_owned(),
+        }
+    }
+
+    fn link(parent: i64, linked: i64) -> DbNoteLink {
+        DbNoteLink {
+            parent_note_id: parent,
+            textarea_id: 0,
+            textarea_row: 0,
+            start_col: 0,
+            end_col: 1,
+            linked_note_id: linked,
+            anchor: None,
+        }
+    }
+
+    /// A -> B -> C -> A is a cycle, C -> D hangs off it and E is isolated
+    fn graph() -> LinkGraph {
+        let nids = [
+            nid(1, "A"),
+            nid(2, "B"),
+            nid(3, "C"),
+            nid(4, "D"),
+            nid(5, "E"),
+        ];
+        let links = [link(1, 2), link(1, 2), link(2, 3), link(3, 1), link(3, 4), link(3, 99)];
+        LinkGraph::new(&nids, &links)
+    }
+
+    #[test]
+    fn test_graph_construction_with_cycle() {
+        let graph = graph();
+
+        assert_eq!(graph.outgoing(1), vec![2]);
+        assert_eq!(graph.incoming(1), vec![3]);
+        assert_eq!(graph.outgoing(3), vec![1, 4]);
+        assert_eq!(graph.incoming(4), vec![3]);
+        assert!(graph.outgoing(5).is_empty());
+        assert!(graph.incoming(5).is_empty());
+        assert!(graph.incoming(99).is_empty());
+    }
+
+    #[test]
+    fn test_neighbourhood_depth() {
+        let graph = graph();
+
+        assert_eq!(graph.neighbourhood(1, 1), vec![(1, 0), (2, 1), (3, 1)]);
+        assert_eq!(
+            graph.neighbourhood(1, 2),
+            vec![(1, 0), (2, 1), (3, 1), (4, 2)]
+        );
+        assert_eq!(graph.neighbourhood(5, 2), vec![(5, 0)]);
+        assert!(graph.neighbourhood(99, 2).is_empty());
+    }
+
+    #[test]
+    fn test_graph_view_focus_selected() {
+        let mut view = GraphView::new(graph(), 1);
+        view.next();
+        view.next();
+        assert_eq!(view.selected_id(), Some(3));
+
+        view.focus_selected();
+
+        assert_eq!(view.focus, 3);
+        assert_eq!(view.selected_id(), Some(3));
+        assert_eq!(view.nodes(), vec![(3, 0), (1, 1), (2, 1), (4, 1)]);
+    }
+}
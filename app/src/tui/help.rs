@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use ratatui::{
+    layout::{Alignment, Constraint},
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        block::Title, Block, BorderType, Borders, Padding, Row, StatefulWidget, Table,
+        TableState, Widget,
+    },
+};
+use tuipaz_textarea::{Input, Key};
+
+use super::{config::get_action_name, events::Action};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum HelpContext {
+    Global,
+    Editor,
+    NoteList,
+}
+
+impl HelpContext {
+    fn label(&self) -> &'static str {
+        match self {
+            HelpContext::Global => "Global",
+            HelpContext::Editor => "Editor",
+            HelpContext::NoteList => "Note list",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HelpEntry {
+    pub(crate) context: HelpContext,
+    pub(crate) action: String,
+    pub(crate) binding: String,
+}
+
+pub(crate) fn action_context(action: &Action) -> HelpContext {
+    match action {
+        Action::ToggleSearchbar(_)
+        | Action::InsertLink(_)
+        | Action::DeleteChar
+        | Action::ExtractNote
+        | Action::MoveNote => HelpContext::Editor,
+        Action::Up(_)
+        | Action::Down(_)
+        | Action::Activate(_)
+        | Action::OpenNoteList
+        | Action::OpenInTab
+        | Action::ToggleMark(_)
+        | Action::TagMarked => HelpContext::NoteList,
+        _ => HelpContext::Global,
+    }
+}
+
+pub(crate) fn format_input(input: &Input) -> String {
+    let key = match input.key {
+        Key::Char(' ') => "Space".to_owned(),
+        Key::Char(c) => c.to_string(),
+        Key::F(n) => format!("F{}", n),
+        Key::Backspace => "Backspace".to_owned(),
+        Key::Enter => "Enter".to_owned(),
+        Key::Left => "Left".to_owned(),
+        Key::Right => "Right".to_owned(),
+        Key::Up => "Up".to_owned(),
+        Key::Down => "Down".to_owned(),
+        Key::Tab => "Tab".to_owned(),
+        Key::Delete => "Delete".to_owned(),
+        Key::Home => "Home".to_owned(),
+        Key::End => "End".to_owned(),
+        Key::PageUp => "PageUp".to_owned(),
+        Key::PageDown => "PageDown".to_owned(),
+        Key::Esc => "Esc".to_owned(),
+        _ => "?".to_owned(),
+    };
+
+    let mut formatted = String::new();
+    if input.ctrl {
+        formatted.push_str("Ctrl-");
+    }
+    if input.alt {
+        formatted.push_str("Alt-");
+    }
+    if input.shift && !matches!(input.key, Key::Char(_)) {
+        formatted.push_str("Shift-");
+    }
+    formatted.push_str(&key);
+    formatted
+}
+
+pub(crate) fn help_entries(keymap: &HashMap<Action, Input>) -> Vec<HelpEntry> {
+    let mut entries = keymap
+        .iter()
+        .filter(|(action, _)| !matches!(action, Action::Null | Action::Edit(_)))
+        .map(|(action, input)| HelpEntry {
+            context: action_context(action),
+            action: get_action_name(action).replace('_', " "),
+            binding: format_input(input),
+        })
+        .collect::<Vec<HelpEntry>>();
+
+    entries.sort_by(|a, b| (a.context, &a.action).cmp(&(b.context, &b.action)));
+    entries
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Help {
+    pub(crate) entries: Vec<HelpEntry>,
+    pub(crate) scroll: u16,
+}
+
+impl Help {
+    pub(crate) fn new(keymap: &HashMap<Action, Input>, scroll: u16) -> Self {
+        Self {
+            entries: help_entries(keymap),
+            scroll,
+        }
+    }
+}
+
+impl Widget for Help {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let title = Span::styled(" Help ", Style::default().bold().fg(Color::Yellow));
+        let info_line = Line::styled(
+            " <Esc> prev screen | <up/k> <down/j> scroll ",
+            Style::default().bold(),
+        )
+        .alignment(Alignment::Center);
+
+        let help_block = Block::default()
+            .title(Title::from(title).alignment(Alignment::Center))
+            .title_bottom(info_line)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        let mut prev_context = None;
+        let rows = self
+            .entries
+            .into_iter()
+            .map(|entry| {
+                // Only label the first row of each group
+                let context = match prev_context == Some(entry.context) {
+                    true => "",
+                    false => entry.context.label(),
+                };
+                prev_context = Some(entry.context);
+
+                Row::new(vec![
+                    Span::styled(context, Style::default().bold()),
+                    Span::raw(entry.action),
+                    Span::styled(entry.binding, Style::default().fg(Color::Cyan)),
+                ])
+            })
+            .collect::<Vec<Row>>();
+
+        let max_offset = rows.len().saturating_sub(1);
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(45),
+                Constraint::Percentage(30),
+            ],
+        )
+        .header(
+            Row::new(vec!["Context", "Action", "Key"])
+                .style(Style::default().bold().underlined()),
+        )
+        .block(help_block);
+
+        let mut state =
+            TableState::default().with_offset(std::cmp::min(self.scroll as usize, max_offset));
+
+        StatefulWidget::render(table, area, buf, &mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::config::Config;
+
+    #[test]
+    fn test_help_entries_include_remapped_binding() {
+        let mut keymap = Config::default().keymap;
+        keymap.insert(
+            Action::SaveNote,
+            Input {
+                key: Key::Char('w'),
+                ctrl: true,
+                alt: false,
+                shift: false,
+            },
+        );
+
+        let entries = help_entries(&keymap);
+        let save = entries
+            .iter()
+            .find(|entry| entry.action == "save")
+            .expect("save should be listed");
+
+        assert_eq!(save.binding, "Ctrl-w");
+        assert_eq!(save.context, HelpContext::Global);
+        assert!(entries
+            .iter()
+            .any(|entry| entry.action == "show help" && entry.binding == "?"));
+    }
+
+    #[test]
+    fn test_help_entries_grouped_by_context() {
+        let entries = help_entries(&Config::default().keymap);
+        let contexts = entries.iter().map(|entry| entry.context).collect::<Vec<_>>();
+        let mut sorted = contexts.clone();
+        sorted.sort();
+
+        assert_eq!(contexts, sorted);
+    }
+}
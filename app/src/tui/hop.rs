@@ -0,0 +1,54 @@
+/// Home row first, so the nearest matches get the easiest labels
+const LABEL_CHARS: &str = "asdfghjklqwertyuiopzxcvbnm";
+
+/// One label per match. Single letters while they last, otherwise every label is two
+/// letters so none is a prefix of another.
+pub(crate) fn hop_labels(count: usize) -> Vec<String> {
+    let chars = LABEL_CHARS.chars().collect::<Vec<char>>();
+    match count <= chars.len() {
+        true => chars.iter().take(count).map(|c| c.to_string()).collect(),
+        false => chars
+            .iter()
+            .flat_map(|&first| chars.iter().map(move |&second| format!("{}{}", first, second)))
+            .take(count)
+            .collect(),
+    }
+}
+
+/// (row, char col) of every occurrence of `pattern`, in the order they appear
+pub(crate) fn hop_matches(lines: &[String], pattern: &str) -> Vec<(usize, usize)> {
+    if pattern.is_empty() {
+        return vec![];
+    }
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            line.match_indices(pattern)
+                .map(move |(byte_idx, _)| (row, line[..byte_idx].chars().count()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hop_labels_are_prefix_free() {
+        assert_eq!(hop_labels(3), ["a", "s", "d"]);
+
+        let labels = hop_labels(30);
+        assert_eq!(labels.len(), 30);
+        assert_eq!(labels[..3], ["aa", "as", "ad"]);
+        assert!(labels.iter().all(|label| label.len() == 2));
+    }
+
+    #[test]
+    fn test_hop_matches_uses_char_cols() {
+        let lines = vec!["héllo hello".to_owned(), "".to_owned(), "hello".to_owned()];
+
+        assert_eq!(hop_matches(&lines, "he"), [(0, 6), (2, 0)]);
+        assert!(hop_matches(&lines, "").is_empty());
+    }
+}
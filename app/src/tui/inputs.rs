@@ -14,6 +14,50 @@ pub(crate) enum InputAction {
     NoteTitle,
     Note,
     LinkedNote,
+    QuickCapture,
+    /// Directory of Markdown files to import as notes
+    ImportDir,
+    /// Tag for every marked note in the note list
+    TagNotes,
+    /// Title of a new note for the selected text, linked from where the text was
+    ExtractNote,
+    /// Folder, or `vault:folder`, to move the open note to
+    MoveNote,
+}
+
+/// Title with the surrounding whitespace trimmed, None if nothing is left
+pub(crate) fn clean_title(title: &str) -> Option<String> {
+    let title = title.trim();
+    match title.is_empty() {
+        true => None,
+        false => Some(title.to_owned()),
+    }
+}
+
+/// Longest title taken from a quick-capture, the whole text goes in the body
+const CAPTURE_TITLE_LEN: usize = 40;
+
+/// Title for a quick-capture, its first line cut short and numbered while `taken`
+pub(crate) fn capture_title(text: &str, taken: impl Fn(&str) -> bool) -> String {
+    let first_line = text.lines().next().unwrap_or_default().trim();
+    let base = first_line
+        .chars()
+        .take(CAPTURE_TITLE_LEN)
+        .collect::<String>()
+        .trim_end()
+        .to_owned();
+    numbered_title(&base, taken)
+}
+
+/// `base`, or "base (2)", "base (3)"... while those are `taken`
+pub(crate) fn numbered_title(base: &str, taken: impl Fn(&str) -> bool) -> String {
+    let mut title = base.to_owned();
+    let mut n = 2;
+    while taken(&title) {
+        title = format!("{} ({})", base, n);
+        n += 1;
+    }
+    title
 }
 
 #[derive(Debug, Clone)]
@@ -27,7 +71,13 @@ impl<'i> UserInput<'i> {
     pub(crate) fn new(state: ComponentState, action: InputAction) -> Self {
         let mut text = TextArea::default();
         text.set_cursor_line_style(Style::default());
-        text.set_placeholder_text("Enter a title...");
+        text.set_placeholder_text(match action {
+            InputAction::QuickCapture => "Jot something down...",
+            InputAction::ImportDir => "Directory of .md files...",
+            InputAction::TagNotes => "Tag to add, -tag to remove it...",
+            InputAction::MoveNote => "Folder, or vault:folder, empty for the root...",
+            _ => "Enter a title...",
+        });
         text.set_placeholder_style(Style::default().dim());
 
         Self {
@@ -66,13 +116,48 @@ impl Widget for UserInput<'_> {
             ComponentState::Error => (Style::default().bold().fg(Color::Red), Style::default().bold().fg(Color::Red), Style::default()),
         };
 
+        let empty_title = clean_title(&self.text.lines()[0]).is_none();
         let (title_span, input_hint) = match (self.action, self.state) {
             (
-                InputAction::NoteTitle | InputAction::Note | InputAction::LinkedNote,
+                InputAction::NoteTitle
+                | InputAction::Note
+                | InputAction::LinkedNote
+                | InputAction::ExtractNote,
                 ComponentState::Error,
-            ) => (
+            ) if empty_title =>
+            {
+                (
+                    Span::styled(" Error: title can't be empty ", title_style),
+                    Span::styled(" Please enter a title ", hint_style),
+                )
+            }
+            (InputAction::LinkedNote, ComponentState::Error) => (
+                Span::styled(
+                    format!(" Error: {:?} already exists ", self.text.lines()[0]),
+                    title_style,
+                ),
+                Span::styled(
+                    " Please choose a different title <Esc> cancel link ",
+                    hint_style,
+                ),
+            ),
+            (InputAction::ExtractNote, ComponentState::Error) => (
+                Span::styled(
+                    format!(" Error: {:?} already exists ", self.text.lines()[0]),
+                    title_style,
+                ),
+                Span::styled(" Please choose a different title <Esc> cancel ", hint_style),
+            ),
+            (InputAction::ExtractNote, _) => (
+                Span::styled(" Extract To New Note ", title_style),
                 Span::styled(
-                    format!(" Error: {:?} already exists ", self.text.lines()),
+                    " <Esc> return to note <Enter> move the selection ",
+                    hint_style,
+                ),
+            ),
+            (InputAction::NoteTitle | InputAction::Note, ComponentState::Error) => (
+                Span::styled(
+                    format!(" Error: {:?} already exists ", self.text.lines()[0]),
                     title_style,
                 ),
                 Span::styled(
@@ -80,6 +165,37 @@ impl Widget for UserInput<'_> {
                     hint_style,
                 ),
             ),
+            (InputAction::QuickCapture, ComponentState::Error) => (
+                Span::styled(" Error: nothing to capture ", title_style),
+                Span::styled(" <Esc> cancel ", hint_style),
+            ),
+            (InputAction::QuickCapture, _) => (
+                Span::styled(" Quick Capture ", title_style),
+                Span::styled(
+                    " <Esc> return to note <Enter> save as a new note ",
+                    hint_style,
+                ),
+            ),
+            (InputAction::ImportDir, ComponentState::Error) => (
+                Span::styled(" Error: no directory given ", title_style),
+                Span::styled(" <Esc> cancel ", hint_style),
+            ),
+            (InputAction::ImportDir, _) => (
+                Span::styled(" Import Markdown ", title_style),
+                Span::styled(" <Esc> return to note <Enter> import ", hint_style),
+            ),
+            (InputAction::TagNotes, ComponentState::Error) => (
+                Span::styled(" Error: a tag is one word ", title_style),
+                Span::styled(" <Esc> cancel ", hint_style),
+            ),
+            (InputAction::TagNotes, _) => (
+                Span::styled(" Tag Marked Notes ", title_style),
+                Span::styled(" <Esc> return to notes <Enter> tag ", hint_style),
+            ),
+            (InputAction::MoveNote, _) => (
+                Span::styled(" Move Note ", title_style),
+                Span::styled(" <Esc> return to note <Enter> move ", hint_style),
+            ),
             (InputAction::NoteTitle | InputAction::Note | InputAction::LinkedNote, _) => (
                 Span::styled(" New Note ", title_style),
                 Span::styled(
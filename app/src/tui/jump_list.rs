@@ -0,0 +1,93 @@
+pub(crate) const JUMP_LIST_CAP: usize = 100;
+
+/// Notes visited by loading or following links, walked with back/forward like Vim's jumplist
+#[derive(Debug, Clone)]
+pub(crate) struct JumpList {
+    pub(crate) entries: Vec<i64>,
+    pub(crate) idx: usize,
+    pub(crate) cap: usize,
+}
+
+impl JumpList {
+    pub(crate) fn new(cap: usize) -> Self {
+        Self {
+            entries: vec![],
+            idx: 0,
+            cap,
+        }
+    }
+
+    pub(crate) fn current(&self) -> Option<i64> {
+        self.entries.get(self.idx).copied()
+    }
+
+    /// Records a visit after the current entry, dropping any forward history
+    pub(crate) fn push(&mut self, id: i64) {
+        if self.current() == Some(id) {
+            return;
+        }
+        self.entries.truncate(self.idx + 1);
+        self.entries.push(id);
+        if self.entries.len() > self.cap {
+            self.entries.remove(0);
+        }
+        self.idx = self.entries.len() - 1;
+    }
+
+    pub(crate) fn back(&mut self) -> Option<i64> {
+        match self.idx > 0 {
+            true => {
+                self.idx -= 1;
+                self.current()
+            }
+            false => None,
+        }
+    }
+
+    pub(crate) fn forward(&mut self) -> Option<i64> {
+        match self.idx + 1 < self.entries.len() {
+            true => {
+                self.idx += 1;
+                self.current()
+            }
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jump_list_back_and_forward() {
+        let mut jumps = JumpList::new(JUMP_LIST_CAP);
+        jumps.push(1);
+        jumps.push(2);
+        jumps.push(2);
+        jumps.push(3);
+
+        assert_eq!(jumps.entries, vec![1, 2, 3]);
+        assert_eq!(jumps.back(), Some(2));
+        assert_eq!(jumps.back(), Some(1));
+        assert_eq!(jumps.back(), None);
+        assert_eq!(jumps.forward(), Some(2));
+
+        // A new visit replaces the forward history
+        jumps.push(4);
+        assert_eq!(jumps.entries, vec![1, 2, 4]);
+        assert_eq!(jumps.forward(), None);
+    }
+
+    #[test]
+    fn test_jump_list_cap() {
+        let mut jumps = JumpList::new(3);
+        for id in 1..=5 {
+            jumps.push(id);
+        }
+
+        assert_eq!(jumps.entries, vec![3, 4, 5]);
+        assert_eq!(jumps.current(), Some(5));
+        assert_eq!(jumps.back(), Some(4));
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use ratatui::{
+    prelude::{Buffer, Rect},
+    widgets::Widget,
+};
+
+use crate::db::db_mac::NoteIdentifier;
+
+use super::{
+    editor::Link,
+    picker::{indexed_entries, Picker},
+};
+
+/// A link in the open note, with the title of the note it points to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LinkEntry {
+    pub(crate) row: usize,
+    pub(crate) col: usize,
+    pub(crate) target: String,
+    pub(crate) state: &'static str,
+}
+
+/// Links in document order. Targets outside the loaded pages of the note list are
+/// shown by id.
+pub(crate) fn link_entries(
+    links: &HashMap<i64, Link>,
+    note_identifiers: &[NoteIdentifier],
+) -> Vec<LinkEntry> {
+    let mut entries = links
+        .values()
+        .map(|link| LinkEntry {
+            row: link.row,
+            col: link.start_col,
+            target: note_identifiers
+                .iter()
+                .find(|nid| nid.id == link.linked_id)
+                .map(|nid| nid.title.clone())
+                .unwrap_or_else(|| format!("note #{}", link.linked_id)),
+            state: link_state(link),
+        })
+        .collect::<Vec<LinkEntry>>();
+    entries.sort_by_key(|entry| (entry.row, entry.col));
+    entries
+}
+
+fn link_state(link: &Link) -> &'static str {
+    match (link.deleted, link.updated, link.saved) {
+        (true, _, _) => "deleted",
+        (false, true, _) => "updated",
+        (false, false, true) => "saved",
+        (false, false, false) => "new",
+    }
+}
+
+/// Panel over the open note's links, picking one moves the cursor to it
+#[derive(Debug, Clone)]
+pub(crate) struct LinkList {
+    pub(crate) entries: Vec<LinkEntry>,
+    pub(crate) picker: Picker,
+}
+
+impl LinkList {
+    pub(crate) fn new(links: &HashMap<i64, Link>, note_identifiers: &[NoteIdentifier]) -> Self {
+        let entries = link_entries(links, note_identifiers);
+        let labels = entries
+            .iter()
+            .map(|entry| format!("L{:<4} {}  [{}]", entry.row + 1, entry.target, entry.state));
+
+        Self {
+            picker: Picker::new(" Links ", indexed_entries(labels)),
+            entries,
+        }
+    }
+
+    /// (row, col) of the link behind a picked row
+    pub(crate) fn pos_at(&self, id: Option<i64>) -> Option<(usize, usize)> {
+        id.and_then(|id| self.entries.get(id as usize))
+            .map(|entry| (entry.row, entry.col))
+    }
+}
+
+impl Widget for LinkList {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        self.picker.render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(text_id: i64, linked_id: i64, row: usize, start_col: usize) -> Link {
+        Link {
+            id: 1,
+            text_id,
+            linked_id,
+            row,
+            start_col,
+            end_col: start_col + 4,
+            saved: true,
+            updated: false,
+            deleted: false,
+            anchor: None,
+        }
+    }
+
+    #[test]
+    fn test_link_list_shows_targets_and_lines() {
+        let nids = vec![
+            NoteIdentifier {
+                id: 2,
+                title: "Second".to_owned(),
+            },
+            NoteIdentifier {
+                id: 3,
+                title: "Third".to_owned(),
+            },
+        ];
+        let mut links = HashMap::new();
+        links.insert(0, link(0, 3, 4, 2));
+        links.insert(
+            1,
+            Link {
+                updated: true,
+                ..link(1, 2, 0, 6)
+            },
+        );
+
+        let list = LinkList::new(&links, &nids);
+
+        assert_eq!(
+            list.entries,
+            [
+                LinkEntry {
+                    row: 0,
+                    col: 6,
+                    target: "Second".to_owned(),
+                    state: "updated",
+                },
+                LinkEntry {
+                    row: 4,
+                    col: 2,
+                    target: "Third".to_owned(),
+                    state: "saved",
+                },
+            ]
+        );
+        assert_eq!(list.picker.entries[1].label, "L5    Third  [saved]");
+        assert_eq!(list.pos_at(list.picker.selected_id()), Some((0, 6)));
+        assert_eq!(list.pos_at(list.picker.numbered_id(2)), Some((4, 2)));
+    }
+}
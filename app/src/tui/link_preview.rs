@@ -0,0 +1,59 @@
+use ratatui::{
+    layout::Alignment,
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{block::Title, Block, BorderType, Borders, Padding, Paragraph, Widget, Wrap},
+};
+
+/// Lines of the linked note shown in the preview popup
+pub(crate) const PREVIEW_LINES: usize = 8;
+
+/// Read-only look at a linked note, nothing is saved or loaded until the user opens it
+#[derive(Debug, Clone)]
+pub(crate) struct LinkPreview {
+    pub(crate) note_id: i64,
+    pub(crate) title: String,
+    pub(crate) lines: Vec<String>,
+}
+
+impl LinkPreview {
+    pub(crate) fn new(note_id: i64, title: String, lines: Vec<String>) -> Self {
+        Self {
+            note_id,
+            title,
+            lines,
+        }
+    }
+}
+
+impl Widget for LinkPreview {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let title = Span::styled(
+            format!(" {} ", self.title),
+            Style::default().bold().fg(Color::Yellow),
+        );
+        let info_line = Line::styled(" <Esc> close | <Enter> open ", Style::default().bold())
+            .alignment(Alignment::Center);
+
+        let preview_block = Block::default()
+            .title(Title::from(title).alignment(Alignment::Center))
+            .title_bottom(info_line)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        let lines = match self.lines.is_empty() {
+            true => vec![Line::styled("(empty note)", Style::default().dim())],
+            false => self.lines.into_iter().map(Line::from).collect(),
+        };
+
+        Paragraph::new(lines)
+            .block(preview_block)
+            .wrap(Wrap { trim: false })
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,214 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Widget},
+};
+
+use crate::db::db_mac::NoteIdentifier;
+
+use super::picker::{Picker, PickerEntry};
+
+/// Titles the popup shows at once, the list scrolls to keep the selection in view
+pub(crate) const SUGGESTION_ROWS: usize = 6;
+const SUGGESTION_WIDTH: u16 = 40;
+
+/// Row and char column of the `[[` just before `cursor`, if there is one
+pub(crate) fn link_trigger(line: &str, cursor: (usize, usize)) -> Option<(usize, usize)> {
+    let (row, col) = cursor;
+    let start = col.checked_sub(2)?;
+    let typed = line.chars().skip(start).take(2).collect::<String>();
+    (typed == "[[").then_some((row, start))
+}
+
+/// `Title#Heading` as the title and the heading, None when nothing follows the `#`
+pub(crate) fn split_heading(query: &str) -> (&str, Option<&str>) {
+    match query.split_once('#') {
+        Some((title, heading)) if !heading.trim().is_empty() => (title, Some(heading.trim())),
+        Some((title, _)) => (title, None),
+        None => (query, None),
+    }
+}
+
+/// Completion popup opened by typing `[[`, the text typed after it filters the titles.
+/// Text after a `#` names a heading in the picked note for the link to jump to.
+#[derive(Debug, Clone)]
+pub(crate) struct LinkSuggestions {
+    /// Row and char column of the first `[`
+    pub(crate) anchor: (usize, usize),
+    pub(crate) picker: Picker,
+    pub(crate) heading: Option<String>,
+}
+
+impl LinkSuggestions {
+    pub(crate) fn new(anchor: (usize, usize), entries: Vec<NoteIdentifier>) -> Self {
+        Self {
+            anchor,
+            picker: Picker::new("", entries.into_iter().map(PickerEntry::from).collect()),
+            heading: None,
+        }
+    }
+
+    /// Text typed between the `[[` and the cursor, None once the cursor has left it
+    pub(crate) fn query(&self, line: &str, cursor: (usize, usize)) -> Option<String> {
+        let (row, col) = self.anchor;
+        if cursor.0 != row || cursor.1 < col + 2 || link_trigger(line, (row, col + 2)).is_none() {
+            return None;
+        }
+        let query = line
+            .chars()
+            .skip(col + 2)
+            .take(cursor.1 - col - 2)
+            .collect::<String>();
+
+        match query.contains(['[', ']']) {
+            true => None,
+            false => Some(query),
+        }
+    }
+
+    /// The selection goes back to the best match whenever the title part changes
+    pub(crate) fn set_query(&mut self, query: &str) {
+        let (title, heading) = split_heading(query);
+        if self.picker.filter != title {
+            self.picker.filter = title.to_owned();
+            self.picker.selected = 0;
+        }
+        self.heading = heading.map(str::to_owned);
+    }
+
+    pub(crate) fn selected_title(&self) -> Option<String> {
+        self.picker
+            .filtered()
+            .get(self.picker.selected)
+            .map(|entry| entry.label.clone())
+    }
+
+    /// Where the popup goes for a `[[` drawn at `at`, below it when it fits, above if not
+    pub(crate) fn area(&self, at: (u16, u16), bounds: Rect) -> Rect {
+        let rows = self.picker.filtered().len().clamp(1, SUGGESTION_ROWS) as u16;
+        let height = (rows + 2).min(bounds.height);
+        let width = SUGGESTION_WIDTH.min(bounds.width);
+        let (x, y) = at;
+
+        let x = x.min(bounds.right().saturating_sub(width)).max(bounds.x);
+        let y = match y + 1 + height <= bounds.bottom() {
+            true => y + 1,
+            false => y.saturating_sub(height).max(bounds.y),
+        };
+        Rect::new(x, y, width, height)
+    }
+}
+
+impl Widget for LinkSuggestions {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Yellow));
+
+        let filtered = self.picker.filtered();
+        let skip = (self.picker.selected + 1).saturating_sub(SUGGESTION_ROWS);
+        let lines = match filtered.is_empty() {
+            true => vec![Line::styled(
+                format!(" new note \"{}\"", self.picker.filter),
+                Style::default().fg(Color::DarkGray).italic(),
+            )],
+            false => filtered
+                .iter()
+                .enumerate()
+                .skip(skip)
+                .take(SUGGESTION_ROWS)
+                .map(|(idx, entry)| {
+                    let style = match idx == self.picker.selected {
+                        true => Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                        false => Style::default(),
+                    };
+                    Line::styled(format!(" {}", entry.label), style)
+                })
+                .collect(),
+        };
+
+        Clear.render(area, buf);
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestions() -> LinkSuggestions {
+        LinkSuggestions::new(
+            (0, 4),
+            ["Meeting notes", "Groceries", "Meetup ideas"]
+                .iter()
+                .enumerate()
+                .map(|(idx, title)| NoteIdentifier {
+                    id: idx as i64 + 1,
+                    title: title.to_string(),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_link_trigger() {
+        assert_eq!(link_trigger("see [[", (2, 6)), Some((2, 4)));
+        assert_eq!(link_trigger("see [[]]", (2, 6)), Some((2, 4)));
+        assert_eq!(link_trigger("see [", (2, 5)), None);
+        assert_eq!(link_trigger("[", (0, 1)), None);
+    }
+
+    #[test]
+    fn test_suggestions_filter_as_query_is_typed() {
+        let mut suggestions = suggestions();
+        let mut line = "see [[".to_owned();
+        for c in "meet".chars() {
+            line.push(c);
+            let query = suggestions.query(&line, (0, line.chars().count())).unwrap();
+            suggestions.set_query(&query);
+        }
+
+        let titles = suggestions
+            .picker
+            .filtered()
+            .iter()
+            .map(|entry| entry.label.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(titles, ["Meeting notes", "Meetup ideas"]);
+
+        suggestions.picker.next();
+        assert_eq!(suggestions.selected_title().as_deref(), Some("Meetup ideas"));
+
+        line.push('x');
+        suggestions.set_query(&suggestions.query(&line, (0, 11)).unwrap());
+        assert!(suggestions.picker.filtered().is_empty());
+        assert_eq!(suggestions.selected_title(), None);
+    }
+
+    #[test]
+    fn test_suggestions_close_when_cursor_leaves_the_link() {
+        let suggestions = suggestions();
+
+        assert_eq!(suggestions.query("see [[gro", (0, 9)).as_deref(), Some("gro"));
+        assert_eq!(suggestions.query("see [[gro", (0, 5)), None);
+        assert_eq!(suggestions.query("see [[gro", (1, 9)), None);
+        assert_eq!(suggestions.query("see [[gro]", (0, 10)), None);
+        assert_eq!(suggestions.query("see [gro", (0, 8)), None);
+    }
+
+    #[test]
+    fn test_heading_after_hash_is_kept_apart_from_the_filter() {
+        let mut suggestions = suggestions();
+        suggestions.set_query("meetup#Next steps ");
+
+        assert_eq!(suggestions.picker.filter, "meetup");
+        assert_eq!(suggestions.heading.as_deref(), Some("Next steps"));
+        assert_eq!(suggestions.selected_title().as_deref(), Some("Meetup ideas"));
+        assert_eq!(split_heading("Groceries#"), ("Groceries", None));
+    }
+}
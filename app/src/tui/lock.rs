@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    layout::Alignment,
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{block::Title, Block, BorderType, Borders, Padding, Paragraph, Widget},
+};
+use sha2::{Digest, Sha256};
+
+use super::app::Screen;
+
+pub(crate) const DEFAULT_LOCK_AFTER_SECS: u64 = 600;
+
+/// Hex SHA-256 of a passphrase, the form `lock_passphrase_hash` is written in
+pub(crate) fn hash_passphrase(passphrase: &str) -> String {
+    Sha256::digest(passphrase.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Hides the notes after a spell without input until the passphrase is typed
+#[derive(Debug, Clone)]
+pub(crate) struct IdleLock {
+    pub(crate) after: Duration,
+    pub(crate) passphrase_hash: String,
+    pub(crate) last_input: Instant,
+    /// Current and previous screen to go back to, Some while locked
+    pub(crate) restore: Option<(Screen, Screen)>,
+    pub(crate) entry: String,
+    pub(crate) failed: bool,
+}
+
+impl IdleLock {
+    /// None, so nothing ever locks, without a passphrase hash or with no timeout
+    pub(crate) fn new(after_secs: u64, passphrase_hash: Option<&str>) -> Option<Self> {
+        let passphrase_hash = passphrase_hash.map(str::trim).filter(|hash| !hash.is_empty())?;
+        if after_secs == 0 {
+            return None;
+        }
+
+        Some(Self {
+            after: Duration::from_secs(after_secs),
+            passphrase_hash: passphrase_hash.to_lowercase(),
+            last_input: Instant::now(),
+            restore: None,
+            entry: String::new(),
+            failed: false,
+        })
+    }
+
+    pub(crate) fn is_locked(&self) -> bool {
+        self.restore.is_some()
+    }
+
+    pub(crate) fn idle_left(&self) -> Duration {
+        self.after.saturating_sub(self.last_input.elapsed())
+    }
+
+    /// Checks the typed passphrase, giving back the screens to restore when it matches
+    pub(crate) fn unlock(&mut self) -> Option<(Screen, Screen)> {
+        let matches = hash_passphrase(&self.entry) == self.passphrase_hash;
+        self.entry.clear();
+        self.failed = !matches;
+        if !matches {
+            return None;
+        }
+        self.last_input = Instant::now();
+        self.restore.take()
+    }
+}
+
+/// Passphrase prompt drawn over a blank screen, the typed text is masked
+#[derive(Debug, Clone)]
+pub(crate) struct LockScreen {
+    pub(crate) typed: usize,
+    pub(crate) failed: bool,
+}
+
+impl Widget for LockScreen {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let (title, title_style) = match self.failed {
+            true => (" Wrong passphrase ", Style::default().bold().fg(Color::Red)),
+            false => (" Locked ", Style::default().bold()),
+        };
+        let info_line = Line::styled(" <Enter> unlock ", Style::default().bold())
+            .alignment(Alignment::Center);
+
+        let lock_block = Block::default()
+            .title(Title::from(Line::styled(title, title_style)).alignment(Alignment::Center))
+            .title_bottom(info_line)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        Paragraph::new(format!("Passphrase: {}", "*".repeat(self.typed)))
+            .block(lock_block)
+            .alignment(Alignment::Center)
+            .render(area, buf);
+    }
+}
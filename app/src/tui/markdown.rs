@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+
+use super::editor::{heading_level, heading_style, EditorTheme, Link};
+
+/// Body lines as rendered Markdown: headings, `**bold**`, `*italic*`, list bullets and
+/// note links are styled. Emphasis markers and heading hashes are hidden, an unclosed
+/// marker is shown as typed.
+pub(crate) fn preview_lines(
+    lines: &[String],
+    links: &HashMap<i64, Link>,
+    theme: &EditorTheme,
+) -> Vec<Line<'static>> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(row, line)| preview_line(row, line, links, theme))
+        .collect()
+}
+
+fn preview_line(
+    row: usize,
+    line: &str,
+    links: &HashMap<i64, Link>,
+    theme: &EditorTheme,
+) -> Line<'static> {
+    let text_style = Style::default().fg(theme.text);
+    if let (Some(level), Some(style)) = (heading_level(line), heading_style(theme, line)) {
+        return Line::styled(line[level + 1..].to_owned(), style);
+    }
+
+    let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+    let rest = line.chars().skip(indent).take(2).collect::<String>();
+    let mut spans = vec![];
+    let start_col = match rest.as_str() {
+        "- " | "* " | "+ " => {
+            spans.push(Span::styled(format!("{}• ", " ".repeat(indent)), text_style));
+            indent + 2
+        }
+        _ => 0,
+    };
+    spans.extend(inline_spans(row, line, start_col, text_style, links, theme));
+    Line::from(spans)
+}
+
+/// Spans for `line` from `start_col`, with emphasis markers dropped
+fn inline_spans(
+    row: usize,
+    line: &str,
+    start_col: usize,
+    text_style: Style,
+    links: &HashMap<i64, Link>,
+    theme: &EditorTheme,
+) -> Vec<Span<'static>> {
+    let chars = line.chars().collect::<Vec<char>>();
+    let mut bold = false;
+    let mut italic: Option<char> = None;
+    let mut styled: Vec<(char, Style)> = vec![];
+
+    let mut col = start_col;
+    while col < chars.len() {
+        let c = chars[col];
+        if c == '*' && chars.get(col + 1) == Some(&'*') {
+            if bold || closes_bold(&chars, col + 2) {
+                bold = !bold;
+                col += 2;
+                continue;
+            }
+        } else if italic == Some(c) {
+            italic = None;
+            col += 1;
+            continue;
+        } else if italic.is_none() && opens_italic(&chars, col) {
+            italic = Some(c);
+            col += 1;
+            continue;
+        }
+
+        let in_link = links.values().any(|link| {
+            !link.deleted && link.row == row && (link.start_col..=link.end_col).contains(&col)
+        });
+        let mut style = match in_link {
+            true => text_style.fg(theme.links).add_modifier(Modifier::UNDERLINED),
+            false => text_style,
+        };
+        if bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if italic.is_some() {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        styled.push((c, style));
+        col += 1;
+    }
+
+    let mut spans: Vec<Span<'static>> = vec![];
+    for (c, style) in styled {
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    spans
+}
+
+fn closes_bold(chars: &[char], from: usize) -> bool {
+    chars
+        .get(from..)
+        .is_some_and(|rest| rest.windows(2).any(|pair| pair == ['*', '*']))
+}
+
+/// A lone `*`, or a `_` at the start of a word, with a lone closing marker after it
+fn opens_italic(chars: &[char], col: usize) -> bool {
+    let c = chars[col];
+    let lone = |idx: usize| {
+        chars[idx] == c
+            && (idx == 0 || chars[idx - 1] != c)
+            && chars.get(idx + 1) != Some(&c)
+    };
+    match c {
+        '*' => lone(col) && (col + 2..chars.len()).any(lone),
+        '_' => {
+            (col == 0 || !chars[col - 1].is_alphanumeric())
+                && lone(col)
+                && (col + 2..chars.len()).any(lone)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Color;
+
+    use super::*;
+    use crate::tui::config::BorderStyle;
+
+    fn theme() -> EditorTheme {
+        EditorTheme {
+            title: Color::Reset,
+            text: Color::White,
+            borders: Color::Reset,
+            normal_mode: Color::Reset,
+            insert_mode: Color::Reset,
+            visual_mode: Color::Reset,
+            visual_line_mode: Color::Reset,
+            search_mode: Color::Reset,
+            select: Color::Reset,
+            search: Color::Reset,
+            links: Color::Blue,
+            hop: Color::Reset,
+            cursor: None,
+            cursor_line: None,
+            main_heading: Color::Yellow,
+            main_heading_modifiers: vec![Modifier::BOLD],
+            sub_heading: Color::Green,
+            sub_heading_modifiers: vec![],
+            border: BorderStyle::Rounded,
+        }
+    }
+
+    #[test]
+    fn test_preview_styles_headings_and_emphasis() {
+        let lines = vec![
+            "# Title".to_owned(),
+            "some **bold** and *it* text, 2 * 3".to_owned(),
+            "- item".to_owned(),
+        ];
+
+        let preview = preview_lines(&lines, &HashMap::new(), &theme());
+
+        assert_eq!(preview[0].spans[0].content, "Title");
+        assert_eq!(preview[0].spans[0].style.fg, Some(Color::Yellow));
+        assert!(preview[0].spans[0].style.add_modifier.contains(Modifier::BOLD));
+
+        let spans = &preview[1].spans;
+        let texts = spans.iter().map(|span| span.content.as_ref()).collect::<Vec<&str>>();
+        assert_eq!(texts, ["some ", "bold", " and ", "it", " text, 2 * 3"]);
+        assert!(spans[1].style.add_modifier.contains(Modifier::BOLD));
+        assert!(!spans[0].style.add_modifier.contains(Modifier::BOLD));
+        assert!(spans[3].style.add_modifier.contains(Modifier::ITALIC));
+
+        assert_eq!(preview[2].spans[0].content, "• ");
+        assert_eq!(preview[2].spans[1].content, "item");
+    }
+}
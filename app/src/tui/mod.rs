@@ -1,12 +1,35 @@
 pub(crate) mod app;
+pub(crate) mod auto_link;
 pub(crate) mod buttons;
+pub(crate) mod code_blocks;
+pub(crate) mod commands;
 pub(crate) mod config;
 pub(crate) mod editor;
 pub(crate) mod errors;
 pub(crate) mod events;
+pub(crate) mod external;
+pub(crate) mod graph;
+pub(crate) mod help;
+pub(crate) mod hop;
 pub(crate) mod inputs;
+pub(crate) mod jump_list;
+pub(crate) mod link_list;
+pub(crate) mod link_preview;
+pub(crate) mod link_suggest;
+pub(crate) mod lock;
+pub(crate) mod markdown;
 pub(crate) mod note_list;
+pub(crate) mod palette;
+pub(crate) mod picker;
+pub(crate) mod recent;
+pub(crate) mod rename;
 pub(crate) mod searchbar;
+pub(crate) mod session;
+pub(crate) mod stats;
+pub(crate) mod statusline;
+pub(crate) mod templates;
 pub(crate) mod ui;
 pub(crate) mod user_messages;
 pub(crate) mod utils;
+pub(crate) mod vaults;
+pub(crate) mod wrap;
@@ -1,22 +1,30 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
 use log::info;
 use ratatui::{
-    layout::Alignment,
+    layout::{Alignment, Margin, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span},
     widgets::{
-        block::Title, Block, BorderType, Borders, List, ListItem, ListState, Padding,
-        StatefulWidget, Widget,
+        block::Title, Block, Borders, List, ListItem, ListState, Padding,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, StatefulWidget, Widget,
     },
 };
 
-use crate::db::db_mac::NoteIdentifier;
+use crate::db::db_mac::{NoteFolder, NoteIdentifier, NoteTag};
+
+use super::{app::ComponentState, config::BorderStyle, utils::fuzzy_match};
 
-use super::app::ComponentState;
+pub(crate) const NOTE_PAGE_SIZE: i64 = 100;
+// Fetch the next page this many rows before the selection hits the end of the loaded window
+const PAGE_PREFETCH: usize = 10;
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum NoteListAction {
     LoadNote,
     LinkNote,
+    /// Picking a note to link at the editor cursor
+    InsertLink,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -25,6 +33,86 @@ pub(crate) enum NoteListMode {
     Fullscreen,
 }
 
+/// A row of the sidebar when notes are grouped by folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TreeRow {
+    Folder {
+        path: String,
+        depth: usize,
+        collapsed: bool,
+    },
+    /// Index into `note_identifiers`
+    Note { idx: usize, depth: usize },
+}
+
+/// "a//b/" and " a/b" are both "a/b", a path with no names is the root
+pub(crate) fn clean_folder(path: &str) -> Option<String> {
+    let path = path
+        .split('/')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<&str>>()
+        .join("/");
+    match path.is_empty() {
+        true => None,
+        false => Some(path),
+    }
+}
+
+fn parent_folder(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+/// Folders first, each followed by its contents unless collapsed, then the notes at the
+/// root. Notes keep their list order within a folder.
+pub(crate) fn build_tree(
+    note_identifiers: &[NoteIdentifier],
+    folders: &HashMap<i64, String>,
+    collapsed: &HashSet<String>,
+) -> Vec<TreeRow> {
+    let mut paths = BTreeSet::new();
+    for folder in folders.values() {
+        let mut path = folder.as_str();
+        while !path.is_empty() {
+            paths.insert(path);
+            path = parent_folder(path);
+        }
+    }
+
+    let mut rows = vec![];
+    push_folder_rows("", 0, note_identifiers, folders, collapsed, &paths, &mut rows);
+    rows
+}
+
+fn push_folder_rows(
+    parent: &str,
+    depth: usize,
+    note_identifiers: &[NoteIdentifier],
+    folders: &HashMap<i64, String>,
+    collapsed: &HashSet<String>,
+    paths: &BTreeSet<&str>,
+    rows: &mut Vec<TreeRow>,
+) {
+    for path in paths.iter().filter(|path| parent_folder(path) == parent) {
+        let is_collapsed = collapsed.contains(*path);
+        rows.push(TreeRow::Folder {
+            path: path.to_string(),
+            depth,
+            collapsed: is_collapsed,
+        });
+        if !is_collapsed {
+            push_folder_rows(path, depth + 1, note_identifiers, folders, collapsed, paths, rows);
+        }
+    }
+
+    let notes = note_identifiers
+        .iter()
+        .enumerate()
+        .filter(|(_, nid)| folders.get(&nid.id).map_or("", String::as_str) == parent)
+        .map(|(idx, _)| TreeRow::Note { idx, depth });
+    rows.extend(notes);
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct SelectionStyle {
     pub(crate) highlight: Color,
@@ -38,38 +126,288 @@ pub(crate) struct NoteListTheme {
     pub(crate) title: Color,
     pub(crate) selection_style: SelectionStyle,
     pub(crate) borders: Color,
+    pub(crate) border: BorderStyle,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct NoteList {
     pub(crate) selected: usize,
     pub(crate) note_identifiers: Vec<NoteIdentifier>,
+    pub(crate) total: usize,
+    pub(crate) paged: usize,
+    pub(crate) last_paged_id: i64,
     pub(crate) action: NoteListAction,
     pub(crate) state: ComponentState,
     pub(crate) mode: NoteListMode,
     pub(crate) theme: NoteListTheme,
+    /// Fuzzy filter typed while picking a link target, `selected` indexes the matches
+    pub(crate) filter: String,
+    /// Notes kept above the rest, loaded from the db whatever page they are in
+    pub(crate) pinned: HashSet<i64>,
+    /// Folder paths by note id, notes at the root have none. With any folders the
+    /// sidebar is a tree and `selected` indexes its visible rows.
+    pub(crate) folders: HashMap<i64, String>,
+    pub(crate) collapsed: HashSet<String>,
+    /// Notes marked for a bulk action, drawn with a checkbox while any are marked
+    pub(crate) marked: BTreeSet<i64>,
+    /// Tags of each note, sorted
+    pub(crate) tags: HashMap<i64, BTreeSet<String>>,
+    /// Moving past either end goes round to the other, otherwise the selection stops
+    pub(crate) wrap_selection: bool,
 }
 
 impl NoteList {
     pub(crate) fn new(
         note_identifiers: Vec<NoteIdentifier>,
+        total: usize,
         action: NoteListAction,
         state: ComponentState,
         theme: NoteListTheme,
     ) -> Self {
         let selected = 0;
+        let paged = note_identifiers.len();
+        let last_paged_id = note_identifiers.iter().map(|nid| nid.id).max().unwrap_or(0);
 
         Self {
             selected,
             note_identifiers,
+            total,
+            paged,
+            last_paged_id,
             action,
             state,
             mode: NoteListMode::Fullscreen,
             theme,
+            filter: String::new(),
+            pinned: HashSet::new(),
+            folders: HashMap::new(),
+            collapsed: HashSet::new(),
+            marked: BTreeSet::new(),
+            tags: HashMap::new(),
+            wrap_selection: true,
+        }
+    }
+
+    pub(crate) fn set_tags(&mut self, tags: Vec<NoteTag>) {
+        self.tags.clear();
+        for nt in tags {
+            self.tags.entry(nt.id).or_default().insert(nt.tag);
+        }
+    }
+
+    /// Adds or takes `tag` off each of `ids`, once the db has done the same
+    pub(crate) fn tag_notes(&mut self, ids: &[i64], tag: &str, tagged: bool) {
+        for id in ids {
+            match tagged {
+                true => {
+                    self.tags.entry(*id).or_default().insert(tag.to_owned());
+                }
+                false => {
+                    if let Some(tags) = self.tags.get_mut(id) {
+                        tags.remove(tag);
+                        if tags.is_empty() {
+                            self.tags.remove(id);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Marks or unmarks the selected note, false when no note is selected
+    pub(crate) fn toggle_mark(&mut self) -> bool {
+        let Some(id) = self.selected_id() else {
+            return false;
+        };
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+        true
+    }
+
+    pub(crate) fn set_folders(&mut self, folders: Vec<NoteFolder>) {
+        let selected_id = self.selected_id();
+        self.folders = folders
+            .into_iter()
+            .filter_map(|nf| clean_folder(&nf.folder).map(|folder| (nf.id, folder)))
+            .collect();
+        self.reselect(selected_id);
+    }
+
+    /// None puts the note back at the root
+    pub(crate) fn set_folder(&mut self, id: i64, folder: Option<String>) {
+        let selected_id = self.selected_id();
+        match folder.as_deref().and_then(clean_folder) {
+            Some(folder) => self.folders.insert(id, folder),
+            None => self.folders.remove(&id),
+        };
+        self.reselect(selected_id);
+    }
+
+    /// The sidebar groups notes by folder, the fullscreen pickers and filtering stay flat
+    pub(crate) fn is_tree(&self) -> bool {
+        matches!(self.mode, NoteListMode::Sidebar)
+            && self.filter.is_empty()
+            && !self.folders.is_empty()
+    }
+
+    pub(crate) fn tree_rows(&self) -> Vec<TreeRow> {
+        build_tree(&self.note_identifiers, &self.folders, &self.collapsed)
+    }
+
+    /// Collapses or expands the selected folder, false when a note is selected
+    pub(crate) fn toggle_folder(&mut self) -> bool {
+        if !self.is_tree() {
+            return false;
+        }
+        let Some(TreeRow::Folder { path, .. }) = self.tree_rows().into_iter().nth(self.selected)
+        else {
+            return false;
+        };
+        if !self.collapsed.remove(&path) {
+            self.collapsed.insert(path);
+        }
+        true
+    }
+
+    /// Points `selected` at the note wherever it is drawn, its folders are expanded
+    pub(crate) fn select_note(&mut self, note_id: i64) -> bool {
+        if self.is_tree() {
+            if let Some(folder) = self.folders.get(&note_id) {
+                let mut path = folder.as_str();
+                while !path.is_empty() {
+                    self.collapsed.remove(path);
+                    path = parent_folder(path);
+                }
+            }
+        }
+        let Some(note_idx) = self.note_identifiers.iter().position(|nid| nid.id == note_id)
+        else {
+            return false;
+        };
+        let row = match (self.is_tree(), self.filter.is_empty()) {
+            (true, _) => self
+                .tree_rows()
+                .iter()
+                .position(|row| matches!(row, TreeRow::Note { idx, .. } if *idx == note_idx)),
+            (false, true) => Some(note_idx),
+            (false, false) => self.filtered().iter().position(|&idx| idx == note_idx),
+        };
+        match row {
+            Some(row) => {
+                self.selected = row;
+                true
+            }
+            None => false,
         }
     }
 
+    /// Keeps the selection on the same note after the rows are reshaped
+    fn reselect(&mut self, note_id: Option<i64>) {
+        if !note_id.is_some_and(|id| self.select_note(id)) {
+            self.clamp_selected();
+        }
+    }
+
+    /// Marks notes as pinned, adding any that sit in pages not loaded yet
+    pub(crate) fn pin_notes(&mut self, pinned: Vec<NoteIdentifier>) {
+        for nid in pinned {
+            self.pinned.insert(nid.id);
+            if !self.note_identifiers.iter().any(|n| n.id == nid.id) {
+                self.note_identifiers.push(nid);
+            }
+        }
+        self.sort_pinned();
+    }
+
+    /// Pins or unpins a note, returning whether it is now pinned
+    pub(crate) fn toggle_pin(&mut self, note_id: i64) -> bool {
+        let pinned = match self.pinned.remove(&note_id) {
+            true => false,
+            false => self.pinned.insert(note_id),
+        };
+        self.sort_pinned();
+
+        // The selection follows the note to its new place
+        if self.filter.is_empty() {
+            self.select_note(note_id);
+        }
+        pinned
+    }
+
+    /// Pinned notes first, both groups in id order like the pages they came from
+    fn sort_pinned(&mut self) {
+        let pinned = &self.pinned;
+        self.note_identifiers
+            .sort_by_key(|nid| (!pinned.contains(&nid.id), nid.id));
+    }
+
+    /// Indices of the notes matching the filter, every note when there is no filter
+    pub(crate) fn filtered(&self) -> Vec<usize> {
+        self.note_identifiers
+            .iter()
+            .enumerate()
+            .filter(|(_, nid)| fuzzy_match(&self.filter, &nid.title))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    pub(crate) fn selected_id(&self) -> Option<i64> {
+        let idx = match (self.is_tree(), self.filter.is_empty()) {
+            (true, _) => match self.tree_rows().get(self.selected) {
+                Some(TreeRow::Note { idx, .. }) => Some(*idx),
+                _ => None,
+            },
+            (false, true) => Some(self.selected),
+            (false, false) => self.filtered().get(self.selected).copied(),
+        };
+        idx.and_then(|idx| self.note_identifiers.get(idx))
+            .map(|nid| nid.id)
+    }
+
+    pub(crate) fn push_filter(&mut self, c: char) {
+        self.filter.push(c);
+        self.selected = 0;
+    }
+
+    pub(crate) fn pop_filter(&mut self) {
+        self.filter.pop();
+        self.selected = 0;
+    }
+
+    pub(crate) fn clear_filter(&mut self) {
+        self.filter.clear();
+        self.selected = 0;
+    }
+
+    /// Rows `selected` can point at: tree rows, filter matches or every note
+    fn row_count(&self) -> usize {
+        match (self.is_tree(), self.filter.is_empty()) {
+            (true, _) => self.tree_rows().len(),
+            (false, true) => self.note_identifiers.len(),
+            (false, false) => self.filtered().len(),
+        }
+    }
+
+    /// Pulls `selected` back inside the list after it shrinks
+    fn clamp_selected(&mut self) {
+        self.selected = self.selected.min(self.row_count().saturating_sub(1));
+    }
+
     pub(crate) fn prev(&mut self) {
+        self.clamp_selected();
+        if !self.wrap_selection {
+            self.selected = self.selected.saturating_sub(1);
+            return;
+        }
+        if !self.filter.is_empty() || self.is_tree() {
+            let len = self.row_count();
+            if len > 0 {
+                self.selected = (self.selected + len - 1) % len;
+            }
+            return;
+        }
+
         let nids_len = self.note_identifiers.len();
         // Guard against crashes if user has no notes
         if nids_len == 0 {
@@ -79,34 +417,112 @@ impl NoteList {
     }
 
     pub(crate) fn next(&mut self) {
+        self.clamp_selected();
+        if !self.wrap_selection {
+            if self.selected + 1 < self.row_count() {
+                self.selected += 1;
+            }
+            return;
+        }
+        if !self.filter.is_empty() || self.is_tree() {
+            let len = self.row_count();
+            if len > 0 {
+                self.selected = (self.selected + 1) % len;
+            }
+            return;
+        }
+
         let nids_len = self.note_identifiers.len();
         // Guard against crashes if user has no notes
         if nids_len == 0 {
             return;
         }
+        // Only wrap around once every page has been loaded
+        if self.selected + 1 >= nids_len && !self.fully_loaded() {
+            return;
+        }
         self.selected = self.selected.saturating_add(1) % nids_len;
     }
 
     pub(crate) fn update(&mut self, new_nid: NoteIdentifier) {
         self.note_identifiers.push(new_nid);
+        self.total += 1;
     }
 
     pub(crate) fn replace(&mut self, replace_nid: NoteIdentifier) {
-        self.note_identifiers
+        // The note may sit in a page that hasn't been loaded yet
+        if let Some(nid) = self
+            .note_identifiers
             .iter_mut()
             .find(|nid| nid.id == replace_nid.id)
-            .expect("Note id should be present")
-            .title = replace_nid.title;
+        {
+            nid.title = replace_nid.title;
+        }
     }
 
     pub(crate) fn remove(&mut self, note_id: i64) {
-        let pos = self
+        if let Some(pos) = self
             .note_identifiers
             .iter()
             .position(|nid| nid.id == note_id)
-            .expect("Note should be in note_identifiers");
+        {
+            self.note_identifiers.remove(pos);
+            // Keep the page offset in step with the rows left in the db
+            if note_id <= self.last_paged_id {
+                self.paged = self.paged.saturating_sub(1);
+            }
+        }
+        self.total = self.total.saturating_sub(1);
+        self.pinned.remove(&note_id);
+        self.folders.remove(&note_id);
+        self.marked.remove(&note_id);
+        self.tags.remove(&note_id);
+        self.clamp_selected();
+    }
 
-        self.note_identifiers.remove(pos);
+    pub(crate) fn fully_loaded(&self) -> bool {
+        self.note_identifiers.len() >= self.total
+    }
+
+    pub(crate) fn needs_next_page(&self) -> bool {
+        !self.fully_loaded() && self.selected + PAGE_PREFETCH >= self.row_count()
+    }
+
+    pub(crate) fn append_page(&mut self, page: Vec<NoteIdentifier>) {
+        self.paged += page.len();
+        if let Some(last) = page.last() {
+            self.last_paged_id = last.id;
+        }
+        // Notes created this session are already in the list
+        for nid in page {
+            if !self.note_identifiers.iter().any(|n| n.id == nid.id) {
+                self.note_identifiers.push(nid);
+            }
+        }
+    }
+
+    /// Index of the row drawn at screen row `row` when rendered in `area`, the value
+    /// `selected` takes when it is clicked
+    pub(crate) fn index_at(&self, area: Rect, row: u16) -> Option<usize> {
+        let (top, bottom) = match self.mode {
+            NoteListMode::Sidebar => (1, 1),
+            NoteListMode::Fullscreen => (2, 2),
+        };
+        let first_row = area.y + top;
+        let visible_rows = area.height.saturating_sub(top + bottom) as usize;
+
+        if row < first_row || row >= area.bottom().saturating_sub(bottom) || visible_rows == 0 {
+            return None;
+        }
+
+        // A fresh ListState scrolls just far enough to keep the selection visible
+        let offset = self.selected.saturating_sub(visible_rows - 1);
+        let idx = offset + (row - first_row) as usize;
+
+        match idx < self.row_count() {
+            true => Some(idx),
+            false => None,
+        }
     }
 
     pub(crate) fn set_state(&mut self, new_state: ComponentState) {
@@ -118,7 +534,9 @@ impl NoteList {
     }
 
     pub(crate) fn set_mode(&mut self, new_mode: NoteListMode) {
+        let selected_id = self.selected_id();
         self.mode = new_mode;
+        self.reselect(selected_id);
     }
 }
 
@@ -127,21 +545,32 @@ impl Widget for NoteList {
     where
         Self: Sized,
     {
+        let link_title = match self.filter.is_empty() {
+            true => " Link Note ".to_owned(),
+            false => format!(" Link Note: {} ", self.filter),
+        };
+
         let (title_text, info_text, borders, padding) = match (self.mode, self.action) {
+            (NoteListMode::Fullscreen, NoteListAction::InsertLink) => (
+                link_title,
+                " <Esc> cancel | <Enter> Insert Link | <up> <down> Select | type to filter ",
+                Borders::ALL,
+                Padding::new(1, 1, 1, 1),
+            ),
             (NoteListMode::Fullscreen, NoteListAction::LoadNote) => (
-                " Load Note ",
+                " Load Note ".to_owned(),
                 " <Esc> prev screen | <Enter> Load Note | <up/j> Next | <down/k> Prev ",
                 Borders::ALL,
                 Padding::new(1, 1, 1, 1),
             ),
             (NoteListMode::Fullscreen, NoteListAction::LinkNote) => (
-                " Link Note ",
-                " <Enter> Link Note | <up/j> Next | <down/k> Prev ",
+                link_title,
+                " <Enter> Link Note | <Tab> New Note | <up> <down> Select | type to filter ",
                 Borders::ALL,
                 Padding::new(1, 1, 1, 1),
             ),
             (NoteListMode::Sidebar, _) => (
-                " File Explorer ",
+                " File Explorer ".to_owned(),
                 " <Alt-f> hide files ",
                 Borders::TOP | Borders::RIGHT | Borders::BOTTOM,
                 Padding::new(1, 1, 0, 0),
@@ -197,30 +626,379 @@ impl Widget for NoteList {
         let info_line = Line::styled(info_text, list_info_style).alignment(Alignment::Center);
         let title = Span::styled(title_text, title_style);
 
+        let visible_rows = area.height.saturating_sub(padding.top + padding.bottom + 2) as usize;
+        let filtered = self.filtered();
+        let tree_rows = match self.is_tree() {
+            true => Some(self.tree_rows()),
+            false => None,
+        };
+        let total_rows = match (&tree_rows, self.filter.is_empty()) {
+            (Some(rows), _) => rows.len(),
+            (None, true) => std::cmp::max(self.total, self.note_identifiers.len()),
+            (None, false) => filtered.len(),
+        };
+
         let load_note_block = Block::default()
             .title(Title::from(title).alignment(Alignment::Center))
             .title_bottom(info_line)
             .padding(padding)
             .borders(borders)
-            .border_type(BorderType::Rounded)
+            .border_type(self.theme.border.border_type())
             .border_style(border_style);
 
         let mut state = ListState::default().with_selected(Some(self.selected));
 
-        let list = List::from_iter(
-            self.note_identifiers
+        let note_title = |nid: &NoteIdentifier| {
+            let checkbox = match (self.marked.is_empty(), self.marked.contains(&nid.id)) {
+                (true, _) => "",
+                (false, true) => "[x] ",
+                (false, false) => "[ ] ",
+            };
+            let pin = match self.pinned.contains(&nid.id) {
+                true => "📌 ",
+                false => "",
+            };
+            let tags = self.tags.get(&nid.id).map_or(String::new(), |tags| {
+                tags.iter().map(|tag| format!(" #{}", tag)).collect()
+            });
+            format!("{}{}{}{}", checkbox, pin, nid.title, tags)
+        };
+        let items = match tree_rows {
+            Some(rows) => rows
                 .into_iter()
-                .map(|nid| ListItem::new(Line::from(nid.title)).style(list_item_style)),
-        )
-        .block(load_note_block)
-        .highlight_style(
-            Style::default()
-                .add_modifier(self.theme.selection_style.modifier)
-                .fg(self.theme.selection_style.highlight),
-        )
-        .highlight_symbol(&self.theme.selection_style.pointer)
-        .repeat_highlight_symbol(true);
+                .map(|row| {
+                    let line = match row {
+                        TreeRow::Folder {
+                            path,
+                            depth,
+                            collapsed,
+                        } => {
+                            let marker = match collapsed {
+                                true => "▸",
+                                false => "▾",
+                            };
+                            let name = path.rsplit('/').next().unwrap_or_default();
+                            Line::from(format!("{}{} {}/", "  ".repeat(depth), marker, name))
+                                .bold()
+                        }
+                        TreeRow::Note { idx, depth } => Line::from(format!(
+                            "{}{}",
+                            "  ".repeat(depth),
+                            note_title(&self.note_identifiers[idx])
+                        )),
+                    };
+                    ListItem::new(line).style(list_item_style)
+                })
+                .collect::<Vec<ListItem>>(),
+            None => filtered
+                .iter()
+                .map(|&idx| {
+                    let title = note_title(&self.note_identifiers[idx]);
+                    ListItem::new(Line::from(title)).style(list_item_style)
+                })
+                .collect(),
+        };
+
+        let list = List::new(items)
+            .block(load_note_block)
+            .highlight_style(
+                Style::default()
+                    .add_modifier(self.theme.selection_style.modifier)
+                    .fg(self.theme.selection_style.highlight),
+            )
+            .highlight_symbol(&self.theme.selection_style.pointer)
+            .repeat_highlight_symbol(true);
 
         StatefulWidget::render(list, area, buf, &mut state);
+
+        if total_rows > visible_rows {
+            let mut scrollbar_state = ScrollbarState::new(total_rows)
+                .position(self.selected)
+                .viewport_content_length(visible_rows);
+
+            StatefulWidget::render(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight).style(border_style),
+                area.inner(&Margin {
+                    vertical: 1,
+                    horizontal: 0,
+                }),
+                buf,
+                &mut scrollbar_state,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::buffer::Buffer;
+
+    fn theme() -> NoteListTheme {
+        NoteListTheme {
+            text: Color::White,
+            title: Color::Red,
+            selection_style: SelectionStyle {
+                highlight: Color::Magenta,
+                pointer: ">> ".to_owned(),
+                modifier: Modifier::BOLD,
+            },
+            borders: Color::White,
+            border: BorderStyle::Rounded,
+        }
+    }
+
+    fn note_list(len: i64) -> NoteList {
+        let nids = (0..len)
+            .map(|id| NoteIdentifier {
+                id,
+                title: format!("Note {}", id),
+            })
+            .collect::<Vec<NoteIdentifier>>();
+        let total = nids.len();
+
+        NoteList::new(
+            nids,
+            total,
+            NoteListAction::LoadNote,
+            ComponentState::Active,
+            theme(),
+        )
+    }
+
+    #[test]
+    fn test_scrollbar_rendered_for_overflowing_list() {
+        let area = Rect::new(0, 0, 30, 8);
+        let mut buf = Buffer::empty(area);
+
+        note_list(20).render(area, &mut buf);
+
+        assert_eq!(buf.get(29, 1).symbol(), "↑");
+        assert_eq!(buf.get(29, 6).symbol(), "↓");
+    }
+
+    #[test]
+    fn test_configured_border_style_is_drawn() {
+        let area = Rect::new(0, 0, 30, 8);
+        let mut buf = Buffer::empty(area);
+        let mut list = note_list(3);
+        list.theme.border = BorderStyle::Double;
+
+        list.render(area, &mut buf);
+
+        assert_eq!(buf.get(0, 0).symbol(), "╔");
+        assert_eq!(buf.get(29, 7).symbol(), "╝");
+        assert_eq!(buf.get(0, 3).symbol(), "║");
+    }
+
+    #[test]
+    fn test_filter_narrows_selection() {
+        let mut list = note_list(12);
+
+        list.push_filter('1');
+        assert_eq!(list.filtered(), vec![1, 10, 11]);
+        assert_eq!(list.selected_id(), Some(1));
+
+        list.next();
+        list.next();
+        assert_eq!(list.selected_id(), Some(11));
+        list.next();
+        assert_eq!(list.selected_id(), Some(1));
+
+        list.push_filter('1');
+        assert_eq!(list.filtered(), vec![11]);
+        list.pop_filter();
+        assert_eq!(list.filtered().len(), 3);
+
+        list.push_filter('x');
+        assert_eq!(list.selected_id(), None);
+    }
+
+    #[test]
+    fn test_marked_notes_drawn_with_checkboxes() {
+        let area = Rect::new(0, 0, 30, 8);
+        let mut list = note_list(3);
+
+        assert!(list.toggle_mark());
+        list.next();
+        list.next();
+        assert!(list.toggle_mark());
+        list.next();
+        assert!(list.toggle_mark());
+        assert!(list.toggle_mark());
+        assert_eq!(list.marked.iter().copied().collect::<Vec<i64>>(), [0, 2]);
+
+        list.tag_notes(&[0, 2], "work", true);
+        list.tag_notes(&[2], "work", false);
+        let mut buf = Buffer::empty(area);
+        list.render(area, &mut buf);
+
+        let row = |y: u16| (0..30).map(|x| buf.get(x, y).symbol()).collect::<String>();
+        assert!(row(2).contains("[x] Note 0 #work"));
+        assert!(row(3).contains("[ ] Note 1"));
+        assert!(row(4).contains("[x] Note 2"));
+        assert!(!row(4).contains("#work"));
+    }
+
+    #[test]
+    fn test_selection_wraps_at_the_ends() {
+        let mut list = note_list(3);
+
+        list.prev();
+        assert_eq!(list.selected, 2);
+        list.next();
+        assert_eq!(list.selected, 0);
+    }
+
+    #[test]
+    fn test_selection_stops_at_the_ends_without_wrap() {
+        let mut list = note_list(3);
+        list.wrap_selection = false;
+
+        list.prev();
+        assert_eq!(list.selected, 0);
+        list.next();
+        list.next();
+        list.next();
+        assert_eq!(list.selected, 2);
+
+        list.push_filter('2');
+        list.next();
+        assert_eq!(list.selected_id(), Some(2));
+        list.prev();
+        assert_eq!(list.selected_id(), Some(2));
+    }
+
+    #[test]
+    fn test_remove_selected_last_note_clamps_selection() {
+        let mut list = note_list(3);
+        list.selected = 2;
+
+        list.remove(2);
+
+        assert_eq!(list.selected, 1);
+        assert_eq!(list.selected_id(), Some(1));
+        list.next();
+        assert_eq!(list.selected_id(), Some(0));
+    }
+
+    #[test]
+    fn test_remove_from_emptied_list_is_noop() {
+        let mut list = note_list(1);
+
+        list.remove(0);
+        assert_eq!(list.selected, 0);
+        assert_eq!(list.selected_id(), None);
+
+        list.remove(0);
+        list.next();
+        list.prev();
+        assert_eq!(list.selected, 0);
+        assert!(list.note_identifiers.is_empty());
+        assert_eq!(list.total, 0);
+    }
+
+    #[test]
+    fn test_toggle_pin_moves_note_to_top() {
+        let mut list = note_list(5);
+        list.selected = 3;
+
+        assert!(list.toggle_pin(3));
+        let ids = list.note_identifiers.iter().map(|nid| nid.id).collect::<Vec<i64>>();
+        assert_eq!(ids, [3, 0, 1, 2, 4]);
+        assert_eq!(list.selected_id(), Some(3));
+
+        let area = Rect::new(0, 0, 30, 8);
+        let mut buf = Buffer::empty(area);
+        list.clone().render(area, &mut buf);
+        assert_eq!(buf.get(5, 2).symbol(), "📌");
+        assert_eq!(buf.get(5, 3).symbol(), "N");
+
+        assert!(!list.toggle_pin(3));
+        let ids = list.note_identifiers.iter().map(|nid| nid.id).collect::<Vec<i64>>();
+        assert_eq!(ids, [0, 1, 2, 3, 4]);
+        assert_eq!(list.selected_id(), Some(3));
+    }
+
+    fn folder(id: i64, folder: &str) -> NoteFolder {
+        NoteFolder {
+            id,
+            folder: folder.to_owned(),
+        }
+    }
+
+    fn tree_list() -> NoteList {
+        let mut list = note_list(5);
+        list.set_mode(NoteListMode::Sidebar);
+        list.set_folders(vec![
+            folder(0, "work/meetings"),
+            folder(1, "/work/ "),
+            folder(3, "home"),
+        ]);
+        list
+    }
+
+    #[test]
+    fn test_tree_groups_notes_by_folder() {
+        let list = tree_list();
+
+        let folder_row = |path: &str, depth| TreeRow::Folder {
+            path: path.to_owned(),
+            depth,
+            collapsed: false,
+        };
+        assert_eq!(
+            list.tree_rows(),
+            [
+                folder_row("home", 0),
+                TreeRow::Note { idx: 3, depth: 1 },
+                folder_row("work", 0),
+                folder_row("work/meetings", 1),
+                TreeRow::Note { idx: 0, depth: 2 },
+                TreeRow::Note { idx: 1, depth: 1 },
+                TreeRow::Note { idx: 2, depth: 0 },
+                TreeRow::Note { idx: 4, depth: 0 },
+            ]
+        );
+        assert_eq!(clean_folder(" / "), None);
+    }
+
+    #[test]
+    fn test_collapsed_folder_is_skipped_by_navigation() {
+        let mut list = tree_list();
+        assert_eq!(list.selected, 4);
+        assert_eq!(list.selected_id(), Some(0));
+
+        list.selected = 2;
+        assert_eq!(list.selected_id(), None);
+        assert!(list.toggle_folder());
+        assert_eq!(list.tree_rows().len(), 5);
+
+        list.next();
+        assert_eq!(list.selected_id(), Some(2));
+        list.prev();
+        list.prev();
+        assert_eq!(list.selected_id(), Some(3));
+
+        list.next();
+        assert!(list.toggle_folder());
+        list.next();
+        list.next();
+        assert_eq!(list.selected_id(), Some(0));
+        assert!(!list.toggle_folder());
+
+        list.set_mode(NoteListMode::Fullscreen);
+        assert_eq!(list.selected, 0);
+        assert_eq!(list.selected_id(), Some(0));
+    }
+
+    #[test]
+    fn test_no_scrollbar_when_list_fits() {
+        let area = Rect::new(0, 0, 30, 8);
+        let mut buf = Buffer::empty(area);
+
+        note_list(2).render(area, &mut buf);
+
+        assert_eq!(buf.get(29, 1).symbol(), "│");
     }
 }
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use ratatui::{
+    prelude::{Buffer, Rect},
+    widgets::Widget,
+};
+use tuipaz_textarea::Input;
+
+use super::{
+    app::Screen,
+    config::get_action_name,
+    events::Action,
+    help::format_input,
+    picker::{indexed_entries, Picker},
+};
+
+/// Actions the palette can run from `screen`. Only the main screen has any, the
+/// others either take their input as text or are pickers themselves.
+pub(crate) fn palette_actions(screen: Screen) -> Vec<Action> {
+    match screen {
+        Screen::Main => vec![
+            Action::SaveNote,
+            Action::LoadNote,
+            Action::NewNote,
+            Action::NewNoteFromTemplate,
+            Action::QuickCapture,
+            Action::ImportNotes,
+            Action::NewTitle,
+            Action::DuplicateNote,
+            Action::DeleteNote,
+            Action::EditExternally,
+            Action::ToggleReadOnly,
+            Action::TogglePreview,
+            Action::TogglePin,
+            Action::SwitchVault,
+            Action::ShowRecentNotes,
+            Action::ShowGraph,
+            Action::ShowStats,
+            Action::ShowLinkList,
+            Action::JumpBack,
+            Action::JumpForward,
+            Action::NextTab,
+            Action::PrevTab,
+            Action::CloseTab,
+            Action::OpenInTab,
+            Action::ToggleSidebar,
+            Action::RevealNote,
+            Action::ToggleZenMode,
+            Action::IncreaseSidebar,
+            Action::DecreaseSidebar,
+            Action::SwitchActiveWidget,
+            Action::ShowHelp(Input::default()),
+            Action::ShowExitScreen,
+        ],
+        _ => vec![],
+    }
+}
+
+/// Name shown in the palette, with the key when the action has one
+pub(crate) fn action_label(action: &Action, keymap: &HashMap<Action, Input>) -> String {
+    let name = get_action_name(action).replace('_', " ");
+    match keymap.get(action) {
+        Some(input) => format!("{}  <{}>", name, format_input(input)),
+        None => name,
+    }
+}
+
+/// Fuzzy launcher over the actions valid where it was opened
+#[derive(Debug, Clone)]
+pub(crate) struct CommandPalette {
+    pub(crate) actions: Vec<Action>,
+    pub(crate) picker: Picker,
+}
+
+impl CommandPalette {
+    pub(crate) fn new(screen: Screen, keymap: &HashMap<Action, Input>) -> Self {
+        let actions = palette_actions(screen);
+        let labels = actions.iter().map(|action| action_label(action, keymap));
+
+        Self {
+            picker: Picker::new(" Commands ", indexed_entries(labels)),
+            actions,
+        }
+    }
+
+    /// The action behind a picked row
+    pub(crate) fn action_at(&self, id: Option<i64>) -> Option<Action> {
+        id.and_then(|id| self.actions.get(id as usize).copied())
+    }
+}
+
+impl Widget for CommandPalette {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        self.picker.render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::config::Config;
+
+    #[test]
+    fn test_palette_filters_actions() {
+        let mut palette = CommandPalette::new(Screen::Main, &Config::default().keymap);
+        for c in "rdonly".chars() {
+            palette.picker.push_filter(c);
+        }
+
+        let selected = palette.action_at(palette.picker.selected_id());
+        assert_eq!(selected, Some(Action::ToggleReadOnly));
+        assert_eq!(palette.picker.filtered().len(), 1);
+        assert!(palette.picker.filtered()[0].label.ends_with("<Alt-R>"));
+        assert!(CommandPalette::new(Screen::Graph, &HashMap::new())
+            .actions
+            .is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+use ratatui::{
+    layout::Alignment,
+    prelude::{Buffer, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{block::Title, Block, BorderType, Borders, Padding, Paragraph, Widget},
+};
+use tuipaz_textarea::{Input, Key};
+
+use crate::db::db_mac::NoteIdentifier;
+
+use super::{events::Action, utils::fuzzy_match};
+
+/// A row of a picker, `id` is whatever its owner picks by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PickerEntry {
+    pub(crate) id: i64,
+    pub(crate) label: String,
+}
+
+impl From<NoteIdentifier> for PickerEntry {
+    fn from(nid: NoteIdentifier) -> Self {
+        Self {
+            id: nid.id,
+            label: nid.title,
+        }
+    }
+}
+
+/// Rows labelled in order, ids are their positions
+pub(crate) fn indexed_entries(labels: impl IntoIterator<Item = String>) -> Vec<PickerEntry> {
+    labels
+        .into_iter()
+        .enumerate()
+        .map(|(idx, label)| PickerEntry {
+            id: idx as i64,
+            label,
+        })
+        .collect()
+}
+
+/// Fuzzy filtered list, picked from with Enter or the number shown beside a row
+#[derive(Debug, Clone)]
+pub(crate) struct Picker {
+    pub(crate) entries: Vec<PickerEntry>,
+    pub(crate) filter: String,
+    pub(crate) selected: usize,
+    pub(crate) heading: String,
+}
+
+impl Picker {
+    pub(crate) fn new(heading: &str, entries: Vec<PickerEntry>) -> Self {
+        Self {
+            entries,
+            filter: String::new(),
+            selected: 0,
+            heading: heading.to_owned(),
+        }
+    }
+
+    pub(crate) fn filtered(&self) -> Vec<&PickerEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| fuzzy_match(&self.filter, &entry.label))
+            .collect()
+    }
+
+    pub(crate) fn selected_id(&self) -> Option<i64> {
+        self.filtered().get(self.selected).map(|entry| entry.id)
+    }
+
+    /// Entries are numbered from 1 as shown in the picker
+    pub(crate) fn numbered_id(&self, number: usize) -> Option<i64> {
+        number
+            .checked_sub(1)
+            .and_then(|idx| self.filtered().get(idx).map(|entry| entry.id))
+    }
+
+    pub(crate) fn push_filter(&mut self, c: char) {
+        self.filter.push(c);
+        self.selected = 0;
+    }
+
+    pub(crate) fn pop_filter(&mut self) {
+        self.filter.pop();
+        self.selected = 0;
+    }
+
+    pub(crate) fn next(&mut self) {
+        let len = self.filtered().len();
+        if len > 0 {
+            self.selected = (self.selected + 1) % len;
+        }
+    }
+
+    pub(crate) fn prev(&mut self) {
+        let len = self.filtered().len();
+        if len > 0 {
+            self.selected = (self.selected + len - 1) % len;
+        }
+    }
+}
+
+/// What a key did to a picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PickerEvent {
+    Close,
+    /// The selected or numbered entry, None when there is no such row
+    Pick(Option<i64>),
+    Stay,
+}
+
+/// Keys every picker handles alike: typing filters, arrows move, Enter and 1-9 pick
+pub(crate) fn picker_input(picker: &mut Picker, action: &Action) -> PickerEvent {
+    match action {
+        Action::Esc => return PickerEvent::Close,
+        Action::Activate(_) => return PickerEvent::Pick(picker.selected_id()),
+        Action::Edit(Input {
+            key: Key::Char(c @ '1'..='9'),
+            ..
+        }) => {
+            let number = *c as usize - '0' as usize;
+            return PickerEvent::Pick(picker.numbered_id(number));
+        }
+        Action::DeleteChar => picker.pop_filter(),
+        // j/k/y/n are bound to other actions but still belong to the filter
+        Action::Down(Input {
+            key: Key::Char(c), ..
+        })
+        | Action::Up(Input {
+            key: Key::Char(c), ..
+        })
+        | Action::Edit(Input {
+            key: Key::Char(c), ..
+        }) => picker.push_filter(*c),
+        Action::Confirm => picker.push_filter('y'),
+        Action::Cancel => picker.push_filter('n'),
+        Action::Down(_) => picker.next(),
+        Action::Up(_) => picker.prev(),
+        _ => {}
+    }
+    PickerEvent::Stay
+}
+
+impl Widget for Picker {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let title = Span::styled(self.heading.clone(), Style::default().bold().fg(Color::Yellow));
+        let info_line = Line::styled(
+            " <Esc> close | <Enter/1-9> open | type to filter ",
+            Style::default().bold(),
+        )
+        .alignment(Alignment::Center);
+
+        let picker_block = Block::default()
+            .title(Title::from(title).alignment(Alignment::Center))
+            .title_bottom(info_line)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled("> ", Style::default().bold()),
+                Span::raw(self.filter.clone()),
+            ]),
+            Line::default(),
+        ];
+        lines.extend(self.filtered().into_iter().enumerate().map(|(idx, entry)| {
+            let style = match idx == self.selected {
+                true => Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                false => Style::default(),
+            };
+            let number = match idx < 9 {
+                true => format!("{} ", idx + 1),
+                false => "  ".to_owned(),
+            };
+            Line::from(vec![
+                Span::styled(number, Style::default().fg(Color::Cyan)),
+                Span::styled(entry.label.clone(), style),
+            ])
+        }));
+
+        Paragraph::new(lines).block(picker_block).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picker() -> Picker {
+        let labels = ["Meeting notes", "Groceries", "Meetup ideas"];
+        Picker::new(" Test ", indexed_entries(labels.map(str::to_owned)))
+    }
+
+    fn char_action(c: char) -> Action {
+        Action::Edit(Input {
+            key: Key::Char(c),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_picker_filter_and_number() {
+        let mut picker = picker();
+        assert_eq!(picker.numbered_id(2), Some(1));
+        assert_eq!(picker.numbered_id(0), None);
+
+        for c in "meet".chars() {
+            picker.push_filter(c);
+        }
+        picker.next();
+
+        assert_eq!(picker.filtered().len(), 2);
+        assert_eq!(picker.selected_id(), Some(2));
+        assert_eq!(picker.numbered_id(1), Some(0));
+    }
+
+    #[test]
+    fn test_picker_input_filters_moves_and_picks() {
+        let mut picker = picker();
+        let down_j = Action::Down(Input {
+            key: Key::Char('j'),
+            ..Default::default()
+        });
+        let down = Action::Down(Input {
+            key: Key::Down,
+            ..Default::default()
+        });
+
+        assert_eq!(picker_input(&mut picker, &char_action('e')), PickerEvent::Stay);
+        assert_eq!(picker_input(&mut picker, &down_j), PickerEvent::Stay);
+        assert_eq!(picker_input(&mut picker, &Action::Cancel), PickerEvent::Stay);
+        assert_eq!(picker.filter, "ejn");
+        assert_eq!(picker_input(&mut picker, &Action::DeleteChar), PickerEvent::Stay);
+        assert_eq!(picker_input(&mut picker, &Action::DeleteChar), PickerEvent::Stay);
+        assert_eq!(picker.filter, "e");
+
+        picker_input(&mut picker, &down);
+        let enter = Action::Activate(Input::default());
+        assert_eq!(picker_input(&mut picker, &enter), PickerEvent::Pick(Some(1)));
+        assert_eq!(picker_input(&mut picker, &char_action('3')), PickerEvent::Pick(Some(2)));
+        assert_eq!(picker_input(&mut picker, &char_action('9')), PickerEvent::Pick(None));
+        assert_eq!(picker_input(&mut picker, &Action::Esc), PickerEvent::Close);
+    }
+}
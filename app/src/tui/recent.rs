@@ -0,0 +1,23 @@
+pub(crate) const DEFAULT_RECENT_NOTES_CAP: usize = 10;
+
+/// Moves `id` to the front of the most recently used list, dropping the oldest past `cap`
+pub(crate) fn push_recent(recent: &mut Vec<i64>, id: i64, cap: usize) {
+    recent.retain(|&recent_id| recent_id != id);
+    recent.insert(0, id);
+    recent.truncate(cap);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_recent_moves_to_front_and_caps() {
+        let mut recent = vec![];
+        for id in [1, 2, 3, 2, 4] {
+            push_recent(&mut recent, id, 3);
+        }
+
+        assert_eq!(recent, vec![4, 2, 3]);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::db::db_mac::DbNoteLink;
+
+/// Rewrites the text of every link in `links` pointing at `target` to `title`, shifting
+/// later links on the same row to match. `links` are all the links of the note `lines`
+/// belongs to, with inclusive columns. Returns how many links were rewritten.
+pub(crate) fn retitle_links(
+    lines: &mut [String],
+    links: &mut [DbNoteLink],
+    target: i64,
+    title: &str,
+) -> usize {
+    let title_len = title.chars().count() as i64;
+    if title_len == 0 {
+        return 0;
+    }
+
+    // Right to left, so rewriting one link never moves the next one to rewrite
+    let mut order = (0..links.len())
+        .filter(|&idx| links[idx].linked_note_id == target)
+        .collect::<Vec<usize>>();
+    order.sort_by_key(|&idx| (links[idx].textarea_row, -links[idx].start_col));
+
+    let mut rewritten = 0;
+    for idx in order {
+        let (row, start, end) = (
+            links[idx].textarea_row,
+            links[idx].start_col,
+            links[idx].end_col,
+        );
+        let Some(line) = lines.get_mut(row as usize) else {
+            continue;
+        };
+        let chars = line.chars().collect::<Vec<char>>();
+        if start < 0 || end < start || end as usize >= chars.len() {
+            continue;
+        }
+
+        *line = chars[..start as usize]
+            .iter()
+            .chain(title.chars().collect::<Vec<char>>().iter())
+            .chain(chars[end as usize + 1..].iter())
+            .collect();
+
+        let shift = title_len - (end - start + 1);
+        for link in links.iter_mut() {
+            if link.textarea_row == row && link.start_col > end {
+                link.start_col += shift;
+                link.end_col += shift;
+            }
+        }
+        links[idx].end_col = start + title_len - 1;
+        rewritten += 1;
+    }
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(textarea_id: i64, row: i64, start_col: i64, end_col: i64, linked: i64) -> DbNoteLink {
+        DbNoteLink {
+            parent_note_id: 1,
+            textarea_id,
+            textarea_row: row,
+            start_col,
+            end_col,
+            linked_note_id: linked,
+            anchor: None,
+        }
+    }
+
+    #[test]
+    fn test_retitle_links_shifts_later_links() {
+        let mut lines = vec!["see Old and Other, Old".to_owned(), "Old".to_owned()];
+        let mut links = vec![
+            link(0, 0, 4, 6, 2),
+            link(1, 0, 12, 16, 3),
+            link(2, 0, 19, 21, 2),
+            link(3, 1, 0, 2, 2),
+        ];
+
+        let rewritten = retitle_links(&mut lines, &mut links, 2, "Newer");
+
+        assert_eq!(rewritten, 3);
+        assert_eq!(lines, ["see Newer and Other, Newer", "Newer"]);
+        let cols = links
+            .iter()
+            .map(|link| (link.start_col, link.end_col))
+            .collect::<Vec<(i64, i64)>>();
+        assert_eq!(cols, [(4, 8), (14, 18), (21, 25), (0, 4)]);
+    }
+}
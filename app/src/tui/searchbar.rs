@@ -1,4 +1,5 @@
 use log::info;
+use regex::Regex;
 use ratatui::{
     style::{Color, Modifier, Style},
     symbols,
@@ -7,7 +8,10 @@ use ratatui::{
 };
 use tuipaz_textarea::{Input, Key, TextInput};
 
-use super::app::ComponentState;
+use super::{app::ComponentState, config::BorderStyle, utils::elide};
+
+/// Oldest queries are dropped past this
+pub(crate) const SEARCH_HISTORY_LEN: usize = 50;
 
 #[derive(Debug, Clone)]
 pub(crate) struct Searchbar<'a> {
@@ -15,6 +19,47 @@ pub(crate) struct Searchbar<'a> {
     pub(crate) sidebar_open: bool,
     pub(crate) state: ComponentState,
     pub(crate) theme: SearchbarTheme,
+    pub(crate) max_col: u16,
+    pub(crate) match_count: usize,
+    pub(crate) match_idx: Option<usize>,
+    pub(crate) case_insensitive: bool,
+    pub(crate) regex: bool,
+    /// Last pattern that was successfully applied to the editor
+    pub(crate) pattern: String,
+    pub(crate) error: Option<String>,
+    /// Recent queries, newest first
+    pub(crate) history: Vec<String>,
+    /// Position in `history` while stepping through it with Up/Down
+    pub(crate) history_idx: Option<usize>,
+    /// What was typed before stepping into the history
+    draft: String,
+}
+
+/// What the events loop should do after the searchbar took a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SearchbarAction {
+    /// The text or a toggle changed, search again
+    Update,
+    NextMatch,
+    PrevMatch,
+    /// Enter on a complete `s/old/new/` command
+    Replace,
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReplaceScope {
+    Next,
+    All,
+    Confirm,
+}
+
+/// A parsed `s/old/new/[g|c]` command
+#[derive(Debug, Clone)]
+pub(crate) struct Substitute {
+    pub(crate) pattern: Regex,
+    pub(crate) replacement: String,
+    pub(crate) scope: ReplaceScope,
 }
 
 #[derive(Debug, Clone)]
@@ -22,6 +67,7 @@ pub(crate) struct SearchbarTheme {
     pub(crate) text: Color,
     pub(crate) search_mode: Color,
     pub(crate) borders: Color,
+    pub(crate) border: BorderStyle,
 }
 
 impl<'a> Searchbar<'a> {
@@ -38,7 +84,29 @@ impl<'a> Searchbar<'a> {
             sidebar_open,
             state,
             theme,
+            max_col,
+            match_count: 0,
+            match_idx: None,
+            case_insensitive: false,
+            regex: false,
+            pattern: String::new(),
+            error: None,
+            history: vec![],
+            history_idx: None,
+            draft: String::new(),
+        }
+    }
+
+    /// Rebuilds the input for a new width, keeping the typed text
+    pub(crate) fn set_max_col(&mut self, max_col: u16) {
+        if max_col == self.max_col {
+            return;
         }
+        let cursor = self.input.cursor;
+        let text = self.get_search_text().to_owned();
+        self.input = TextInput::new(text, max_col, self.theme.text, "Search...".to_owned());
+        self.input.cursor = cursor;
+        self.max_col = max_col;
     }
 
     pub(crate) fn set_state(&mut self, new_state: ComponentState) {
@@ -48,39 +116,263 @@ impl<'a> Searchbar<'a> {
     pub(crate) fn clear_search(&mut self) {
         self.input.clear();
         self.input.cursor = (0, 0);
+        self.match_count = 0;
+        self.match_idx = None;
+        self.pattern.clear();
+        self.error = None;
+    }
+
+    /// Translates the search text and toggles into a pattern for `set_search_pattern`.
+    /// While typing a substitute command only the `old` part is searched for.
+    pub(crate) fn build_pattern(&self) -> Result<String, regex::Error> {
+        let text = match split_substitute(self.get_search_text()) {
+            Some((old, _, _)) => old,
+            None => self.get_search_text().to_owned(),
+        };
+        let pattern = match self.regex {
+            true => text,
+            false => regex::escape(&text),
+        };
+        let pattern = match self.case_insensitive && !pattern.is_empty() {
+            true => format!("(?i){}", pattern),
+            false => pattern,
+        };
+        Regex::new(&pattern)?;
+
+        Ok(pattern)
+    }
+
+    /// None unless the search text is a complete `s/old/new/` command
+    pub(crate) fn substitute(&self) -> Option<Result<Substitute, regex::Error>> {
+        let (_, Some(replacement), flags) = split_substitute(self.get_search_text())? else {
+            return None;
+        };
+
+        let scope = match (flags.contains('c'), flags.contains('g')) {
+            (true, _) => ReplaceScope::Confirm,
+            (false, true) => ReplaceScope::All,
+            (false, false) => ReplaceScope::Next,
+        };
+        let replacement = match self.regex {
+            true => replacement,
+            false => replacement.replace('$', "$$"),
+        };
+        let pattern = match self.build_pattern() {
+            Ok(pattern) => pattern,
+            Err(e) => return Some(Err(e)),
+        };
+
+        Some(Regex::new(&pattern).map(|pattern| Substitute {
+            pattern,
+            replacement,
+            scope,
+        }))
+    }
+
+    /// Keeps only the last line of the error, the rest repeats the pattern
+    pub(crate) fn set_error(&mut self, err: &regex::Error) {
+        let msg = err.to_string();
+        let msg = msg
+            .lines()
+            .last()
+            .unwrap_or("invalid regex")
+            .trim_start_matches("error: ");
+        self.error = Some(msg.to_owned());
+    }
+
+    /// `matches` must be in document order, the current match is the first one at or after the cursor
+    pub(crate) fn update_matches(&mut self, matches: &[(usize, usize)], cursor: (usize, usize)) {
+        self.match_count = matches.len();
+        self.match_idx = match matches.is_empty() {
+            true => None,
+            false => Some(matches.iter().position(|&m| m >= cursor).unwrap_or(0)),
+        };
+    }
+
+    pub(crate) fn match_counter(&self) -> String {
+        match self.match_idx {
+            Some(idx) => format!("{}/{}", idx + 1, self.match_count),
+            None => format!("0/{}", self.match_count),
+        }
     }
 
     pub(crate) fn get_search_text(&self) -> &str {
         self.input.get_text()
     }
 
-    pub(crate) fn handle_input(&mut self, input: Input) {
+    /// Moves `query` to the front, dropping the oldest entries past the cap
+    pub(crate) fn push_history(&mut self, query: &str) {
+        self.history_idx = None;
+        if query.is_empty() {
+            return;
+        }
+        self.history.retain(|entry| entry != query);
+        self.history.insert(0, query.to_owned());
+        self.history.truncate(SEARCH_HISTORY_LEN);
+    }
+
+    /// Up, prefills the field with the next older query
+    pub(crate) fn history_prev(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let idx = match self.history_idx {
+            Some(idx) => (idx + 1).min(self.history.len() - 1),
+            None => {
+                self.draft = self.get_search_text().to_owned();
+                0
+            }
+        };
+        self.history_idx = Some(idx);
+        self.set_search_text(&self.history[idx].clone());
+    }
+
+    /// Down, steps back towards the newest query and then the typed draft
+    pub(crate) fn history_next(&mut self) {
+        match self.history_idx {
+            Some(0) => {
+                self.history_idx = None;
+                self.set_search_text(&self.draft.clone());
+            }
+            Some(idx) => {
+                self.history_idx = Some(idx - 1);
+                self.set_search_text(&self.history[idx - 1].clone());
+            }
+            None => {}
+        }
+    }
+
+    fn set_search_text(&mut self, text: &str) {
+        self.input.clear();
+        self.input.cursor = (0, 0);
+        for c in text.chars() {
+            self.input.input(Input {
+                key: Key::Char(c),
+                ..Default::default()
+            });
+        }
+    }
+
+    /// Letters are always typed, so matches are stepped through with Enter or
+    /// Ctrl-n/Ctrl-p rather than `n`/`N`. Up/Down step through the search history.
+    pub(crate) fn handle_input(&mut self, input: Input) -> SearchbarAction {
         match input {
             Input { key: Key::Esc, .. } => {
+                let query = self.get_search_text().to_owned();
+                self.push_history(&query);
                 self.clear_search();
                 self.set_state(ComponentState::Inactive);
+                SearchbarAction::Close
+            }
+            Input {
+                key: Key::Enter, ..
+            } => {
+                let query = self.get_search_text().to_owned();
+                self.push_history(&query);
+                match self.substitute() {
+                    Some(_) => SearchbarAction::Replace,
+                    None => SearchbarAction::NextMatch,
+                }
+            }
+            Input {
+                key: Key::Char('n'),
+                ctrl: true,
+                ..
+            } => SearchbarAction::NextMatch,
+            Input {
+                key: Key::Char('p'),
+                ctrl: true,
+                ..
+            } => SearchbarAction::PrevMatch,
+            Input { key: Key::Up, .. } => {
+                self.history_prev();
+                SearchbarAction::Update
+            }
+            Input { key: Key::Down, .. } => {
+                self.history_next();
+                SearchbarAction::Update
+            }
+            Input {
+                key: Key::Char('c'),
+                alt: true,
+                ..
+            } => {
+                self.case_insensitive = !self.case_insensitive;
+                SearchbarAction::Update
+            }
+            Input {
+                key: Key::Char('r'),
+                alt: true,
+                ..
+            } => {
+                self.regex = !self.regex;
+                SearchbarAction::Update
+            }
+            Input {
+                key: Key::Backspace,
+                ..
+            } => {
+                self.history_idx = None;
+                self.input.delete_char();
+                SearchbarAction::Update
             }
             _ => {
+                self.history_idx = None;
                 self.input.input(input);
+                SearchbarAction::Update
             }
         }
     }
 }
 
+/// Splits `s/old/new/flags` on unescaped slashes, `\/` is a literal slash
+fn split_substitute(text: &str) -> Option<(String, Option<String>, String)> {
+    let rest = text.strip_prefix("s/")?;
+    let mut parts = vec![String::new()];
+    let mut chars = rest.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'/') => {
+                chars.next();
+                parts.last_mut()?.push('/');
+            }
+            '/' => parts.push(String::new()),
+            c => parts.last_mut()?.push(c),
+        }
+    }
+
+    if parts.len() > 3 {
+        return None;
+    }
+    let mut parts = parts.into_iter();
+    let old = parts.next()?;
+    let new = parts.next();
+    let flags = parts.next().unwrap_or_default();
+
+    Some((old, new, flags))
+}
+
 impl<'a> Widget for Searchbar<'a> {
     fn render(mut self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
     where
         Self: Sized,
     {
+        let set = self.theme.border.set();
         let (file_explorer_span_text, bottom_right) = match self.sidebar_open {
-            true => ("".to_owned(), "┴"),
-            false => (" <Alt-f> show files ".to_owned(), "╯"),
+            true => ("".to_owned(), self.theme.border.joins().bottom),
+            false => (" <Alt-f> show files ".to_owned(), set.bottom_right),
         };
 
         let (mode_span, key_hint_span, cursor_style) = match self.state {
             ComponentState::Active => (
                 Span::styled(
-                    " <| SEARCH |>",
+                    format!(
+                        " <| SEARCH{}{} {} |>",
+                        if self.case_insensitive { " [i]" } else { "" },
+                        if self.regex { " [.*]" } else { "" },
+                        self.error.clone().unwrap_or_else(|| self.match_counter()),
+                    ),
                     Style::default()
                         .add_modifier(Modifier::BOLD)
                         .fg(self.theme.search_mode),
@@ -98,21 +390,30 @@ impl<'a> Widget for Searchbar<'a> {
             ),
         };
 
-        let ms_len = mode_span.content.len();
-        let kh_len = key_hint_span.content.len();
+        // Space between the corners, the key hints are cut short first
+        let room = (area.width as usize).saturating_sub(5);
+        let ms_len = mode_span.content.chars().count();
+        let file_explorer_span_text = match ms_len + file_explorer_span_text.len() > room {
+            true => "".to_owned(),
+            false => file_explorer_span_text,
+        };
         let fh_len = file_explorer_span_text.len();
-        let tb_bottom_len = (ms_len + kh_len + fh_len) as u16;
+        let key_hint_span = Span::styled(
+            elide(&key_hint_span.content, room.saturating_sub(ms_len + fh_len)),
+            key_hint_span.style,
+        );
+        let kh_len = key_hint_span.content.chars().count();
         let padding_len = match self.sidebar_open {
             true => 0,
-            false => (area.width - tb_bottom_len - 5) as usize,
+            false => room.saturating_sub(ms_len + kh_len + fh_len),
         };
 
         let prefix_padding = Span::styled(
-            "─".to_owned(),
+            set.horizontal_bottom.to_owned(),
             Style::default().add_modifier(Modifier::BOLD),
         );
         let padding = Span::styled(
-            "─".repeat(padding_len),
+            set.horizontal_bottom.repeat(padding_len),
             Style::default().add_modifier(Modifier::BOLD),
         );
         let file_explorer_span = Span::styled(
@@ -132,12 +433,9 @@ impl<'a> Widget for Searchbar<'a> {
             .border_set(symbols::border::Set {
                 top_left: " ",
                 top_right: " ",
-                bottom_left: "╰",
                 bottom_right,
-                vertical_left: "│",
-                vertical_right: "│",
-                horizontal_bottom: "─",
                 horizontal_top: " ",
+                ..set
             })
             .border_style(Style::default().fg(self.theme.borders))
             .padding(Padding {
@@ -155,3 +453,93 @@ impl<'a> Widget for Searchbar<'a> {
         self.input.widget().render(area, buf);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::prelude::{Buffer, Rect};
+
+    fn searchbar() -> Searchbar<'static> {
+        let theme = SearchbarTheme {
+            text: Color::White,
+            search_mode: Color::Yellow,
+            borders: Color::White,
+            border: BorderStyle::Rounded,
+        };
+        Searchbar::new(false, ComponentState::Active, 40, theme)
+    }
+
+    fn press(searchbar: &mut Searchbar, key: Key) -> SearchbarAction {
+        searchbar.handle_input(Input {
+            key,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_render_narrow_elides_hints() {
+        let area = Rect::new(0, 0, 30, 3);
+        let mut buf = Buffer::empty(area);
+        searchbar().render(area, &mut buf);
+
+        let bottom = (0..area.width)
+            .map(|x| buf.get(x, 2).symbol().to_owned())
+            .collect::<String>();
+        assert!(bottom.contains('…'));
+
+        let tiny = Rect::new(0, 0, 12, 3);
+        searchbar().render(tiny, &mut buf);
+    }
+
+    #[test]
+    fn test_search_history_cycles() {
+        let mut searchbar = searchbar();
+        searchbar.push_history("first");
+        searchbar.push_history("second");
+        for c in "dra".chars() {
+            press(&mut searchbar, Key::Char(c));
+        }
+
+        press(&mut searchbar, Key::Up);
+        assert_eq!(searchbar.get_search_text(), "second");
+        press(&mut searchbar, Key::Up);
+        assert_eq!(searchbar.get_search_text(), "first");
+        press(&mut searchbar, Key::Up);
+        assert_eq!(searchbar.get_search_text(), "first");
+        press(&mut searchbar, Key::Down);
+        assert_eq!(searchbar.get_search_text(), "second");
+        press(&mut searchbar, Key::Down);
+        assert_eq!(searchbar.get_search_text(), "dra");
+        assert_eq!(searchbar.history_idx, None);
+    }
+
+    #[test]
+    fn test_search_history_dedupes() {
+        let mut searchbar = searchbar();
+        for query in ["foo", "bar", "foo", "foo"] {
+            searchbar.set_search_text(query);
+            assert_eq!(press(&mut searchbar, Key::Enter), SearchbarAction::NextMatch);
+        }
+        assert_eq!(searchbar.history, ["foo", "bar"]);
+
+        for n in 0..SEARCH_HISTORY_LEN + 5 {
+            searchbar.push_history(&n.to_string());
+        }
+        assert_eq!(searchbar.history.len(), SEARCH_HISTORY_LEN);
+        assert_eq!(searchbar.history[0], (SEARCH_HISTORY_LEN + 4).to_string());
+    }
+
+    #[test]
+    fn test_split_substitute() {
+        assert_eq!(split_substitute("foo"), None);
+        assert_eq!(
+            split_substitute("s/fo"),
+            Some(("fo".to_owned(), None, "".to_owned()))
+        );
+        assert_eq!(
+            split_substitute("s/a\\/b/c/g"),
+            Some(("a/b".to_owned(), Some("c".to_owned()), "g".to_owned()))
+        );
+        assert_eq!(split_substitute("s/a/b/c/d"), None);
+    }
+}
@@ -0,0 +1,110 @@
+use std::{fs, io, path::Path};
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    app::{App, SearchbarState, SidebarState, DEFAULT_SIDEBAR_SIZE},
+    vaults::DEFAULT_VAULT,
+};
+
+pub(crate) const SESSION_FILE: &str = "session.json";
+
+/// Layout and last opened note, restored on the next startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Session {
+    pub(crate) last_note_id: Option<i64>,
+    pub(crate) sidebar_open: bool,
+    pub(crate) sidebar_size: u16,
+    pub(crate) searchbar_open: bool,
+    /// Recent searchbar queries, newest first
+    #[serde(default)]
+    pub(crate) search_history: Vec<String>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            last_note_id: None,
+            sidebar_open: false,
+            sidebar_size: DEFAULT_SIDEBAR_SIZE,
+            searchbar_open: false,
+            search_history: vec![],
+        }
+    }
+}
+
+impl Session {
+    pub(crate) fn from_app(app: &App) -> Self {
+        let (sidebar_open, sidebar_size) = match app.sidebar_state {
+            SidebarState::Open => (true, app.sidebar_size),
+            SidebarState::Hidden(n) => (false, n),
+        };
+
+        Self {
+            // Startup always opens the default vault
+            last_note_id: app
+                .editor
+                .note_id
+                .filter(|_| app.active_vault == DEFAULT_VAULT),
+            sidebar_open,
+            sidebar_size,
+            searchbar_open: app.searchbar_state == SearchbarState::Open,
+            search_history: app.searchbar.history.clone(),
+        }
+    }
+
+    pub(crate) fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+
+        match serde_json::from_str(&content) {
+            Ok(session) => {
+                info!("session::load: {:?}", session);
+                Some(session)
+            }
+            Err(e) => {
+                error!("Failed to parse session file {:?}: {:?}", path, e);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trip() {
+        let path = std::env::temp_dir().join("tuipaz_test_session_round_trip.json");
+        let session = Session {
+            last_note_id: Some(7),
+            sidebar_open: true,
+            sidebar_size: 24,
+            searchbar_open: true,
+            search_history: vec!["foo".to_owned(), "bar".to_owned()],
+        };
+
+        session.save(&path).expect("session should be written");
+        let loaded = Session::load(&path);
+        fs::remove_file(&path).ok();
+
+        assert_eq!(loaded, Some(session));
+    }
+
+    #[test]
+    fn test_session_load_missing_or_corrupt() {
+        let path = std::env::temp_dir().join("tuipaz_test_session_corrupt.json");
+        fs::write(&path, "{ not json").unwrap();
+        let corrupt = Session::load(&path);
+        fs::remove_file(&path).ok();
+
+        assert_eq!(corrupt, None);
+        assert_eq!(Session::load(Path::new("does/not/exist.json")), None);
+    }
+}
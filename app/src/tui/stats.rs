@@ -0,0 +1,103 @@
+use ratatui::{
+    layout::Alignment,
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{block::Title, Block, BorderType, Borders, Padding, Paragraph, Widget},
+};
+
+use crate::db::db_mac::{LinkCount, NoteIdentifier};
+
+/// How many of the most linked notes the dashboard lists
+pub(crate) const MOST_LINKED_LIMIT: i64 = 10;
+
+/// Summary of the whole vault, gathered when the stats screen opens
+#[derive(Debug, Clone, Default)]
+pub(crate) struct VaultStats {
+    pub(crate) note_count: usize,
+    pub(crate) word_count: usize,
+    pub(crate) most_linked: Vec<LinkCount>,
+    pub(crate) orphans: Vec<NoteIdentifier>,
+    pub(crate) scroll: u16,
+}
+
+impl VaultStats {
+    /// `bodies` are the plaintext bodies of every note
+    pub(crate) fn new(
+        bodies: &[String],
+        most_linked: Vec<LinkCount>,
+        orphans: Vec<NoteIdentifier>,
+    ) -> Self {
+        Self {
+            note_count: bodies.len(),
+            word_count: bodies.iter().map(|body| body.split_whitespace().count()).sum(),
+            most_linked,
+            orphans,
+            scroll: 0,
+        }
+    }
+
+    /// Words per note, rounded down
+    pub(crate) fn average_words(&self) -> usize {
+        self.word_count.checked_div(self.note_count).unwrap_or(0)
+    }
+
+    pub(crate) fn scroll_down(&mut self) {
+        self.scroll = self.scroll.saturating_add(1);
+    }
+
+    pub(crate) fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+}
+
+impl Widget for VaultStats {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let title = Span::styled(" Vault Stats ", Style::default().bold().fg(Color::Yellow));
+        let info_line = Line::styled(" <Esc> prev screen | <j/k> scroll ", Style::default().bold())
+            .alignment(Alignment::Center);
+
+        let stats_block = Block::default()
+            .title(Title::from(title).alignment(Alignment::Center))
+            .title_bottom(info_line)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::new(1, 1, 1, 1));
+
+        let heading = |text: &str| Line::styled(text.to_owned(), Style::default().bold());
+        let mut lines = vec![
+            Line::from(format!("Notes: {}", self.note_count)),
+            Line::from(format!("Words: {}", self.word_count)),
+            Line::from(format!("Average words per note: {}", self.average_words())),
+            Line::default(),
+            heading("Most linked"),
+        ];
+
+        match self.most_linked.is_empty() {
+            true => lines.push(Line::styled("  none", Style::default().fg(Color::DarkGray))),
+            false => lines.extend(self.most_linked.iter().map(|link_count| {
+                Line::from(vec![
+                    Span::raw(format!("  {} ", link_count.title)),
+                    Span::styled(
+                        format!("← {}", link_count.count),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                ])
+            })),
+        }
+
+        lines.push(Line::default());
+        lines.push(heading(&format!("Orphans ({})", self.orphans.len())));
+        lines.extend(self.orphans.iter().map(|nid| {
+            Line::styled(format!("  {}", nid.title), Style::default().fg(Color::Cyan))
+        }));
+
+        Paragraph::new(lines)
+            .block(stats_block)
+            .scroll((self.scroll, 0))
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,59 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::Style,
+    text::Line,
+    widgets::Widget,
+};
+
+use super::config::StatuslineSettings;
+
+/// One row under the editor describing the focused note, built fresh every frame
+#[derive(Debug, Clone)]
+pub(crate) struct StatusLine {
+    /// Cursor as (row, col), zero based
+    pub(crate) cursor: (usize, usize),
+    pub(crate) total_lines: usize,
+    pub(crate) note_id: Option<i64>,
+    pub(crate) link_count: usize,
+    /// Keys of an unfinished editor command, shown whatever the settings
+    pub(crate) pending_keys: String,
+    pub(crate) settings: StatuslineSettings,
+    pub(crate) style: Style,
+}
+
+impl StatusLine {
+    pub(crate) fn text(&self) -> String {
+        let mut fields = vec![];
+        if self.settings.position {
+            fields.push(format!("{}:{}", self.cursor.0 + 1, self.cursor.1 + 1));
+        }
+        if self.settings.line_count {
+            fields.push(format!("{} lines", self.total_lines));
+        }
+        if self.settings.note_id {
+            fields.push(match self.note_id {
+                Some(id) => format!("note {}", id),
+                None => "unsaved".to_owned(),
+            });
+        }
+        if self.settings.link_count {
+            fields.push(format!("{} links", self.link_count));
+        }
+        if !self.pending_keys.is_empty() {
+            fields.push(self.pending_keys.clone());
+        }
+        format!(" {} ", fields.join(" | "))
+    }
+}
+
+impl Widget for StatusLine {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        if !self.settings.enabled {
+            return;
+        }
+        Line::styled(self.text(), self.style).render(area, buf);
+    }
+}
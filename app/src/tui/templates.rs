@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+
+use super::{
+    picker::{indexed_entries, Picker},
+    utils::{civil_from_days, unix_now},
+};
+
+/// Fills in the `{{title}}` and `{{date}}` placeholders of a template body
+pub(crate) fn expand_template(template: &str, title: &str, date: &str) -> String {
+    template
+        .replace("{{title}}", title.trim())
+        .replace("{{date}}", date)
+}
+
+/// Picker over the configured templates, ids are positions in name order
+pub(crate) fn template_picker(templates: &BTreeMap<String, String>) -> Picker {
+    Picker::new(" Templates ", indexed_entries(templates.keys().cloned()))
+}
+
+/// Today's date as YYYY-MM-DD in UTC
+pub(crate) fn today() -> String {
+    let (year, month, day) = civil_from_days((unix_now() / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_template_fills_placeholders() {
+        let body = expand_template("# {{title}}\nDate: {{date}}\n{{other}}", " Standup ", "2024-06-01");
+
+        assert_eq!(body, "# Standup\nDate: 2024-06-01\n{{other}}");
+    }
+}
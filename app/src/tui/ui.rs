@@ -1,15 +1,18 @@
 use log::info;
 
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout},
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Style, Modifier},
     text::{Span, Line, Text},
-    widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph, Widget, Wrap},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Tabs, Widget, Wrap},
     Frame,
 };
 
 use super::{
     app::{App, Screen, SearchbarState},
+    help::Help,
+    lock::LockScreen,
     user_messages::centered_rect,
 };
 
@@ -20,9 +23,41 @@ pub(crate) fn ui(app: &mut App, frame: &mut Frame) {
         Screen::NewNote => render_new_note_screen(app, frame),
         Screen::NewLinkedNote => render_new_linked_note_screen(app, frame),
         Screen::LoadNote => render_load_note_screen(app, frame),
+        Screen::LinkExistingNote => render_load_note_screen(app, frame),
         Screen::DeleteNoteConfirmation => render_popup(app, frame),
-        Screen::Popup => render_popup(app, frame),
-        Screen::Exiting => render_exit_screen(frame),
+        Screen::CloseTabConfirmation => render_popup(app, frame),
+        Screen::NavigateConfirmation => render_popup(app, frame),
+        Screen::RenameLinksConfirmation => render_popup(app, frame),
+        Screen::CreateLinkTargetConfirmation => render_popup(app, frame),
+        Screen::ReplaceConfirmation => {
+            render_main_screen(app, frame);
+            render_popup(app, frame);
+        }
+        Screen::Help => render_help_screen(app, frame),
+        Screen::Graph => render_graph_screen(app, frame),
+        Screen::Stats => render_stats_screen(app, frame),
+        Screen::RecentNotes => render_recent_notes(app, frame),
+        Screen::TemplatePicker => render_template_picker(app, frame),
+        Screen::VaultSwitcher => render_vault_switcher(app, frame),
+        Screen::CommandPalette => render_command_palette(app, frame),
+        Screen::LinkList => render_link_list(app, frame),
+        Screen::LinkPreview => render_link_preview(app, frame),
+        Screen::Locked => render_lock_screen(app, frame),
+        Screen::Exiting => render_exit_screen(app, frame),
+    }
+
+    // The main screen keeps a row for the status message, elsewhere it covers the last row
+    if !matches!(
+        app.current_screen,
+        Screen::Main | Screen::ReplaceConfirmation | Screen::Locked
+    ) {
+        let area = frame.size();
+        let last_row = Rect {
+            y: area.bottom().saturating_sub(1),
+            height: area.height.min(1),
+            ..area
+        };
+        render_status_msg(app, last_row, frame.buffer_mut());
     }
 }
 
@@ -83,7 +118,20 @@ fn render_welcome_screen(app: &mut App, frame: &mut Frame) {
 }
 
 fn render_main_screen(app: &mut App, frame: &mut Frame) {
-    let area = frame.size();
+    // The status line takes the bottom row under both the editor and the sidebar
+    let status_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(match (&app.status_msg, &app.zen_layout) {
+                _ if app.command_line.is_some() => 1,
+                (Some(_), _) => 1,
+                (None, Some(_)) => 0,
+                (None, None) => app.statusline.height(),
+            }),
+        ])
+        .split(frame.size());
+    let area = status_layout[0];
     let buf = frame.buffer_mut();
 
     let h_layout = Layout::default()
@@ -107,25 +155,207 @@ fn render_main_screen(app: &mut App, frame: &mut Frame) {
         ])
         .split(h_layout[0]);
     
-    app.editor.clone().render(v_layout[0], buf);
+    // The tab bar is only shown once a second note is open
+    let tab_bar_height = match app.tabs.len() {
+        1 => 0,
+        _ => 1,
+    };
+    let editor_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(tab_bar_height), Constraint::Min(0)])
+        .split(v_layout[0]);
+
+    // Remembered so mouse events can be mapped back onto widgets
+    app.editor_area = editor_layout[1];
+    app.note_list_area = h_layout[1];
+    app.editor
+        .update_viewport(editor_layout[1].height.saturating_sub(4) as usize);
+
+    render_tab_bar(app, editor_layout[0], buf);
+    let mut editor = app.editor.clone();
+    editor.hide_hints = app.zen_layout.is_some();
+    editor.render(editor_layout[1], buf);
     app.searchbar.clone().render(v_layout[1], buf);
     app.note_list.clone().render(h_layout[1], buf);
+    if let Some(suggestions) = &app.link_suggestions {
+        if let Some(at) = app.editor.screen_position(app.editor_area, suggestions.anchor) {
+            let area = suggestions.area(at, app.editor_area);
+            suggestions.clone().render(area, buf);
+        }
+    }
+    match (&app.command_line, &app.status_msg) {
+        (Some(command), _) => {
+            Clear.render(status_layout[1], buf);
+            Line::from(vec![
+                Span::raw(format!(":{}", command)),
+                Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+            ])
+            .render(status_layout[1], buf);
+        }
+        (None, Some(_)) => render_status_msg(app, status_layout[1], buf),
+        (None, None) => app
+            .editor
+            .status_line(&app.statusline)
+            .render(status_layout[1], buf),
+    }
+}
+
+fn render_status_msg(app: &App, area: Rect, buf: &mut Buffer) {
+    if let Some(status_msg) = &app.status_msg {
+        Clear.render(area, buf);
+        status_msg.status_line().render(area, buf);
+    }
+}
+
+fn render_tab_bar(app: &App, area: Rect, buf: &mut Buffer) {
+    let titles = (0..app.tabs.len())
+        .map(|idx| {
+            let tab = app.tab(idx);
+            let dirty_marker = match tab.is_dirty() {
+                true => " *",
+                false => "",
+            };
+            format!(" {}{} ", tab.title.trim(), dirty_marker)
+        })
+        .collect::<Vec<String>>();
+
+    Tabs::new(titles)
+        .select(app.active_tab)
+        .style(Style::default().fg(app.editor.theme.borders))
+        .highlight_style(
+            Style::default()
+                .fg(app.editor.theme.title)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+        )
+        .divider("│")
+        .padding("", "")
+        .render(area, buf);
 }
 
 fn render_popup(app: &mut App<'_>, frame: &mut Frame) {
     let area = frame.size();
     let buf = frame.buffer_mut();
-    app.user_msg.clone().render(area, buf);
+    let mut user_msg = app.user_msg.clone();
+    user_msg.border = app.popup_border;
+    user_msg.render(area, buf);
 }
 
-fn render_exit_screen(frame: &mut Frame) {
+fn render_help_screen(app: &mut App<'_>, frame: &mut Frame) {
+    let area = frame.size();
+    frame.render_widget(Clear, area);
+    let buf = frame.buffer_mut();
+
+    Help::new(&app.keymap, app.help_scroll).render(centered_rect(60, 80, area), buf);
+}
+
+fn render_graph_screen(app: &mut App<'_>, frame: &mut Frame) {
+    let area = frame.size();
+    frame.render_widget(Clear, area);
+    let buf = frame.buffer_mut();
+
+    if let Some(graph) = &app.graph {
+        graph.clone().render(centered_rect(80, 80, area), buf);
+    }
+}
+
+fn render_stats_screen(app: &mut App<'_>, frame: &mut Frame) {
+    let area = frame.size();
+    frame.render_widget(Clear, area);
+    let buf = frame.buffer_mut();
+
+    if let Some(stats) = &app.stats {
+        stats.clone().render(centered_rect(60, 80, area), buf);
+    }
+}
+
+fn render_recent_notes(app: &mut App<'_>, frame: &mut Frame) {
+    render_main_screen(app, frame);
+    let area = centered_rect(50, 50, frame.size());
+    frame.render_widget(Clear, area);
+    let buf = frame.buffer_mut();
+
+    if let Some(recent) = &app.recent_switcher {
+        recent.clone().render(area, buf);
+    }
+}
+
+fn render_template_picker(app: &mut App<'_>, frame: &mut Frame) {
+    render_main_screen(app, frame);
+    let area = centered_rect(50, 50, frame.size());
+    frame.render_widget(Clear, area);
+    let buf = frame.buffer_mut();
+
+    if let Some(picker) = &app.template_picker {
+        picker.clone().render(area, buf);
+    }
+}
+
+fn render_vault_switcher(app: &mut App<'_>, frame: &mut Frame) {
+    render_main_screen(app, frame);
+    let area = centered_rect(40, 40, frame.size());
+    frame.render_widget(Clear, area);
+    let buf = frame.buffer_mut();
+
+    if let Some(switcher) = &app.vault_switcher {
+        switcher.clone().render(area, buf);
+    }
+}
+
+fn render_command_palette(app: &mut App<'_>, frame: &mut Frame) {
+    render_main_screen(app, frame);
+    let area = centered_rect(50, 60, frame.size());
+    frame.render_widget(Clear, area);
+    let buf = frame.buffer_mut();
+
+    if let Some(palette) = &app.command_palette {
+        palette.clone().render(area, buf);
+    }
+}
+
+fn render_link_list(app: &mut App<'_>, frame: &mut Frame) {
+    render_main_screen(app, frame);
+    let area = centered_rect(50, 60, frame.size());
+    frame.render_widget(Clear, area);
+    let buf = frame.buffer_mut();
+
+    if let Some(link_list) = &app.link_list {
+        link_list.clone().render(area, buf);
+    }
+}
+
+fn render_link_preview(app: &mut App<'_>, frame: &mut Frame) {
+    render_main_screen(app, frame);
+    let area = centered_rect(60, 40, frame.size());
+    frame.render_widget(Clear, area);
+    let buf = frame.buffer_mut();
+
+    if let Some(preview) = &app.link_preview {
+        preview.clone().render(area, buf);
+    }
+}
+
+fn render_lock_screen(app: &mut App<'_>, frame: &mut Frame) {
+    let area = centered_rect(40, 20, frame.size());
+    frame.render_widget(Clear, frame.size());
+    let buf = frame.buffer_mut();
+
+    if let Some(lock) = &app.idle_lock {
+        LockScreen {
+            typed: lock.entry.chars().count(),
+            failed: lock.failed,
+        }
+        .render(area, buf);
+    }
+}
+
+fn render_exit_screen(app: &mut App<'_>, frame: &mut Frame) {
     let area = frame.size();
     frame.render_widget(Clear, area);
     let buf = frame.buffer_mut();
 
     let popup_block = Block::default()
         .borders(Borders::ALL)
-        .border_type(BorderType::Rounded)
+        .border_type(app.popup_border.border_type())
         .padding(Padding::new(0, 0, 1, 1))
         .style(Style::default());
     
@@ -149,9 +379,8 @@ fn render_load_note_screen(app: &mut App<'_>, frame: &mut Frame) {
     let area = frame.size();
     let buf = frame.buffer_mut();
 
-    app.note_list
-        .clone()
-        .render(centered_rect(60, 100, area), buf);
+    app.note_list_area = centered_rect(60, 100, area);
+    app.note_list.clone().render(app.note_list_area, buf);
 }
 
 fn render_new_note_screen(app: &mut App<'_>, frame: &mut Frame) {
@@ -1,12 +1,17 @@
+use std::time::{Duration, Instant};
+
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout},
     prelude::{Buffer, Rect},
     style::{Style, Stylize},
     text::{Line, Span},
-    widgets::{block::Title, Block, BorderType, Borders, Padding, Paragraph, Widget, Wrap},
+    widgets::{block::Title, Block, Borders, Padding, Paragraph, Widget, Wrap},
 };
 
-use super::app::Screen;
+use super::config::BorderStyle;
+
+/// How long a message stays in the status line
+pub(crate) const STATUS_DURATION: Duration = Duration::from_secs(3);
 
 #[derive(Debug, Clone)]
 pub(crate) enum MessageType {
@@ -19,7 +24,11 @@ pub(crate) enum MessageType {
 pub(crate) struct UserMessage {
     pub(crate) msg: String,
     pub(crate) typ: MessageType,
-    pub(crate) next_screen: Option<Screen>,
+    /// Set from the config as the popup is drawn
+    pub(crate) border: BorderStyle,
+    /// How long a status line message is shown for, None stays until dismissed
+    pub(crate) duration: Option<Duration>,
+    pub(crate) start: Instant,
 }
 
 impl UserMessage {
@@ -27,12 +36,48 @@ impl UserMessage {
         Self {
             msg: "Welcome to Tuipaz!".to_string(),
             typ: MessageType::Info,
-            next_screen: None,
+            border: BorderStyle::default(),
+            duration: None,
+            start: Instant::now(),
         }
     }
 
-    pub(crate) fn new(msg: String, typ: MessageType, next_screen: Option<Screen>) -> Self {
-        Self { msg, typ, next_screen }
+    pub(crate) fn new(msg: String, typ: MessageType) -> Self {
+        Self {
+            msg,
+            typ,
+            border: BorderStyle::default(),
+            duration: None,
+            start: Instant::now(),
+        }
+    }
+
+    /// A message for the status line that clears itself after `STATUS_DURATION`
+    pub(crate) fn transient(msg: String, typ: MessageType) -> Self {
+        Self {
+            duration: Some(STATUS_DURATION),
+            ..Self::new(msg, typ)
+        }
+    }
+
+    pub(crate) fn time_left(&self) -> Option<Duration> {
+        self.duration
+            .map(|duration| duration.saturating_sub(self.start.elapsed()))
+    }
+
+    pub(crate) fn expired(&self) -> bool {
+        self.time_left().is_some_and(|left| left.is_zero())
+    }
+
+    /// The message on a single row, coloured by its type
+    pub(crate) fn status_line(&self) -> Line<'static> {
+        let style = match self.typ {
+            MessageType::Info => Style::new().blue(),
+            MessageType::Warning => Style::new().yellow(),
+            MessageType::Error => Style::new().red().bold(),
+        };
+        let text = self.msg.lines().collect::<Vec<&str>>().join(" ");
+        Line::styled(format!(" {} ", text), style)
     }
 }
 
@@ -56,7 +101,7 @@ impl Widget for UserMessage {
             .borders(Borders::ALL)
             .title_bottom(bottom_title)
             .padding(Padding::vertical(1))
-            .border_type(BorderType::Rounded);
+            .border_type(self.border.border_type());
 
         let msg_width = self
             .msg
@@ -124,3 +169,23 @@ pub(crate) fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         ])
         .split(popup_layout[1])[1] // Return the middle chunk
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_message_expires_after_its_duration() {
+        let mut status = UserMessage::transient("Note saved!".to_owned(), MessageType::Info);
+        assert!(!status.expired());
+        assert!(status.time_left().is_some_and(|left| left <= STATUS_DURATION));
+
+        status.start -= STATUS_DURATION;
+        assert!(status.expired());
+        assert_eq!(status.time_left(), Some(Duration::ZERO));
+
+        let popup = UserMessage::new("Delete it? (y/n)".to_owned(), MessageType::Warning);
+        assert!(!popup.expired());
+        assert_eq!(popup.time_left(), None);
+    }
+}
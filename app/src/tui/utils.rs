@@ -1,23 +1,75 @@
-use std::io::{self, stdout, Stdout};
+use std::{
+    io::{self, stdout, Stdout, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use crossterm::{execute, terminal::*};
+use crossterm::{
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+    },
+    execute,
+    terminal::*,
+};
 use ratatui::{backend::CrosstermBackend, Terminal};
 
 // Terminal type used in this application
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
 pub(crate) fn init() -> io::Result<Tui> {
-    execute!(stdout(), EnterAlternateScreen)?;
+    execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
     enable_raw_mode()?;
     Terminal::new(CrosstermBackend::new(stdout()))
 }
 
 pub(crate) fn restore() -> io::Result<()> {
-    execute!(stdout(), LeaveAlternateScreen)?;
+    execute!(stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
     disable_raw_mode()?;
     Ok(())
 }
 
+/// Hands the terminal back to the shell, e.g. while an external editor runs
+pub(crate) fn suspend() -> io::Result<()> {
+    restore()
+}
+
+/// Takes the terminal back after `suspend`, redrawing from scratch
+pub(crate) fn resume(terminal: &mut Tui) -> io::Result<()> {
+    execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    enable_raw_mode()?;
+    terminal.clear()
+}
+
+/// Reads a line without echoing it, for the passphrase before the TUI starts
+pub(crate) fn prompt_hidden(prompt: &str) -> io::Result<String> {
+    print!("{}", prompt);
+    stdout().flush()?;
+    enable_raw_mode()?;
+
+    let mut text = String::new();
+    let result = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        };
+        match key.code {
+            KeyCode::Enter => break Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                break Err(io::Error::new(io::ErrorKind::Interrupted, "passphrase cancelled"));
+            }
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            _ => {}
+        }
+    };
+
+    disable_raw_mode()?;
+    println!();
+    result.map(|_| text)
+}
+
 pub(crate) fn log_format<T: std::fmt::Debug>(data: &T, prefix: &str) -> String {
     let mut s = String::new();
     s.push_str(prefix);
@@ -25,3 +77,73 @@ pub(crate) fn log_format<T: std::fmt::Debug>(data: &T, prefix: &str) -> String {
     s.push_str(&format!("{:?}", data));
     s
 }
+
+/// Case-insensitive subsequence match, so "mtg" matches "Meeting notes"
+pub(crate) fn fuzzy_match(pattern: &str, text: &str) -> bool {
+    let mut text_chars = text.chars().flat_map(char::to_lowercase);
+    pattern
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|p| text_chars.any(|t| t == p))
+}
+
+/// Cuts `text` to at most `max` chars, marking the cut with an ellipsis
+pub(crate) fn elide(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_owned();
+    }
+    match max {
+        0 => String::new(),
+        _ => text.chars().take(max - 1).chain(['…']).collect(),
+    }
+}
+
+/// Seconds since the Unix epoch, 0 if the clock is set before it
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Converts days since 1970-01-01 into a (year, month, day) date
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("", "Anything"));
+        assert!(fuzzy_match("mtg", "Meeting notes"));
+        assert!(fuzzy_match("MN", "meeting notes"));
+        assert!(!fuzzy_match("gtm", "Meeting notes"));
+        assert!(!fuzzy_match("notes!", "Meeting notes"));
+    }
+
+    #[test]
+    fn test_elide() {
+        assert_eq!(elide("short", 10), "short");
+        assert_eq!(elide("a longer title", 6), "a lon…");
+        assert_eq!(elide("anything", 0), "");
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_783), (2024, 3, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::db::init_db::DB_FILE;
+
+use super::{
+    note_list::clean_folder,
+    picker::{indexed_entries, Picker},
+};
+
+/// The database opened on startup, always listed first in the switcher
+pub(crate) const DEFAULT_VAULT: &str = "default";
+
+/// Vault names in switcher order, a configured vault named "default" is shadowed
+pub(crate) fn vault_names(vaults: &BTreeMap<String, String>) -> Vec<String> {
+    let mut names = vec![DEFAULT_VAULT.to_owned()];
+    names.extend(vaults.keys().filter(|name| *name != DEFAULT_VAULT).cloned());
+    names
+}
+
+pub(crate) fn vault_path(vaults: &BTreeMap<String, String>, name: &str) -> Option<PathBuf> {
+    match name == DEFAULT_VAULT {
+        true => Some(PathBuf::from(DB_FILE)),
+        false => vaults.get(name).map(PathBuf::from),
+    }
+}
+
+/// Where a moved note goes, None for the vault is the open one and None for the folder
+/// is the root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Destination {
+    pub(crate) vault: Option<String>,
+    pub(crate) folder: Option<String>,
+}
+
+/// `folder/path`, or `vault:folder/path` when the text before the `:` names a vault
+pub(crate) fn parse_destination(text: &str, vaults: &BTreeMap<String, String>) -> Destination {
+    let names = vault_names(vaults);
+    match text.split_once(':') {
+        Some((vault, folder)) if names.iter().any(|name| name == vault.trim()) => Destination {
+            vault: Some(vault.trim().to_owned()),
+            folder: clean_folder(folder),
+        },
+        _ => Destination {
+            vault: None,
+            folder: clean_folder(text),
+        },
+    }
+}
+
+/// Switcher over the vaults, ids are positions in `vault_names`
+pub(crate) fn vault_picker(vaults: &BTreeMap<String, String>, active: &str) -> Picker {
+    let labels = vault_names(vaults).into_iter().map(|name| match name == active {
+        true => format!("{} (open)", name),
+        false => name,
+    });
+
+    Picker::new(" Vaults ", indexed_entries(labels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_vault_comes_first() {
+        let vaults = [("work", "work.db"), ("default", "other.db"), ("home", "home.db")]
+            .iter()
+            .map(|(name, path)| (name.to_string(), path.to_string()))
+            .collect::<BTreeMap<String, String>>();
+
+        assert_eq!(vault_names(&vaults), ["default", "home", "work"]);
+        assert_eq!(vault_path(&vaults, "default"), Some(PathBuf::from(DB_FILE)));
+        assert_eq!(vault_path(&vaults, "work"), Some(PathBuf::from("work.db")));
+        assert_eq!(vault_path(&vaults, "play"), None);
+    }
+
+    #[test]
+    fn test_parse_destination() {
+        let vaults = BTreeMap::from([("work".to_owned(), "work.db".to_owned())]);
+
+        assert_eq!(
+            parse_destination(" projects//tuipaz/ ", &vaults),
+            Destination {
+                vault: None,
+                folder: Some("projects/tuipaz".to_owned()),
+            }
+        );
+        assert_eq!(
+            parse_destination("work:inbox", &vaults),
+            Destination {
+                vault: Some("work".to_owned()),
+                folder: Some("inbox".to_owned()),
+            }
+        );
+        assert_eq!(
+            parse_destination("default:", &vaults),
+            Destination {
+                vault: Some("default".to_owned()),
+                folder: None,
+            }
+        );
+        // Not a vault, so the colon is part of the folder name
+        assert_eq!(parse_destination("a:b", &vaults).folder.as_deref(), Some("a:b"));
+    }
+}
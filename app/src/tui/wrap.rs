@@ -0,0 +1,68 @@
+/// Char ranges `[start, end)` of the screen rows `line` takes when soft wrapped at
+/// `width`. Rows break after the last space that fits, or mid-word when none does.
+pub(crate) fn wrap_segments(line: &str, width: usize) -> Vec<(usize, usize)> {
+    let chars = line.chars().collect::<Vec<char>>();
+    if width == 0 || chars.len() <= width {
+        return vec![(0, chars.len())];
+    }
+
+    let mut segments = vec![];
+    let mut start = 0;
+    while chars.len() - start > width {
+        let end = start + width;
+        let brk = chars[start + 1..end]
+            .iter()
+            .rposition(|&c| c == ' ')
+            .map_or(end, |idx| start + 1 + idx + 1);
+        segments.push((start, brk));
+        start = brk;
+    }
+    segments.push((start, chars.len()));
+    segments
+}
+
+/// Screen row within the line and column within that row for char `col`.
+/// A cursor past the end of the line sits on the last row.
+pub(crate) fn segment_position(segments: &[(usize, usize)], col: usize) -> (usize, usize) {
+    let idx = segments
+        .iter()
+        .position(|&(start, end)| start <= col && col < end)
+        .unwrap_or(segments.len().saturating_sub(1));
+    let start = segments.get(idx).map_or(0, |&(start, _)| start);
+    (idx, col.saturating_sub(start))
+}
+
+/// Char col for screen column `offset` in `segments[idx]`, kept on that row
+pub(crate) fn segment_col(segments: &[(usize, usize)], idx: usize, offset: usize) -> usize {
+    let (start, end) = segments[idx];
+    let last = match idx + 1 == segments.len() {
+        true => end.saturating_sub(1).max(start),
+        false => end - 1,
+    };
+    std::cmp::min(start + offset, last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_segments_break_at_spaces() {
+        assert_eq!(wrap_segments("short", 10), vec![(0, 5)]);
+        assert_eq!(
+            wrap_segments("the quick brown fox", 8),
+            vec![(0, 4), (4, 10), (10, 16), (16, 19)]
+        );
+        assert_eq!(wrap_segments("abcdefghijkl", 5), vec![(0, 5), (5, 10), (10, 12)]);
+    }
+
+    #[test]
+    fn test_segment_position() {
+        let segments = wrap_segments("the quick brown fox", 8);
+        assert_eq!(segment_position(&segments, 2), (0, 2));
+        assert_eq!(segment_position(&segments, 12), (2, 2));
+        assert_eq!(segment_position(&segments, 19), (3, 3));
+        assert_eq!(segment_col(&segments, 1, 7), 9);
+        assert_eq!(segment_col(&segments, 3, 7), 18);
+    }
+}